@@ -240,6 +240,44 @@ impl NodeConfig {
             )?;
         }
 
+        invariant(
+            config.state_sync.runtime_threads != Some(0),
+            "The state sync runtime needs at least one worker thread".into(),
+        )?;
+        invariant(
+            config.state_sync.chunk_limit > 0,
+            "State sync chunk requests need to ask for at least one transaction".into(),
+        )?;
+        invariant(
+            config.state_sync.max_pending_chunks > 0,
+            "State sync needs to queue at least one chunk response".into(),
+        )?;
+        invariant(
+            config.state_sync.max_chunk_response_bytes
+                <= MAX_FRAME_SIZE - CHUNK_RESPONSE_FRAME_OVERHEAD,
+            "State sync chunk responses have to fit into a network frame along with its overhead"
+                .into(),
+        )?;
+        invariant(
+            config.state_sync.commit_timeout_ms > 0,
+            "State sync needs a non-zero timeout to acknowledge commits".into(),
+        )?;
+        invariant(
+            config.state_sync.backoff_multiplier >= 1.0,
+            "The backoff of state sync peers can't shrink after a failure".into(),
+        )?;
+        invariant(
+            config.state_sync.base_backoff_ms <= config.state_sync.max_backoff_ms,
+            "The base backoff of state sync peers can't exceed the max backoff".into(),
+        )?;
+        if let Some(file) = &config.state_sync.waypoint_advancement_file {
+            invariant(
+                matches!(&config.base.waypoint, WaypointConfig::FromFile(path) if path == file),
+                "The waypoint has to be advanced in the file the node reads its waypoint from"
+                    .into(),
+            )?;
+        }
+
         let mut network_ids = HashSet::new();
         let input_dir = RootPath::new(input_path);
         config.execution.load(&input_dir)?;
@@ -1,43 +1,376 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::{
+    config::{PeerNetworkId, RoleType, MAX_FRAME_SIZE},
+    network_id::NetworkId,
+};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Bytes of a network frame that don't count toward `max_chunk_response_bytes` by default: the
+/// size of a chunk response is measured without the tag of the state sync message that carries
+/// it, nor the direct-send envelope (protocol id, priority and payload length) around that message.
+pub const CHUNK_RESPONSE_FRAME_OVERHEAD: usize = 1024;
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct StateSyncConfig {
+    // whether a sync request to a target of an epoch older than the trusted epoch succeeds
+    // immediately if the target version is already committed. Otherwise such stale targets are
+    // rejected, as are the ones that aren't committed
+    pub accept_committed_stale_targets: bool,
+    // whether a chunk response proven to a target LI newer than the one of the sync request is
+    // accepted (the newer LI is still verified against the trusted epoch), in which case the node
+    // may sync beyond the requested target. Otherwise such responses are rejected
+    pub accept_stronger_targets: bool,
+    // whether the chunk limit of the requests adapts to each peer, starting at chunk_limit: it
+    // grows up to max_chunk_limit while the peer responds fast and successfully, and shrinks
+    // after its timeouts
+    pub adaptive_chunk_sizing: bool,
+    // window over which the rate chunks are applied at is reported, in versions per second.
+    // If 0, the rate isn't reported
+    pub apply_throughput_window_ms: u64,
+    // factor the backoff of a peer is multiplied by at every consecutive failure, see
+    // base_backoff_ms
+    pub backoff_multiplier: f64,
+    // window over which the bytes of the chunk requests and responses sent and received are
+    // reported by the bandwidth stats. If 0, the bytes aren't counted
+    pub bandwidth_stats_window_ms: u64,
+    // duration a peer is skipped for when it times out or fails a chunk request, before the
+    // backoff grows by backoff_multiplier up to max_backoff_ms at every consecutive failure and
+    // is reset by a chunk that makes progress. The actual duration is randomized down to half of
+    // it, so that nodes don't retry in lockstep. If 0, peers aren't skipped
+    pub base_backoff_ms: u64,
+    // whether a ledger info carried by multiple chunks of the same epoch is signature-verified
+    // only once instead of once per chunk
+    pub batch_verify_signatures: bool,
+    // whether a chunk response proven against an LI of an epoch beyond the trusted epoch, which
+    // can't be verified yet, is buffered until the node reaches the epoch instead of discarded
+    pub buffer_future_epoch_chunks: bool,
+    // whether the frozen subtree roots of the local transaction accumulator are cached and compared
+    // directly to the range proofs of chunks, instead of rebuilding an accumulator from every proof
+    pub cache_frozen_subtrees: bool,
     // Size of chunk to request for state synchronization
     pub chunk_limit: u64,
+    // max duration to wait for the response to a chunk request before re-requesting the chunk
+    // from another peer, independently of how long the sync can go without progress
+    // (sync_request_timeout_ms). Full nodes also wait for the long poll timeout on top of it.
+    // If not set, it is derived from tick_interval_ms
+    pub chunk_request_timeout_ms: Option<u64>,
+    // max size in bytes of the cache of the chunks recently served to other peers, which saves the
+    // storage reads and proof assembly of the chunks requested by multiple lagging peers.
+    // 0 disables the cache
+    pub chunk_response_cache_bytes: usize,
+    // directory of pre-downloaded chunk responses, one LCS serialized response per file, that are
+    // applied in the order of the file names before syncing from the network, e.g. to bootstrap a
+    // node from genesis. Syncing falls back to the network at the first file that can't be applied
+    pub chunk_source_dir: Option<PathBuf>,
+    // max duration a client waits for state synchronizer to acknowledge a commit from consensus.
+    // State synchronizer waits for mempool to acknowledge the commit for half of it
+    pub commit_timeout_ms: u64,
+    // whether the signatures of ledger infos are verified without exiting early on the first
+    // invalid one, so that the verification time doesn't reveal which signature failed. Slower
+    pub constant_time_signature_verification: bool,
+    // whether chunk requests are ignored until the node is bootstrapped to its waypoint, so that
+    // a node that is still catching up doesn't serve partial data
+    pub disable_serving_during_bootstrap: bool,
+    // number of most recently committed LIs over which distinct signers are counted
+    pub distinct_signers_window: usize,
+    // whether the chunk responses received while state sync is paused are dropped instead of
+    // being queued to be applied once it resumes
+    pub drop_chunks_while_paused: bool,
+    // max duration to go without crossing an epoch boundary while catching up through multiple
+    // epochs before the catch up is reported as stalled
+    pub epoch_catch_up_timeout_ms: u64,
+    // max number of the most recent epoch transitions kept in the epoch transition history.
+    // If 0, no transition is kept
+    pub epoch_transition_history_size: usize,
+    // window over which repetitions of an identical error (e.g. a peer repeatedly sending the
+    // same bad chunk) are coalesced into a single summary log. The first occurrence is always
+    // logged immediately. If 0, every occurrence is logged
+    pub error_log_throttle_window_ms: u64,
+    // max duration of a chunk execution before it's reported as hung, e.g. on a storage deadlock
+    // The execution isn't cancelled. If 0, hung executions aren't detected
+    pub executor_hang_timeout_ms: u64,
+    // max duration without a commit advancing the local storage before the health check reports
+    // the node as not progressing, unless it's idle at the tip of the chain
+    pub health_check_staleness_ms: u64,
+    // networks this node advertises its highest version to, i.e. attaches its highest LI to chunk
+    // responses on top of the requested target
+    pub highest_version_advertisement: AdvertisementPolicy,
+    // min duration the node has to stay at (or behind) the tip of the chain before it's reported
+    // as idle at the tip (or syncing), so that a node hovering near the tip doesn't flap
+    pub idle_at_tip_debounce_ms: u64,
+    // whether a commit notification from consensus that doesn't commit any version beyond the
+    // previous notification (e.g. a retried notification) is acknowledged without notifying
+    // mempool and reconfig subscribers again
+    pub ignore_duplicate_commits: bool,
+    // number of chunks committed between two progress reports to the clients waiting for the
+    // initialization with progress, 0 reports after every chunk
+    pub init_progress_interval_chunks: u64,
     // default timeout used for long polling to remote peer
     pub long_poll_timeout_ms: u64,
+    // cap on the backoff of a peer, see base_backoff_ms
+    pub max_backoff_ms: u64,
+    // max serialized size of the transactions of a chunk, e.g. for chains with very large
+    // transactions. It's advertised in the chunk requests, and the chunks served stop at the min of
+    // the own and the requested limit, on top of the version limits. If 0, the size is unbounded
+    pub max_chunk_bytes: usize,
     // valid maximum chunk limit for sanity check
     pub max_chunk_limit: u64,
+    // max serialized size of a chunk response, which should not exceed the max frame size of the
+    // networks minus CHUNK_RESPONSE_FRAME_OVERHEAD. Chunks that are too large are halved until they
+    // fit, as the network would drop them
+    pub max_chunk_response_bytes: usize,
+    // max number of sequential chunks packed into a single chunk response, e.g. for links with a
+    // high latency. It's advertised in the chunk requests, and the responses carry up to the min of
+    // the own and the requested number of chunks, within max_chunk_response_bytes
+    pub max_chunks_in_response: u8,
+    // max duration verified chunks are kept in the commit batch before they're committed, even
+    // if the batch is smaller than its min size
+    pub max_commit_batch_delay_ms: u64,
+    // max number of epoch changes a sync request applies without reaching its target before it's
+    // aborted, e.g. against peers serving a long chain of tiny epochs to exhaust the CPU verifying
+    // their signatures. If not set, the number of epoch changes isn't capped
+    pub max_epoch_changes_per_session: Option<u64>,
+    // max number of future epoch chunk responses buffered, see buffer_future_epoch_chunks
+    pub max_future_epoch_chunks: usize,
+    // max number of received chunk responses queued to be processed, e.g. when responses from many
+    // upstream peers arrive while a chunk is executed. Beyond it, the responses that don't make
+    // progress and are from the peers that were useful least recently are dropped
+    pub max_pending_chunks: usize,
     // max number of pending ledger info's to keep in memory
     // This is to prevent OOM
     pub max_pending_li_limit: usize,
+    // max serialized size of a received chunk response. Larger responses are rejected and their
+    // sender penalized. If 0, the size of received responses isn't bounded
+    pub max_received_chunk_bytes: usize,
+    // max number of reconfig notifications published to subscriptions per commit
+    // The remaining notifications are deferred to later commits or progress checks,
+    // so that many subscriptions don't block the commit path
+    pub max_reconfig_notifications_per_commit: usize,
+    // max number of chunk requests sent per second, as a token bucket that allows bursts of up to
+    // that many requests. Requests beyond it are deferred until a token is available. If not set,
+    // chunk requests aren't rate limited
+    pub max_requests_per_sec: Option<u64>,
+    // cap on the estimated memory of the in-memory structures of state sync. When exceeded, caches
+    // and diagnostic structures are dropped first, while the structures needed to sync correctly
+    // are kept. If 0, the memory isn't capped
+    pub max_state_sync_memory_bytes: usize,
     // valid maximum timeout limit for sanity check
     pub max_timeout_ms: u64,
+    // min number of transactions of a received chunk response, in percent of the requested chunk
+    // limit. Shorter responses are rejected and their sender penalized, unless the chunk reaches
+    // the version of its LI or the end of its epoch. If 0, short responses are accepted
+    pub min_chunk_response_percent: u64,
+    // min serialized size of the transactions of consecutive verified chunks to accumulate before
+    // executing and committing them to storage together, to reduce storage write amplification.
+    // Chunks reaching the version of their LI (e.g. the end of an epoch) are committed right away.
+    // If 0 (and min_commit_batch_versions is 0), chunks are committed one by one
+    pub min_commit_batch_bytes: usize,
+    // min number of transactions of consecutive verified chunks to accumulate before executing and
+    // committing them to storage together, see min_commit_batch_bytes
+    pub min_commit_batch_versions: u64,
+    // min number of distinct validators expected to sign the LIs committed over the distinct
+    // signers window. Fewer signers are reported, as a diagnostic of validator participation,
+    // but don't fail the sync. If 0, signer diversity isn't monitored
+    pub min_distinct_signers: usize,
+    // min number of available upstream peers before the first background chunk request is sent,
+    // so that a node starting up doesn't sync from the first peer that connects when better ones
+    // connect right after. Requests to the waypoint or to a consensus sync target don't wait.
+    // The node syncs with fewer peers once min_peers_before_sync_timeout_ms elapsed
+    pub min_peers_before_sync: usize,
+    // max duration since startup to wait for min_peers_before_sync upstream peers
+    pub min_peers_before_sync_timeout_ms: u64,
     // default timeout to make state sync progress by sending chunk requests to a certain number of networks
     // if no progress is made by sending chunk requests to a number of networks,
     // the next sync request will be multicasted, i.e. sent to more networks
     pub multicast_timeout_ms: u64,
+    // whether the valid prefix of a chunk whose last transactions fail verification is committed,
+    // instead of rejecting the whole chunk, so that only the remainder is requested again. Chunks
+    // are verified once more before execution
+    pub partial_chunk_application: bool,
+    // if set, chunks are only requested from this upstream peer, e.g. a trusted archival node,
+    // without falling back to other peers when it's unavailable
+    pub pinned_upstream: Option<PeerNetworkId>,
+    // whether the node periodically broadcasts its highest committed LI to a random subset of its
+    // connected peers, so that they learn of its version without waiting for its chunk requests.
+    // Nodes only broadcast while they're at the tip, not while they're syncing
+    pub proactive_li_broadcast: bool,
+    // interval between two broadcasts of the committed LI. Each interval is jittered down to half
+    // of it, so that nodes don't all broadcast at the same time
+    pub proactive_li_broadcast_interval_ms: u64,
+    // number of connected peers the committed LI is broadcast to
+    pub proactive_li_broadcast_peers: usize,
+    // number of worker threads of the state sync runtime, e.g. to bound its CPU footprint on shared
+    // machines. Has to be at least 1. If not set, there is one thread per CPU core
+    pub runtime_threads: Option<usize>,
+    // whether the node only serves chunk requests and never syncs itself, e.g. an archival node
+    // whose storage is fed out-of-band. Sync requests fail right away
+    pub serve_only: bool,
+    // networks to serve chunk requests on, independently of the upstream networks to sync from
+    // Chunk requests from peers on other networks are ignored. If not set, all networks are served
+    pub serving_networks: Option<Vec<NetworkId>>,
+    // max duration to keep processing responses to in-flight chunk requests after a shutdown is
+    // accepted. If 0, in-flight chunk requests are cancelled and all responses are dropped
+    pub shutdown_drain_timeout_ms: u64,
+    // escalation ladder of a node that makes no progress while it has something to sync: each
+    // step is taken once the node went that long without syncing a new version, in the order
+    // below. The escalation starts over once the node makes progress. If 0, the step is skipped.
+    // A node syncing to a target of consensus takes the n-th recovery step after at most n
+    // multicast_timeout_ms.
+    // * reset the scores of the upstream peers, so that penalized peers are picked again
+    pub stall_escalation_widen_peers_ms: u64,
+    // * drop the in-flight chunk requests, so that the next one is sent right away to new peers
+    pub stall_escalation_refresh_peers_ms: u64,
+    // * send chunk requests to all the upstream networks, down to the least preferred one
+    pub stall_escalation_switch_networks_ms: u64,
+    // * report the stall as critical, for manual intervention
+    pub stall_escalation_alert_ms: u64,
+    // version the node stops syncing at, e.g. to inspect or replay the chain at a given version.
+    // Chunks beyond it are neither requested nor applied, and reaching it completes the
+    // initialization. Sync requests to a later version fail. If not set, there is no ceiling
+    pub sync_ceiling_version: Option<u64>,
     // default timeout for sync request
     pub sync_request_timeout_ms: u64,
     // interval used for checking state synchronization progress
     pub tick_interval_ms: u64,
+    // number of regressing peer advertisements within the tip flap window after which the tip
+    // estimate is reported as flapping, which hints at conflicting or malicious peers.
+    // If 0, flapping isn't detected
+    pub tip_flap_threshold: usize,
+    // window over which regressing peer advertisements are counted for tip flapping detection
+    pub tip_flap_window_ms: u64,
+    // interval the upstream config is refreshed at from the upstream resolver, so that the
+    // upstream networks can change at runtime. If 0, the upstream config isn't refreshed
+    pub upstream_refresh_interval_ms: u64,
+    // whether chunks are only verified, i.e. their LIs and proofs, without being executed or
+    // committed, e.g. to audit the chunks peers serve. The synced version doesn't advance, so the
+    // same chunks keep being requested
+    pub verification_only: bool,
+    // max number of ledger infos whose signatures were verified that are remembered with
+    // batch_verify_signatures, the least recently used are evicted first. The cache is cleared
+    // at every epoch change
+    pub verified_li_cache_size: usize,
+    // whether to read back the local storage after executing a chunk to verify that it was
+    // actually persisted before the commit is acknowledged, at the cost of a storage read
+    pub verify_commit_durability: bool,
+    // whether the LIs committed by syncing chunks are cross-checked against the last LI committed
+    // by consensus, for validators advancing through both. A conflict, e.g. syncing chunks rolled
+    // back versions consensus committed, is reported as critical
+    pub verify_consensus_commits: bool,
+    // file the waypoint is automatically advanced in, to the waypoint of the latest epoch-ending
+    // LI committed locally, so that a restart trusts a recent waypoint instead of the original one.
+    // Has to be the file the node reads its waypoint from. If not set, the waypoint isn't advanced
+    pub waypoint_advancement_file: Option<PathBuf>,
+    // min duration between two automatic waypoint advancements
+    pub waypoint_advancement_interval_ms: u64,
 }
 
 impl Default for StateSyncConfig {
     fn default() -> Self {
         Self {
+            accept_committed_stale_targets: true,
+            accept_stronger_targets: false,
+            adaptive_chunk_sizing: false,
+            apply_throughput_window_ms: 30_000,
+            backoff_multiplier: 2.0,
+            bandwidth_stats_window_ms: 60_000,
+            base_backoff_ms: 1_000,
+            batch_verify_signatures: false,
+            buffer_future_epoch_chunks: false,
+            cache_frozen_subtrees: false,
             chunk_limit: 1000,
+            chunk_request_timeout_ms: None,
+            chunk_response_cache_bytes: 0,
+            chunk_source_dir: None,
+            commit_timeout_ms: 5_000,
+            constant_time_signature_verification: false,
+            disable_serving_during_bootstrap: true,
+            distinct_signers_window: 100,
+            drop_chunks_while_paused: false,
+            epoch_catch_up_timeout_ms: 60_000,
+            epoch_transition_history_size: 100,
+            error_log_throttle_window_ms: 10_000,
+            executor_hang_timeout_ms: 30_000,
+            health_check_staleness_ms: 60_000,
+            highest_version_advertisement: AdvertisementPolicy::default(),
+            idle_at_tip_debounce_ms: 5_000,
+            ignore_duplicate_commits: true,
+            init_progress_interval_chunks: 10,
             long_poll_timeout_ms: 10_000,
+            max_backoff_ms: 30_000,
+            max_chunk_bytes: 0,
             max_chunk_limit: 1000,
+            max_chunk_response_bytes: MAX_FRAME_SIZE - CHUNK_RESPONSE_FRAME_OVERHEAD,
+            max_chunks_in_response: 1,
+            max_commit_batch_delay_ms: 1_000,
+            max_epoch_changes_per_session: None,
+            max_future_epoch_chunks: 10,
+            max_pending_chunks: 16,
             max_pending_li_limit: 1000,
+            max_received_chunk_bytes: 0,
+            max_reconfig_notifications_per_commit: 100,
+            max_requests_per_sec: None,
+            max_state_sync_memory_bytes: 0,
             max_timeout_ms: 120_000,
+            min_chunk_response_percent: 0,
+            min_commit_batch_bytes: 0,
+            min_commit_batch_versions: 0,
+            min_distinct_signers: 0,
+            min_peers_before_sync: 1,
+            min_peers_before_sync_timeout_ms: 10_000,
             multicast_timeout_ms: 30_000,
+            partial_chunk_application: false,
+            pinned_upstream: None,
+            proactive_li_broadcast: false,
+            proactive_li_broadcast_interval_ms: 30_000,
+            proactive_li_broadcast_peers: 3,
+            runtime_threads: None,
+            serve_only: false,
+            serving_networks: None,
+            shutdown_drain_timeout_ms: 5_000,
+            stall_escalation_widen_peers_ms: 120_000,
+            stall_escalation_refresh_peers_ms: 180_000,
+            stall_escalation_switch_networks_ms: 240_000,
+            stall_escalation_alert_ms: 600_000,
+            sync_ceiling_version: None,
             sync_request_timeout_ms: 60_000,
             tick_interval_ms: 100,
+            tip_flap_threshold: 3,
+            tip_flap_window_ms: 60_000,
+            upstream_refresh_interval_ms: 60_000,
+            verification_only: false,
+            verified_li_cache_size: 100,
+            verify_commit_durability: false,
+            verify_consensus_commits: false,
+            waypoint_advancement_file: None,
+            waypoint_advancement_interval_ms: 600_000,
         }
     }
 }
+
+/// Networks a node advertises its highest version to, depending on the role of the node.
+/// A role without a network list advertises to all networks, e.g. validators that only
+/// advertise to each other and not to public fullnodes are configured as `validator: [validator]`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdvertisementPolicy {
+    pub validator: Option<Vec<NetworkId>>,
+    pub full_node: Option<Vec<NetworkId>>,
+}
+
+impl AdvertisementPolicy {
+    /// Returns whether a node with the given role advertises its highest version to peers in
+    /// `network`
+    pub fn allows(&self, role: RoleType, network: &NetworkId) -> bool {
+        let networks = match role {
+            RoleType::Validator => &self.validator,
+            RoleType::FullNode => &self.full_node,
+        };
+        networks
+            .as_ref()
+            .map_or(true, |networks| networks.contains(network))
+    }
+}
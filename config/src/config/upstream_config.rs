@@ -19,6 +19,10 @@ pub struct UpstreamConfig {
     // it is the first network defined here. If the primary upstream network goes down, the node will fall back to the networks
     // specified here, in this order
     pub networks: Vec<NetworkId>,
+    // peers trusted to serve the chunks that cross an epoch boundary, e.g. archival nodes
+    // If not empty, state sync only sends the chunk requests that cross an epoch boundary to these
+    // peers, while the other chunk requests are sent to any upstream peer
+    pub epoch_trusted_peers: Vec<PeerId>,
 }
 
 impl UpstreamConfig {
@@ -36,6 +40,12 @@ impl UpstreamConfig {
         }
     }
 
+    /// Returns whether a peer is trusted to serve chunks that cross an epoch boundary, i.e. if it's
+    /// tagged as such, or if no peer is
+    pub fn is_epoch_trusted(&self, peer: &PeerId) -> bool {
+        self.epoch_trusted_peers.is_empty() || self.epoch_trusted_peers.contains(peer)
+    }
+
     /// Returns the number of upstream networks possible for a node with this config
     pub fn upstream_count(&self) -> usize {
         // `self.networks.len()` is not enough because for validators, this is empty
@@ -100,7 +100,7 @@ impl StateComputer for ExecutionProxy {
         // Similarily, after the state synchronization, we have to reset the cache
         // of BlockExecutor to guarantee the latest committed state is up to date.
         self.execution_correctness_client.lock().reset()?;
-        res?;
+        res.map_err(anyhow::Error::from)?;
         Ok(())
     }
 }
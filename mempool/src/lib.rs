@@ -61,9 +61,9 @@ mod tests;
 pub use shared_mempool::{
     bootstrap, network,
     types::{
-        gen_mempool_reconfig_subscription, CommitNotification, CommitResponse,
-        CommittedTransaction, ConsensusRequest, ConsensusResponse, MempoolClientSender,
-        SubmissionStatus, TransactionExclusion,
+        gen_mempool_reconfig_subscription, CommitAck, CommitError, CommitNotification,
+        CommitResponse, CommittedTransaction, ConsensusRequest, ConsensusResponse,
+        MempoolClientSender, SubmissionStatus, TransactionExclusion,
     },
 };
 #[cfg(any(test, feature = "fuzzing"))]
@@ -368,13 +368,7 @@ pub(crate) async fn process_state_sync_request(
         .observe(req.transactions.len() as f64);
     commit_txns(&mempool, req.transactions, req.block_timestamp_usecs, false).await;
     // send back to callback
-    let result = if req
-        .callback
-        .send(Ok(CommitResponse {
-            msg: "".to_string(),
-        }))
-        .is_err()
-    {
+    let result = if req.callback.send(Ok(CommitResponse::success())).is_err() {
         error!(LogSchema::event_log(
             LogEntry::StateSyncCommit,
             LogEvent::CallbackFail
@@ -201,10 +201,70 @@ impl fmt::Display for CommitNotification {
 /// ACK response to commit notification
 #[derive(Debug)]
 pub struct CommitResponse {
-    /// error msg if applicable - empty string if commit was processed successfully by mempool
-    pub msg: String,
+    /// outcome of the commit notification
+    pub result: Result<CommitAck, CommitError>,
 }
 
+impl CommitResponse {
+    /// response to a commit notification that was processed successfully
+    pub fn success() -> Self {
+        Self {
+            result: Ok(CommitAck),
+        }
+    }
+
+    /// response to a commit notification that failed to be processed
+    pub fn failure(error: CommitError) -> Self {
+        Self { result: Err(error) }
+    }
+}
+
+/// ACK of a commit notification processed successfully
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitAck;
+
+/// Reason a commit notification failed to be processed
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommitError {
+    /// the commit notification couldn't be sent to mempool
+    MempoolNotificationFailed,
+    /// mempool didn't acknowledge the commit notification in time
+    MempoolAckTimeout,
+    /// mempool dropped the commit notification without acknowledging it
+    MempoolAckCanceled,
+    /// mempool failed to process the commit notification
+    MempoolCommitFailed(String),
+}
+
+impl fmt::Display for CommitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitError::MempoolNotificationFailed => {
+                write!(f, "failed to send commit notification to shared mempool")
+            }
+            CommitError::MempoolAckTimeout => {
+                write!(
+                    f,
+                    "did not receive ACK for commit notification sent to mempool"
+                )
+            }
+            CommitError::MempoolAckCanceled => write!(
+                f,
+                "mempool dropped the commit notification without acknowledging it"
+            ),
+            CommitError::MempoolCommitFailed(error) => {
+                write!(
+                    f,
+                    "mempool failed to process the commit notification: {}",
+                    error
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommitError {}
+
 /// successfully executed and committed txn
 pub struct CommittedTransaction {
     /// sender
@@ -0,0 +1,51 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{bandwidth_usage::BandwidthUsage, counters};
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+// DS to measure the rate chunks are applied at, in versions per second over a sliding window. The
+// rate is refreshed on every tick, so that it decays to zero once the node stops syncing
+pub struct ApplyThroughput {
+    window: Duration,
+    // (time, number of versions) of the recently applied chunks, oldest first
+    samples: VecDeque<(SystemTime, u64)>,
+}
+
+impl ApplyThroughput {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Records a chunk of `num_versions` versions applied at `now`.
+    pub fn record(&mut self, num_versions: u64, now: SystemTime) {
+        if self.window == Duration::from_millis(0) {
+            return;
+        }
+        self.samples.push_back((now, num_versions));
+        self.update(now);
+    }
+
+    /// Drops the chunks applied before the window and returns the current rate, in versions per
+    /// second.
+    pub fn update(&mut self, now: SystemTime) -> u64 {
+        if self.window == Duration::from_millis(0) {
+            return 0;
+        }
+        BandwidthUsage::prune(&mut self.samples, now, self.window);
+        let num_versions: u64 = self
+            .samples
+            .iter()
+            .map(|(_, num_versions)| num_versions)
+            .sum();
+        let throughput = (num_versions as f64 / self.window.as_secs_f64()) as u64;
+        counters::CHUNK_APPLY_THROUGHPUT.set(throughput as i64);
+        throughput
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{counters, network::StateSynchronizerMsg, BandwidthStats};
+use diem_config::config::PeerNetworkId;
+use std::{
+    collections::VecDeque,
+    mem::size_of,
+    time::{Duration, SystemTime},
+};
+
+// DS to count the bytes of the chunk messages sent and received over a sliding window, for
+// syncing (chunk requests sent and responses received) and for serving other nodes (chunk
+// requests received and responses sent)
+pub struct BandwidthUsage {
+    window: Duration,
+    // (time, bytes) of the recent messages, oldest first
+    syncing_sent: VecDeque<(SystemTime, u64)>,
+    syncing_received: VecDeque<(SystemTime, u64)>,
+    serving_sent: VecDeque<(SystemTime, u64)>,
+    serving_received: VecDeque<(SystemTime, u64)>,
+}
+
+impl BandwidthUsage {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            syncing_sent: VecDeque::new(),
+            syncing_received: VecDeque::new(),
+            serving_sent: VecDeque::new(),
+            serving_received: VecDeque::new(),
+        }
+    }
+
+    /// Records the bytes of a message sent to (`outbound`) or received from `peer`.
+    pub fn record(
+        &mut self,
+        peer: &PeerNetworkId,
+        msg: &StateSynchronizerMsg,
+        outbound: bool,
+        now: SystemTime,
+    ) {
+        if self.window == Duration::from_millis(0) {
+            return;
+        }
+        // the messages are serialized by the network, so their size is computed separately
+        let bytes = match lcs::to_bytes(msg) {
+            Ok(bytes) => bytes.len() as u64,
+            Err(_) => return,
+        };
+        let (samples, msg_type) = match (msg, outbound) {
+            (StateSynchronizerMsg::GetChunkRequest(_), true) => {
+                (&mut self.syncing_sent, counters::CHUNK_REQUEST_MSG_LABEL)
+            }
+            (StateSynchronizerMsg::GetChunkResponse(_), false) => (
+                &mut self.syncing_received,
+                counters::CHUNK_RESPONSE_MSG_LABEL,
+            ),
+            (StateSynchronizerMsg::GetChunkResponse(_), true) => {
+                (&mut self.serving_sent, counters::CHUNK_RESPONSE_MSG_LABEL)
+            }
+            (StateSynchronizerMsg::GetChunkRequest(_), false) => (
+                &mut self.serving_received,
+                counters::CHUNK_REQUEST_MSG_LABEL,
+            ),
+            (StateSynchronizerMsg::CommittedLedgerInfo(_), true) => {
+                (&mut self.serving_sent, counters::COMMITTED_LI_MSG_LABEL)
+            }
+            (StateSynchronizerMsg::CommittedLedgerInfo(_), false) => {
+                (&mut self.syncing_received, counters::COMMITTED_LI_MSG_LABEL)
+            }
+        };
+        let direction = if outbound {
+            counters::OUTBOUND_LABEL
+        } else {
+            counters::INBOUND_LABEL
+        };
+        counters::NETWORK_BYTES
+            .with_label_values(&[&peer.raw_network_id().to_string(), direction, msg_type])
+            .inc_by(bytes);
+        Self::prune(samples, now, self.window);
+        samples.push_back((now, bytes));
+    }
+
+    pub fn stats(&mut self, now: SystemTime) -> BandwidthStats {
+        let window = self.window;
+        let total = |samples: &mut VecDeque<(SystemTime, u64)>| -> u64 {
+            Self::prune(samples, now, window);
+            samples.iter().map(|(_, bytes)| bytes).sum()
+        };
+        BandwidthStats {
+            window,
+            syncing_bytes_sent: total(&mut self.syncing_sent),
+            syncing_bytes_received: total(&mut self.syncing_received),
+            serving_bytes_sent: total(&mut self.serving_sent),
+            serving_bytes_received: total(&mut self.serving_received),
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        (self.syncing_sent.len()
+            + self.syncing_received.len()
+            + self.serving_sent.len()
+            + self.serving_received.len())
+            * size_of::<(SystemTime, u64)>()
+    }
+
+    /// Drops the recent samples, so that the stats start over. Returns the memory freed.
+    pub fn shed(&mut self) -> usize {
+        let memory = self.memory_bytes();
+        self.syncing_sent.clear();
+        self.syncing_received.clear();
+        self.serving_sent.clear();
+        self.serving_received.clear();
+        memory
+    }
+
+    pub fn prune(samples: &mut VecDeque<(SystemTime, u64)>, now: SystemTime, window: Duration) {
+        while let Some((tst, _)) = samples.front() {
+            if now.duration_since(*tst).map_or(true, |age| age <= window) {
+                break;
+            }
+            samples.pop_front();
+        }
+    }
+}
@@ -89,6 +89,14 @@ pub struct GetChunkRequest {
     pub limit: u64,
     /// The target of the given request.
     target: TargetType,
+    /// Max serialized size in bytes of the transactions of a chunk response, 0 if unbounded.
+    pub max_chunk_bytes: u64,
+    /// Max number of sequential chunks of a chunk response.
+    pub max_chunks_in_response: u8,
+    /// Whether consensus is blocked on the request, which then bypasses the throttles of
+    /// background sync. Only known to the requester, it isn't sent to the peers.
+    #[serde(skip)]
+    critical: bool,
 }
 
 impl GetChunkRequest {
@@ -98,12 +106,37 @@ impl GetChunkRequest {
             current_epoch,
             limit,
             target,
+            max_chunk_bytes: 0,
+            max_chunks_in_response: 1,
+            critical: false,
         }
     }
 
+    /// Bounds the serialized size of the transactions of the chunk response, on top of `limit`.
+    pub fn with_max_chunk_bytes(mut self, max_chunk_bytes: u64) -> Self {
+        self.max_chunk_bytes = max_chunk_bytes;
+        self
+    }
+
+    /// Asks for up to `max_chunks_in_response` sequential chunks in the chunk response.
+    pub fn with_max_chunks_in_response(mut self, max_chunks_in_response: u8) -> Self {
+        self.max_chunks_in_response = max_chunks_in_response;
+        self
+    }
+
+    /// Marks the request as critical, i.e. consensus is blocked on it.
+    pub fn with_critical(mut self, critical: bool) -> Self {
+        self.critical = critical;
+        self
+    }
+
     pub fn target(&self) -> &TargetType {
         &self.target
     }
+
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
 }
 
 impl fmt::Debug for GetChunkRequest {
@@ -116,10 +149,12 @@ impl fmt::Display for GetChunkRequest {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "[ChunkRequest: known version: {}, epoch: {}, limit: {}, target: {}]",
+            "[ChunkRequest: known version: {}, epoch: {}, limit: {}, max bytes: {}, max chunks: {}, target: {}]",
             self.known_version,
             self.current_epoch,
             self.limit,
+            self.max_chunk_bytes,
+            self.max_chunks_in_response,
             self.target(),
         )
     }
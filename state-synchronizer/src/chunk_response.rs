@@ -47,6 +47,31 @@ impl ResponseLedgerInfo {
             }
         }
     }
+
+    /// The epoch of the LedgerInfo relative to which the transactions proofs are built.
+    pub fn epoch(&self) -> u64 {
+        match self {
+            ResponseLedgerInfo::VerifiableLedgerInfo(li) => li.ledger_info().epoch(),
+            ResponseLedgerInfo::ProgressiveLedgerInfo { target_li, .. } => {
+                target_li.ledger_info().epoch()
+            }
+            ResponseLedgerInfo::LedgerInfoForWaypoint { waypoint_li, .. } => {
+                waypoint_li.ledger_info().epoch()
+            }
+        }
+    }
+
+    /// The highest version the responding peer advertises, i.e., the version of `highest_li`
+    /// for progressive LedgerInfos and the version of the LedgerInfo otherwise.
+    pub fn highest_version(&self) -> Version {
+        match self {
+            ResponseLedgerInfo::ProgressiveLedgerInfo {
+                highest_li: Some(highest_li),
+                ..
+            } => highest_li.ledger_info().version(),
+            _ => self.version(),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
@@ -58,6 +83,9 @@ pub struct GetChunkResponse {
     pub response_li: ResponseLedgerInfo,
     /// chunk of transactions with proof corresponding to the ledger info carried by the response.
     pub txn_list_with_proof: TransactionListWithProof,
+    /// Chunks following `txn_list_with_proof`, in order, each with its own proof corresponding to
+    /// the ledger info carried by the response. Empty unless the request asked for more chunks.
+    pub additional_chunks: Vec<TransactionListWithProof>,
 }
 
 impl GetChunkResponse {
@@ -68,8 +96,39 @@ impl GetChunkResponse {
         Self {
             response_li,
             txn_list_with_proof,
+            additional_chunks: vec![],
         }
     }
+
+    /// Splits the response into a response per chunk, in order.
+    pub fn into_chunks(self) -> Vec<GetChunkResponse> {
+        let response_li = self.response_li;
+        std::iter::once(self.txn_list_with_proof)
+            .chain(self.additional_chunks)
+            .map(|txn_list_with_proof| {
+                GetChunkResponse::new(response_li.clone(), txn_list_with_proof)
+            })
+            .collect()
+    }
+
+    /// Whether the chunk ends an epoch, i.e., whether its last transaction is committed by an
+    /// epoch-ending LedgerInfo of the response.
+    pub fn ends_epoch(&self) -> bool {
+        let last_version = match self.txn_list_with_proof.first_transaction_version {
+            Some(first_version) => first_version + self.txn_list_with_proof.len() as u64 - 1,
+            None => return false,
+        };
+        let epoch_ending_li = match &self.response_li {
+            ResponseLedgerInfo::VerifiableLedgerInfo(li) => Some(li),
+            ResponseLedgerInfo::ProgressiveLedgerInfo { target_li, .. } => Some(target_li),
+            ResponseLedgerInfo::LedgerInfoForWaypoint {
+                end_of_epoch_li, ..
+            } => end_of_epoch_li.as_ref(),
+        };
+        epoch_ending_li.map_or(false, |li| {
+            li.ledger_info().ends_epoch() && li.ledger_info().version() == last_version
+        })
+    }
 }
 impl fmt::Debug for GetChunkResponse {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -79,14 +138,23 @@ impl fmt::Debug for GetChunkResponse {
 
 impl fmt::Display for GetChunkResponse {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let txns_repr = match self.txn_list_with_proof.first_transaction_version {
+        let num_txns = self.txn_list_with_proof.len()
+            + self
+                .additional_chunks
+                .iter()
+                .map(|chunk| chunk.len())
+                .sum::<usize>();
+        let mut txns_repr = match self.txn_list_with_proof.first_transaction_version {
             None => "empty".to_string(),
             Some(first_ver) => format!(
                 "versions [{} - {}]",
                 first_ver,
-                first_ver - 1 + self.txn_list_with_proof.len() as u64
+                first_ver - 1 + num_txns as u64
             ),
         };
+        if !self.additional_chunks.is_empty() {
+            txns_repr.push_str(&format!(" in {} chunks", 1 + self.additional_chunks.len()));
+        }
         let response_li_repr = match &self.response_li {
             ResponseLedgerInfo::VerifiableLedgerInfo(li) => {
                 format!("[verifiable LI {}]", li.ledger_info())
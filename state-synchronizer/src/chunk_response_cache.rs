@@ -0,0 +1,116 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::counters;
+use diem_types::transaction::{TransactionListWithProof, Version};
+use std::collections::{HashMap, VecDeque};
+
+// (known version, limit, epoch of the response LI, max chunk bytes) of a served chunk
+type ChunkCacheKey = (Version, u64, u64, u64);
+
+// DS to cache the chunks recently served to other peers, so that the same chunk requested by
+// multiple lagging peers is read from storage and proven once. A chunk is only served from the
+// cache if its proof is relative to the LI of the response. The chunks are evicted least recently
+// used first once the cache exceeds its size in bytes.
+pub struct ChunkResponseCache {
+    max_bytes: usize,
+    // version of the LI the proof of the chunk is relative to, the chunk, and its size in bytes
+    chunks: HashMap<ChunkCacheKey, (Version, TransactionListWithProof, usize)>,
+    // keys of the cached chunks, least recently used first
+    lru: VecDeque<ChunkCacheKey>,
+    bytes: usize,
+}
+
+impl ChunkResponseCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            chunks: HashMap::new(),
+            lru: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+
+    /// Returns the cached chunk of `key` if its proof is relative to the LI at `li_version`.
+    pub fn get(
+        &mut self,
+        key: &ChunkCacheKey,
+        li_version: Version,
+    ) -> Option<TransactionListWithProof> {
+        let chunk = match self.chunks.get(key) {
+            Some((version, chunk, _)) if *version == li_version => chunk.clone(),
+            _ => return None,
+        };
+        self.touch(*key);
+        counters::CHUNK_RESPONSE_CACHE_HITS.inc();
+        Some(chunk)
+    }
+
+    // Marks the chunk of the given key as the most recently used
+    fn touch(&mut self, key: ChunkCacheKey) {
+        if let Some(position) = self.lru.iter().position(|lru_key| *lru_key == key) {
+            self.lru.remove(position);
+        }
+        self.lru.push_back(key);
+    }
+
+    // Caches a served chunk, evicting the least recently used ones beyond the max size
+    pub fn insert(
+        &mut self,
+        key: ChunkCacheKey,
+        li_version: Version,
+        chunk: TransactionListWithProof,
+        bytes: usize,
+    ) {
+        if bytes > self.max_bytes {
+            return;
+        }
+        self.remove(&key);
+        self.chunks.insert(key, (li_version, chunk, bytes));
+        self.bytes += bytes;
+        self.touch(key);
+        while self.bytes > self.max_bytes {
+            match self.lru.front().copied() {
+                Some(evicted) => self.remove(&evicted),
+                None => break,
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &ChunkCacheKey) {
+        if let Some((_, _, bytes)) = self.chunks.remove(key) {
+            self.bytes -= bytes;
+            self.lru.retain(|lru_key| lru_key != key);
+        }
+    }
+
+    /// Drops the chunks proven relative to the LIs of `epoch` older than `committed_version`:
+    /// once the committed LI advances, the chunks of the epoch are proven relative to the new one.
+    /// The chunks proven relative to the LIs ending past epochs are still served as they are.
+    pub fn invalidate(&mut self, epoch: u64, committed_version: Version) {
+        let stale: Vec<_> = self
+            .chunks
+            .iter()
+            .filter(|((_, _, chunk_epoch, _), (version, _, _))| {
+                *chunk_epoch == epoch && *version < committed_version
+            })
+            .map(|(key, _)| *key)
+            .collect();
+        for key in stale {
+            self.remove(&key);
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Drops the cached chunks, which are read from storage again. Returns the memory freed.
+    pub fn shed(&mut self) -> usize {
+        let memory = self.memory_bytes();
+        self.chunks.clear();
+        self.lru.clear();
+        self.bytes = 0;
+        memory
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::chunk_response::GetChunkResponse;
+use anyhow::{format_err, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Source of chunks outside of the network, e.g. pre-downloaded chunks to bootstrap a node from
+/// genesis faster than over the network.
+pub trait ChunkSource: Send {
+    /// Returns the next chunk response of the source, in version order, or None once the source is
+    /// drained. The chunks are verified by state synchronizer like the chunks of the network.
+    /// An `Err`, e.g. for an unreadable chunk, stops syncing from the source.
+    fn next_chunk(&mut self) -> Result<Option<GetChunkResponse>>;
+}
+
+/// Reads the chunk responses of a local directory, one LCS serialized chunk response per file, in
+/// the order of the file names, e.g. files named after the first version of their chunk, padded
+/// with zeros.
+pub struct FileChunkSource {
+    dir: PathBuf,
+    // files that weren't read yet, in the reverse order of their names, once the directory is listed
+    pending_files: Option<Vec<PathBuf>>,
+}
+
+impl FileChunkSource {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            pending_files: None,
+        }
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        files.reverse();
+        Ok(files)
+    }
+}
+
+impl ChunkSource for FileChunkSource {
+    fn next_chunk(&mut self) -> Result<Option<GetChunkResponse>> {
+        if self.pending_files.is_none() {
+            let files = self.list_files().map_err(|e| {
+                format_err!(
+                    "[state sync] failed to list chunk files in {}: {}",
+                    self.dir.display(),
+                    e
+                )
+            })?;
+            self.pending_files = Some(files);
+        }
+        let file = match self.pending_files.as_mut().and_then(|files| files.pop()) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let bytes = fs::read(&file).map_err(|e| {
+            format_err!(
+                "[state sync] failed to read chunk file {}: {}",
+                file.display(),
+                e
+            )
+        })?;
+        let response = lcs::from_bytes(&bytes).map_err(|e| {
+            format_err!("[state sync] corrupt chunk file {}: {}", file.display(), e)
+        })?;
+        Ok(Some(response))
+    }
+}
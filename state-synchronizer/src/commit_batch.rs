@@ -0,0 +1,147 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use diem_types::{
+    ledger_info::LedgerInfoWithSignatures,
+    proof::{TransactionAccumulatorRangeProof, TransactionListProof},
+    transaction::{Transaction, TransactionListWithProof, Version},
+};
+use std::time::{Duration, SystemTime};
+
+// DS to accumulate consecutive verified chunks of the same LI, so that they're executed and
+// committed to storage together once the batch reaches its min size or max delay, instead of one
+// by one. Batched chunks are merged into a single chunk: as they're proven against the same LI,
+// the merged range proof is the left side of the first chunk and the right side of the last one.
+pub struct CommitBatch {
+    min_versions: u64,
+    min_bytes: usize,
+    max_delay: Duration,
+    // merged chunk, its LI and the time its first chunk was batched
+    pending: Option<(
+        TransactionListWithProof,
+        LedgerInfoWithSignatures,
+        SystemTime,
+    )>,
+    num_chunks: usize,
+    num_bytes: usize,
+    // transactions committed since they were last taken, for the post-commit stage
+    committed_txns: Vec<Transaction>,
+}
+
+impl CommitBatch {
+    pub fn new(min_versions: u64, min_bytes: usize, max_delay: Duration) -> Self {
+        Self {
+            min_versions,
+            min_bytes,
+            max_delay,
+            pending: None,
+            num_chunks: 0,
+            num_bytes: 0,
+            committed_txns: vec![],
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.min_versions > 0 || self.min_bytes > 0
+    }
+
+    /// Returns the number of batched transactions
+    pub fn len(&self) -> u64 {
+        self.pending
+            .as_ref()
+            .map_or(0, |(chunk, _, _)| chunk.len() as u64)
+    }
+
+    /// Returns whether the transaction at `version` is batched
+    pub fn contains(&self, version: Version) -> bool {
+        self.pending.as_ref().map_or(false, |(chunk, _, _)| {
+            chunk
+                .first_transaction_version
+                .map_or(false, |first_version| {
+                    first_version <= version && version < first_version + chunk.len() as u64
+                })
+        })
+    }
+
+    pub fn target(&self) -> Option<&LedgerInfoWithSignatures> {
+        self.pending.as_ref().map(|(_, target, _)| target)
+    }
+
+    /// Appends `chunk`, which is proven against `target` and follows the batched chunks
+    pub fn add(
+        &mut self,
+        chunk: TransactionListWithProof,
+        target: LedgerInfoWithSignatures,
+        now: SystemTime,
+    ) {
+        if self.min_bytes > 0 {
+            self.num_bytes += lcs::to_bytes(&chunk.transactions).map_or(0, |bytes| bytes.len());
+        }
+        self.num_chunks += 1;
+        self.pending = Some(match self.pending.take() {
+            Some((batch, _, batched_tst)) => (Self::merge(batch, chunk), target, batched_tst),
+            None => (chunk, target, now),
+        });
+    }
+
+    /// Returns whether the batch has to be committed, i.e. it reached its min size or max delay
+    pub fn is_ready(&self, now: SystemTime) -> bool {
+        let batched_tst = match &self.pending {
+            Some((_, _, batched_tst)) => *batched_tst,
+            None => return false,
+        };
+        (self.min_versions > 0 && self.len() >= self.min_versions)
+            || (self.min_bytes > 0 && self.num_bytes >= self.min_bytes)
+            || now
+                .duration_since(batched_tst)
+                .map_or(false, |delay| delay >= self.max_delay)
+    }
+
+    /// Empties the batch. Returns the merged chunk, its LI and the number of chunks merged.
+    pub fn take(&mut self) -> Option<(TransactionListWithProof, LedgerInfoWithSignatures, usize)> {
+        let num_chunks = self.num_chunks;
+        self.num_chunks = 0;
+        self.num_bytes = 0;
+        self.pending
+            .take()
+            .map(|(chunk, target, _)| (chunk, target, num_chunks))
+    }
+
+    pub fn record_committed(&mut self, txns: &[Transaction]) {
+        self.committed_txns.extend_from_slice(txns);
+    }
+
+    pub fn take_committed_txns(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.committed_txns)
+    }
+
+    fn merge(
+        batch: TransactionListWithProof,
+        chunk: TransactionListWithProof,
+    ) -> TransactionListWithProof {
+        let (batch_range_proof, mut txn_infos) = batch.proof.unpack();
+        let (chunk_range_proof, chunk_txn_infos) = chunk.proof.unpack();
+        txn_infos.extend(chunk_txn_infos);
+        let mut transactions = batch.transactions;
+        transactions.extend(chunk.transactions);
+        let events = match (batch.events, chunk.events) {
+            (Some(mut events), Some(chunk_events)) => {
+                events.extend(chunk_events);
+                Some(events)
+            }
+            _ => None,
+        };
+        TransactionListWithProof::new(
+            transactions,
+            events,
+            batch.first_transaction_version,
+            TransactionListProof::new(
+                TransactionAccumulatorRangeProof::new(
+                    batch_range_proof.left_siblings().clone(),
+                    chunk_range_proof.right_siblings().clone(),
+                ),
+                txn_infos,
+            ),
+        )
+    }
+}
@@ -2,26 +2,54 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    apply_throughput::ApplyThroughput,
+    bandwidth_usage::BandwidthUsage,
     chunk_request::{GetChunkRequest, TargetType},
     chunk_response::{GetChunkResponse, ResponseLedgerInfo},
+    chunk_response_cache::ChunkResponseCache,
+    chunk_source::ChunkSource,
+    commit_batch::CommitBatch,
     counters,
+    epoch_catch_up::EpochCatchUp,
+    error::StateSyncError,
     executor_proxy::ExecutorProxyTrait,
-    logging::{LogEntry, LogEvent, LogSchema},
+    executor_watchdog::ExecutorWatchdog,
+    frozen_subtree_cache::FrozenSubtreeCache,
+    ledger_info_verifier::LedgerInfoVerifier,
+    li_broadcast::LiBroadcast,
+    logging::{ErrorLogThrottle, LogEntry, LogEvent, LogSchema},
     network::{StateSynchronizerEvents, StateSynchronizerMsg, StateSynchronizerSender},
-    request_manager::{PeerScoreUpdateType, RequestManager},
-    SynchronizerState,
+    peer_selector::{PeerSelector, WeightedPeerSelector},
+    recent_ledger_infos::RecentLedgerInfos,
+    request_manager::{BackoffPolicy, PeerScoreUpdateType, RequestManager},
+    signature_verifier::{CpuSignatureVerifier, SignatureVerifier},
+    signer_diversity::SignerDiversity,
+    stall_escalation::{EscalationStep, StallEscalation},
+    sync_lifecycle::SyncLifecycle,
+    sync_rate_stats::SyncRateStats,
+    time_provider::{SystemTimeProvider, TimeProvider},
+    tip_flap_detector::TipFlapDetector,
+    upstream_resolver::UpstreamResolver,
+    waypoint_advancement::WaypointAdvancement,
+    BandwidthStats, CommitOutcome, EpochChangeListener, EpochChangeNotification, EpochTransition,
+    InitProgress, PeerSyncStats, ReconfigNotification, StallReason, SyncHealth, SyncProgress,
+    SyncRatePercentiles, SynchronizerState, VerificationReport,
 };
 use anyhow::{bail, ensure, format_err, Result};
 use diem_config::{
-    config::{PeerNetworkId, RoleType, StateSyncConfig, UpstreamConfig},
+    config::{PeerNetworkId, RoleType, StateSyncConfig},
     network_id::NodeNetworkId,
 };
+use diem_crypto::{ed25519::Ed25519Signature, hash::TransactionAccumulatorHasher, HashValue};
 use diem_logger::prelude::*;
-use diem_mempool::{CommitNotification, CommitResponse, CommittedTransaction};
+use diem_mempool::{CommitError, CommitNotification, CommitResponse, CommittedTransaction};
 use diem_types::{
+    account_address::AccountAddress,
     contract_event::ContractEvent,
-    epoch_change::Verifier,
+    epoch_change::EpochChangeProof,
+    epoch_state::EpochState,
     ledger_info::LedgerInfoWithSignatures,
+    proof::accumulator::InMemoryAccumulator,
     transaction::{Transaction, TransactionListWithProof, Version},
     waypoint::Waypoint,
 };
@@ -29,12 +57,17 @@ use fail::fail_point;
 use futures::{
     channel::{mpsc, oneshot},
     stream::select_all,
-    StreamExt,
+    FutureExt, StreamExt,
 };
+use netcore::transport::ConnectionOrigin;
 use network::protocols::network::Event;
+use rand::{seq::SliceRandom, thread_rng};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, VecDeque},
+    mem::size_of,
     ops::Bound::Included,
+    path::Path,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 use tokio::time::{interval, timeout};
@@ -42,15 +75,28 @@ use tokio::time::{interval, timeout};
 pub struct SyncRequest {
     // The Result value returned to the caller is Error in case the StateSynchronizer failed to
     // reach the target (the LI in the storage remains unchanged as if nothing happened).
-    pub callback: oneshot::Sender<Result<()>>,
+    pub callback: oneshot::Sender<Result<(), StateSyncError>>,
     pub target: LedgerInfoWithSignatures,
     pub last_progress_tst: SystemTime,
+    // Sequence number of the request, so that a cancel only applies to the request that was
+    // current when the cancel was issued
+    pub generation: u64,
+}
+
+pub struct VersionSyncRequest {
+    // The Result value returned to the caller is Error in case no LI reached the target version
+    // on time (the transactions committed so far remain committed).
+    pub callback: oneshot::Sender<Result<(), StateSyncError>>,
+    pub version: Version,
+    pub last_progress_tst: SystemTime,
 }
 
 /// message used by StateSyncClient for communication with Coordinator
 pub enum CoordinatorMessage {
     // used to initiate new sync
     Request(Box<SyncRequest>),
+    // used to initiate new sync to a version, whatever LI ends up committing it
+    SyncToVersion(Box<VersionSyncRequest>),
     // used to notify about new txn commit
     Commit(
         // committed transactions
@@ -60,9 +106,77 @@ pub enum CoordinatorMessage {
         // callback for recipient to send response back to this sender
         oneshot::Sender<Result<CommitResponse>>,
     ),
+    // Same as Commit, and receive the subscriptions notified of the reconfiguration events of the
+    // commit via a given channel.
+    CommitWithOutcome(
+        Vec<Transaction>,
+        Vec<ContractEvent>,
+        oneshot::Sender<Result<CommitResponse>>,
+        oneshot::Sender<Result<CommitOutcome>>,
+    ),
     GetState(oneshot::Sender<SynchronizerState>),
+    // used to report the health of state sync to external orchestration
+    HealthCheck(oneshot::Sender<SyncHealth>),
+    // Receive the sync progress via a given channel.
+    GetSyncProgress(oneshot::Sender<SyncProgress>),
     // Receive a notification via a given channel when coordinator is initialized.
-    WaitInitialize(oneshot::Sender<Result<()>>),
+    WaitInitialize(oneshot::Sender<Result<(), StateSyncError>>),
+    // Same as WaitInitialize, and receive the progress of the initialization via a given channel
+    // until it completes.
+    WaitInitializeWithProgress(
+        oneshot::Sender<Result<(), StateSyncError>>,
+        mpsc::Sender<InitProgress>,
+    ),
+    // Stop issuing new requests and receive a notification via a given channel once all synced
+    // transactions are committed.
+    Quiesce(oneshot::Sender<Result<()>>),
+    // Stop the coordinator and receive a notification via a given channel once it stopped, i.e.
+    // once the responses to in-flight chunk requests are processed or the drain timeout expired.
+    Shutdown(oneshot::Sender<Result<()>>),
+    // Receive the reconfiguration events committed from now on via a given channel.
+    SubscribeReconfigs(mpsc::UnboundedSender<ReconfigNotification>),
+    // Run a background sync pass now instead of waiting for the next tick.
+    TriggerSyncCycle,
+    // Stop requesting and applying chunks until resumed, while still serving chunks and
+    // processing commits.
+    Pause,
+    // Resume requesting and applying chunks after a pause.
+    Resume,
+    // Only request chunks from the given peer from now on, or from any peer if None.
+    PinPeer(Option<PeerNetworkId>),
+    // Receive the epoch change proof from a given epoch to the trusted epoch via a given channel.
+    GetEpochChangeProof(u64, oneshot::Sender<Result<EpochChangeProof>>),
+    // Cancel the sync request of a given generation, if it's still the current one.
+    CancelSync(u64),
+    // Receive the bytes sent and received over the bandwidth stats window via a given channel.
+    GetBandwidthStats(oneshot::Sender<BandwidthStats>),
+    // Receive the percentiles of the sync rate, if any chunk was synced, via a given channel.
+    GetSyncRatePercentiles(oneshot::Sender<Option<SyncRatePercentiles>>),
+    // Call a given listener at every epoch transition from now on.
+    AddEpochChangeListener(Box<dyn EpochChangeListener>),
+    // Receive the reason the node most recently failed to make progress via a given channel.
+    GetLastStallReason(oneshot::Sender<Option<StallReason>>),
+    // Receive the peer the in-flight chunk request was sent to via a given channel.
+    GetActiveUpstream(oneshot::Sender<Option<PeerNetworkId>>),
+    // Receive the target LI of the in-flight sync request via a given channel.
+    GetActiveTarget(oneshot::Sender<Option<LedgerInfoWithSignatures>>),
+    // Receive the chunk statistics of every known upstream peer via a given channel.
+    GetPeerStats(oneshot::Sender<HashMap<PeerNetworkId, PeerSyncStats>>),
+    // Receive the results of the chunk verification in verification-only mode via a given channel.
+    GetVerificationReport(oneshot::Sender<VerificationReport>),
+    // Receive the most recent epoch transitions, oldest first, via a given channel.
+    GetEpochTransitionHistory(oneshot::Sender<Vec<EpochTransition>>),
+    // Replace the in-memory state with the one of the local storage, e.g. after the storage was
+    // rolled back externally, and receive the reloaded state via a given channel.
+    ReloadFromStorage(oneshot::Sender<Result<SynchronizerState>>),
+    // Process a given chunk response as if it was received from a given peer, and receive the
+    // state once it's processed via a given channel.
+    #[cfg(any(test, feature = "fuzzing"))]
+    InjectChunkResponse(
+        PeerNetworkId,
+        Box<GetChunkResponse>,
+        oneshot::Sender<SynchronizerState>,
+    ),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -71,6 +185,14 @@ struct PendingRequestInfo {
     known_version: u64,
     request_epoch: u64,
     limit: u64,
+    max_chunk_bytes: u64,
+    max_chunks: u8,
+}
+
+/// Estimated memory of a ledger info with its signatures, in bytes
+pub(crate) fn li_memory_bytes(li: &LedgerInfoWithSignatures) -> usize {
+    size_of::<LedgerInfoWithSignatures>()
+        + li.signatures().len() * (size_of::<AccountAddress>() + size_of::<Ed25519Signature>())
 }
 
 // DS to help sync requester to keep track of ledger infos in the future
@@ -148,6 +270,48 @@ impl PendingLedgerInfos {
     fn highest_version(&self) -> Option<Version> {
         self.pending_li_queue.keys().last().cloned()
     }
+
+    fn memory_bytes(&self) -> usize {
+        self.pending_li_queue
+            .values()
+            .chain(self.target_li.iter())
+            .map(|li| size_of::<Version>() + li_memory_bytes(li))
+            .sum()
+    }
+}
+
+// DS to track a shutdown that has been accepted but hasn't completed yet: until the drain deadline,
+// the coordinator keeps processing responses to chunk requests that were in flight when the
+// shutdown was accepted. Any other chunk response is dropped without processing.
+struct PendingShutdown {
+    listener: oneshot::Sender<Result<()>>,
+    drain_deadline: SystemTime,
+}
+
+/// Pluggable dependencies of the coordinator, which default to the ones of a node in production
+pub struct SyncCoordinatorDeps {
+    // verifies the signatures of the ledger infos
+    pub signature_verifier: Box<dyn SignatureVerifier>,
+    // notified of the epoch transitions once they're committed, if set
+    pub epoch_change_sender: Option<mpsc::Sender<EpochChangeNotification>>,
+    // picks the upstream peers chunk requests are sent to
+    pub peer_selector: Box<dyn PeerSelector>,
+    // chunks applied before syncing from the network, if any
+    pub chunk_source: Option<Box<dyn ChunkSource>>,
+    // clock of the timeouts and deadlines of the coordinator
+    pub time_provider: Arc<dyn TimeProvider>,
+}
+
+impl Default for SyncCoordinatorDeps {
+    fn default() -> Self {
+        Self {
+            signature_verifier: Box::new(CpuSignatureVerifier),
+            epoch_change_sender: None,
+            peer_selector: Box::new(WeightedPeerSelector),
+            chunk_source: None,
+            time_provider: Arc::new(SystemTimeProvider),
+        }
+    }
 }
 
 /// Coordination of synchronization process is driven by SyncCoordinator, which `start()` function
@@ -163,6 +327,9 @@ pub(crate) struct SyncCoordinator<T> {
     client_events: mpsc::UnboundedReceiver<CoordinatorMessage>,
     // used to send messages (e.g. notifications about newly committed txns) to mempool
     state_sync_to_mempool_sender: mpsc::Sender<CommitNotification>,
+    // max duration to wait for mempool to acknowledge a commit notification, half the commit
+    // timeout of the clients, so that a commit is acknowledged before its client gives up on it
+    mempool_ack_timeout: Duration,
     // Current state of the storage, which includes both the latest committed transaction and the
     // latest transaction covered by the LedgerInfo (see `SynchronizerState` documentation).
     // The state is updated via syncing with the local storage.
@@ -174,6 +341,9 @@ pub(crate) struct SyncCoordinator<T> {
     // An initial waypoint: for as long as the local version is less than a version determined by
     // waypoint a node is not going to be abl
     waypoint: Waypoint,
+    // epoch-ending waypoints pinned at startup, by version: the LIs committed at their versions
+    // have to match them, so that the node doesn't sync to another fork than the one they pin
+    pinned_waypoints: BTreeMap<Version, Waypoint>,
     // network senders - (k, v) = (network ID, network sender)
     network_senders: HashMap<NodeNetworkId, StateSynchronizerSender>,
     // Actor for sending chunk requests
@@ -181,14 +351,113 @@ pub(crate) struct SyncCoordinator<T> {
     request_manager: RequestManager,
     // Optional sync request to be called when the target sync is reached
     sync_request: Option<SyncRequest>,
+    // time the active sync request was accepted at, until the first chunk toward its target is
+    // committed
+    sync_request_accepted_tst: Option<SystemTime>,
+    // number of epoch changes applied since the active sync request was accepted
+    epoch_changes_in_session: u64,
+    // Optional sync request to be called when a committed LI reaches the target version
+    version_sync_request: Option<VersionSyncRequest>,
     // Ledger infos in the future that have not been committed yet
     pending_ledger_infos: PendingLedgerInfos,
     // Option initialization listener to be called when the coordinator is caught up with
     // its waypoint.
-    initialization_listener: Option<oneshot::Sender<Result<()>>>,
+    initialization_listener: Option<oneshot::Sender<Result<(), StateSyncError>>>,
+    // optional channel the progress of the initialization is reported to, dropped (i.e. closed)
+    // once the initialization completes
+    init_progress_sender: Option<mpsc::Sender<InitProgress>>,
+    // number of chunks committed since the last initialization progress report
+    chunks_since_init_progress: u64,
+    // if set, chunks are only requested to commit the transactions that are already synced
+    quiesced: bool,
+    // whether syncing is paused: no chunk is requested, and the chunk responses are queued or
+    // dropped until it's resumed
+    paused: bool,
+    // option callback to send to when all synced transactions are committed after a quiesce
+    quiesce_listener: Option<oneshot::Sender<Result<()>>>,
+    // whether local storage is ahead of the highest version advertised by all known peers
+    ahead_of_peers: bool,
+    // whether chunk requests are served, which may be disabled until the node is bootstrapped
+    serving_enabled: bool,
+    // shutdown that is waiting for in-flight chunk requests to drain
+    pending_shutdown: Option<PendingShutdown>,
     // queue of incoming long polling requests
     // peer will be notified about new chunk of transactions if it's available before expiry time
     subscriptions: HashMap<PeerNetworkId, PendingRequestInfo>,
+    // local consumers of committed reconfiguration events
+    reconfig_subscribers: Vec<mpsc::UnboundedSender<ReconfigNotification>>,
+    // local listeners of epoch transitions
+    epoch_change_listeners: Vec<Box<dyn EpochChangeListener>>,
+    // optional channel notified of the epoch transitions once they're committed
+    epoch_change_sender: Option<mpsc::Sender<EpochChangeNotification>>,
+    // progress of catching up through multiple epochs
+    epoch_catch_up: EpochCatchUp,
+    // verifies ledger infos of chunk responses against the trusted epoch
+    li_verifier: LedgerInfoVerifier,
+    // frozen subtree roots of the local transaction accumulator
+    frozen_subtree_cache: FrozenSubtreeCache,
+    // ledger infos recently served by peers
+    recent_ledger_infos: RecentLedgerInfos,
+    // chunks recently served to other peers
+    chunk_response_cache: ChunkResponseCache,
+    // verified chunks that aren't committed yet
+    commit_batch: CommitBatch,
+    // whether the node is syncing or idle at the tip of the chain
+    sync_lifecycle: SyncLifecycle,
+    // regressions of the versions advertised by peers
+    tip_flap_detector: TipFlapDetector,
+    // recovery steps taken since the node last made progress
+    stall_escalation: StallEscalation,
+    // advancement of the waypoint trusted on restart
+    waypoint_advancement: WaypointAdvancement,
+    // proactive broadcasts of the committed LI to peers
+    li_broadcast: LiBroadcast,
+    // signers of the recently committed LIs
+    signer_diversity: SignerDiversity,
+    // bytes of the chunk messages recently sent and received
+    bandwidth_usage: BandwidthUsage,
+    // percentiles of the rate chunks were synced at
+    sync_rate_stats: SyncRateStats,
+    // versions per second recently applied by executing chunks
+    apply_throughput: ApplyThroughput,
+    // chunk responses received but not processed yet, oldest first
+    pending_chunks: VecDeque<(PeerNetworkId, GetChunkResponse)>,
+    // chunk responses of epochs beyond the trusted epoch, kept until they can be verified
+    future_epoch_chunks: VecDeque<(PeerNetworkId, GetChunkResponse)>,
+    // last time the upstream config was refreshed from the upstream resolver
+    last_upstream_refresh: SystemTime,
+    // failure of the last chunk response that could be applied, if it failed
+    last_chunk_failure: Option<StallReason>,
+    // number of transactions applied of the last chunk, if only its prefix passed verification
+    partial_chunk_len: Option<usize>,
+    // reason of the most recent progress stall
+    last_stall_reason: Option<StallReason>,
+    // results of the chunk verification in verification-only mode
+    verification_report: VerificationReport,
+    // most recent epoch transitions, oldest first
+    epoch_transitions: VecDeque<EpochTransition>,
+    // repetitions of the errors of chunk responses that aren't logged yet
+    error_log_throttle: ErrorLogThrottle,
+    // reports hung chunk executions, if executor_hang_timeout_ms is set
+    executor_watchdog: Option<ExecutorWatchdog>,
+    // (committed version, transaction accumulator hash) of the local storage after the last commit
+    // msg from consensus
+    last_consensus_commit: Option<(Version, HashValue)>,
+    // last time a commit advanced the local storage, or the time the coordinator was created
+    last_progress_tst: SystemTime,
+    // chunks applied before syncing from the network, until the source is drained
+    chunk_source: Option<Box<dyn ChunkSource>>,
+    // whether the chunks of the chunk source are being applied, in which case no chunk request is
+    // sent to the network
+    draining_chunk_source: bool,
+    // whether the chunk being applied is followed by other chunks of the same chunk response, in
+    // which case the chunk request following it isn't sent
+    applying_packed_chunks: bool,
+    // deadline of the wait for min_peers_before_sync upstream peers, until the first chunk
+    // request is sent
+    min_peers_deadline: Option<SystemTime>,
+    // clock the staleness, backoffs and timeouts of the sync process are computed with
+    time_provider: Arc<dyn TimeProvider>,
     executor_proxy: T,
 }
 
@@ -198,37 +467,190 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         state_sync_to_mempool_sender: mpsc::Sender<CommitNotification>,
         network_senders: HashMap<NodeNetworkId, StateSynchronizerSender>,
         role: RoleType,
-        waypoint: Waypoint,
+        // the node syncs to the highest waypoint before it's initialized
+        waypoints: Vec<Waypoint>,
         config: StateSyncConfig,
-        upstream_config: UpstreamConfig,
+        upstream_resolver: Box<dyn UpstreamResolver>,
         executor_proxy: T,
         initial_state: SynchronizerState,
+        deps: SyncCoordinatorDeps,
     ) -> Self {
+        let SyncCoordinatorDeps {
+            signature_verifier,
+            epoch_change_sender,
+            peer_selector,
+            chunk_source,
+            time_provider,
+        } = deps;
+        let now = time_provider.now();
+        let mut pinned_waypoints = BTreeMap::new();
+        for waypoint in waypoints {
+            if let Some(other) = pinned_waypoints.insert(waypoint.version(), waypoint) {
+                assert_eq!(
+                    other,
+                    waypoint,
+                    "[state sync] conflicting waypoints at version {}",
+                    other.version()
+                );
+            }
+        }
+        let waypoint = pinned_waypoints
+            .values()
+            .next_back()
+            .copied()
+            .unwrap_or_default();
         info!(LogSchema::event_log(LogEntry::Waypoint, LogEvent::Initialize).waypoint(waypoint));
-        let retry_timeout_val = match role {
-            RoleType::FullNode => config.tick_interval_ms + config.long_poll_timeout_ms,
-            RoleType::Validator => 2 * config.tick_interval_ms,
+        let retry_timeout_val = match (role, config.chunk_request_timeout_ms) {
+            (RoleType::FullNode, Some(timeout_ms)) => timeout_ms + config.long_poll_timeout_ms,
+            (RoleType::Validator, Some(timeout_ms)) => timeout_ms,
+            (RoleType::FullNode, None) => config.tick_interval_ms + config.long_poll_timeout_ms,
+            (RoleType::Validator, None) => 2 * config.tick_interval_ms,
         };
         let multicast_timeout = Duration::from_millis(config.multicast_timeout_ms);
+        let backoff_policy = BackoffPolicy {
+            base: Duration::from_millis(config.base_backoff_ms),
+            multiplier: config.backoff_multiplier,
+            max: Duration::from_millis(config.max_backoff_ms),
+        };
+        let mut request_manager = RequestManager::new_with_upstream_resolver(
+            upstream_resolver,
+            peer_selector,
+            backoff_policy,
+            Duration::from_millis(retry_timeout_val),
+            multicast_timeout,
+            network_senders.clone(),
+        );
+        request_manager.set_pinned_peer(config.pinned_upstream.clone());
+        request_manager.set_time_provider(time_provider.clone());
+        if let Some(max_requests_per_sec) = config.max_requests_per_sec {
+            request_manager.set_max_requests_per_sec(max_requests_per_sec);
+        }
+        if let Some(ceiling_version) = config.sync_ceiling_version {
+            request_manager.set_sync_ceiling_version(ceiling_version);
+        }
+        if config.adaptive_chunk_sizing {
+            request_manager.set_adaptive_chunk_sizing(config.chunk_limit, config.max_chunk_limit);
+        }
+        let epoch_catch_up = EpochCatchUp::new(
+            initial_state.epoch(),
+            Duration::from_millis(config.epoch_catch_up_timeout_ms),
+            now,
+        );
+        let li_verifier = LedgerInfoVerifier::new(
+            signature_verifier,
+            config.batch_verify_signatures,
+            config.verified_li_cache_size,
+        );
+        let mut frozen_subtree_cache = FrozenSubtreeCache::new(config.cache_frozen_subtrees);
+        frozen_subtree_cache.update(initial_state.synced_trees.txn_accumulator());
+        let commit_batch = CommitBatch::new(
+            config.min_commit_batch_versions,
+            config.min_commit_batch_bytes,
+            Duration::from_millis(config.max_commit_batch_delay_ms),
+        );
+        let sync_lifecycle =
+            SyncLifecycle::new(Duration::from_millis(config.idle_at_tip_debounce_ms));
+        let tip_flap_detector = TipFlapDetector::new(
+            Duration::from_millis(config.tip_flap_window_ms),
+            config.tip_flap_threshold,
+        );
+        let stall_escalation = StallEscalation::new(
+            &config,
+            initial_state.highest_version_in_local_storage(),
+            now,
+        );
+        let waypoint_advancement = WaypointAdvancement::new(
+            config.waypoint_advancement_file.clone(),
+            Duration::from_millis(config.waypoint_advancement_interval_ms),
+        );
+        let li_broadcast = LiBroadcast::new(
+            config.proactive_li_broadcast,
+            Duration::from_millis(config.proactive_li_broadcast_interval_ms),
+            now,
+        );
+        let signer_diversity =
+            SignerDiversity::new(config.distinct_signers_window, config.min_distinct_signers);
+        let bandwidth_usage =
+            BandwidthUsage::new(Duration::from_millis(config.bandwidth_stats_window_ms));
+        let apply_throughput =
+            ApplyThroughput::new(Duration::from_millis(config.apply_throughput_window_ms));
+        let executor_watchdog = if config.executor_hang_timeout_ms > 0 {
+            Some(ExecutorWatchdog::new(Duration::from_millis(
+                config.executor_hang_timeout_ms,
+            )))
+        } else {
+            None
+        };
+        let min_peers_deadline =
+            Some(now + Duration::from_millis(config.min_peers_before_sync_timeout_ms));
+        let error_log_throttle =
+            ErrorLogThrottle::new(Duration::from_millis(config.error_log_throttle_window_ms));
+        let serving_enabled = !config.disable_serving_during_bootstrap
+            || waypoint.version() <= initial_state.highest_local_li.ledger_info().version();
+        counters::SERVING_ENABLED.set(serving_enabled as i64);
+        Self::check_epoch_walk_resume(&waypoint, &initial_state);
 
         Self {
             client_events,
             state_sync_to_mempool_sender,
+            mempool_ack_timeout: Duration::from_millis(config.commit_timeout_ms / 2),
             local_state: initial_state,
             pending_ledger_infos: PendingLedgerInfos::new(config.max_pending_li_limit),
             config,
             role,
             waypoint,
-            request_manager: RequestManager::new(
-                upstream_config,
-                Duration::from_millis(retry_timeout_val),
-                multicast_timeout,
-                network_senders.clone(),
-            ),
+            pinned_waypoints,
+            request_manager,
             network_senders,
             subscriptions: HashMap::new(),
+            reconfig_subscribers: vec![],
+            epoch_change_listeners: vec![],
+            epoch_change_sender,
             sync_request: None,
+            sync_request_accepted_tst: None,
+            epoch_changes_in_session: 0,
+            version_sync_request: None,
             initialization_listener: None,
+            init_progress_sender: None,
+            chunks_since_init_progress: 0,
+            quiesced: false,
+            paused: false,
+            quiesce_listener: None,
+            ahead_of_peers: false,
+            serving_enabled,
+            pending_shutdown: None,
+            epoch_catch_up,
+            li_verifier,
+            frozen_subtree_cache,
+            recent_ledger_infos: RecentLedgerInfos::new(),
+            chunk_response_cache: ChunkResponseCache::new(config.chunk_response_cache_bytes),
+            commit_batch,
+            sync_lifecycle,
+            tip_flap_detector,
+            stall_escalation,
+            waypoint_advancement,
+            li_broadcast,
+            signer_diversity,
+            bandwidth_usage,
+            sync_rate_stats: SyncRateStats::new(),
+            apply_throughput,
+            pending_chunks: VecDeque::new(),
+            future_epoch_chunks: VecDeque::new(),
+            last_upstream_refresh: now,
+            last_chunk_failure: None,
+            partial_chunk_len: None,
+            last_stall_reason: None,
+            verification_report: VerificationReport::default(),
+            epoch_transitions: VecDeque::new(),
+            error_log_throttle,
+            executor_watchdog,
+            last_consensus_commit: None,
+            last_progress_tst: now,
+            chunk_source,
+            draining_chunk_source: false,
+            applying_packed_chunks: false,
+            min_peers_deadline,
+            time_provider,
             executor_proxy,
         }
     }
@@ -243,6 +665,13 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         )>,
     ) {
         info!(LogSchema::new(LogEntry::RuntimeStart));
+        // chunks of the chunk source aren't committed in verification-only mode, so they can't be
+        // chained together
+        if let Some(chunk_source) = self.chunk_source.take() {
+            if !self.config.verification_only {
+                self.drain_chunk_source(chunk_source).await;
+            }
+        }
         let mut interval = interval(Duration::from_millis(self.config.tick_interval_ms)).fuse();
 
         let events: Vec<_> = network_handles
@@ -255,73 +684,246 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             let _timer = counters::MAIN_LOOP.start_timer();
             ::futures::select! {
                 msg = self.client_events.select_next_some() => {
-                    match msg {
-                        CoordinatorMessage::Request(request) => {
-                            let _timer = counters::PROCESS_COORDINATOR_MSG_LATENCY
-                                .with_label_values(&[counters::SYNC_MSG_LABEL])
-                                .start_timer();
-                            if let Err(e) = self.request_sync(*request) {
-                                error!(LogSchema::new(LogEntry::SyncRequest).error(&e));
-                                counters::SYNC_REQUEST_RESULT.with_label_values(&[counters::FAIL_LABEL]).inc();
-                            }
-                        }
-                        CoordinatorMessage::Commit(txns, events, callback) => {
-                            {
-                                let _timer = counters::PROCESS_COORDINATOR_MSG_LATENCY
-                                    .with_label_values(&[counters::COMMIT_MSG_LABEL])
-                                    .start_timer();
-                                if let Err(e) = self.process_commit(txns, Some(callback), None).await {
-                                    counters::CONSENSUS_COMMIT_FAIL_COUNT.inc();
-                                    error!(LogSchema::event_log(LogEntry::ConsensusCommit, LogEvent::PostCommitFail).error(&e));
-                                }
-                            }
-                            if let Err(e) = self.executor_proxy.publish_on_chain_config_updates(events) {
-                                counters::RECONFIG_PUBLISH_COUNT
-                                    .with_label_values(&[counters::FAIL_LABEL])
-                                    .inc();
-                                error!(LogSchema::event_log(LogEntry::Reconfig, LogEvent::Fail).error(&e));
-                            }
-                        }
-                        CoordinatorMessage::GetState(callback) => {
-                            self.get_state(callback);
-                        }
-                        CoordinatorMessage::WaitInitialize(cb_sender) => {
-                            self.set_initialization_listener(cb_sender);
-                        }
-                    };
+                    self.process_client_message(msg).await;
                 },
                 (network_id, event) = network_events.select_next_some() => {
-                    match event {
-                        Event::NewPeer(peer_id, origin) => {
-                            let peer = PeerNetworkId(network_id, peer_id);
-                            self.request_manager.enable_peer(peer, origin);
-                            self.check_progress();
-                        }
-                        Event::LostPeer(peer_id, origin) => {
-                            let peer = PeerNetworkId(network_id, peer_id);
-                            self.request_manager.disable_peer(&peer, origin);
-                        }
-                        Event::Message(peer_id, message) => self.process_one_message(PeerNetworkId(network_id.clone(), peer_id), message).await,
-                        unexpected_event => {
-                            counters::NETWORK_ERROR_COUNT.inc();
-                            warn!(LogSchema::new(LogEntry::NetworkError),
-                            "received unexpected network event: {:?}", unexpected_event);
-                        },
-                    }
+                    self.process_network_event(network_id, event);
                 },
                 _ = interval.select_next_some() => {
                     self.check_progress();
+                    self.check_commit_batch().await;
+                    self.publish_deferred_reconfig_notifications();
+                }
+            }
+            // the chunk responses already received are queued before any of them is applied, so
+            // that the least useful ones are dropped if too many are pending
+            let mut num_drained = 0;
+            while !self.pending_chunks.is_empty() && num_drained < self.config.max_pending_chunks {
+                match network_events.next().now_or_never() {
+                    Some(Some((network_id, event))) => {
+                        self.process_network_event(network_id, event);
+                        num_drained += 1;
+                    }
+                    _ => break,
+                }
+            }
+            self.process_pending_chunks().await;
+            if self.check_shutdown() {
+                break;
+            }
+        }
+    }
+
+    pub(crate) async fn process_client_message(&mut self, msg: CoordinatorMessage) {
+        match msg {
+            CoordinatorMessage::Request(request) => {
+                let _timer = counters::PROCESS_COORDINATOR_MSG_LATENCY
+                    .with_label_values(&[counters::SYNC_MSG_LABEL])
+                    .start_timer();
+                if let Err(e) = self.request_sync(*request) {
+                    error!(LogSchema::new(LogEntry::SyncRequest).error(&e));
+                    counters::SYNC_REQUEST_RESULT
+                        .with_label_values(&[counters::FAIL_LABEL])
+                        .inc();
+                }
+            }
+            CoordinatorMessage::SyncToVersion(request) => {
+                let _timer = counters::PROCESS_COORDINATOR_MSG_LATENCY
+                    .with_label_values(&[counters::SYNC_MSG_LABEL])
+                    .start_timer();
+                if let Err(e) = self.request_sync_to_version(*request) {
+                    error!(LogSchema::new(LogEntry::SyncRequest).error(&e));
+                    counters::SYNC_REQUEST_RESULT
+                        .with_label_values(&[counters::FAIL_LABEL])
+                        .inc();
+                }
+            }
+            CoordinatorMessage::Commit(txns, events, callback) => {
+                self.process_consensus_commit(txns, events, callback, None)
+                    .await;
+            }
+            CoordinatorMessage::CommitWithOutcome(txns, events, callback, outcome_callback) => {
+                self.process_consensus_commit(txns, events, callback, Some(outcome_callback))
+                    .await;
+            }
+            CoordinatorMessage::GetState(callback) => {
+                self.get_state(callback);
+            }
+            CoordinatorMessage::HealthCheck(callback) => {
+                self.health_check(callback);
+            }
+            CoordinatorMessage::GetSyncProgress(callback) => {
+                self.get_sync_progress(callback);
+            }
+            CoordinatorMessage::WaitInitialize(cb_sender) => {
+                self.set_initialization_listener(cb_sender);
+            }
+            CoordinatorMessage::WaitInitializeWithProgress(cb_sender, progress_sender) => {
+                self.set_initialization_listener(cb_sender);
+                self.set_init_progress_sender(progress_sender);
+            }
+            CoordinatorMessage::Quiesce(cb_sender) => {
+                self.quiesce(cb_sender);
+            }
+            CoordinatorMessage::Pause => {
+                self.pause();
+            }
+            CoordinatorMessage::Resume => {
+                self.resume().await;
+            }
+            CoordinatorMessage::Shutdown(cb_sender) => {
+                self.shutdown(cb_sender);
+            }
+            CoordinatorMessage::SubscribeReconfigs(subscriber) => {
+                self.reconfig_subscribers.push(subscriber);
+            }
+            CoordinatorMessage::AddEpochChangeListener(listener) => {
+                self.epoch_change_listeners.push(listener);
+            }
+            CoordinatorMessage::GetEpochChangeProof(start_epoch, callback) => {
+                let proof = self.get_epoch_change_proof(start_epoch);
+                if callback.send(proof).is_err() {
+                    error!("[state sync] failed to send epoch change proof");
+                }
+            }
+            CoordinatorMessage::TriggerSyncCycle => {
+                debug!(
+                    LogSchema::event_log(LogEntry::SyncCycle, LogEvent::Triggered)
+                        .version(self.local_state.highest_version_in_local_storage())
+                );
+                self.check_progress();
+                self.check_commit_batch().await;
+            }
+            CoordinatorMessage::PinPeer(peer) => {
+                info!(
+                    LogSchema::new(LogEntry::PinPeer),
+                    "[state sync] pinned upstream peer: {:?}", peer
+                );
+                self.request_manager.set_pinned_peer(peer);
+            }
+            CoordinatorMessage::CancelSync(generation) => {
+                self.cancel_sync(generation);
+            }
+            CoordinatorMessage::GetBandwidthStats(callback) => {
+                if callback
+                    .send(self.bandwidth_usage.stats(self.time_provider.now()))
+                    .is_err()
+                {
+                    error!("[state sync] failed to send bandwidth stats");
+                }
+            }
+            CoordinatorMessage::GetSyncRatePercentiles(callback) => {
+                if callback.send(self.sync_rate_stats.percentiles()).is_err() {
+                    error!("[state sync] failed to send sync rate percentiles");
+                }
+            }
+            CoordinatorMessage::GetLastStallReason(callback) => {
+                if callback.send(self.last_stall_reason.clone()).is_err() {
+                    error!("[state sync] failed to send last stall reason");
+                }
+            }
+            CoordinatorMessage::GetActiveUpstream(callback) => {
+                let active_upstream = self.request_manager.active_upstream(self.known_version());
+                if callback.send(active_upstream).is_err() {
+                    error!("[state sync] failed to send active upstream");
+                }
+            }
+            CoordinatorMessage::GetActiveTarget(callback) => {
+                let active_target = self.sync_request.as_ref().map(|req| req.target.clone());
+                if callback.send(active_target).is_err() {
+                    error!("[state sync] failed to send active target");
+                }
+            }
+            CoordinatorMessage::GetPeerStats(callback) => {
+                if callback
+                    .send(self.request_manager.get_peer_stats())
+                    .is_err()
+                {
+                    error!("[state sync] failed to send peer stats");
+                }
+            }
+            CoordinatorMessage::GetVerificationReport(callback) => {
+                if callback.send(self.verification_report.clone()).is_err() {
+                    error!("[state sync] failed to send verification report");
+                }
+            }
+            CoordinatorMessage::GetEpochTransitionHistory(callback) => {
+                let history = self.epoch_transitions.iter().cloned().collect();
+                if callback.send(history).is_err() {
+                    error!("[state sync] failed to send epoch transition history");
+                }
+            }
+            CoordinatorMessage::ReloadFromStorage(callback) => {
+                if callback.send(self.reload_from_storage()).is_err() {
+                    error!("[state sync] failed to send reloaded state");
                 }
             }
+            #[cfg(any(test, feature = "fuzzing"))]
+            CoordinatorMessage::InjectChunkResponse(peer, response, callback) => {
+                self.process_one_message(peer, StateSynchronizerMsg::GetChunkResponse(response))
+                    .await;
+                self.get_state(callback);
+            }
+        }
+    }
+
+    fn process_network_event(
+        &mut self,
+        network_id: NodeNetworkId,
+        event: Event<StateSynchronizerMsg>,
+    ) {
+        match event {
+            Event::NewPeer(peer_id, origin) => {
+                self.process_new_peer(PeerNetworkId(network_id, peer_id), origin);
+            }
+            Event::LostPeer(peer_id, origin) => {
+                self.process_lost_peer(PeerNetworkId(network_id, peer_id), origin);
+            }
+            Event::Message(peer_id, message) => {
+                self.receive_message(PeerNetworkId(network_id, peer_id), message)
+            }
+            unexpected_event => {
+                counters::NETWORK_ERROR_COUNT.inc();
+                warn!(
+                    LogSchema::new(LogEntry::NetworkError),
+                    "received unexpected network event: {:?}", unexpected_event
+                );
+            }
         }
     }
 
+    pub(crate) fn process_new_peer(&mut self, peer: PeerNetworkId, origin: ConnectionOrigin) {
+        self.request_manager.enable_peer(peer, origin);
+        self.check_progress();
+    }
+
+    pub(crate) fn process_lost_peer(&mut self, peer: PeerNetworkId, origin: ConnectionOrigin) {
+        self.request_manager.disable_peer(&peer, origin);
+    }
+
     pub(crate) async fn process_one_message(
         &mut self,
         peer: PeerNetworkId,
         msg: StateSynchronizerMsg,
     ) {
+        self.receive_message(peer, msg);
+        self.process_pending_chunks().await;
+    }
+
+    /// Processes a message from the network, except for chunk responses, which are queued to be
+    /// processed by `process_pending_chunks`.
+    pub(crate) fn receive_message(&mut self, peer: PeerNetworkId, msg: StateSynchronizerMsg) {
+        msg.record_received();
+        self.bandwidth_usage
+            .record(&peer, &msg, false, self.time_provider.now());
         match msg {
+            StateSynchronizerMsg::GetChunkRequest(request) if !self.is_serving(&peer) => {
+                debug!(
+                    LogSchema::event_log(LogEntry::ProcessChunkRequest, LogEvent::NotServing)
+                        .peer(&peer)
+                        .chunk_req(&request)
+                );
+            }
             StateSynchronizerMsg::GetChunkRequest(request) => {
                 let _timer = counters::PROCESS_MSG_LATENCY
                     .with_label_values(&[
@@ -330,6 +932,12 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                         counters::CHUNK_REQUEST_MSG_LABEL,
                     ])
                     .start_timer();
+                counters::NETWORK_CHUNK_REQUESTS
+                    .with_label_values(&[
+                        &peer.raw_network_id().to_string(),
+                        counters::INBOUND_LABEL,
+                    ])
+                    .inc();
                 let result_label =
                     if let Err(err) = self.process_chunk_request(peer.clone(), *request.clone()) {
                         error!(
@@ -353,19 +961,143 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                     ])
                     .inc();
             }
+            StateSynchronizerMsg::GetChunkResponse(_)
+                if self.paused && self.config.drop_chunks_while_paused =>
+            {
+                counters::CHUNKS_DROPPED_WHILE_PAUSED.inc();
+                debug!(LogSchema::event_log(
+                    LogEntry::ProcessChunkResponse,
+                    LogEvent::DroppedWhilePaused
+                )
+                .peer(&peer));
+            }
+            StateSynchronizerMsg::GetChunkResponse(_) if self.is_dropped_during_shutdown() => {
+                counters::CHUNKS_DROPPED_DURING_SHUTDOWN.inc();
+                debug!(LogSchema::event_log(
+                    LogEntry::ProcessChunkResponse,
+                    LogEvent::DroppedDuringShutdown
+                )
+                .peer(&peer));
+            }
             StateSynchronizerMsg::GetChunkResponse(response) => {
-                let _timer = counters::PROCESS_MSG_LATENCY
-                    .with_label_values(&[
-                        &peer.raw_network_id().to_string(),
-                        &peer.peer_id().to_string(),
-                        counters::CHUNK_RESPONSE_MSG_LABEL,
-                    ])
-                    .start_timer();
-                self.process_chunk_response(&peer, *response).await;
+                Self::observe_chunk_response_size(&peer, &response);
+                self.enqueue_chunk_response(peer, *response);
+            }
+            StateSynchronizerMsg::CommittedLedgerInfo(ledger_info) => {
+                // like the versions advertised by chunk requests, the LI isn't verified: it's only
+                // used to pick peers and estimate the tip, and is ignored for non-upstream peers
+                if self.request_manager.is_known_upstream_peer(&peer) {
+                    self.process_peer_advertisement(&peer, ledger_info.ledger_info().version());
+                }
             }
         }
     }
 
+    /// Records the size of a received chunk response, in bytes and in transactions per chunk, to
+    /// compare it with the chunk limits.
+    fn observe_chunk_response_size(peer: &PeerNetworkId, response: &GetChunkResponse) {
+        let network = peer.raw_network_id().to_string();
+        let sender = peer.peer_id().to_string();
+        let labels = [network.as_str(), sender.as_str()];
+        if let Ok(response_bytes) = lcs::to_bytes(response) {
+            counters::CHUNK_RESPONSE_BYTES
+                .with_label_values(&labels)
+                .observe(response_bytes.len() as f64);
+        }
+        for chunk in
+            std::iter::once(&response.txn_list_with_proof).chain(&response.additional_chunks)
+        {
+            counters::CHUNK_RESPONSE_TXNS
+                .with_label_values(&labels)
+                .observe(chunk.len() as f64);
+        }
+    }
+
+    /// Queues a chunk response to be processed. If too many are pending, the response that is
+    /// the least useful is dropped: responses that make progress are kept over the others, and
+    /// otherwise the ones of the peers that were useful least recently are dropped first.
+    fn enqueue_chunk_response(&mut self, peer: PeerNetworkId, response: GetChunkResponse) {
+        self.pending_chunks.push_back((peer, response));
+        if self.pending_chunks.len() <= self.config.max_pending_chunks {
+            return;
+        }
+        let next_version = self.known_version() + 1;
+        let least_useful = self
+            .pending_chunks
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (peer, response))| {
+                let makes_progress = self.request_manager.is_known_upstream_peer(peer)
+                    && response.txn_list_with_proof.first_transaction_version == Some(next_version);
+                (makes_progress, self.request_manager.last_useful_time(peer))
+            })
+            .map(|(index, _)| index);
+        if let Some((peer, _)) = least_useful.and_then(|index| self.pending_chunks.remove(index)) {
+            counters::PENDING_CHUNK_OVERFLOW.inc();
+            debug!(LogSchema::event_log(
+                LogEntry::ProcessChunkResponse,
+                LogEvent::PendingChunkOverflow
+            )
+            .peer(&peer));
+        }
+    }
+
+    /// Buffers a chunk response that can't be verified until the node reaches its epoch. If the
+    /// buffer is full, the response is discarded.
+    fn buffer_future_epoch_chunk(&mut self, peer: PeerNetworkId, response: GetChunkResponse) {
+        if self.future_epoch_chunks.len() >= self.config.max_future_epoch_chunks {
+            counters::FUTURE_EPOCH_CHUNK_COUNT
+                .with_label_values(&[counters::OVER_MAX_LABEL])
+                .inc();
+            return;
+        }
+        counters::FUTURE_EPOCH_CHUNK_COUNT
+            .with_label_values(&[counters::BUFFERED_LABEL])
+            .inc();
+        debug!(LogSchema::event_log(
+            LogEntry::ProcessChunkResponse,
+            LogEvent::FutureEpochBuffered
+        )
+        .peer(&peer)
+        .local_epoch(self.local_state.trusted_epoch.epoch)
+        .target_epoch(response.response_li.epoch()));
+        self.future_epoch_chunks.push_back((peer, response));
+    }
+
+    /// Queues the buffered chunk responses of the epochs the node reached to be processed.
+    fn replay_future_epoch_chunks(&mut self) {
+        let trusted_epoch = self.local_state.trusted_epoch.epoch;
+        let (reached, future): (VecDeque<_>, VecDeque<_>) =
+            std::mem::take(&mut self.future_epoch_chunks)
+                .into_iter()
+                .partition(|(_, response)| response.response_li.epoch() <= trusted_epoch);
+        self.future_epoch_chunks = future;
+        for (peer, response) in reached {
+            counters::FUTURE_EPOCH_CHUNK_COUNT
+                .with_label_values(&[counters::REPLAYED_LABEL])
+                .inc();
+            self.enqueue_chunk_response(peer, response);
+        }
+    }
+
+    /// Processes the queued chunk responses, in the order they were received.
+    pub(crate) async fn process_pending_chunks(&mut self) {
+        // the queued responses are kept until syncing resumes
+        if self.paused {
+            return;
+        }
+        while let Some((peer, response)) = self.pending_chunks.pop_front() {
+            let _timer = counters::PROCESS_MSG_LATENCY
+                .with_label_values(&[
+                    &peer.raw_network_id().to_string(),
+                    &peer.peer_id().to_string(),
+                    counters::CHUNK_RESPONSE_MSG_LABEL,
+                ])
+                .start_timer();
+            self.process_chunk_response(&peer, response).await;
+        }
+    }
+
     /// Sync up coordinator state with the local storage
     /// and updates the pending ledger info accordingly
     fn sync_state_with_local_storage(&mut self) -> Result<()> {
@@ -373,25 +1105,162 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             counters::STORAGE_READ_FAIL_COUNT.inc();
             e
         })?;
+        let new_li = &new_state.highest_local_li;
+        if new_li.ledger_info().version()
+            > self.local_state.highest_local_li.ledger_info().version()
+        {
+            self.check_signer_diversity(new_li);
+        }
         if new_state.epoch() > self.local_state.epoch() {
+            if self.sync_request.is_some() {
+                self.epoch_changes_in_session += new_state.epoch() - self.local_state.epoch();
+            }
             info!(LogSchema::new(LogEntry::EpochChange)
                 .old_epoch(self.local_state.epoch())
                 .new_epoch(new_state.epoch()));
+            self.epoch_catch_up
+                .process_epoch_change(new_state.epoch(), self.time_provider.now());
+            self.li_verifier.invalidate(new_state.epoch());
+            for listener in self.epoch_change_listeners.iter_mut() {
+                if let Err(e) = listener
+                    .on_epoch_change(&self.local_state.trusted_epoch, &new_state.trusted_epoch)
+                {
+                    error!(
+                        LogSchema::event_log(LogEntry::EpochChange, LogEvent::ListenerFail)
+                            .new_epoch(new_state.epoch())
+                            .error(&e)
+                    );
+                }
+            }
+            self.notify_epoch_change(self.local_state.epoch(), &new_state.trusted_epoch);
+            self.record_epoch_transition(new_state.epoch(), new_li.ledger_info().version());
         }
         self.local_state = new_state;
+        self.replay_future_epoch_chunks();
+        self.frozen_subtree_cache
+            .update(self.local_state.synced_trees.txn_accumulator());
+        self.chunk_response_cache.invalidate(
+            self.local_state.epoch(),
+            self.local_state.highest_local_li.ledger_info().version(),
+        );
 
         self.pending_ledger_infos
             .update(&self.local_state, self.config.chunk_limit);
         Ok(())
     }
 
+    /// Appends an epoch transition to the epoch transition history, evicting the oldest ones
+    /// beyond the configured history size.
+    fn record_epoch_transition(&mut self, epoch: u64, version: Version) {
+        self.epoch_transitions.push_back(EpochTransition {
+            epoch,
+            version,
+            timestamp: self.time_provider.now(),
+        });
+        while self.epoch_transitions.len() > self.config.epoch_transition_history_size {
+            self.epoch_transitions.pop_front();
+        }
+    }
+
+    /// Sends the notification of an epoch transition to the epoch change channel, if any. The
+    /// transition is read from the local storage, so it's already committed.
+    fn notify_epoch_change(&mut self, old_epoch: u64, epoch_state: &EpochState) {
+        let sender = match self.epoch_change_sender.as_mut() {
+            Some(sender) => sender,
+            None => return,
+        };
+        let notification = EpochChangeNotification {
+            old_epoch,
+            new_epoch: epoch_state.epoch,
+            epoch_state: epoch_state.clone(),
+        };
+        if let Err(e) = sender.try_send(notification) {
+            counters::FAILED_CHANNEL_SEND
+                .with_label_values(&[counters::EPOCH_CHANGE_NOTIFICATION])
+                .inc();
+            error!(
+                LogSchema::event_log(LogEntry::EpochChange, LogEvent::ListenerFail)
+                    .new_epoch(epoch_state.epoch),
+                "failed to send epoch change notification: {}", e
+            );
+        }
+    }
+
+    /// Reports the number of distinct signers over the recently committed LIs, and alerts once it
+    /// drops below the configured minimum.
+    fn check_signer_diversity(&mut self, committed_li: &LedgerInfoWithSignatures) {
+        let (distinct_signers, dropped_below_min) = match self.signer_diversity.record(committed_li)
+        {
+            Some(diversity) => diversity,
+            None => return,
+        };
+        counters::DISTINCT_SIGNERS.set(distinct_signers as i64);
+        if dropped_below_min {
+            counters::SIGNER_DIVERSITY_ALERT_COUNT.inc();
+            warn!(
+                LogSchema::event_log(LogEntry::SignerDiversity, LogEvent::BelowMin)
+                    .local_li_version(committed_li.ledger_info().version()),
+                "[state sync] only {} distinct validators signed the last {} committed LIs, expected at least {}",
+                distinct_signers,
+                self.config.distinct_signers_window,
+                self.config.min_distinct_signers
+            );
+        }
+    }
+
+    /// Reports a walk through the epochs up to the waypoint that is resumed after a restart.
+    /// Each verified epoch is committed along with its epoch-ending LI, so the epochs verified
+    /// before the restart are skipped: the walk resumes from the trusted epoch of local storage.
+    fn check_epoch_walk_resume(waypoint: &Waypoint, initial_state: &SynchronizerState) {
+        let local_li = initial_state.highest_local_li.ledger_info();
+        // a node still at genesis starts the walk from scratch
+        if local_li.version() == 0 || waypoint.version() <= local_li.version() {
+            return;
+        }
+        counters::EPOCH_WALK_RESUME_COUNT.inc();
+        info!(
+            LogSchema::event_log(LogEntry::Waypoint, LogEvent::Resume)
+                .local_epoch(initial_state.epoch())
+                .local_li_version(local_li.version())
+                .waypoint(*waypoint),
+            "resuming the epoch walk to the waypoint from epoch {}",
+            initial_state.epoch()
+        );
+    }
+
     /// Verify that the local state's latest LI version (i.e. committed version) has reached the waypoint version.
     fn is_initialized(&self) -> bool {
         self.waypoint.version() <= self.local_state.highest_local_li.ledger_info().version()
     }
 
-    fn set_initialization_listener(&mut self, cb_sender: oneshot::Sender<Result<()>>) {
-        if self.is_initialized() {
+    /// Whether the synced version reached the sync ceiling version, past which nothing is synced.
+    fn reached_sync_ceiling(&self) -> bool {
+        self.config
+            .sync_ceiling_version
+            .map_or(false, |ceiling_version| {
+                self.local_state.highest_version_in_local_storage() >= ceiling_version
+            })
+    }
+
+    /// Fails a sync request to a version beyond the sync ceiling version, as it can't be reached.
+    fn check_sync_ceiling(&self, target_version: u64) -> Result<(), StateSyncError> {
+        match self.config.sync_ceiling_version {
+            Some(ceiling_version) if target_version > ceiling_version => {
+                Err(StateSyncError::BeyondSyncCeiling {
+                    target_version,
+                    ceiling_version,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn set_initialization_listener(
+        &mut self,
+        cb_sender: oneshot::Sender<Result<(), StateSyncError>>,
+    ) {
+        // reaching the sync ceiling completes the initialization, as nothing is synced past it
+        if self.is_initialized() || self.reached_sync_ceiling() {
             if let Err(e) = Self::send_initialization_callback(cb_sender, Ok(())) {
                 error!(LogSchema::event_log(LogEntry::Waypoint, LogEvent::CallbackFail).error(&e));
             }
@@ -400,52 +1269,452 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         }
     }
 
-    /// In case there has been another pending request it's going to be overridden.
-    /// The caller will be notified about request completion via request.callback oneshot:
-    /// at that moment it's guaranteed that the highest LI exposed by the storage is equal to the
-    /// target LI.
-    /// StateSynchronizer assumes that it's the only one modifying the storage (consensus is not
-    /// trying to commit transactions concurrently).
-    fn request_sync(&mut self, request: SyncRequest) -> Result<()> {
-        fail_point!("state_sync::request_sync", |_| {
-            Err(anyhow::anyhow!("Injected error in request_sync"))
-        });
-        let local_li_version = self.local_state.highest_local_li.ledger_info().version();
-        let target_version = request.target.ledger_info().version();
-        debug!(
-            LogSchema::event_log(LogEntry::SyncRequest, LogEvent::Received)
-                .target_version(target_version)
-                .local_li_version(local_li_version)
-        );
+    /// Reports the progress of the initialization to `progress_sender` from now on, starting with
+    /// the current progress. The sender is dropped right away if the node is initialized already.
+    fn set_init_progress_sender(&mut self, progress_sender: mpsc::Sender<InitProgress>) {
+        if self.is_initialized() {
+            return;
+        }
+        self.init_progress_sender = Some(progress_sender);
+        self.chunks_since_init_progress = 0;
+        self.report_init_progress();
+    }
 
-        self.sync_state_with_local_storage()?;
-        ensure!(
-            self.is_initialized(),
-            "[state sync] Sync request but initialization is not complete!"
-        );
-        if target_version == local_li_version {
-            return Self::send_sync_req_callback(request, Ok(()));
+    fn report_init_progress(&mut self) {
+        let progress = InitProgress {
+            synced_version: self.local_state.highest_version_in_local_storage(),
+            target_version: self.waypoint.version(),
+            epoch: self.local_state.epoch(),
+        };
+        if let Some(sender) = self.init_progress_sender.as_mut() {
+            // a report is dropped if the channel is full: the next one supersedes it anyway
+            if let Err(e) = sender.try_send(progress) {
+                if e.is_disconnected() {
+                    self.init_progress_sender = None;
+                }
+            }
         }
+    }
 
-        if target_version < local_li_version {
-            Self::send_sync_req_callback(request, Err(format_err!("Sync request to old version")))?;
-            bail!(
-                "[state sync] Sync request for version {} < known version {}",
-                target_version,
-                local_li_version,
-            );
+    /// Stops issuing new requests ahead of a restart: from now on chunks are only requested to
+    /// commit the transactions that are already synced, and new sync requests are rejected.
+    /// The listener is notified once there are no synced but uncommitted transactions left.
+    fn quiesce(&mut self, cb_sender: oneshot::Sender<Result<()>>) {
+        info!(
+            LogSchema::event_log(LogEntry::Quiesce, LogEvent::Initialize)
+                .local_li_version(self.local_state.highest_local_li.ledger_info().version())
+                .local_synced_version(self.local_state.highest_version_in_local_storage())
+        );
+        self.quiesced = true;
+        if self.has_uncommitted_txns() {
+            self.quiesce_listener = Some(cb_sender);
+        } else if let Err(e) = Self::send_quiesce_callback(cb_sender, Ok(())) {
+            error!(LogSchema::event_log(LogEntry::Quiesce, LogEvent::CallbackFail).error(&e));
         }
+    }
 
-        self.sync_request = Some(request);
+    fn pause(&mut self) {
+        info!(LogSchema::event_log(LogEntry::Pause, LogEvent::Initialize)
+            .local_li_version(self.local_state.highest_local_li.ledger_info().version())
+            .local_synced_version(self.local_state.highest_version_in_local_storage()));
+        self.paused = true;
+        counters::SYNC_PAUSED.set(1);
+    }
+
+    /// Resumes syncing from the state of the local storage, which may have been committed to by
+    /// consensus meanwhile: the chunks queued while paused are applied first, then the next chunk
+    /// is requested.
+    async fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        counters::SYNC_PAUSED.set(0);
+        if let Err(e) = self.sync_state_with_local_storage() {
+            error!(LogSchema::event_log(LogEntry::Pause, LogEvent::Fail).error(&e));
+        }
+        info!(LogSchema::event_log(LogEntry::Pause, LogEvent::Resume)
+            .local_li_version(self.local_state.highest_local_li.ledger_info().version())
+            .local_synced_version(self.local_state.highest_version_in_local_storage()));
+        // the sync requests made no progress while paused, which doesn't count against them
+        let now = self.time_provider.now();
+        if let Some(req) = self.sync_request.as_mut() {
+            req.last_progress_tst = now;
+        }
+        if let Some(req) = self.version_sync_request.as_mut() {
+            req.last_progress_tst = now;
+        }
+        // the requests sent before the pause are unlikely to be answered anymore
+        self.request_manager.clear_requests();
+        self.process_pending_chunks().await;
+        self.check_progress();
+    }
+
+    fn shutdown(&mut self, cb_sender: oneshot::Sender<Result<()>>) {
+        info!(
+            LogSchema::event_log(LogEntry::Shutdown, LogEvent::Initialize)
+                .local_li_version(self.local_state.highest_local_li.ledger_info().version())
+                .local_synced_version(self.local_state.highest_version_in_local_storage())
+        );
+        let drain_timeout = Duration::from_millis(self.config.shutdown_drain_timeout_ms);
+        if let Some(shutdown) = self.pending_shutdown.take() {
+            // only the latest shutdown is tracked, so the earlier one is failed right away
+            if let Err(e) = Self::send_shutdown_callback(
+                shutdown.listener,
+                Err(format_err!("Shutdown overridden by a later shutdown")),
+            ) {
+                error!(LogSchema::event_log(LogEntry::Shutdown, LogEvent::CallbackFail).error(&e));
+            }
+        }
+        let now = self.time_provider.now();
+        self.pending_shutdown = Some(PendingShutdown {
+            listener: cb_sender,
+            drain_deadline: now.checked_add(drain_timeout).unwrap_or(now),
+        });
+    }
+
+    /// Returns whether there are responses to in-flight chunk requests that a pending shutdown
+    /// still waits for.
+    fn is_draining(&self) -> bool {
+        self.pending_shutdown.as_ref().map_or(false, |shutdown| {
+            self.time_provider.now() < shutdown.drain_deadline
+                && self
+                    .request_manager
+                    .has_in_flight_requests(self.local_state.highest_version_in_local_storage())
+        })
+    }
+
+    /// Returns whether chunk requests from `peer` are served: serving may be disabled until the
+    /// node is bootstrapped and restricted to the serving networks.
+    fn is_serving(&self, peer: &PeerNetworkId) -> bool {
+        self.serving_enabled
+            && self
+                .config
+                .serving_networks
+                .as_ref()
+                .map_or(true, |networks| networks.contains(&peer.raw_network_id()))
+    }
+
+    /// Chunk responses arriving after a shutdown was accepted are dropped without processing,
+    /// unless they are a response to an in-flight chunk request that the shutdown drains.
+    fn is_dropped_during_shutdown(&self) -> bool {
+        self.pending_shutdown.is_some() && !self.is_draining()
+    }
+
+    /// Returns true if a shutdown was accepted and there's nothing left to drain, in which case
+    /// the shutdown is acknowledged and the coordinator should stop.
+    pub(crate) fn check_shutdown(&mut self) -> bool {
+        if self.pending_shutdown.is_none() || self.is_draining() {
+            return false;
+        }
+        info!(LogSchema::event_log(LogEntry::Shutdown, LogEvent::Complete)
+            .local_li_version(self.local_state.highest_local_li.ledger_info().version())
+            .local_synced_version(self.local_state.highest_version_in_local_storage()));
+        if let Some(shutdown) = self.pending_shutdown.take() {
+            if let Err(e) = Self::send_shutdown_callback(shutdown.listener, Ok(())) {
+                error!(LogSchema::event_log(LogEntry::Shutdown, LogEvent::CallbackFail).error(&e));
+            }
+        }
+        true
+    }
+
+    fn has_uncommitted_txns(&self) -> bool {
+        self.local_state.highest_version_in_local_storage()
+            > self.local_state.highest_local_li.ledger_info().version()
+    }
+
+    /// In case there has been another pending request it's going to be overridden, and fails
+    /// with `StateSyncError::Replaced`.
+    /// The caller will be notified about request completion via request.callback oneshot:
+    /// at that moment it's guaranteed that the highest LI exposed by the storage is equal to the
+    /// target LI.
+    /// StateSynchronizer assumes that it's the only one modifying the storage (consensus is not
+    /// trying to commit transactions concurrently).
+    fn request_sync(&mut self, request: SyncRequest) -> Result<()> {
+        fail_point!("state_sync::request_sync", |_| {
+            Err(anyhow::anyhow!("Injected error in request_sync"))
+        });
+        let local_li_version = self.local_state.highest_local_li.ledger_info().version();
+        let target_version = request.target.ledger_info().version();
+        debug!(
+            LogSchema::event_log(LogEntry::SyncRequest, LogEvent::Received)
+                .target_version(target_version)
+                .local_li_version(local_li_version)
+        );
+        if self.config.serve_only {
+            Self::send_sync_req_callback(request, Err(StateSyncError::ServeOnly))?;
+            bail!(
+                "[state sync] Sync request for version {} on a serve-only node",
+                target_version
+            );
+        }
+
+        self.sync_state_with_local_storage()?;
+        ensure!(
+            self.is_initialized(),
+            "[state sync] Sync request but initialization is not complete!"
+        );
+        if self.quiesced {
+            Self::send_sync_req_callback(request, Err(StateSyncError::Quiesced))?;
+            bail!(
+                "[state sync] Sync request for version {} while quiesced",
+                target_version
+            );
+        }
+        if target_version == local_li_version {
+            return Self::send_sync_req_callback(request, Ok(()));
+        }
+        if let Err(e) = self.check_sync_ceiling(target_version) {
+            Self::send_sync_req_callback(request, Err(e.clone()))?;
+            bail!("[state sync] {}", e);
+        }
+
+        let target_epoch = request.target.ledger_info().epoch();
+        let trusted_epoch = self.local_state.epoch();
+        if target_epoch < trusted_epoch {
+            // the node moved past the target, which can't be verified against the trusted epoch
+            counters::STALE_SYNC_TARGET_COUNT.inc();
+            if self.config.accept_committed_stale_targets && target_version < local_li_version {
+                return Self::send_sync_req_callback(request, Ok(()));
+            }
+            Self::send_sync_req_callback(
+                request,
+                Err(StateSyncError::StaleTarget {
+                    target_epoch,
+                    trusted_epoch,
+                }),
+            )?;
+            bail!(
+                "[state sync] Sync request for epoch {} < trusted epoch {}",
+                target_epoch,
+                trusted_epoch
+            );
+        }
+
+        if target_version < local_li_version {
+            Self::send_sync_req_callback(
+                request,
+                Err(StateSyncError::TargetBehindCommitted {
+                    target_version,
+                    committed_version: local_li_version,
+                }),
+            )?;
+            bail!(
+                "[state sync] Sync request for version {} < known version {}",
+                target_version,
+                local_li_version,
+            );
+        }
+
+        self.epoch_catch_up
+            .update_target(&request.target, self.time_provider.now());
+        self.sync_request_accepted_tst = Some(self.time_provider.now());
+        self.epoch_changes_in_session = 0;
+        if let Some(previous) = self.sync_request.replace(request) {
+            if let Err(e) = Self::send_sync_req_callback(previous, Err(StateSyncError::Replaced)) {
+                error!(
+                    LogSchema::event_log(LogEntry::SyncRequest, LogEvent::CallbackFail).error(&e)
+                );
+            }
+        }
+        self.update_sync_lifecycle();
+        self.send_chunk_request(
+            self.local_state.highest_version_in_local_storage(),
+            self.local_state.epoch(),
+        )
+    }
+
+    /// Syncs to the given version, without a target LI: the chunks are requested to the highest
+    /// LIs available, across epochs, until a committed LI reaches the version.
+    /// In case there has been another pending version request it's going to be failed.
+    /// The caller will be notified about request completion via request.callback oneshot, as
+    /// soon as the version is committed, which may be right away.
+    fn request_sync_to_version(&mut self, request: VersionSyncRequest) -> Result<()> {
+        if self.config.serve_only {
+            Self::send_version_sync_req_callback(request, Err(StateSyncError::ServeOnly))?;
+            bail!("[state sync] Sync request to version on a serve-only node");
+        }
+        self.sync_state_with_local_storage()?;
+        let committed_version = self.local_state.highest_local_li.ledger_info().version();
+        debug!(
+            LogSchema::event_log(LogEntry::SyncRequest, LogEvent::Received)
+                .target_version(request.version)
+                .local_li_version(committed_version)
+        );
+        if !self.is_initialized() {
+            Self::send_version_sync_req_callback(request, Err(StateSyncError::NotInitialized))?;
+            bail!("[state sync] Sync request to version but initialization is not complete!");
+        }
+        if self.quiesced {
+            Self::send_version_sync_req_callback(request, Err(StateSyncError::Quiesced))?;
+            bail!("[state sync] Sync request to version while quiesced");
+        }
+        if request.version <= committed_version {
+            return Self::send_version_sync_req_callback(request, Ok(()));
+        }
+        if let Err(e) = self.check_sync_ceiling(request.version) {
+            Self::send_version_sync_req_callback(request, Err(e.clone()))?;
+            bail!("[state sync] {}", e);
+        }
+
+        if let Some(previous) = self.version_sync_request.replace(request) {
+            Self::send_version_sync_req_callback(previous, Err(StateSyncError::Replaced))?;
+        }
+        self.update_sync_lifecycle();
         self.send_chunk_request(
             self.local_state.highest_version_in_local_storage(),
             self.local_state.epoch(),
         )
     }
 
+    /// Cancels the current sync request if it's of the given generation.
+    /// A cancel racing with a newer sync request is stale: it's ignored, so that it doesn't
+    /// cancel a sync request it wasn't issued for.
+    fn cancel_sync(&mut self, generation: u64) {
+        let is_current = self
+            .sync_request
+            .as_ref()
+            .map_or(false, |request| request.generation == generation);
+        if !is_current {
+            counters::STALE_SYNC_CANCEL_COUNT.inc();
+            debug!(
+                LogSchema::event_log(LogEntry::SyncRequest, LogEvent::StaleCancel),
+                "ignoring cancel of sync request generation {}", generation
+            );
+            return;
+        }
+        if let Some(sync_request) = self.sync_request.take() {
+            counters::SYNC_REQUEST_RESULT
+                .with_label_values(&[counters::CANCEL_LABEL])
+                .inc();
+            info!(
+                LogSchema::event_log(LogEntry::SyncRequest, LogEvent::Cancelled)
+                    .target_version(sync_request.target.ledger_info().version())
+            );
+            if let Err(e) =
+                Self::send_sync_req_callback(sync_request, Err(StateSyncError::Cancelled))
+            {
+                error!(
+                    LogSchema::event_log(LogEntry::SyncRequest, LogEvent::CallbackFail).error(&e)
+                );
+            }
+        }
+    }
+
+    /// Aborts the sync request once it applied more epoch changes than allowed without reaching
+    /// its target, instead of verifying epoch changes indefinitely.
+    fn check_epoch_changes_in_session(&mut self) -> Result<()> {
+        let max_epoch_changes = match self.config.max_epoch_changes_per_session {
+            Some(max_epoch_changes) => max_epoch_changes,
+            None => return Ok(()),
+        };
+        if self.epoch_changes_in_session <= max_epoch_changes {
+            return Ok(());
+        }
+        if let Some(sync_request) = self.sync_request.take() {
+            counters::SYNC_REQUEST_RESULT
+                .with_label_values(&[counters::EPOCH_CHANGES_EXCEEDED_LABEL])
+                .inc();
+            warn!(
+                LogSchema::event_log(LogEntry::SyncRequest, LogEvent::EpochChangesExceeded)
+                    .target_version(sync_request.target.ledger_info().version())
+                    .local_epoch(self.local_state.epoch()),
+                "aborting the sync request after {} epoch changes", self.epoch_changes_in_session
+            );
+            Self::send_sync_req_callback(
+                sync_request,
+                Err(StateSyncError::TooManyEpochChanges {
+                    epoch_changes: self.epoch_changes_in_session,
+                    max_epoch_changes,
+                }),
+            )?;
+        }
+        Ok(())
+    }
+
     /// The function is called after new txns have been applied to the local storage.
     /// As a result it might:
     /// 1) help remote subscribers with long poll requests, 2) finish local sync request
+    /// Returns whether a commit msg from consensus doesn't commit any version beyond the previous
+    /// commit msg, e.g. because consensus retried it. Consensus waits for the ACK of a commit msg
+    /// before committing the next block, so every new commit msg advances the committed version.
+    fn is_duplicate_commit(&mut self) -> bool {
+        if self.sync_state_with_local_storage().is_err() {
+            // let the commit be processed as usual, which reports the failure
+            return false;
+        }
+        self.last_consensus_commit.map(|(version, _)| version)
+            == Some(self.local_state.highest_local_li.ledger_info().version())
+    }
+
+    /// ACKs a duplicate commit msg to consensus without notifying mempool and the reconfig
+    /// subscribers again, as they were notified by the original commit msg
+    fn ack_duplicate_commit(&mut self, callback: oneshot::Sender<Result<CommitResponse>>) {
+        counters::DUPLICATE_COMMIT_COUNT.inc();
+        debug!(
+            LogSchema::event_log(LogEntry::ConsensusCommit, LogEvent::DuplicateCommit)
+                .local_li_version(self.local_state.highest_local_li.ledger_info().version()),
+            "ignoring duplicate commit msg from consensus"
+        );
+        if callback.send(Ok(CommitResponse::success())).is_err() {
+            counters::COMMIT_FLOW_FAIL
+                .with_label_values(&[counters::CONSENSUS_LABEL])
+                .inc();
+            error!(
+                LogSchema::new(LogEntry::CommitFlow),
+                "failed to send commit ACK to consensus"
+            );
+        }
+    }
+
+    /// Processes a commit from consensus and publishes its reconfiguration events. The
+    /// subscriptions notified are sent to `outcome_callback`, if any.
+    async fn process_consensus_commit(
+        &mut self,
+        txns: Vec<Transaction>,
+        events: Vec<ContractEvent>,
+        callback: oneshot::Sender<Result<CommitResponse>>,
+        outcome_callback: Option<oneshot::Sender<Result<CommitOutcome>>>,
+    ) {
+        if self.config.ignore_duplicate_commits && self.is_duplicate_commit() {
+            self.ack_duplicate_commit(callback);
+            if let Some(outcome_callback) = outcome_callback {
+                if outcome_callback.send(Ok(CommitOutcome::default())).is_err() {
+                    error!("[state sync] failed to send commit outcome");
+                }
+            }
+            return;
+        }
+        {
+            let _timer = counters::PROCESS_COORDINATOR_MSG_LATENCY
+                .with_label_values(&[counters::COMMIT_MSG_LABEL])
+                .start_timer();
+            if let Err(e) = self.process_commit(txns, Some(callback), None).await {
+                counters::CONSENSUS_COMMIT_FAIL_COUNT.inc();
+                error!(
+                    LogSchema::event_log(LogEntry::ConsensusCommit, LogEvent::PostCommitFail)
+                        .error(&e)
+                );
+            }
+        }
+        let committed_li = self.local_state.highest_local_li.ledger_info();
+        let (epoch, version) = (committed_li.epoch(), committed_li.version());
+        self.last_consensus_commit = Some((version, committed_li.transaction_accumulator_hash()));
+        self.notify_reconfig_subscribers(epoch, version, events.clone());
+        let outcome = self.executor_proxy.publish_on_chain_config_updates(events);
+        if let Err(e) = &outcome {
+            counters::RECONFIG_PUBLISH_COUNT
+                .with_label_values(&[counters::FAIL_LABEL])
+                .inc();
+            error!(LogSchema::event_log(LogEntry::Reconfig, LogEvent::Fail).error(e));
+        }
+        if let Some(outcome_callback) = outcome_callback {
+            if outcome_callback.send(outcome).is_err() {
+                error!("[state sync] failed to send commit outcome");
+            }
+        }
+    }
+
     async fn process_commit(
         &mut self,
         transactions: Vec<Transaction>,
@@ -456,6 +1725,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         // in case the performance implications of re-syncing upon every commit are high,
         // it's possible to manage some of the highest known versions in memory.
         self.sync_state_with_local_storage()?;
+        self.last_progress_tst = self.time_provider.now();
         let synced_version = self.local_state.highest_version_in_local_storage();
         let committed_version = self.local_state.highest_local_li.ledger_info().version();
         let local_epoch = self.local_state.epoch();
@@ -503,8 +1773,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             callback,
         };
         let mut mempool_channel = self.state_sync_to_mempool_sender.clone();
-        let mut msg = "";
-        if let Err(e) = mempool_channel.try_send(req) {
+        let commit_error = if let Err(e) = mempool_channel.try_send(req) {
             error!(
                 LogSchema::new(LogEntry::CommitFlow).error(&e.into()),
                 "failed to notify mempool of commit"
@@ -512,26 +1781,33 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             counters::COMMIT_FLOW_FAIL
                 .with_label_values(&[counters::TO_MEMPOOL_LABEL])
                 .inc();
-            msg = "state sync failed to send commit notif to shared mempool";
-        } else if let Err(e) = timeout(Duration::from_secs(5), callback_rcv).await {
-            error!(
-                LogSchema::new(LogEntry::CommitFlow).error(&e.into()),
-                "did not receive ACK for commit notification sent to mempool"
-            );
-            counters::COMMIT_FLOW_FAIL
-                .with_label_values(&[counters::FROM_MEMPOOL_LABEL])
-                .inc();
-            msg = "state sync did not receive ACK for commit notification sent to mempool";
-        }
+            Some(CommitError::MempoolNotificationFailed)
+        } else {
+            let commit_error = match timeout(self.mempool_ack_timeout, callback_rcv).await {
+                Err(_) => Some(CommitError::MempoolAckTimeout),
+                Ok(Err(_)) => Some(CommitError::MempoolAckCanceled),
+                Ok(Ok(Err(e))) => Some(CommitError::MempoolCommitFailed(e.to_string())),
+                Ok(Ok(Ok(response))) => response.result.err(),
+            };
+            if let Some(error) = commit_error.as_ref() {
+                error!(
+                    LogSchema::new(LogEntry::CommitFlow).error(&error.clone().into()),
+                    "mempool didn't acknowledge the commit notification"
+                );
+                counters::COMMIT_FLOW_FAIL
+                    .with_label_values(&[counters::FROM_MEMPOOL_LABEL])
+                    .inc();
+            }
+            commit_error
+        };
 
         if let Some(cb) = commit_callback {
             // send back ACK to consensus
-            if cb
-                .send(Ok(CommitResponse {
-                    msg: msg.to_string(),
-                }))
-                .is_err()
-            {
+            let response = match commit_error {
+                Some(error) => CommitResponse::failure(error),
+                None => CommitResponse::success(),
+            };
+            if cb.send(Ok(response)).is_err() {
                 counters::COMMIT_FLOW_FAIL
                     .with_label_values(&[counters::CONSENSUS_LABEL])
                     .inc();
@@ -549,24 +1825,48 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         }
 
         if let Some(mut req) = self.sync_request.as_mut() {
-            req.last_progress_tst = SystemTime::now();
+            req.last_progress_tst = self.time_provider.now();
+            if chunk_sender.is_some() {
+                if let Some(accepted_tst) = self.sync_request_accepted_tst.take() {
+                    if let Ok(duration) = req.last_progress_tst.duration_since(accepted_tst) {
+                        counters::TIME_TO_FIRST_CHUNK.observe(duration.as_secs_f64());
+                    }
+                }
+            }
         }
         let sync_request_complete = match self.sync_request.as_ref() {
             Some(sync_req) => {
                 // Each `ChunkResponse` is verified to make sure it never goes beyond the requested
-                // target version, hence, the local version should never go beyond sync req target.
+                // target version (unless stronger targets are accepted), hence, the local version
+                // should never go beyond sync req target.
                 let sync_target_version = sync_req.target.ledger_info().version();
                 ensure!(
-                    synced_version <= sync_target_version,
+                    self.config.accept_stronger_targets || synced_version <= sync_target_version,
                     "local version {} is beyond sync req target {}",
                     synced_version,
                     sync_target_version
                 );
-                sync_target_version == synced_version
+                sync_target_version <= synced_version
             }
             None => false,
         };
 
+        if let Some(mut req) = self.version_sync_request.as_mut() {
+            req.last_progress_tst = self.time_provider.now();
+        }
+        let version_sync_request_complete =
+            self.version_sync_request.as_ref().map_or(false, |req| {
+                req.version <= committed_version || self.reached_sync_ceiling()
+            });
+        if version_sync_request_complete {
+            counters::SYNC_REQUEST_RESULT
+                .with_label_values(&[counters::COMPLETE_LABEL])
+                .inc();
+            if let Some(request) = self.version_sync_request.take() {
+                Self::send_version_sync_req_callback(request, Ok(()))?;
+            }
+        }
+
         if sync_request_complete {
             debug!(
                 LogSchema::event_log(LogEntry::SyncRequest, LogEvent::Complete)
@@ -580,12 +1880,34 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             if let Some(sync_request) = self.sync_request.take() {
                 Self::send_sync_req_callback(sync_request, Ok(()))?;
             }
+        } else {
+            self.check_epoch_changes_in_session()?;
         }
 
-        let initialization_complete = self
-            .initialization_listener
-            .as_ref()
-            .map_or(false, |_| self.is_initialized());
+        if !self.serving_enabled && self.is_initialized() {
+            self.serving_enabled = true;
+            counters::SERVING_ENABLED.set(1);
+            info!(
+                LogSchema::event_log(LogEntry::ProcessChunkRequest, LogEvent::ServingEnabled)
+                    .local_li_version(committed_version),
+                "[state sync] bootstrapped to the waypoint, serving chunk requests"
+            );
+        }
+
+        if self.is_initialized() {
+            // closes the progress channel
+            self.init_progress_sender = None;
+        } else if self.init_progress_sender.is_some() {
+            self.chunks_since_init_progress += 1;
+            if self.chunks_since_init_progress >= self.config.init_progress_interval_chunks {
+                self.chunks_since_init_progress = 0;
+                self.report_init_progress();
+            }
+        }
+
+        let initialization_complete = self.initialization_listener.as_ref().map_or(false, |_| {
+            self.is_initialized() || self.reached_sync_ceiling()
+        });
         if initialization_complete {
             info!(LogSchema::event_log(LogEntry::Waypoint, LogEvent::Complete)
                 .local_li_version(committed_version)
@@ -595,6 +1917,18 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 Self::send_initialization_callback(listener, Ok(()))?;
             }
         }
+
+        self.update_sync_lifecycle();
+
+        if self.quiesce_listener.is_some() && synced_version == committed_version {
+            info!(LogSchema::event_log(LogEntry::Quiesce, LogEvent::Complete)
+                .local_li_version(committed_version)
+                .local_synced_version(synced_version)
+                .local_epoch(local_epoch));
+            if let Some(listener) = self.quiesce_listener.take() {
+                Self::send_quiesce_callback(listener, Ok(()))?;
+            }
+        }
         Ok(())
     }
 
@@ -610,6 +1944,243 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         }
     }
 
+    fn health_check(&mut self, callback: oneshot::Sender<SyncHealth>) {
+        let since_last_progress = self
+            .time_provider
+            .now()
+            .duration_since(self.last_progress_tst)
+            .unwrap_or_default();
+        let is_progressing = self.sync_lifecycle.idle_at_tip
+            || since_last_progress <= Duration::from_millis(self.config.health_check_staleness_ms);
+        let health = SyncHealth {
+            committed_version: self.local_state.highest_local_li.ledger_info().version(),
+            is_idle_at_tip: self.sync_lifecycle.idle_at_tip,
+            tip_estimate: self.request_manager.tip_estimate(),
+            peer_health: self.request_manager.peer_health(),
+            is_progressing,
+            seconds_since_last_progress: since_last_progress.as_secs(),
+        };
+        if callback.send(health).is_err() {
+            error!("[state sync] failed to send health");
+        }
+    }
+
+    fn get_sync_progress(&self, callback: oneshot::Sender<SyncProgress>) {
+        let progress = SyncProgress {
+            committed_version: self.local_state.highest_local_li.ledger_info().version(),
+            synced_version: self.local_state.highest_version_in_local_storage(),
+            target_version: self
+                .sync_request
+                .as_ref()
+                .map(|sync_req| sync_req.target.ledger_info().version())
+                .or_else(|| self.version_sync_request.as_ref().map(|req| req.version)),
+            trusted_epoch: self.local_state.trusted_epoch.epoch,
+        };
+        if callback.send(progress).is_err() {
+            error!("[state sync] failed to send sync progress");
+        }
+    }
+
+    /// Builds the epoch change proof that lets a verifier trusting `start_epoch` advance to the
+    /// trusted epoch, out of the epoch-ending LIs of the epochs in between.
+    fn get_epoch_change_proof(&self, start_epoch: u64) -> Result<EpochChangeProof> {
+        let trusted_epoch = self.local_state.trusted_epoch.epoch;
+        ensure!(
+            start_epoch < trusted_epoch,
+            "[state sync] no epoch change proof from epoch {}: trusted epoch is {}",
+            start_epoch,
+            trusted_epoch
+        );
+        let ledger_info_with_sigs = (start_epoch..trusted_epoch)
+            .map(|epoch| self.executor_proxy.get_epoch_proof(epoch))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(EpochChangeProof::new(ledger_info_with_sigs, false))
+    }
+
+    /// Returns whether the node has nothing left to sync: it is initialized, has no sync request
+    /// in progress, and has committed everything it synced and every LI it knows of. A node that
+    /// reached the sync ceiling has nothing left to sync either.
+    fn is_at_tip(&self) -> bool {
+        if self.reached_sync_ceiling() {
+            return true;
+        }
+        let committed_version = self.local_state.highest_local_li.ledger_info().version();
+        self.is_initialized()
+            && self.sync_request.is_none()
+            && self.version_sync_request.is_none()
+            && !self.has_uncommitted_txns()
+            && self
+                .pending_ledger_infos
+                .highest_version()
+                .map_or(true, |version| version <= committed_version)
+    }
+
+    /// Advances the waypoint trusted on restart to the latest epoch-ending LI committed locally,
+    /// if it's beyond the current waypoint. Only committed LIs, which were verified before they
+    /// were committed, are advanced to.
+    fn check_waypoint_advancement(&mut self) {
+        let file = match self.waypoint_advancement.due(self.time_provider.now()) {
+            Some(file) => file,
+            None => return,
+        };
+        if !self.is_initialized() {
+            return;
+        }
+        // the trusted epoch starts right after the latest epoch-ending LI
+        let last_ended_epoch = match self.local_state.trusted_epoch.epoch.checked_sub(1) {
+            Some(epoch) => epoch,
+            None => return,
+        };
+        match self.advance_waypoint(&file, last_ended_epoch) {
+            Ok(Some(waypoint)) => {
+                counters::WAYPOINT_ADVANCEMENT_COUNT.inc();
+                info!(
+                    LogSchema::event_log(LogEntry::Waypoint, LogEvent::Advanced).waypoint(waypoint),
+                    "advanced the waypoint from version {} to version {}",
+                    self.waypoint.version(),
+                    waypoint.version()
+                );
+                self.waypoint = waypoint;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!(LogSchema::event_log(LogEntry::Waypoint, LogEvent::Fail).error(&e));
+            }
+        }
+    }
+
+    /// Broadcasts the highest committed LI to a random subset of the connected peers, if a
+    /// broadcast is due. Nodes that are syncing don't broadcast, as their LI is about to change
+    /// and their peers know of more recent ones anyway.
+    fn check_li_broadcast(&mut self) {
+        if !self.li_broadcast.due(self.time_provider.now()) || !self.is_at_tip() {
+            return;
+        }
+        let peers: Vec<_> = self
+            .request_manager
+            .connected_peers()
+            .choose_multiple(&mut thread_rng(), self.config.proactive_li_broadcast_peers)
+            .cloned()
+            .collect();
+        for peer in peers {
+            let msg = StateSynchronizerMsg::CommittedLedgerInfo(Box::new(
+                self.local_state.highest_local_li.clone(),
+            ));
+            let network_sender = match self.network_senders.get_mut(&peer.network_id()) {
+                Some(network_sender) => network_sender,
+                None => continue,
+            };
+            // counted before it's sent, as sending consumes it
+            self.bandwidth_usage
+                .record(&peer, &msg, true, self.time_provider.now());
+            let send_result_label = match network_sender.send_to(peer.peer_id(), msg) {
+                Ok(()) => counters::SEND_SUCCESS_LABEL,
+                Err(e) => {
+                    debug!(
+                        LogSchema::event_log(LogEntry::LiBroadcast, LogEvent::NetworkSendError)
+                            .peer(&peer)
+                            .error(&e.into())
+                    );
+                    counters::SEND_FAIL_LABEL
+                }
+            };
+            counters::LI_BROADCASTS
+                .with_label_values(&[&peer.raw_network_id().to_string(), send_result_label])
+                .inc();
+        }
+    }
+
+    /// Persists the waypoint of the LI ending `epoch` in `file` if it's beyond the current
+    /// waypoint. Returns the persisted waypoint, if any.
+    fn advance_waypoint(&self, file: &Path, epoch: u64) -> Result<Option<Waypoint>> {
+        let epoch_ending_li = self.executor_proxy.get_epoch_proof(epoch)?;
+        if epoch_ending_li.ledger_info().version() <= self.waypoint.version() {
+            return Ok(None);
+        }
+        let waypoint = Waypoint::new_epoch_boundary(epoch_ending_li.ledger_info())?;
+        WaypointAdvancement::persist(file, &waypoint)?;
+        Ok(Some(waypoint))
+    }
+
+    /// Takes the recovery steps due for a node that made no progress for too long, e.g. because
+    /// all its upstream peers are unresponsive or serve invalid chunks.
+    fn check_stall_escalation(&mut self) {
+        let known_version = self.known_version();
+        // consensus is blocked on its sync request
+        let critical = self.is_initialized() && self.sync_request.is_some();
+        let now = self.time_provider.now();
+        // a paused node isn't expected to make progress, like a node at the tip, and neither is a
+        // node that only verifies chunks
+        let steps = self.stall_escalation.update(
+            known_version,
+            self.is_at_tip() || self.paused || self.config.verification_only,
+            critical,
+            now,
+        );
+        for (step, bypassed) in steps {
+            if bypassed {
+                counters::CRITICAL_SYNC_THROTTLE_BYPASSES
+                    .with_label_values(&[counters::STALL_ESCALATION_LABEL])
+                    .inc();
+            }
+            let (label, event) = match step {
+                EscalationStep::WidenPeers => (counters::WIDEN_PEERS_LABEL, LogEvent::WidenPeers),
+                EscalationStep::RefreshPeers => {
+                    (counters::REFRESH_PEERS_LABEL, LogEvent::RefreshPeers)
+                }
+                EscalationStep::SwitchNetworks => {
+                    (counters::SWITCH_NETWORKS_LABEL, LogEvent::SwitchNetworks)
+                }
+                EscalationStep::Alert => (counters::ALERT_LABEL, LogEvent::Alert),
+            };
+            counters::STALL_ESCALATION_COUNT
+                .with_label_values(&[label])
+                .inc();
+            let log = LogSchema::event_log(LogEntry::StallEscalation, event)
+                .local_synced_version(known_version);
+            let stalled_for = self.stall_escalation.stalled_for(now);
+            match step {
+                EscalationStep::WidenPeers => self.request_manager.reset_peer_scores(),
+                EscalationStep::RefreshPeers => self.request_manager.clear_requests(),
+                EscalationStep::SwitchNetworks => self.request_manager.multicast_to_all_networks(),
+                EscalationStep::Alert => {
+                    error!(
+                        log,
+                        "CRITICAL: no progress beyond version {} in {:?}",
+                        known_version,
+                        stalled_for
+                    );
+                    continue;
+                }
+            }
+            warn!(
+                log,
+                "no progress beyond version {} in {:?}, escalating", known_version, stalled_for
+            );
+        }
+    }
+
+    /// Updates the sync lifecycle with the current state of the node and reports transitions
+    /// between syncing and idle at the tip.
+    fn update_sync_lifecycle(&mut self) {
+        if !self
+            .sync_lifecycle
+            .update(self.is_at_tip(), self.time_provider.now())
+        {
+            return;
+        }
+        let idle_at_tip = self.sync_lifecycle.idle_at_tip;
+        counters::IDLE_AT_TIP.set(idle_at_tip as i64);
+        let event = if idle_at_tip {
+            LogEvent::IdleAtTip
+        } else {
+            LogEvent::Syncing
+        };
+        info!(LogSchema::event_log(LogEntry::SyncLifecycle, event)
+            .local_li_version(self.local_state.highest_local_li.ledger_info().version())
+            .local_synced_version(self.local_state.highest_version_in_local_storage()));
+    }
+
     /// There are two types of ChunkRequests:
     /// 1) Validator chunk requests are for a specific target LI and don't ask for long polling.
     /// 2) FullNode chunk requests don't specify a target LI and can allow long polling.
@@ -628,6 +2199,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             Err(anyhow::anyhow!("Injected error in process_chunk_request"))
         });
         self.sync_state_with_local_storage()?;
+        self.process_peer_advertisement(&peer, request.known_version);
 
         match request.target().clone() {
             TargetType::TargetLedgerInfo(li) => self.process_request_target_li(peer, request, li),
@@ -650,6 +2222,8 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         target_li: LedgerInfoWithSignatures,
     ) -> Result<()> {
         let limit = std::cmp::min(request.limit, self.config.max_chunk_limit);
+        let max_chunk_bytes = self.max_chunk_bytes(&request);
+        let max_chunks = self.max_chunks_in_response(&request);
         let response_li = self.choose_response_li(request.current_epoch, Some(target_li))?;
         // In case known_version is lower than the requested ledger info an empty response might be
         // sent.
@@ -658,6 +2232,8 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             request.known_version,
             ResponseLedgerInfo::VerifiableLedgerInfo(response_li),
             limit,
+            max_chunk_bytes,
+            max_chunks,
         )
     }
 
@@ -672,19 +2248,26 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         timeout_ms: u64,
     ) -> Result<()> {
         let limit = std::cmp::min(request.limit, self.config.max_chunk_limit);
+        let max_chunk_bytes = self.max_chunk_bytes(&request);
+        let max_chunks = self.max_chunks_in_response(&request);
         let timeout = std::cmp::min(timeout_ms, self.config.max_timeout_ms);
 
         // If there is nothing a node can help with, and the request supports long polling,
         // add it to the subscriptions.
         let local_version = self.local_state.highest_local_li.ledger_info().version();
         if local_version <= request.known_version && timeout > 0 {
-            let expiration_time = SystemTime::now().checked_add(Duration::from_millis(timeout));
+            let expiration_time = self
+                .time_provider
+                .now()
+                .checked_add(Duration::from_millis(timeout));
             if let Some(time) = expiration_time {
                 let request_info = PendingRequestInfo {
                     expiration_time: time,
                     known_version: request.known_version,
                     request_epoch: request.current_epoch,
                     limit,
+                    max_chunk_bytes,
+                    max_chunks,
                 };
                 self.subscriptions.insert(peer, request_info);
             }
@@ -697,7 +2280,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         let highest_li = if target_li.ledger_info().version() < local_version
             && target_li.ledger_info().epoch() == self.local_state.epoch()
         {
-            Some(self.local_state.highest_local_li.clone())
+            self.advertised_highest_li(&peer)
         } else {
             None
         };
@@ -710,9 +2293,33 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 highest_li,
             },
             limit,
+            max_chunk_bytes,
+            max_chunks,
         )
     }
 
+    /// Returns the highest local LI if the advertisement policy allows advertising it to `peer`.
+    fn advertised_highest_li(&self, peer: &PeerNetworkId) -> Option<LedgerInfoWithSignatures> {
+        let network = peer.raw_network_id();
+        let advertise = self
+            .config
+            .highest_version_advertisement
+            .allows(self.role, &network);
+        let decision_label = if advertise {
+            counters::ADVERTISE_LABEL
+        } else {
+            counters::WITHHOLD_LABEL
+        };
+        counters::HIGHEST_VERSION_ADVERTISEMENT
+            .with_label_values(&[&network.to_string(), decision_label])
+            .inc();
+        if advertise {
+            Some(self.local_state.highest_local_li.clone())
+        } else {
+            None
+        }
+    }
+
     fn process_request_waypoint(
         &mut self,
         peer: PeerNetworkId,
@@ -720,6 +2327,8 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         waypoint_version: Version,
     ) -> Result<()> {
         let mut limit = std::cmp::min(request.limit, self.config.max_chunk_limit);
+        let max_chunk_bytes = self.max_chunk_bytes(&request);
+        let max_chunks = self.max_chunks_in_response(&request);
         ensure!(
             self.local_state.highest_local_li.ledger_info().version() >= waypoint_version,
             "Local version {} < requested waypoint version {}.",
@@ -763,6 +2372,31 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 end_of_epoch_li,
             },
             limit,
+            max_chunk_bytes,
+            max_chunks,
+        )
+    }
+
+    /// Returns the max serialized size of the transactions of the chunk response to `request`,
+    /// i.e. the min of the own and the requested limit, 0 if both are unbounded.
+    fn max_chunk_bytes(&self, request: &GetChunkRequest) -> u64 {
+        let own_limit = self.config.max_chunk_bytes as u64;
+        match (own_limit, request.max_chunk_bytes) {
+            (0, requested_limit) => requested_limit,
+            (own_limit, 0) => own_limit,
+            (own_limit, requested_limit) => std::cmp::min(own_limit, requested_limit),
+        }
+    }
+
+    /// Returns the max number of chunks of the chunk response to `request`, i.e. the min of the own
+    /// and the requested number, at least one.
+    fn max_chunks_in_response(&self, request: &GetChunkRequest) -> u8 {
+        std::cmp::max(
+            1,
+            std::cmp::min(
+                self.config.max_chunks_in_response,
+                request.max_chunks_in_response,
+            ),
         )
     }
 
@@ -776,15 +2410,19 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         known_version: u64,
         response_li: ResponseLedgerInfo,
         limit: u64,
+        max_chunk_bytes: u64,
+        max_chunks: u8,
     ) -> Result<()> {
-        let txns = self
-            .executor_proxy
-            .get_chunk(known_version, limit, response_li.version())?;
-        let chunk_response = GetChunkResponse::new(response_li, txns);
+        let mut chunk_response =
+            self.build_chunk_response(known_version, response_li, limit, max_chunk_bytes)?;
+        self.pack_additional_chunks(&mut chunk_response, max_chunks, limit, max_chunk_bytes)?;
         let log = LogSchema::event_log(LogEntry::ProcessChunkRequest, LogEvent::DeliverChunk)
             .chunk_resp(&chunk_response)
             .peer(&peer);
         let msg = StateSynchronizerMsg::GetChunkResponse(Box::new(chunk_response));
+        // counted before it's sent, as sending consumes it
+        self.bandwidth_usage
+            .record(&peer, &msg, true, self.time_provider.now());
 
         let network_sender = self
             .network_senders
@@ -811,8 +2449,153 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         })
     }
 
+    /// Builds the chunk response for the given request params. The chunk stops at the last
+    /// transaction that fits into `max_chunk_bytes`, if bounded, but has at least one transaction.
+    /// If the serialized response exceeds `max_chunk_response_bytes`, which the network would
+    /// reject, the chunk size is halved until the response fits or the chunk is a single
+    /// transaction.
+    /// Chunks recently served are served from the chunk response cache, if enabled.
+    fn build_chunk_response(
+        &mut self,
+        known_version: u64,
+        response_li: ResponseLedgerInfo,
+        limit: u64,
+        max_chunk_bytes: u64,
+    ) -> Result<GetChunkResponse> {
+        // only committed transactions are served: synced transactions beyond the highest local LI
+        // aren't proven by any local LI yet
+        let committed_version = self.local_state.highest_local_li.ledger_info().version();
+        if response_li.version() > committed_version {
+            counters::SERVE_BEYOND_COMMITTED_COUNT.inc();
+            bail!(
+                "[state sync] Chunk response proven to version {} beyond the committed version {}",
+                response_li.version(),
+                committed_version
+            );
+        }
+        let cache_key = (known_version, limit, response_li.epoch(), max_chunk_bytes);
+        if let Some(txns) = self
+            .chunk_response_cache
+            .get(&cache_key, response_li.version())
+        {
+            // the response LI may differ in size from the one the chunk was cached with
+            let chunk_response = GetChunkResponse::new(response_li.clone(), txns);
+            if lcs::to_bytes(&chunk_response)?.len() <= self.config.max_chunk_response_bytes {
+                return Ok(chunk_response);
+            }
+        }
+        let mut limit = limit;
+        loop {
+            let txns =
+                self.executor_proxy
+                    .get_chunk(known_version, limit, response_li.version())?;
+            if max_chunk_bytes > 0 {
+                let num_txns = Self::num_txns_within_bytes(&txns, max_chunk_bytes)?;
+                if num_txns < txns.len() {
+                    // the proof has to be relative to the shorter chunk
+                    limit = num_txns as u64;
+                    counters::CHUNK_RESPONSE_BYTE_LIMITED_COUNT.inc();
+                    continue;
+                }
+            }
+            let chunk_response = GetChunkResponse::new(response_li.clone(), txns);
+            let msg_size = lcs::to_bytes(&chunk_response)?.len();
+            if msg_size <= self.config.max_chunk_response_bytes {
+                self.chunk_response_cache.insert(
+                    cache_key,
+                    response_li.version(),
+                    chunk_response.txn_list_with_proof.clone(),
+                    msg_size,
+                );
+                return Ok(chunk_response);
+            }
+            ensure!(
+                limit > 1,
+                "[state sync] Chunk response of {} bytes for a single transaction exceeds max size {}",
+                msg_size,
+                self.config.max_chunk_response_bytes
+            );
+
+            limit /= 2;
+            counters::CHUNK_RESPONSE_DOWNSIZE_COUNT.inc();
+            counters::CHUNK_RESPONSE_DOWNSIZED_LIMIT.set(limit as i64);
+            debug!(
+                LogSchema::event_log(LogEntry::ProcessChunkRequest, LogEvent::Downsize)
+                    .version(known_version),
+                "Chunk response of {} bytes exceeds max size {}, downsizing to {} transactions",
+                msg_size,
+                self.config.max_chunk_response_bytes,
+                limit
+            );
+        }
+    }
+
+    /// Packs up to `max_chunks - 1` chunks following the one of `response` into it, while the
+    /// serialized response fits into `max_chunk_response_bytes`. The chunks stop at the version of
+    /// the response LI, or at the end of the epoch for waypoint responses, so that they never
+    /// cross an epoch boundary.
+    fn pack_additional_chunks(
+        &mut self,
+        response: &mut GetChunkResponse,
+        max_chunks: u8,
+        limit: u64,
+        max_chunk_bytes: u64,
+    ) -> Result<()> {
+        let end_version = match &response.response_li {
+            ResponseLedgerInfo::LedgerInfoForWaypoint {
+                end_of_epoch_li: Some(li),
+                ..
+            } => li.ledger_info().version(),
+            response_li => response_li.version(),
+        };
+        let mut known_version = match response.txn_list_with_proof.first_transaction_version {
+            Some(first_version) => first_version + response.txn_list_with_proof.len() as u64 - 1,
+            None => return Ok(()),
+        };
+        for _ in 1..max_chunks {
+            if known_version >= end_version {
+                break;
+            }
+            let chunk_limit = std::cmp::min(limit, end_version - known_version);
+            // the chunks already packed are served even if the next one can't be built
+            let chunk = match self.build_chunk_response(
+                known_version,
+                response.response_li.clone(),
+                chunk_limit,
+                max_chunk_bytes,
+            ) {
+                Ok(chunk_response) => chunk_response.txn_list_with_proof,
+                Err(_) => break,
+            };
+            if chunk.is_empty() {
+                break;
+            }
+            known_version += chunk.len() as u64;
+            response.additional_chunks.push(chunk);
+            if lcs::to_bytes(&*response)?.len() > self.config.max_chunk_response_bytes {
+                response.additional_chunks.pop();
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of the first transactions of the chunk whose serialized size fits into
+    /// `max_bytes`, at least one if the chunk isn't empty.
+    fn num_txns_within_bytes(txns: &TransactionListWithProof, max_bytes: u64) -> Result<usize> {
+        let mut chunk_bytes = 0;
+        for (num_txns, txn) in txns.transactions.iter().enumerate() {
+            chunk_bytes += lcs::to_bytes(txn)?.len() as u64;
+            if chunk_bytes > max_bytes {
+                return Ok(std::cmp::max(num_txns, 1));
+            }
+        }
+        Ok(txns.transactions.len())
+    }
+
     /// The choice of the LedgerInfo in the response follows the following logic:
-    /// * response LI is either the requested target or the highest local LI if target is None.
+    /// * response LI is either the requested target or the highest local LI if target is None or
+    /// beyond the highest local LI, as the transactions beyond it aren't proven by a local LI.
     /// * if the response LI would not belong to `request_epoch`, change
     /// the response LI to the LI that is terminating `request_epoch`.
     fn choose_response_li(
@@ -820,7 +2603,10 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         request_epoch: u64,
         target: Option<LedgerInfoWithSignatures>,
     ) -> Result<LedgerInfoWithSignatures> {
-        let mut target_li = target.unwrap_or_else(|| self.local_state.highest_local_li.clone());
+        let committed_version = self.local_state.highest_local_li.ledger_info().version();
+        let mut target_li = target
+            .filter(|li| li.ledger_info().version() <= committed_version)
+            .unwrap_or_else(|| self.local_state.highest_local_li.clone());
         let target_epoch = target_li.ledger_info().epoch();
         if target_epoch > request_epoch {
             let end_of_epoch_li = self.executor_proxy.get_epoch_proof(request_epoch)?;
@@ -839,6 +2625,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
     /// Chunk response checks performed:
     /// - does chunk contain no transactions?
     /// - does chunk of transactions matches the local state's version?
+    /// - does the number of transactions agree with the chunk's version range?
     /// - verify LIs in chunk response against local state
     /// - execute and commit chunk
     /// Returns error if above chunk response checks fail or chunk was not able to be stored to storage, else
@@ -864,7 +2651,19 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         }
 
         let txn_list_with_proof = response.txn_list_with_proof.clone();
-        let known_version = self.local_state.highest_version_in_local_storage();
+        let known_version = self.known_version();
+        self.request_manager
+            .process_chunk_received(peer, known_version);
+        // the LI the chunk is built relative to, if it's verified against the trusted epoch
+        let served_li = match &response.response_li {
+            ResponseLedgerInfo::VerifiableLedgerInfo(li)
+            | ResponseLedgerInfo::ProgressiveLedgerInfo { target_li: li, .. } => Some(li.clone()),
+            ResponseLedgerInfo::LedgerInfoForWaypoint { .. } => None,
+        };
+        // checked first, as a chunk relative to the LI of another epoch fails the other checks too
+        if let Some(li) = served_li.as_ref().filter(|_| self.is_initialized()) {
+            self.check_epoch_match(peer, li)?;
+        }
         let chunk_start_version =
             txn_list_with_proof
                 .first_transaction_version
@@ -882,9 +2681,87 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 known_version,
             )?;
         }
+        if let Some(ceiling_version) = self.config.sync_ceiling_version {
+            let chunk_end_version = chunk_start_version + txn_list_with_proof.len() as u64 - 1;
+            if chunk_end_version > ceiling_version {
+                counters::SYNC_CEILING_DROPPED_CHUNKS.inc();
+                bail!(
+                    "[state sync] Chunk up to version {} from {:?} is beyond the sync ceiling version {}",
+                    chunk_end_version,
+                    peer,
+                    ceiling_version
+                );
+            }
+        }
+        self.check_chunk_response_size(peer, &response)?;
+        self.check_chunk_version_range(peer, chunk_start_version, &response)?;
+        if let Some(li) = served_li.as_ref() {
+            self.check_equivocation(peer, li);
+        }
+
+        // a chunk extending the commit batch doesn't extend the local accumulator yet: the executor
+        // verifies that it chains with the batch when it verifies the merged chunk against the LI
+        let continuity = if self.commit_batch.len() > 0 {
+            Ok(())
+        } else {
+            self.verify_accumulator_continuity(chunk_start_version, &txn_list_with_proof)
+        };
+        if let Err(e) = continuity {
+            counters::ACCUMULATOR_CONTINUITY_VIOLATION_COUNT
+                .with_label_values(&[
+                    &peer.raw_network_id().to_string(),
+                    &peer.peer_id().to_string(),
+                ])
+                .inc();
+            self.request_manager
+                .update_score(&peer, PeerScoreUpdateType::InvalidChunk);
+            return Err(e);
+        }
+
+        let chunk_size = txn_list_with_proof.len() as u64;
+        self.process_response_li(txn_list_with_proof, response.response_li)
+            .map_err(|e| {
+                self.request_manager
+                    .update_score(peer, PeerScoreUpdateType::InvalidChunk);
+                format_err!("[state sync] failed to apply chunk: {}", e)
+            })?;
+        if let Some(li) = served_li.as_ref() {
+            self.recent_ledger_infos.record(peer, li);
+        }
+
+        counters::STATE_SYNC_CHUNK_SIZE
+            .with_label_values(&[
+                &peer.raw_network_id().to_string(),
+                &peer.peer_id().to_string(),
+            ])
+            .observe(chunk_size as f64);
+        debug!(
+            LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ApplyChunkSuccess),
+            "Applied chunk of size {}. Previous version: {}, new version {}",
+            chunk_size,
+            known_version,
+            known_version + chunk_size
+        );
+
+        // The overall chunk processing duration is calculated starting from the very first attempt
+        // until the commit
+        if let Some(first_attempt_tst) = self.request_manager.get_first_request_time(known_version)
+        {
+            if let Ok(duration) = self.time_provider.now().duration_since(first_attempt_tst) {
+                counters::SYNC_PROGRESS_DURATION.observe_duration(duration);
+                self.sync_rate_stats.record(chunk_size, duration);
+            }
+        }
+        Ok(())
+    }
 
-        let chunk_size = txn_list_with_proof.len() as u64;
-        match response.response_li {
+    /// Verifies the LI of a chunk response and stores its transactions.
+    fn process_response_li(
+        &mut self,
+        txn_list_with_proof: TransactionListWithProof,
+        response_li: ResponseLedgerInfo,
+    ) -> Result<()> {
+        match response_li {
             ResponseLedgerInfo::VerifiableLedgerInfo(li) => {
                 self.process_response_with_verifiable_li(txn_list_with_proof, li, None)
             }
@@ -914,50 +2791,160 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 end_of_epoch_li,
             ),
         }
-        .map_err(|e| {
-            self.request_manager
-                .update_score(peer, PeerScoreUpdateType::InvalidChunk);
-            format_err!("[state sync] failed to apply chunk: {}", e)
-        })?;
+    }
 
-        counters::STATE_SYNC_CHUNK_SIZE
-            .with_label_values(&[
-                &peer.raw_network_id().to_string(),
-                &peer.peer_id().to_string(),
-            ])
-            .observe(chunk_size as f64);
-        debug!(
-            LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ApplyChunkSuccess),
-            "Applied chunk of size {}. Previous version: {}, new version {}",
-            chunk_size,
-            known_version,
-            known_version + chunk_size
-        );
+    /// Applies the chunks of a local chunk source, e.g. pre-downloaded chunks to bootstrap the
+    /// node, before syncing from the network. The chunks are verified like the chunks of the
+    /// network. The source is abandoned at the first chunk that can't be read or applied, e.g. a
+    /// truncated file, and the rest is synced from the network.
+    pub(crate) async fn drain_chunk_source(&mut self, mut chunk_source: Box<dyn ChunkSource>) {
+        self.draining_chunk_source = true;
+        loop {
+            let response = match chunk_source.next_chunk() {
+                Ok(Some(response)) => response,
+                Ok(None) => break,
+                Err(e) => {
+                    counters::CHUNK_SOURCE_COUNT
+                        .with_label_values(&[counters::FAIL_LABEL])
+                        .inc();
+                    error!(
+                        LogSchema::event_log(LogEntry::ChunkSource, LogEvent::Fail).error(&e),
+                        "[state sync] failed to read the chunk source, syncing from the network"
+                    );
+                    break;
+                }
+            };
+            let first_version = response.txn_list_with_proof.first_transaction_version;
+            let new_txns = response.txn_list_with_proof.transactions.clone();
+            match self.apply_local_chunk(response) {
+                Ok(true) => (),
+                // the chunk was synced already, e.g. before a restart
+                Ok(false) => continue,
+                Err(e) => {
+                    counters::CHUNK_SOURCE_COUNT
+                        .with_label_values(&[counters::FAIL_LABEL])
+                        .inc();
+                    error!(
+                        LogSchema::event_log(LogEntry::ChunkSource, LogEvent::Fail).error(&e),
+                        "[state sync] failed to apply a chunk of the chunk source, syncing from the network"
+                    );
+                    break;
+                }
+            }
+            counters::CHUNK_SOURCE_COUNT
+                .with_label_values(&[counters::SUCCESS_LABEL])
+                .inc();
 
-        // The overall chunk processing duration is calculated starting from the very first attempt
-        // until the commit
-        if let Some(first_attempt_tst) = self.request_manager.get_first_request_time(known_version)
-        {
-            if let Ok(duration) = SystemTime::now().duration_since(first_attempt_tst) {
-                counters::SYNC_PROGRESS_DURATION.observe_duration(duration);
+            let new_txns = if self.commit_batch.is_enabled() {
+                if first_version.map_or(false, |version| self.commit_batch.contains(version)) {
+                    continue;
+                }
+                self.commit_batch.take_committed_txns()
+            } else {
+                new_txns
+            };
+            if let Err(e) = self.process_commit(new_txns, None, None).await {
+                error!(
+                    LogSchema::event_log(LogEntry::ChunkSource, LogEvent::PostCommitFail).error(&e)
+                );
             }
         }
-        Ok(())
+        self.draining_chunk_source = false;
+        info!(
+            LogSchema::event_log(LogEntry::ChunkSource, LogEvent::Complete)
+                .local_synced_version(self.known_version()),
+            "[state sync] chunk source drained"
+        );
+    }
+
+    /// Verifies and stores a chunk of the chunk source. Returns false if the chunk was synced
+    /// already.
+    fn apply_local_chunk(&mut self, response: GetChunkResponse) -> Result<bool> {
+        let txn_list_with_proof = response.txn_list_with_proof;
+        let first_version = txn_list_with_proof
+            .first_transaction_version
+            .ok_or_else(|| format_err!("[state sync] Empty chunk in the chunk source"))?;
+        let known_version = self.known_version();
+        if first_version + (txn_list_with_proof.len() as u64) <= known_version + 1 {
+            return Ok(false);
+        }
+        ensure!(
+            first_version == known_version + 1,
+            "[state sync] Chunk of the chunk source starts at version {}, the synced version is {}",
+            first_version,
+            known_version
+        );
+        if self.commit_batch.len() == 0 {
+            self.verify_accumulator_continuity(first_version, &txn_list_with_proof)?;
+        }
+        self.process_response_li(txn_list_with_proof, response.response_li)?;
+        Ok(true)
     }
 
     /// * Verifies and stores chunk in response
     /// * Triggers post-commit actions based on new local state after successful chunk processing in above step
+    /// Applies the chunks of a chunk response in order, each verified independently, stopping at
+    /// the first one that isn't fully applied. The next chunk is only requested once the last
+    /// chunk of the response is applied.
     async fn process_chunk_response(&mut self, peer: &PeerNetworkId, response: GetChunkResponse) {
+        let mut chunks = response.into_chunks().into_iter().peekable();
+        while let Some(chunk) = chunks.next() {
+            self.applying_packed_chunks = chunks.peek().is_some();
+            let applied = self.process_single_chunk_response(peer, chunk).await;
+            self.applying_packed_chunks = false;
+            if !applied {
+                break;
+            }
+        }
+    }
+
+    /// Applies a chunk response carrying a single chunk. Returns whether the chunk was fully
+    /// applied.
+    async fn process_single_chunk_response(
+        &mut self,
+        peer: &PeerNetworkId,
+        response: GetChunkResponse,
+    ) -> bool {
+        if self.config.buffer_future_epoch_chunks
+            && self.is_initialized()
+            && response.response_li.epoch() > self.local_state.trusted_epoch.epoch
+        {
+            self.buffer_future_epoch_chunk(peer.clone(), response);
+            return true;
+        }
+        let received_tst = self.time_provider.now();
+        let ends_epoch = response.ends_epoch();
         let new_txns = response.txn_list_with_proof.transactions.clone();
+        let first_version = response.txn_list_with_proof.first_transaction_version;
+        let advertised_version = response.response_li.highest_version();
+        // a chunk for the next version from an upstream peer is the one that makes progress, as
+        // opposed to old, multicasted or unsolicited ones
+        let applicable = self.request_manager.is_known_upstream_peer(peer)
+            && first_version == Some(self.known_version() + 1);
+        if applicable {
+            self.last_chunk_failure = None;
+        }
         // Part 1: check response, validate and store chunk
         // any errors thrown here should be for detecting actual bad chunks
+        self.partial_chunk_len = None;
         if let Err(e) = self.apply_chunk(peer, response) {
+            // execution failures are recorded as such when they happen
+            if applicable && self.last_chunk_failure.is_none() {
+                self.last_chunk_failure = Some(StallReason::VerificationFailure(e.to_string()));
+            }
+            if applicable && self.config.verification_only {
+                self.verification_report.failed_chunks += 1;
+                self.verification_report.last_failure = Some(e.to_string());
+            }
             // count, log, and exit
-            error!(
-                LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ApplyChunkFail)
-                    .peer(peer)
-                    .error(&e)
-            );
+            if self.error_log_throttle.record(format!("{}: {}", peer, e)) {
+                error!(LogSchema::event_log(
+                    LogEntry::ProcessChunkResponse,
+                    LogEvent::ApplyChunkFail
+                )
+                .peer(peer)
+                .error(&e));
+            }
 
             counters::APPLY_CHUNK_COUNT
                 .with_label_values(&[
@@ -966,7 +2953,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                     counters::FAIL_LABEL,
                 ])
                 .inc();
-            return;
+            return false;
         }
 
         counters::APPLY_CHUNK_COUNT
@@ -976,6 +2963,41 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 counters::SUCCESS_LABEL,
             ])
             .inc();
+        if let Ok(duration) = self.time_provider.now().duration_since(received_tst) {
+            counters::CHUNK_COMMIT_LATENCY
+                .with_label_values(&[&ends_epoch.to_string()])
+                .observe(duration.as_secs_f64());
+        }
+        self.process_peer_advertisement(peer, advertised_version);
+        // nothing was committed
+        if self.config.verification_only {
+            return true;
+        }
+        // only the prefix of the chunk was committed, the peer served invalid transactions
+        let partial_chunk_len = self.partial_chunk_len.take();
+        if partial_chunk_len.is_some() {
+            self.request_manager
+                .update_score(peer, PeerScoreUpdateType::InvalidChunk);
+        }
+
+        let new_txns = if self.commit_batch.is_enabled() {
+            if first_version.map_or(false, |version| self.commit_batch.contains(version)) {
+                debug!(
+                    LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::Batched)
+                        .peer(peer),
+                    "{} transactions batched for commit",
+                    self.commit_batch.len()
+                );
+                return partial_chunk_len.is_none();
+            }
+            self.commit_batch.take_committed_txns()
+        } else {
+            let mut new_txns = new_txns;
+            if let Some(len) = partial_chunk_len {
+                new_txns.truncate(len);
+            }
+            new_txns
+        };
 
         // Part 2: post-chunk-process stage: process commit
         if let Err(e) = self.process_commit(new_txns, None, Some(peer)).await {
@@ -984,6 +3006,253 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                     .error(&e)
             );
         }
+        if self.config.verify_consensus_commits {
+            self.check_consensus_commit_conflict();
+        }
+        partial_chunk_len.is_none()
+    }
+
+    /// Reports the LI committed by syncing a chunk as critical if it conflicts with the last LI
+    /// committed by consensus, i.e. it's of a lower version (syncing rolled the storage back) or
+    /// of the same version with a different transaction accumulator.
+    fn check_consensus_commit_conflict(&self) {
+        let (consensus_version, consensus_accumulator_hash) = match self.last_consensus_commit {
+            Some(consensus_commit) => consensus_commit,
+            None => return,
+        };
+        let synced_li = self.local_state.highest_local_li.ledger_info();
+        let conflicts = synced_li.version() < consensus_version
+            || (synced_li.version() == consensus_version
+                && synced_li.transaction_accumulator_hash() != consensus_accumulator_hash);
+        if conflicts {
+            counters::CONSENSUS_COMMIT_CONFLICT_COUNT.inc();
+            error!(
+                LogSchema::event_log(LogEntry::ConsensusCommit, LogEvent::CommitConflict)
+                    .local_li_version(synced_li.version()),
+                "[state sync] CRITICAL: synced LI (version {}, accumulator {}) conflicts with the LI committed by consensus (version {}, accumulator {})",
+                synced_li.version(),
+                synced_li.transaction_accumulator_hash(),
+                consensus_version,
+                consensus_accumulator_hash
+            );
+        }
+    }
+
+    /// Rejects a chunk response, and penalizes its sender, if its serialized size exceeds the max
+    /// or it carries fewer transactions than the min share of the requested chunk limit while
+    /// stopping short of both the version of its LI and the end of its epoch.
+    fn check_chunk_response_size(
+        &mut self,
+        peer: &PeerNetworkId,
+        response: &GetChunkResponse,
+    ) -> Result<()> {
+        let max_bytes = self.config.max_received_chunk_bytes;
+        if max_bytes > 0 {
+            let response_bytes = lcs::to_bytes(response)?.len();
+            if response_bytes > max_bytes {
+                self.on_chunk_response_size_violation(peer, counters::OVER_MAX_LABEL);
+                bail!(
+                    "[state sync] Chunk response of {} bytes from {:?} exceeds the max of {} bytes",
+                    response_bytes,
+                    peer,
+                    max_bytes
+                );
+            }
+        }
+
+        let min_txns = self.config.chunk_limit * self.config.min_chunk_response_percent / 100;
+        let num_txns = response.txn_list_with_proof.len() as u64;
+        let last_version = response
+            .txn_list_with_proof
+            .first_transaction_version
+            .map_or(0, |first_version| first_version + num_txns - 1);
+        if num_txns < min_txns
+            && last_version < response.response_li.version()
+            && !response.ends_epoch()
+        {
+            self.on_chunk_response_size_violation(peer, counters::UNDER_MIN_LABEL);
+            bail!(
+                "[state sync] Chunk response of {} transactions from {:?} is below the min of {} transactions",
+                num_txns,
+                peer,
+                min_txns
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that the number of transactions of a chunk response agrees with the version range
+    /// it claims, before any proof is verified: the range starts at the first transaction version
+    /// and can't end beyond the version of the LI it's proven against, and if the chunk carries
+    /// events, it carries them for every transaction of the range.
+    fn check_chunk_version_range(
+        &mut self,
+        peer: &PeerNetworkId,
+        first_version: Version,
+        response: &GetChunkResponse,
+    ) -> Result<()> {
+        let txn_list_with_proof = &response.txn_list_with_proof;
+        let num_txns = txn_list_with_proof.len() as u64;
+        let mismatch = num_txns == 0
+            || first_version
+                .checked_add(num_txns - 1)
+                .map_or(true, |last_version| {
+                    last_version > response.response_li.version()
+                })
+            || txn_list_with_proof
+                .events
+                .as_ref()
+                .map_or(false, |events| events.len() as u64 != num_txns);
+        if mismatch {
+            counters::CHUNK_VERSION_RANGE_MISMATCH_COUNT
+                .with_label_values(&[
+                    &peer.raw_network_id().to_string(),
+                    &peer.peer_id().to_string(),
+                ])
+                .inc();
+            self.request_manager
+                .update_score(peer, PeerScoreUpdateType::InvalidChunk);
+            bail!(
+                "[state sync] Chunk response from {:?} of {} transactions from version {} contradicts its LI version {}",
+                peer,
+                num_txns,
+                first_version,
+                response.response_li.version()
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks whether `li`, served by `peer`, conflicts with the LI another peer served for the
+    /// same version. Sync isn't halted: the LI goes through the usual verification, but the
+    /// conflict is recorded. If the LI does verify, both LIs are signed by the trusted validators
+    /// (the recorded one verified before it was recorded), so there's no telling which peer is
+    /// honest: both are flagged and down-ranked, as they wouldn't otherwise be penalized.
+    fn check_equivocation(&mut self, peer: &PeerNetworkId, li: &LedgerInfoWithSignatures) {
+        let conflicting_peer = match self.recent_ledger_infos.conflicting_peer(li) {
+            Some(conflicting_peer) => conflicting_peer.clone(),
+            None => return,
+        };
+        let verified = self
+            .li_verifier
+            .verify_all(&self.local_state.trusted_epoch, &[li])
+            .is_ok();
+        let flagged_peers = if verified {
+            vec![peer.clone(), conflicting_peer.clone()]
+        } else {
+            vec![peer.clone()]
+        };
+        for flagged_peer in flagged_peers.iter() {
+            counters::EQUIVOCATING_PEER
+                .with_label_values(&[
+                    &flagged_peer.raw_network_id().to_string(),
+                    &flagged_peer.peer_id().to_string(),
+                ])
+                .inc();
+        }
+        warn!(
+            LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::EquivocatingPeer)
+                .peer(peer)
+                .version(li.ledger_info().version())
+                .ledger_info(li.clone()),
+            "Peer serves a LI conflicting with the one served by {} for the same version, verified: {}",
+            conflicting_peer,
+            verified
+        );
+        if verified {
+            for flagged_peer in flagged_peers.iter() {
+                self.request_manager
+                    .update_score(flagged_peer, PeerScoreUpdateType::InvalidChunk);
+            }
+        }
+    }
+
+    /// Rejects a chunk whose LI isn't of the trusted epoch, as it can't be verified, recording
+    /// whether the LI is ahead of or behind the trusted epoch apart from the other verification
+    /// failures.
+    fn check_epoch_match(
+        &mut self,
+        peer: &PeerNetworkId,
+        li: &LedgerInfoWithSignatures,
+    ) -> Result<()> {
+        let expected_epoch = self.local_state.trusted_epoch.epoch;
+        let received_epoch = li.ledger_info().epoch();
+        if received_epoch == expected_epoch {
+            return Ok(());
+        }
+        let direction = if received_epoch > expected_epoch {
+            counters::AHEAD_LABEL
+        } else {
+            counters::BEHIND_LABEL
+        };
+        counters::EPOCH_MISMATCH_CHUNK
+            .with_label_values(&[direction])
+            .inc();
+        warn!(
+            LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::EpochMismatch)
+                .peer(peer)
+                .expected_epoch(expected_epoch)
+                .received_epoch(received_epoch)
+                .version(li.ledger_info().version())
+        );
+        self.request_manager
+            .update_score(peer, PeerScoreUpdateType::InvalidChunk);
+        bail!(
+            "[state sync] Chunk LI of epoch {} is {} the trusted epoch {}",
+            received_epoch,
+            direction,
+            expected_epoch
+        );
+    }
+
+    fn on_chunk_response_size_violation(&mut self, peer: &PeerNetworkId, bound: &'static str) {
+        counters::CHUNK_RESPONSE_SIZE_VIOLATION_COUNT
+            .with_label_values(&[
+                &peer.raw_network_id().to_string(),
+                &peer.peer_id().to_string(),
+                bound,
+            ])
+            .inc();
+        self.request_manager
+            .update_score(peer, PeerScoreUpdateType::InvalidChunk);
+    }
+
+    /// Verifies that the chunk extends the local transaction accumulator, i.e., chunks served
+    /// across requests chain together. The left side of the chunk's range proof has to be the
+    /// frozen subtree roots of the accumulator right before the first transaction of the chunk.
+    fn verify_accumulator_continuity(
+        &mut self,
+        first_txn_version: Version,
+        txn_list_with_proof: &TransactionListWithProof,
+    ) -> Result<()> {
+        let frozen_subtree_roots_from_proof = txn_list_with_proof
+            .proof
+            .left_siblings()
+            .iter()
+            .rev()
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(local_roots) = self.frozen_subtree_cache.get(first_txn_version) {
+            ensure!(
+                frozen_subtree_roots_from_proof == *local_roots,
+                "[state sync] Chunk starting at version {} does not extend the local accumulator: frozen subtree roots from chunk proof don't match the local ones",
+                first_txn_version
+            );
+            return Ok(());
+        }
+        let accu_from_proof = InMemoryAccumulator::<TransactionAccumulatorHasher>::new(
+            frozen_subtree_roots_from_proof,
+            first_txn_version,
+        )?;
+        let local_root = self.local_state.synced_trees.state_id();
+        ensure!(
+            accu_from_proof.root_hash() == local_root,
+            "[state sync] Chunk starting at version {} does not extend the local accumulator: local root {}, root from chunk proof {}",
+            first_txn_version,
+            local_root,
+            accu_from_proof.root_hash()
+        );
+        Ok(())
     }
 
     /// Processing chunk responses that carry a LedgerInfo that should be verified using the
@@ -1001,8 +3270,11 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             "Response with a non-waypoint LI while still not initialized"
         );
         if let Some(sync_req) = self.sync_request.as_ref() {
-            // Valid responses should not exceed the LI version of the request.
-            if sync_req.target.ledger_info().version() < response_li.ledger_info().version() {
+            // Valid responses should not exceed the LI version of the request, unless stronger
+            // targets are accepted: the newer LI is verified against the trusted epoch below.
+            if sync_req.target.ledger_info().version() < response_li.ledger_info().version()
+                && !self.config.accept_stronger_targets
+            {
                 bail!(
                     "[state sync] Response has an LI version {} higher than requested version {}.",
                     response_li.ledger_info().version(),
@@ -1012,10 +3284,10 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         }
         // Optimistically fetch the next chunk assuming the current chunk is going to be applied
         // successfully.
-        let new_version =
-            self.local_state.highest_version_in_local_storage() + txn_list_with_proof.len() as u64;
+        let new_version = self.known_version() + txn_list_with_proof.len() as u64;
         let new_epoch = if response_li.ledger_info().version() == new_version
             && response_li.ledger_info().ends_epoch()
+            && !self.config.verification_only
         {
             // This chunk is going to finish the current epoch, optimistically request a chunk
             // from the next epoch.
@@ -1024,19 +3296,30 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             // Remain in the current epoch
             self.local_state.epoch()
         };
-        self.local_state.trusted_epoch.verify(&response_li)?;
+        self.check_epoch_proof_available(&response_li)?;
+        // the response LI and the pending LI are verified in a single batch
+        let mut lis = vec![&response_li];
+        if let Some(li) = pending_li.as_ref().filter(|li| **li != response_li) {
+            lis.push(li);
+        }
+        self.li_verifier
+            .verify_all(&self.local_state.trusted_epoch, &lis)?;
         if let Some(li) = pending_li {
-            if li != response_li {
-                self.local_state.trusted_epoch.verify(&li)?;
-            }
             self.pending_ledger_infos.add_li(li);
         }
-        self.validate_and_store_chunk(txn_list_with_proof, response_li, None)?;
+        self.store_chunk(txn_list_with_proof, response_li)?;
 
         // need to sync with local storage to see whether response LI was actually committed
         // and update pending_ledger_infos accordingly
         self.sync_state_with_local_storage()?;
-        let new_version = self.local_state.highest_version_in_local_storage();
+        let expected_version = new_version;
+        let new_version = self.known_version();
+        // the remainder of a partially applied chunk is requested in the current epoch
+        let new_epoch = if new_version == expected_version {
+            new_epoch
+        } else {
+            self.local_state.epoch()
+        };
 
         // don't throw error for failed chunk request send, as this failure is not related to
         // validity of the chunk response itself
@@ -1076,8 +3359,14 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                     self.local_state.epoch()
                 }
             });
-        if new_version < self.waypoint.version() {
-            if let Err(e) = self.send_chunk_request(new_version, new_epoch) {
+        // in verification-only mode, the same chunk is requested again once it's verified
+        let (request_version, request_epoch) = if self.config.verification_only {
+            (self.known_version(), self.local_state.epoch())
+        } else {
+            (new_version, new_epoch)
+        };
+        if request_version < self.waypoint.version() {
+            if let Err(e) = self.send_chunk_request(request_version, request_epoch) {
                 error!(LogSchema::event_log(
                     LogEntry::ProcessChunkResponse,
                     LogEvent::SendChunkRequestFail
@@ -1096,51 +3385,479 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         let end_of_epoch_li = end_of_epoch_li
             .map(|li| {
                 // verify end-of-epoch-li against local state
-                self.local_state.trusted_epoch.verify(&li).map(|_| li)
+                self.li_verifier
+                    .verify(&self.local_state.trusted_epoch, &li)
+                    .map(|_| li)
             })
             .transpose()?
             .filter(|li| {
                 li.ledger_info().version() == new_version && li.ledger_info().ends_epoch()
             });
         self.waypoint.verify(waypoint_li.ledger_info())?;
+        self.epoch_catch_up
+            .update_target(&waypoint_li, self.time_provider.now());
         self.validate_and_store_chunk(txn_list_with_proof, waypoint_li, end_of_epoch_li)
     }
 
-    // Assumes that the target LI has been already verified by the caller.
-    fn validate_and_store_chunk(
+    /// Returns `StateSyncError::UnknownEpoch` if `li` belongs to an epoch beyond the trusted
+    /// epoch, i.e., it can't be verified without the epoch change proofs in between.
+    fn check_epoch_proof_available(
+        &self,
+        li: &LedgerInfoWithSignatures,
+    ) -> Result<(), StateSyncError> {
+        let local_epoch = self.local_state.trusted_epoch.epoch;
+        let target_epoch = li.ledger_info().epoch();
+        if target_epoch <= local_epoch {
+            return Ok(());
+        }
+        warn!(
+            LogSchema::event_log(LogEntry::EpochChange, LogEvent::EpochProofUnavailable)
+                .local_epoch(local_epoch)
+                .target_epoch(target_epoch),
+            "[state sync] missing epoch change proofs for epochs {} to {}",
+            local_epoch,
+            target_epoch - 1
+        );
+        Err(StateSyncError::UnknownEpoch {
+            local_epoch,
+            target_epoch,
+        })
+    }
+
+    /// Verifies that `li`, if it ends an epoch, moves to the very next epoch: a transition that
+    /// skips epochs indicates a missing epoch change proof or malicious data.
+    fn verify_epoch_transition(&self, li: &LedgerInfoWithSignatures) -> Result<()> {
+        let next_epoch = match li.ledger_info().next_epoch_state() {
+            Some(next_epoch_state) => next_epoch_state.epoch,
+            None => return Ok(()),
+        };
+        let epoch = li.ledger_info().epoch();
+        if epoch.checked_add(1) == Some(next_epoch) {
+            return Ok(());
+        }
+        counters::EPOCH_SKIP_VIOLATION_COUNT.inc();
+        error!(
+            LogSchema::event_log(LogEntry::EpochChange, LogEvent::EpochSkipped)
+                .old_epoch(epoch)
+                .new_epoch(next_epoch),
+            "[state sync] LI at version {} moves from epoch {} to epoch {} instead of {}",
+            li.ledger_info().version(),
+            epoch,
+            next_epoch,
+            epoch.saturating_add(1)
+        );
+        bail!(
+            "[state sync] epoch transition from epoch {} to epoch {} skips epochs",
+            epoch,
+            next_epoch
+        )
+    }
+
+    /// Executes a chunk while the executor watchdog reports the execution as hung if it doesn't
+    /// complete within the executor hang timeout. The execution itself can't be cancelled.
+    fn execute_chunk_with_watchdog(
+        &mut self,
+        txn_list_with_proof: TransactionListWithProof,
+        target: LedgerInfoWithSignatures,
+        intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
+    ) -> Result<Vec<ContractEvent>> {
+        if let Some(watchdog) = self.executor_watchdog.as_ref() {
+            watchdog.start(self.local_state.highest_version_in_local_storage());
+        }
+        let result = self.executor_proxy.execute_chunk(
+            txn_list_with_proof,
+            target,
+            intermediate_end_of_epoch_li,
+        );
+        if let Some(watchdog) = self.executor_watchdog.as_ref() {
+            watchdog.stop();
+        }
+        result
+    }
+
+    // Assumes that the target LI has been already verified by the caller.
+    fn validate_and_store_chunk(
+        &mut self,
+        txn_list_with_proof: TransactionListWithProof,
+        target: LedgerInfoWithSignatures,
+        intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
+    ) -> Result<()> {
+        let target_epoch = target.ledger_info().epoch();
+        let target_version = target.ledger_info().version();
+        let local_epoch = self.local_state.highest_local_li.ledger_info().epoch();
+        let local_version = self.local_state.highest_local_li.ledger_info().version();
+        if (target_epoch, target_version) <= (local_epoch, local_version) {
+            warn!(
+                LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::OldResponseLI)
+                    .local_li_version(local_version)
+                    .local_epoch(local_epoch),
+                response_li_version = target_version,
+                response_li_epoch = target_epoch
+            );
+            return Ok(());
+        }
+        for li in intermediate_end_of_epoch_li
+            .iter()
+            .chain(std::iter::once(&target))
+        {
+            self.verify_epoch_transition(li)?;
+        }
+        if self.config.verification_only {
+            return self.verify_without_commit(&txn_list_with_proof, &target);
+        }
+        // a chunk ending an epoch can't be committed partially, as its LI has to be committed
+        let txn_list_with_proof =
+            if self.config.partial_chunk_application && intermediate_end_of_epoch_li.is_none() {
+                self.valid_chunk_prefix(txn_list_with_proof, &target)?
+            } else {
+                txn_list_with_proof
+            };
+
+        let synced_version = txn_list_with_proof
+            .first_transaction_version
+            .filter(|_| !txn_list_with_proof.transactions.is_empty())
+            .map(|first_version| first_version + txn_list_with_proof.len() as u64 - 1);
+        let committed_version = match &intermediate_end_of_epoch_li {
+            Some(li) => Some(li.ledger_info().version()),
+            None if synced_version == Some(target_version) => Some(target_version),
+            None => None,
+        };
+        let committed_lis: Vec<_> = intermediate_end_of_epoch_li
+            .iter()
+            .chain(std::iter::once(&target))
+            .filter(|li| Some(li.ledger_info().version()) == committed_version)
+            .collect();
+        self.verify_pinned_waypoints(&txn_list_with_proof, synced_version, &committed_lis)?;
+        let reconfig_li = intermediate_end_of_epoch_li.as_ref().unwrap_or(&target);
+        let (reconfig_epoch, reconfig_version) = (
+            reconfig_li.ledger_info().epoch(),
+            reconfig_li.ledger_info().version(),
+        );
+        // the transactions are only recorded for the post-commit stage once they're committed
+        let batched_txns = if self.commit_batch.is_enabled() {
+            Some(txn_list_with_proof.transactions.clone())
+        } else {
+            None
+        };
+        let num_versions = txn_list_with_proof.len() as u64;
+        let reconfig_events = self
+            .execute_chunk_with_watchdog(txn_list_with_proof, target, intermediate_end_of_epoch_li)
+            .map_err(|e| self.on_chunk_execution_failure(e))?;
+        self.apply_throughput
+            .record(num_versions, self.time_provider.now());
+        if let Some(batched_txns) = batched_txns {
+            self.commit_batch.record_committed(&batched_txns);
+        }
+        self.notify_reconfig_subscribers(reconfig_epoch, reconfig_version, reconfig_events);
+        match synced_version {
+            Some(synced_version) if self.config.verify_commit_durability => self
+                .verify_commit_durability(synced_version, committed_version)
+                .map_err(|e| self.on_chunk_execution_failure(e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Verifies the chunk against the pinned waypoints of its versions: each of them has to match
+    /// the LI committed with the chunk at its version, or else the chunk is of another fork than
+    /// the one the waypoints pin.
+    fn verify_pinned_waypoints(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        synced_version: Option<Version>,
+        committed_lis: &[&LedgerInfoWithSignatures],
+    ) -> Result<()> {
+        let versions = match (
+            txn_list_with_proof.first_transaction_version,
+            synced_version,
+        ) {
+            (Some(first_version), Some(synced_version)) => first_version..=synced_version,
+            _ => return Ok(()),
+        };
+        for (version, waypoint) in self.pinned_waypoints.range(versions) {
+            let result = match committed_lis
+                .iter()
+                .find(|li| li.ledger_info().version() == *version)
+            {
+                Some(li) => waypoint.verify(li.ledger_info()),
+                None => Err(format_err!("No LI committed at version {}", version)),
+            };
+            if let Err(e) = result {
+                counters::PINNED_WAYPOINT_MISMATCH.inc();
+                error!(
+                    LogSchema::event_log(LogEntry::Waypoint, LogEvent::PinnedWaypointMismatch)
+                        .waypoint(*waypoint)
+                        .error(&e),
+                    "CRITICAL: chunk doesn't match the pinned waypoint at version {}", version
+                );
+                bail!("Chunk mismatches pinned waypoint {}: {}", waypoint, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the chunk if it passes verification against `target`, or else its longest prefix
+    /// that does, so that the valid transactions of a large chunk aren't wasted. The remainder is
+    /// requested again once the prefix is committed.
+    fn valid_chunk_prefix(
+        &mut self,
+        txn_list_with_proof: TransactionListWithProof,
+        target: &LedgerInfoWithSignatures,
+    ) -> Result<TransactionListWithProof> {
+        let error = match self
+            .executor_proxy
+            .verify_chunk(&txn_list_with_proof, target)
+        {
+            Ok(()) => return Ok(txn_list_with_proof),
+            Err(e) => e,
+        };
+        let prefix = match self
+            .executor_proxy
+            .verified_chunk_prefix(&txn_list_with_proof, target)
+        {
+            Ok(Some(prefix)) => prefix,
+            _ => return Err(error),
+        };
+        counters::PARTIAL_CHUNK_APPLIED.inc();
+        warn!(
+            LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::PartialChunkApplied)
+                .version(self.known_version()),
+            "[state sync] applying the first {} of the {} transactions of a chunk that failed verification: {}",
+            prefix.len(),
+            txn_list_with_proof.len(),
+            error
+        );
+        self.partial_chunk_len = Some(prefix.len());
+        Ok(prefix)
+    }
+
+    /// Verifies the transactions of a chunk against its LI in verification-only mode, and records the versions that passed verification, without executing them.
+    fn verify_without_commit(
+        &mut self,
+        txn_list_with_proof: &TransactionListWithProof,
+        target: &LedgerInfoWithSignatures,
+    ) -> Result<()> {
+        let first_version = txn_list_with_proof
+            .first_transaction_version
+            .ok_or_else(|| format_err!("[state sync] Empty chunk"))?;
+        self.executor_proxy
+            .verify_chunk(txn_list_with_proof, target)?;
+        let last_version = first_version + txn_list_with_proof.len() as u64 - 1;
+        debug!(
+            LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ChunkVerified)
+                .version(first_version),
+            "verified chunk of versions {} to {}", first_version, last_version
+        );
+        self.verification_report.verified_chunks += 1;
+        self.verification_report
+            .verified_versions
+            .insert((first_version, last_version));
+        Ok(())
+    }
+
+    /// Returns the highest version synced, i.e. in local storage or batched for commit
+    fn known_version(&self) -> Version {
+        self.local_state.highest_version_in_local_storage() + self.commit_batch.len()
+    }
+
+    /// Stores a verified chunk. With commit batching, the chunk is batched with the previous ones,
+    /// which are committed together once the batch is ready or reaches the version of its LI, e.g.
+    /// the end of an epoch.
+    fn store_chunk(
         &mut self,
         txn_list_with_proof: TransactionListWithProof,
         target: LedgerInfoWithSignatures,
-        intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
     ) -> Result<()> {
-        let target_epoch = target.ledger_info().epoch();
-        let target_version = target.ledger_info().version();
-        let local_epoch = self.local_state.highest_local_li.ledger_info().epoch();
-        let local_version = self.local_state.highest_local_li.ledger_info().version();
-        if (target_epoch, target_version) <= (local_epoch, local_version) {
+        // chunks that are only verified aren't batched for commit
+        if !self.commit_batch.is_enabled() || self.config.verification_only {
+            return self.validate_and_store_chunk(txn_list_with_proof, target, None);
+        }
+        // only chunks proven against the same LI are merged
+        if self
+            .commit_batch
+            .target()
+            .map_or(false, |batch_target| *batch_target != target)
+        {
+            self.commit_batched_chunks()?;
+            return self.validate_and_store_chunk(txn_list_with_proof, target, None);
+        }
+        let reaches_target = self.known_version() + txn_list_with_proof.len() as u64
+            == target.ledger_info().version();
+        let now = self.time_provider.now();
+        self.commit_batch.add(txn_list_with_proof, target, now);
+        if reaches_target || self.commit_batch.is_ready(now) {
+            self.commit_batched_chunks()?;
+        }
+        Ok(())
+    }
+
+    fn commit_batched_chunks(&mut self) -> Result<()> {
+        if let Some((txn_list_with_proof, target, num_chunks)) = self.commit_batch.take() {
+            if num_chunks > 1 {
+                counters::COALESCED_COMMIT_COUNT.inc();
+            }
+            self.validate_and_store_chunk(txn_list_with_proof, target, None)?;
+        }
+        Ok(())
+    }
+
+    /// Commits the batched chunks once they reached the max delay, so that the last chunks of a
+    /// sync aren't stuck in the batch.
+    async fn check_commit_batch(&mut self) {
+        if !self.commit_batch.is_ready(self.time_provider.now()) {
+            return;
+        }
+        if let Err(e) = self.commit_batched_chunks() {
+            error!(
+                LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ApplyChunkFail)
+                    .error(&e)
+            );
+            return;
+        }
+        let committed_txns = self.commit_batch.take_committed_txns();
+        if let Err(e) = self.process_commit(committed_txns, None, None).await {
+            error!(
+                LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::PostCommitFail)
+                    .error(&e)
+            );
+        }
+    }
+
+    fn on_chunk_execution_failure(&mut self, error: anyhow::Error) -> anyhow::Error {
+        self.last_chunk_failure = Some(StallReason::ExecutionFailure(error.to_string()));
+        self.rollback_local_state();
+        error
+    }
+
+    /// Resets the in-memory state to the local storage after a failed commit. The storage may have
+    /// persisted none, part or all of the chunk, so neither the previous `synced_trees` nor the
+    /// ones the chunk would have led to can be trusted: the next chunk is requested from the
+    /// version the storage actually holds, and only the LIs it actually committed are dropped
+    /// from the pending LIs.
+    fn rollback_local_state(&mut self) {
+        let previous_version = self.known_version();
+        if let Err(e) = self.sync_state_with_local_storage() {
+            // the next commit re-syncs the state with the storage again
+            error!(
+                LogSchema::event_log(LogEntry::LocalState, LogEvent::LocalStateRollback).error(&e),
+                "[state sync] failed to read the local storage back after a failed commit"
+            );
+            return;
+        }
+        let version = self.known_version();
+        if version != previous_version {
             warn!(
-                LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::OldResponseLI)
-                    .local_li_version(local_version)
-                    .local_epoch(local_epoch),
-                response_li_version = target_version,
-                response_li_epoch = target_epoch
+                LogSchema::event_log(LogEntry::LocalState, LogEvent::LocalStateRollback)
+                    .local_synced_version(version)
+                    .local_li_version(self.local_state.highest_local_li.ledger_info().version()),
+                "[state sync] storage persisted part of a failed commit: known version moved from {} to {}",
+                previous_version,
+                version
             );
+        }
+    }
+
+    /// Replaces the in-memory state with the one of the local storage, e.g. after an external
+    /// process truncated or restored the storage. Everything that assumed the previous state is
+    /// dropped: the in-flight chunk requests, the chunk responses not processed yet and the
+    /// batched chunks, so that the next chunk is requested from the reloaded version.
+    fn reload_from_storage(&mut self) -> Result<SynchronizerState> {
+        let previous_version = self.known_version();
+        let previous_epoch = self.local_state.epoch();
+        self.sync_state_with_local_storage()?;
+        // LIs verified in later epochs can't be trusted once the storage is back to an older one
+        if self.local_state.epoch() < previous_epoch {
+            self.li_verifier.invalidate(self.local_state.epoch());
+        }
+        self.request_manager.clear_requests();
+        self.pending_chunks.clear();
+        self.commit_batch.take();
+        // the chunks served before may not be in the storage anymore
+        self.chunk_response_cache.shed();
+        info!(
+            LogSchema::event_log(LogEntry::LocalState, LogEvent::LocalStateReload)
+                .local_synced_version(self.known_version())
+                .local_li_version(self.local_state.highest_local_li.ledger_info().version()),
+            "[state sync] reloaded the local state from storage: known version moved from {} to {}",
+            previous_version,
+            self.known_version()
+        );
+        Ok(self.local_state.clone())
+    }
+
+    /// Reads back the local storage to verify that it actually persisted an executed chunk, i.e.,
+    /// it holds the transactions up to `synced_version` and the LI at `committed_version`, if any.
+    fn verify_commit_durability(
+        &self,
+        synced_version: Version,
+        committed_version: Option<Version>,
+    ) -> Result<()> {
+        let storage_state = self.executor_proxy.get_local_storage_state()?;
+        let storage_synced_version = storage_state.highest_version_in_local_storage();
+        let storage_committed_version = storage_state.highest_local_li.ledger_info().version();
+        if storage_synced_version >= synced_version
+            && committed_version.map_or(true, |version| storage_committed_version >= version)
+        {
             return Ok(());
         }
 
-        self.executor_proxy
-            .execute_chunk(txn_list_with_proof, target, intermediate_end_of_epoch_li)
+        counters::COMMIT_DURABILITY_CHECK_FAIL_COUNT.inc();
+        error!(
+            LogSchema::event_log(LogEntry::CommitFlow, LogEvent::DurabilityCheckFail)
+                .local_synced_version(storage_synced_version)
+                .local_li_version(storage_committed_version),
+            "[state sync] CRITICAL: storage did not persist the executed chunk up to version {}",
+            synced_version
+        );
+        bail!(
+            "[state sync] Storage did not persist chunk up to version {}: storage synced version {}, committed version {}",
+            synced_version,
+            storage_synced_version,
+            storage_committed_version
+        )
     }
 
     /// Ensures that StateSynchronizer is making progress:
     /// * kick-starts initial sync process (= initialization syncing to waypoint)
     /// * issue a new request if too much time passed since requesting highest_synced_version + 1.
-    fn check_progress(&mut self) {
+    pub(crate) fn check_progress(&mut self) {
+        let now = self.time_provider.now();
+        self.apply_throughput.update(now);
+        self.epoch_catch_up.check_stall(now);
+        self.update_sync_lifecycle();
+        self.check_stall_escalation();
+        self.check_waypoint_advancement();
+        self.check_li_broadcast();
+        self.check_upstream_refresh();
+        self.check_ahead_of_peers();
+        self.update_tip_estimate();
+        // multicasted requests are only pruned once timed out, which may be after the last commit
+        self.request_manager
+            .remove_requests(self.local_state.highest_version_in_local_storage());
+        self.check_memory_cap();
+        self.log_throttled_errors();
+        if self.config.serve_only || self.paused {
+            return;
+        }
         if self.request_manager.no_available_peers() {
+            self.last_stall_reason = Some(if self.is_at_tip() {
+                StallReason::AtTip
+            } else {
+                StallReason::NoPeers
+            });
             return;
         }
-        if self.role == RoleType::Validator && self.sync_request.is_none() && self.is_initialized()
+        if self.role == RoleType::Validator
+            && self.sync_request.is_none()
+            && self.version_sync_request.is_none()
+            && self.is_initialized()
         {
+            self.last_stall_reason = Some(StallReason::AtTip);
+            return;
+        }
+        if self.quiesced && !self.has_uncommitted_txns() {
+            return;
+        }
+        if self.pending_shutdown.is_some() {
             return;
         }
 
@@ -1148,7 +3865,7 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         let sync_request_expired = self.sync_request.as_ref().map_or(false, |req| {
             let default_timeout = Duration::from_millis(self.config.sync_request_timeout_ms);
             if let Some(tst) = req.last_progress_tst.checked_add(default_timeout) {
-                return SystemTime::now().duration_since(tst).is_ok();
+                return self.time_provider.now().duration_since(tst).is_ok();
             }
             false
         });
@@ -1163,10 +3880,15 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             ));
 
             if let Some(sync_request) = self.sync_request.take() {
-                if let Err(e) = Self::send_sync_req_callback(
-                    sync_request,
-                    Err(format_err!("request timed out")),
-                ) {
+                // a request to a future epoch can't make progress without the epoch change proofs
+                let error = self
+                    .check_epoch_proof_available(&sync_request.target)
+                    .err()
+                    .unwrap_or_else(|| StateSyncError::NoProgress {
+                        target_version: sync_request.target.ledger_info().version(),
+                        synced_version: self.local_state.highest_version_in_local_storage(),
+                    });
+                if let Err(e) = Self::send_sync_req_callback(sync_request, Err(error)) {
                     error!(
                         LogSchema::event_log(LogEntry::SyncRequest, LogEvent::CallbackFail)
                             .error(&e)
@@ -1175,11 +3897,20 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             }
         }
 
-        let known_version = self.local_state.highest_version_in_local_storage();
+        self.check_version_sync_request_timeout();
+
+        let known_version = self.known_version();
 
         // if coordinator didn't make progress by expected time or did not send a request for current
         // local synced version, issue new request
+        let requested = self
+            .request_manager
+            .get_last_request_time(known_version)
+            .is_some();
         if self.request_manager.check_timeout(known_version) {
+            if requested {
+                self.last_stall_reason = Some(self.stall_reason());
+            }
             // log and count timeout
             counters::TIMEOUT.inc();
             warn!(LogSchema::new(LogEntry::Timeout).version(known_version));
@@ -1193,9 +3924,145 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         }
     }
 
+    /// Fails the version sync request if no LI committed a new version for the sync request
+    /// timeout, e.g. because the LIs of the current epoch never reach the version.
+    fn check_version_sync_request_timeout(&mut self) {
+        let timeout = Duration::from_millis(self.config.sync_request_timeout_ms);
+        let expired = self.version_sync_request.as_ref().map_or(false, |req| {
+            req.last_progress_tst
+                .checked_add(timeout)
+                .map_or(false, |tst| {
+                    self.time_provider.now().duration_since(tst).is_ok()
+                })
+        });
+        if !expired {
+            return;
+        }
+        if let Some(request) = self.version_sync_request.take() {
+            counters::SYNC_REQUEST_RESULT
+                .with_label_values(&[counters::TIMEOUT_LABEL])
+                .inc();
+            warn!(
+                LogSchema::event_log(LogEntry::SyncRequest, LogEvent::Timeout)
+                    .target_version(request.version)
+            );
+            let error = StateSyncError::VersionNotReached {
+                version: request.version,
+                committed_version: self.local_state.highest_local_li.ledger_info().version(),
+            };
+            if let Err(e) = Self::send_version_sync_req_callback(request, Err(error)) {
+                error!(
+                    LogSchema::event_log(LogEntry::SyncRequest, LogEvent::CallbackFail).error(&e)
+                );
+            }
+        }
+    }
+
+    /// Refreshes the upstream config from the upstream resolver once the refresh interval elapsed
+    /// since the last refresh.
+    fn check_upstream_refresh(&mut self) {
+        let interval = Duration::from_millis(self.config.upstream_refresh_interval_ms);
+        let now = self.time_provider.now();
+        let due = interval > Duration::from_millis(0)
+            && now
+                .duration_since(self.last_upstream_refresh)
+                .map_or(false, |elapsed| elapsed >= interval);
+        if due {
+            self.last_upstream_refresh = now;
+            self.request_manager.refresh_upstream_config();
+        }
+    }
+
+    /// Returns the reason the chunk request for the current version timed out.
+    fn stall_reason(&self) -> StallReason {
+        if self.is_at_tip() {
+            StallReason::AtTip
+        } else if self.ahead_of_peers {
+            StallReason::PeersBehind
+        } else {
+            self.last_chunk_failure
+                .clone()
+                .unwrap_or(StallReason::NoResponse)
+        }
+    }
+
+    /// Returns whether the first background chunk request can be sent, i.e. min_peers_before_sync
+    /// upstream peers are available or the wait for them timed out. Always true once background
+    /// sync started. Requests to the waypoint or to the target of a sync request don't wait.
+    fn has_enough_peers_to_start_sync(&mut self) -> bool {
+        if self.min_peers_deadline.is_none() {
+            return true;
+        }
+        if self.request_manager.num_available_peers() < self.config.min_peers_before_sync {
+            if self.is_waiting_for_peers() {
+                debug!(LogSchema::event_log(
+                    LogEntry::SendChunkRequest,
+                    LogEvent::WaitingForPeers
+                ));
+                return false;
+            }
+            counters::SYNC_STARTED_BELOW_MIN_PEERS.inc();
+        }
+        self.min_peers_deadline = None;
+        true
+    }
+
+    /// Returns whether background sync is held back until min_peers_before_sync upstream peers
+    /// are available.
+    fn is_waiting_for_peers(&self) -> bool {
+        self.min_peers_deadline.map_or(false, |deadline| {
+            self.request_manager.num_available_peers() < self.config.min_peers_before_sync
+                && self.time_provider.now() < deadline
+        })
+    }
+
     /// Sends a chunk request with a given `known_version` and `known_epoch`
     /// (might be chosen optimistically).
     fn send_chunk_request(&mut self, known_version: u64, known_epoch: u64) -> Result<()> {
+        if self.pending_shutdown.is_some() {
+            debug!(LogSchema::event_log(
+                LogEntry::SendChunkRequest,
+                LogEvent::ShuttingDown
+            ));
+            return Ok(());
+        }
+        if self.quiesced && !self.has_uncommitted_txns() {
+            debug!(LogSchema::event_log(
+                LogEntry::SendChunkRequest,
+                LogEvent::Quiesced
+            ));
+            return Ok(());
+        }
+        if self.draining_chunk_source {
+            return Ok(());
+        }
+        if self.paused {
+            debug!(LogSchema::event_log(
+                LogEntry::SendChunkRequest,
+                LogEvent::Paused
+            ));
+            return Ok(());
+        }
+        if self.applying_packed_chunks {
+            return Ok(());
+        }
+        if self.config.serve_only {
+            debug!(LogSchema::event_log(
+                LogEntry::SendChunkRequest,
+                LogEvent::ServeOnly
+            ));
+            return Ok(());
+        }
+        if let Some(ceiling_version) = self.config.sync_ceiling_version {
+            if known_version >= ceiling_version {
+                debug!(
+                    LogSchema::event_log(LogEntry::SendChunkRequest, LogEvent::SyncCeiling)
+                        .local_synced_version(known_version),
+                    "reached the sync ceiling version {}", ceiling_version
+                );
+                return Ok(());
+            }
+        }
         if self.request_manager.no_available_peers() {
             warn!(LogSchema::event_log(
                 LogEntry::SendChunkRequest,
@@ -1204,6 +4071,9 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             bail!("No peers to send chunk request to");
         }
 
+        // A single target is synced at a time, prioritized by its source: the waypoint takes
+        // precedence over a sync request from consensus, which in turn takes precedence over
+        // background sync.
         let target = if !self.is_initialized() {
             let waypoint_version = self.waypoint.version();
             TargetType::Waypoint(waypoint_version)
@@ -1233,6 +4103,18 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
                 }
             }
         };
+        // consensus is blocked on the target of its sync request
+        let critical = matches!(target, TargetType::TargetLedgerInfo(_));
+        if critical && self.is_waiting_for_peers() {
+            counters::CRITICAL_SYNC_THROTTLE_BYPASSES
+                .with_label_values(&[counters::MIN_PEERS_LABEL])
+                .inc();
+        }
+        if matches!(target, TargetType::HighestAvailable { .. })
+            && !self.has_enough_peers_to_start_sync()
+        {
+            return Ok(());
+        }
 
         let target_version = target
             .version()
@@ -1243,8 +4125,21 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             .highest_version()
             .unwrap_or(target_version);
         counters::set_version(counters::VersionType::Highest, highest_version);
-        let req = GetChunkRequest::new(known_version, known_epoch, self.config.chunk_limit, target);
-        self.request_manager.send_chunk_request(req)
+        let priority = match target {
+            TargetType::Waypoint(_) => counters::WAYPOINT_PRIORITY_LABEL,
+            TargetType::TargetLedgerInfo(_) => counters::CONSENSUS_PRIORITY_LABEL,
+            TargetType::HighestAvailable { .. } => counters::BACKGROUND_PRIORITY_LABEL,
+        };
+        let req = GetChunkRequest::new(known_version, known_epoch, self.config.chunk_limit, target)
+            .with_max_chunk_bytes(self.config.max_chunk_bytes as u64)
+            .with_max_chunks_in_response(self.config.max_chunks_in_response)
+            .with_critical(critical);
+        self.request_manager
+            .send_chunk_request(req, &mut self.bandwidth_usage)?;
+        counters::CHUNK_REQUESTS_PER_PRIORITY
+            .with_label_values(&[priority])
+            .inc();
+        Ok(())
     }
 
     fn deliver_subscription(
@@ -1258,9 +4153,190 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             request_info.known_version,
             ResponseLedgerInfo::VerifiableLedgerInfo(response_li),
             request_info.limit,
+            request_info.max_chunk_bytes,
+            request_info.max_chunks,
         )
     }
 
+    /// Sheds caches and diagnostic structures, least important first, while the estimated memory
+    /// of state sync exceeds `max_state_sync_memory_bytes`. The structures needed to sync
+    /// correctly (pending LIs, in-flight requests, subscriptions) are never shed.
+    fn check_memory_cap(&mut self) {
+        let mut memory = self.memory_bytes();
+        let cap = self.config.max_state_sync_memory_bytes;
+        if cap > 0 && memory > cap {
+            memory -= Self::on_memory_shed(
+                counters::CHUNK_RESPONSE_CACHE_LABEL,
+                self.chunk_response_cache.shed(),
+            );
+        }
+        if cap > 0 && memory > cap {
+            memory -=
+                Self::on_memory_shed(counters::VERIFIED_LI_CACHE_LABEL, self.li_verifier.shed());
+        }
+        if cap > 0 && memory > cap {
+            memory -= Self::on_memory_shed(
+                counters::FROZEN_SUBTREE_CACHE_LABEL,
+                self.frozen_subtree_cache.shed(),
+            );
+        }
+        if cap > 0 && memory > cap {
+            memory -=
+                Self::on_memory_shed(counters::BANDWIDTH_STATS_LABEL, self.bandwidth_usage.shed());
+        }
+        if cap > 0 && memory > cap {
+            memory -= Self::on_memory_shed(
+                counters::SIGNER_DIVERSITY_LABEL,
+                self.signer_diversity.shed(),
+            );
+        }
+        if cap > 0 && memory > cap {
+            debug!(
+                LogSchema::event_log(LogEntry::MemoryCap, LogEvent::OverCap),
+                "estimated memory of {} bytes exceeds the cap of {} bytes", memory, cap
+            );
+        }
+        counters::MEMORY_BYTES.set(memory as i64);
+    }
+
+    fn on_memory_shed(structure: &'static str, freed: usize) -> usize {
+        if freed > 0 {
+            counters::MEMORY_SHED_COUNT
+                .with_label_values(&[structure])
+                .inc();
+            info!(
+                LogSchema::event_log(LogEntry::MemoryCap, LogEvent::Shed),
+                "shed {} bytes of {}", freed, structure
+            );
+        }
+        freed
+    }
+
+    /// Returns the estimated memory of the in-memory structures of state sync, in bytes
+    pub(crate) fn memory_bytes(&self) -> usize {
+        self.pending_ledger_infos.memory_bytes()
+            + self.li_verifier.memory_bytes()
+            + self.frozen_subtree_cache.memory_bytes()
+            + self.chunk_response_cache.memory_bytes()
+            + self.request_manager.memory_bytes()
+            + self.subscriptions.len() * size_of::<(PeerNetworkId, PendingRequestInfo)>()
+            + self.bandwidth_usage.memory_bytes()
+            + self.signer_diversity.memory_bytes()
+    }
+
+    /// Logs a summary of the repetitions of the errors whose throttling window expired
+    fn log_throttled_errors(&mut self) {
+        let window = self.error_log_throttle.window();
+        for (error, repetitions) in self.error_log_throttle.expire() {
+            error!(
+                LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ApplyChunkFail)
+                    .count(repetitions),
+                "[state sync] error occurred {} more times in the last {:?}: {}",
+                repetitions,
+                window,
+                error
+            );
+        }
+    }
+
+    /// Records a version advertised by `peer` and updates the tip estimate.
+    fn process_peer_advertisement(&mut self, peer: &PeerNetworkId, version: u64) {
+        if self
+            .request_manager
+            .update_peer_highest_version(peer, version)
+        {
+            self.tip_flap_detector
+                .record_regression(self.time_provider.now());
+        }
+        self.update_tip_estimate();
+    }
+
+    /// Exports the tip estimate and reports transitions between flapping and stable peer
+    /// advertisements.
+    fn update_tip_estimate(&mut self) {
+        let tip_estimate = self.request_manager.tip_estimate();
+        if let Some(version) = tip_estimate {
+            counters::TIP_ESTIMATE.set(version as i64);
+        }
+        if !self.tip_flap_detector.update(self.time_provider.now()) {
+            return;
+        }
+        let flapping = self.tip_flap_detector.flapping;
+        counters::TIP_FLAPPING.set(flapping as i64);
+        if flapping {
+            counters::TIP_FLAP_COUNT.inc();
+            warn!(
+                LogSchema::event_log(LogEntry::TipEstimate, LogEvent::Flapping),
+                "[state sync] peers advertised regressing versions {} times within {:?}, tip estimate: {:?}",
+                self.tip_flap_detector.regressions.len(),
+                self.tip_flap_detector.window,
+                tip_estimate
+            );
+        } else {
+            info!(LogSchema::event_log(
+                LogEntry::TipEstimate,
+                LogEvent::Stable
+            ));
+        }
+    }
+
+    /// Detects whether local storage is ahead of the highest version advertised by every known
+    /// peer, which means that either this node is at the tip or all of its peers are stale.
+    fn check_ahead_of_peers(&mut self) {
+        let synced_version = self.local_state.highest_version_in_local_storage();
+        let peer_versions = self.request_manager.peer_highest_versions();
+        let ahead_of_peers = !peer_versions.is_empty()
+            && peer_versions
+                .iter()
+                .all(|(_, version)| *version < synced_version);
+        if ahead_of_peers == self.ahead_of_peers {
+            return;
+        }
+
+        self.ahead_of_peers = ahead_of_peers;
+        counters::AHEAD_OF_PEERS.set(ahead_of_peers as i64);
+        if ahead_of_peers {
+            info!(
+                LogSchema::new(LogEntry::AheadOfPeers).local_synced_version(synced_version),
+                "[state sync] local storage is ahead of all known peers: {:?}", peer_versions
+            );
+        }
+    }
+
+    /// Sends committed reconfiguration events to the local reconfig subscribers. Subscribers
+    /// whose receivers are closed are dropped.
+    fn notify_reconfig_subscribers(
+        &mut self,
+        epoch: u64,
+        version: u64,
+        events: Vec<ContractEvent>,
+    ) {
+        if events.is_empty() {
+            return;
+        }
+        let notification = ReconfigNotification {
+            epoch,
+            version,
+            events,
+        };
+        self.reconfig_subscribers
+            .retain(|subscriber| subscriber.unbounded_send(notification.clone()).is_ok());
+    }
+
+    /// Publishes reconfig notifications that were deferred by previous commits, so that they
+    /// are delivered even if no further commit happens.
+    fn publish_deferred_reconfig_notifications(&mut self) {
+        if let Err(e) = self
+            .executor_proxy
+            .publish_deferred_on_chain_config_updates()
+        {
+            counters::RECONFIG_PUBLISH_COUNT
+                .with_label_values(&[counters::FAIL_LABEL])
+                .inc();
+            error!(LogSchema::event_log(LogEntry::Reconfig, LogEvent::Fail).error(&e));
+        }
+    }
+
     /// The function is called after the local storage is updated with new transactions:
     /// it might deliver chunks for the subscribers that have been waiting with the long polls.
     ///
@@ -1271,13 +4347,11 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
     fn check_subscriptions(&mut self) {
         let highest_li_version = self.local_state.highest_local_li.ledger_info().version();
 
+        let now = self.time_provider.now();
         let mut ready = vec![];
         self.subscriptions.retain(|peer, request_info| {
             // filter out expired peer requests
-            if SystemTime::now()
-                .duration_since(request_info.expiration_time)
-                .is_ok()
-            {
+            if now.duration_since(request_info.expiration_time).is_ok() {
                 return false;
             }
             if request_info.known_version < highest_li_version {
@@ -1308,7 +4382,52 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         });
     }
 
-    fn send_sync_req_callback(sync_req: SyncRequest, msg: Result<()>) -> Result<()> {
+    #[cfg(test)]
+    pub(crate) fn epoch_catch_up_progress(&self) -> Option<(u64, u64)> {
+        if self.epoch_catch_up.in_progress() {
+            Some((
+                self.epoch_catch_up.current_epoch,
+                self.epoch_catch_up.target_epoch,
+            ))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_ahead_of_peers(&self) -> bool {
+        self.ahead_of_peers
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_tip_flapping(&self) -> bool {
+        self.tip_flap_detector.flapping
+    }
+
+    #[cfg(test)]
+    pub(crate) fn peer_score(&self, peer: &PeerNetworkId) -> Option<f64> {
+        self.request_manager.peer_score(peer)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn num_pending_chunks(&self) -> usize {
+        self.pending_chunks.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn num_li_crypto_calls(&self) -> u64 {
+        self.li_verifier.num_crypto_calls
+    }
+
+    #[cfg(test)]
+    pub(crate) fn num_verified_lis(&self) -> usize {
+        self.li_verifier.verified.len()
+    }
+
+    fn send_sync_req_callback(
+        sync_req: SyncRequest,
+        msg: Result<(), StateSyncError>,
+    ) -> Result<()> {
         sync_req.callback.send(msg).map_err(|failed_msg| {
             counters::FAILED_CHANNEL_SEND
                 .with_label_values(&[counters::CONSENSUS_SYNC_REQ_CALLBACK])
@@ -1320,9 +4439,24 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
         })
     }
 
+    fn send_version_sync_req_callback(
+        sync_req: VersionSyncRequest,
+        msg: Result<(), StateSyncError>,
+    ) -> Result<()> {
+        sync_req.callback.send(msg).map_err(|failed_msg| {
+            counters::FAILED_CHANNEL_SEND
+                .with_label_values(&[counters::VERSION_SYNC_REQ_CALLBACK])
+                .inc();
+            format_err!(
+                "Version sync request callback error - failed to send following msg: {:?}",
+                failed_msg
+            )
+        })
+    }
+
     fn send_initialization_callback(
-        cb: oneshot::Sender<Result<()>>,
-        msg: Result<()>,
+        cb: oneshot::Sender<Result<(), StateSyncError>>,
+        msg: Result<(), StateSyncError>,
     ) -> Result<()> {
         cb.send(msg).map_err(|failed_msg| {
             counters::FAILED_CHANNEL_SEND
@@ -1334,4 +4468,28 @@ impl<T: ExecutorProxyTrait> SyncCoordinator<T> {
             )
         })
     }
+
+    fn send_shutdown_callback(cb: oneshot::Sender<Result<()>>, msg: Result<()>) -> Result<()> {
+        cb.send(msg).map_err(|failed_msg| {
+            counters::FAILED_CHANNEL_SEND
+                .with_label_values(&[counters::SHUTDOWN_CALLBACK])
+                .inc();
+            format_err!(
+                "Shutdown callback error - failed to send following msg: {:?}",
+                failed_msg
+            )
+        })
+    }
+
+    fn send_quiesce_callback(cb: oneshot::Sender<Result<()>>, msg: Result<()>) -> Result<()> {
+        cb.send(msg).map_err(|failed_msg| {
+            counters::FAILED_CHANNEL_SEND
+                .with_label_values(&[counters::QUIESCE_CALLBACK])
+                .inc();
+            format_err!(
+                "Quiesce callback error - failed to send following msg: {:?}",
+                failed_msg
+            )
+        })
+    }
 }
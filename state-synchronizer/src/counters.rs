@@ -17,6 +17,7 @@ pub const SYNC_MSG_LABEL: &str = "sync";
 pub const COMMIT_MSG_LABEL: &str = "commit";
 pub const CHUNK_REQUEST_MSG_LABEL: &str = "chunk_request";
 pub const CHUNK_RESPONSE_MSG_LABEL: &str = "chunk_response";
+pub const COMMITTED_LI_MSG_LABEL: &str = "committed_li";
 
 pub fn set_timestamp(timestamp_type: TimestampType, time_as_usecs: u64) {
     TIMESTAMP
@@ -73,7 +74,11 @@ impl VersionType {
 
 // failed channel send type labels
 pub const CONSENSUS_SYNC_REQ_CALLBACK: &str = "consensus_sync_req_callback";
+pub const VERSION_SYNC_REQ_CALLBACK: &str = "version_sync_req_callback";
 pub const WAYPOINT_INIT_CALLBACK: &str = "waypoint_init_callback";
+pub const QUIESCE_CALLBACK: &str = "quiesce_callback";
+pub const SHUTDOWN_CALLBACK: &str = "shutdown_callback";
+pub const EPOCH_CHANGE_NOTIFICATION: &str = "epoch_change_notification";
 
 // result labels
 pub const SUCCESS_LABEL: &str = "success";
@@ -85,9 +90,67 @@ pub const FROM_MEMPOOL_LABEL: &str = "from_mempool";
 pub const CONSENSUS_LABEL: &str = "consensus";
 pub const STATE_SYNC_LABEL: &str = "state_sync";
 
+// epoch catch up gauge labels
+pub const CURRENT_LABEL: &str = "current";
+pub const TARGET_LABEL: &str = "target";
+
+// highest version advertisement decision labels
+pub const ADVERTISE_LABEL: &str = "advertise";
+pub const WITHHOLD_LABEL: &str = "withhold";
+
+// chunk response size bound labels
+pub const UNDER_MIN_LABEL: &str = "under_min";
+pub const OVER_MAX_LABEL: &str = "over_max";
+
+// memory shedding labels
+pub const CHUNK_RESPONSE_CACHE_LABEL: &str = "chunk_response_cache";
+pub const VERIFIED_LI_CACHE_LABEL: &str = "verified_li_cache";
+pub const FROZEN_SUBTREE_CACHE_LABEL: &str = "frozen_subtree_cache";
+pub const BANDWIDTH_STATS_LABEL: &str = "bandwidth_stats";
+pub const SIGNER_DIVERSITY_LABEL: &str = "signer_diversity";
+
+// stall escalation step labels
+pub const WIDEN_PEERS_LABEL: &str = "widen_peers";
+pub const REFRESH_PEERS_LABEL: &str = "refresh_peers";
+pub const SWITCH_NETWORKS_LABEL: &str = "switch_networks";
+pub const ALERT_LABEL: &str = "alert";
+
+// throttles of background sync bypassed by critical chunk requests
+pub const STALL_ESCALATION_LABEL: &str = "stall_escalation";
+pub const BACKOFF_LABEL: &str = "backoff";
+pub const MIN_PEERS_LABEL: &str = "min_peers";
+pub const RATE_LIMIT_LABEL: &str = "rate_limit";
+
+// future epoch chunk labels
+pub const BUFFERED_LABEL: &str = "buffered";
+pub const REPLAYED_LABEL: &str = "replayed";
+
+// epoch mismatch direction labels, of the chunk's epoch relative to the trusted epoch
+pub const AHEAD_LABEL: &str = "ahead";
+pub const BEHIND_LABEL: &str = "behind";
+
+// quantile labels
+pub const P50_LABEL: &str = "0.5";
+pub const P90_LABEL: &str = "0.9";
+pub const P99_LABEL: &str = "0.99";
+
 // sync request result labels
 pub const COMPLETE_LABEL: &str = "complete";
 pub const TIMEOUT_LABEL: &str = "timeout";
+pub const CANCEL_LABEL: &str = "cancel";
+pub const EPOCH_CHANGES_EXCEEDED_LABEL: &str = "epoch_changes_exceeded";
+
+// chunk request direction labels
+pub const INBOUND_LABEL: &str = "inbound";
+pub const OUTBOUND_LABEL: &str = "outbound";
+
+// chunk request priority class labels
+pub const WAYPOINT_PRIORITY_LABEL: &str = "waypoint";
+pub const CONSENSUS_PRIORITY_LABEL: &str = "consensus";
+pub const BACKGROUND_PRIORITY_LABEL: &str = "background";
+
+// pinned upstream failure labels, on top of TIMEOUT_LABEL and BACKOFF_LABEL
+pub const DISCONNECTED_LABEL: &str = "disconnected";
 
 /// Counter of pending network events to State Synchronizer
 pub static PENDING_STATE_SYNCHRONIZER_NETWORK_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
@@ -111,6 +174,27 @@ pub static REQUESTS_SENT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of chunk requests issued per priority class, i.e., per source of the sync target:
+/// bootstrapping to the waypoint, a sync request from consensus, or background sync
+pub static CHUNK_REQUESTS_PER_PRIORITY: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_chunk_requests_per_priority_total",
+        "Number of chunk requests issued per priority class",
+        &["priority"]
+    )
+    .unwrap()
+});
+
+/// Number of chunk requests sent to sync (outbound) and processed to serve (inbound) per network
+pub static NETWORK_CHUNK_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_network_chunk_requests_total",
+        "Number of chunk requests sent to sync and processed to serve per network",
+        &["network", "direction"]
+    )
+    .unwrap()
+});
+
 /// Number of chunk responses sent from a node (including FN subscriptions)
 pub static RESPONSES_SENT: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
@@ -121,6 +205,82 @@ pub static RESPONSES_SENT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of committed LIs proactively broadcast to peers
+pub static LI_BROADCASTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_li_broadcasts_total",
+        "Number of committed LIs proactively broadcast to peers",
+        &["network", "result"]
+    )
+    .unwrap()
+});
+
+/// Number of state sync messages sent (outbound) and received (inbound) over the network, per
+/// message type
+pub static NETWORK_MESSAGES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_network_messages_total",
+        "Number of state sync messages sent and received over the network per message type",
+        &["type", "direction"]
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses dropped without processing because they arrived while paused
+pub static CHUNKS_DROPPED_WHILE_PAUSED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_chunks_dropped_while_paused_total",
+        "Number of chunk responses dropped without processing because they arrived while paused"
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses dropped without processing because they arrived during shutdown
+pub static CHUNKS_DROPPED_DURING_SHUTDOWN: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_chunks_dropped_during_shutdown_count",
+        "Number of chunk responses dropped without processing because they arrived during shutdown"
+    )
+    .unwrap()
+});
+
+/// Number of times a chunk response was halved because it exceeded the max response size
+pub static CHUNK_RESPONSE_DOWNSIZE_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_chunk_response_downsize_count",
+        "Number of times a chunk response was halved because it exceeded the max response size"
+    )
+    .unwrap()
+});
+
+/// Number of chunks of the chunk source applied and failed before syncing from the network
+pub static CHUNK_SOURCE_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_chunk_source_count",
+        "Number of chunks of the chunk source applied and failed before syncing from the network",
+        &["result"] // success, fail
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses cut short because their transactions exceeded the byte limit
+pub static CHUNK_RESPONSE_BYTE_LIMITED_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_chunk_response_byte_limited_count",
+        "Number of chunk responses cut short because their transactions exceeded the byte limit"
+    )
+    .unwrap()
+});
+
+/// Chunk size (in transactions) that the last downsized chunk response was reduced to
+pub static CHUNK_RESPONSE_DOWNSIZED_LIMIT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_chunk_response_downsized_limit",
+        "Chunk size that the last downsized chunk response was reduced to"
+    )
+    .unwrap()
+});
+
 pub static RESPONSE_FROM_DOWNSTREAM_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
         "diem_state_sync_responses_from_downstream_total",
@@ -130,6 +290,113 @@ pub static RESPONSE_FROM_DOWNSTREAM_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of chunks that do not extend the local transaction accumulator
+pub static ACCUMULATOR_CONTINUITY_VIOLATION_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_accumulator_continuity_violation_total",
+        "Number of chunks received from a peer that do not extend the local transaction accumulator",
+        &["network", "peer"]
+    )
+    .unwrap()
+});
+
+/// Number of times a peer is flagged for serving a LI that conflicts with the one another peer
+/// served for the same version
+pub static EQUIVOCATING_PEER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_equivocating_peer_total",
+        "Number of times a peer is flagged for serving a LI conflicting with the one another peer served for the same version",
+        &["network", "peer"]
+    )
+    .unwrap()
+});
+
+/// Number of chunks rejected because they mismatch a pinned waypoint
+pub static PINNED_WAYPOINT_MISMATCH: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_pinned_waypoint_mismatch_total",
+        "Number of chunks rejected because they mismatch a pinned waypoint"
+    )
+    .unwrap()
+});
+
+/// Number of chunks of which only the prefix that passed verification was applied
+pub static PARTIAL_CHUNK_APPLIED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_partial_chunk_applied_total",
+        "Number of chunks of which only the prefix that passed verification was applied"
+    )
+    .unwrap()
+});
+
+/// Number of commits of multiple chunks batched together
+pub static COALESCED_COMMIT_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_coalesced_commit_total",
+        "Number of commits of multiple chunks batched together"
+    )
+    .unwrap()
+});
+
+/// Percentage of the accumulator continuity checks served by the cached frozen subtree roots of the
+/// local transaction accumulator
+pub static FROZEN_SUBTREE_CACHE_HIT_RATE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_frozen_subtree_cache_hit_rate",
+        "Percentage of the accumulator continuity checks served by the frozen subtree cache"
+    )
+    .unwrap()
+});
+
+/// Number of chunks served to other peers from the chunk response cache
+pub static CHUNK_RESPONSE_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_chunk_response_cache_hits_total",
+        "Number of chunks served to other peers from the chunk response cache"
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses whose number of transactions contradicts their version range
+pub static CHUNK_VERSION_RANGE_MISMATCH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_chunk_version_range_mismatch_total",
+        "Number of chunk responses received from a peer whose transaction count contradicts their version range",
+        &["network", "peer"]
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses that would have served transactions beyond the committed version.
+/// Should stay 0: only transactions proven by a local LI are served
+pub static SERVE_BEYOND_COMMITTED_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_serve_beyond_committed_total",
+        "Number of chunk responses not served because they extend beyond the committed version"
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses rejected for being shorter than the min or larger than the max size
+pub static CHUNK_RESPONSE_SIZE_VIOLATION_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_chunk_response_size_violation_total",
+        "Number of chunk responses received from a peer rejected for their size",
+        &["network", "peer", "bound"] // under_min or over_max
+    )
+    .unwrap()
+});
+
+/// Number of decisions on attaching the highest local LI to a chunk response
+pub static HIGHEST_VERSION_ADVERTISEMENT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_highest_version_advertisement_total",
+        "Number of times the highest local version was advertised or withheld in a chunk response",
+        &["network", "decision"]
+    )
+    .unwrap()
+});
+
 /// Number of attempts to apply a chunk
 pub static APPLY_CHUNK_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
@@ -159,6 +426,29 @@ pub static STATE_SYNC_CHUNK_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Serialized size in bytes of a received chunk response, from 1KiB to 64MiB
+pub static CHUNK_RESPONSE_BYTES: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "diem_state_sync_chunk_response_bytes",
+        "Serialized size in bytes of a received chunk response",
+        &["network", "sender"],
+        (10..=26).map(|exp| (1u64 << exp) as f64).collect()
+    )
+    .unwrap()
+});
+
+/// Number of transactions in each chunk of a received chunk response, packed chunks included,
+/// from 1 to 16384
+pub static CHUNK_RESPONSE_TXNS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "diem_state_sync_chunk_response_txns",
+        "Number of transactions in each chunk of a received chunk response",
+        &["network", "sender"],
+        (0..=14).map(|exp| (1u64 << exp) as f64).collect()
+    )
+    .unwrap()
+});
+
 /// Number of peers that are currently active and upstream.
 /// They are the set of nodes a node can make sync requests to
 pub static ACTIVE_UPSTREAM_PEERS: Lazy<IntGaugeVec> = Lazy::new(|| {
@@ -207,6 +497,155 @@ pub static EPOCH: Lazy<IntGauge> = Lazy::new(|| {
     register_int_gauge!("diem_state_sync_epoch", "Current epoch in local state").unwrap()
 });
 
+/// Progress through a catch up that spans multiple epochs
+pub static EPOCH_CATCH_UP: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "diem_state_sync_epoch_catch_up",
+        "Current and target epoch of an epoch catch up in progress",
+        &["type"] // current or target
+    )
+    .unwrap()
+});
+
+/// Number of times an epoch catch up went too long without crossing an epoch boundary
+pub static EPOCH_CATCH_UP_STALL_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_epoch_catch_up_stall_total",
+        "Number of times an epoch catch up did not cross an epoch boundary on time"
+    )
+    .unwrap()
+});
+
+/// Number of escalation steps taken by a node that made no progress for too long
+pub static STALL_ESCALATION_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_stall_escalation_total",
+        "Number of escalation steps taken by a stalled node",
+        &["step"] // widen_peers, refresh_peers, switch_networks or alert
+    )
+    .unwrap()
+});
+
+/// Number of times a critical chunk request, i.e. for the target of a sync request from consensus,
+/// bypassed a throttle of background sync, by throttle
+pub static CRITICAL_SYNC_THROTTLE_BYPASSES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_critical_sync_throttle_bypasses_total",
+        "Number of times a critical chunk request bypassed a throttle of background sync",
+        &["throttle"]
+    )
+    .unwrap()
+});
+
+/// Number of times the waypoint trusted on restart was advanced to a recent epoch-ending LI
+pub static WAYPOINT_ADVANCEMENT_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_waypoint_advancement_total",
+        "Number of automatic waypoint advancements"
+    )
+    .unwrap()
+});
+
+/// Number of walks through the epochs up to the waypoint resumed from local storage after a restart
+pub static EPOCH_WALK_RESUME_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_epoch_walk_resume_total",
+        "Number of epoch walks to the waypoint resumed after a restart"
+    )
+    .unwrap()
+});
+
+/// Number of epoch-ending ledger infos rejected for not moving to the next epoch, i.e., for
+/// skipping epochs
+pub static EPOCH_SKIP_VIOLATION_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_epoch_skip_violation_total",
+        "Number of epoch-ending ledger infos rejected for skipping epochs"
+    )
+    .unwrap()
+});
+
+/// Whether the node is idle at the tip of the chain (1) or syncing (0)
+pub static IDLE_AT_TIP: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_idle_at_tip",
+        "Whether the node is idle at the tip of the chain (1) or syncing (0)"
+    )
+    .unwrap()
+});
+
+/// Whether the node serves chunk requests (1) or ignores them while bootstrapping (0)
+pub static SERVING_ENABLED: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_serving_enabled",
+        "Whether the node serves chunk requests (1) or ignores them while bootstrapping (0)"
+    )
+    .unwrap()
+});
+
+/// Whether syncing is paused (1) or running (0)
+pub static SYNC_PAUSED: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_paused",
+        "Whether syncing is paused (1) or running (0)"
+    )
+    .unwrap()
+});
+
+/// Number of distinct validators that signed the recently committed LIs
+pub static DISTINCT_SIGNERS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_distinct_signers",
+        "Number of distinct validators that signed the recently committed LIs"
+    )
+    .unwrap()
+});
+
+/// Number of times the distinct signers of the recently committed LIs dropped below the minimum
+pub static SIGNER_DIVERSITY_ALERT_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_signer_diversity_alert_total",
+        "Number of times the distinct signers of the recently committed LIs dropped below the minimum"
+    )
+    .unwrap()
+});
+
+/// Estimated tip of the chain: the highest version advertised last by at least half of the peers
+pub static TIP_ESTIMATE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_tip_estimate",
+        "Highest version advertised last by at least half of the peers"
+    )
+    .unwrap()
+});
+
+/// Whether peer advertisements of the tip of the chain are flapping (1) or not (0)
+pub static TIP_FLAPPING: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_tip_flapping",
+        "Whether peer advertisements of the tip of the chain are flapping"
+    )
+    .unwrap()
+});
+
+/// Number of times peer advertisements of the tip of the chain started flapping
+pub static TIP_FLAP_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_tip_flap_total",
+        "Number of times peer advertisements of the tip of the chain started flapping"
+    )
+    .unwrap()
+});
+
+/// Whether local storage is ahead of the highest version advertised by all known peers (1) or not (0)
+pub static AHEAD_OF_PEERS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_ahead_of_peers",
+        "Whether local storage is ahead of the highest version advertised by all known peers"
+    )
+    .unwrap()
+});
+
 /// How long it takes to make progress, from requesting a chunk to processing the response and
 /// committing the block
 pub static SYNC_PROGRESS_DURATION: Lazy<DurationHistogram> = Lazy::new(|| {
@@ -219,6 +658,27 @@ pub static SYNC_PROGRESS_DURATION: Lazy<DurationHistogram> = Lazy::new(|| {
     )
 });
 
+/// Time it takes a sync request to make progress, from the coordinator accepting the request to
+/// committing the first chunk toward its target
+pub static TIME_TO_FIRST_CHUNK: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "diem_state_sync_time_to_first_chunk_s",
+        "Histogram of time it takes from accepting a sync request to committing the first chunk toward its target"
+    )
+    .unwrap()
+});
+
+/// End-to-end latency of applying a chunk, from receiving the chunk response to its transactions
+/// being committed to storage (covering verification, execution and persistence)
+pub static CHUNK_COMMIT_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "diem_state_sync_chunk_commit_latency_s",
+        "Histogram of time it takes from receiving a chunk response to committing its transactions to storage",
+        &["epoch_change"] // whether the chunk ends an epoch: true, false
+    )
+    .unwrap()
+});
+
 /// Number of timeouts that occur during sync
 pub static TIMEOUT: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
@@ -238,6 +698,25 @@ pub static SYNC_REQUEST_RESULT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of sync cancels ignored because the sync request they were issued for was already
+/// replaced or completed
+pub static STALE_SYNC_CANCEL_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_stale_sync_cancel_total",
+        "Number of sync cancels ignored for targeting a stale sync request"
+    )
+    .unwrap()
+});
+
+/// Number of sync requests to a target of an epoch older than the trusted epoch
+pub static STALE_SYNC_TARGET_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_stale_sync_target_total",
+        "Number of sync requests to a target of an epoch older than the trusted epoch"
+    )
+    .unwrap()
+});
+
 /// Number of failures that occur during the commit flow across consensus, state sync, and mempool
 pub static COMMIT_FLOW_FAIL: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
@@ -296,6 +775,211 @@ pub static PROCESS_MSG_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of chunk executions that did not complete within the executor hang timeout
+pub static EXECUTOR_HANG_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_executor_hang_count",
+        "Number of chunk executions that did not complete within the executor hang timeout"
+    )
+    .unwrap()
+});
+
+/// Whether a chunk execution is currently exceeding the executor hang timeout (1) or not (0)
+pub static EXECUTOR_HUNG: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_executor_hung",
+        "Whether a chunk execution is currently exceeding the executor hang timeout (1) or not (0)"
+    )
+    .unwrap()
+});
+
+/// Number of bytes of chunk messages sent and received, per network
+pub static NETWORK_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_network_bytes_total",
+        "Number of bytes of chunk messages sent and received",
+        &["network", "direction", "msg_type"]
+    )
+    .unwrap()
+});
+
+/// Number of versions per second applied by executing chunks, over a sliding window
+pub static CHUNK_APPLY_THROUGHPUT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_chunk_apply_throughput_versions_per_sec",
+        "Number of versions per second applied by executing chunks, over a sliding window"
+    )
+    .unwrap()
+});
+
+/// Estimated percentiles of the rate chunks are synced at since the node started, in transactions
+/// per second
+pub static SYNC_RATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "diem_state_sync_sync_rate_txns_per_sec",
+        "Estimated percentiles of the number of transactions per second chunks are synced at",
+        &["quantile"]
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses of future epochs buffered, dropped because the buffer was full
+/// (`OVER_MAX_LABEL`), and replayed once their epoch was reached
+pub static FUTURE_EPOCH_CHUNK_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_future_epoch_chunk_count",
+        "Number of chunk responses of epochs beyond the trusted epoch buffered, dropped and replayed",
+        &["result"]
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses rejected because their LI isn't of the trusted epoch, by whether the
+/// LI is ahead of or behind it
+pub static EPOCH_MISMATCH_CHUNK: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_epoch_mismatch_chunk_total",
+        "Number of chunk responses rejected because their LI is ahead of or behind the trusted epoch",
+        &["direction"]
+    )
+    .unwrap()
+});
+
+/// Number of refreshes of the upstream config from the upstream resolver, by result
+pub static UPSTREAM_CONFIG_REFRESH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_upstream_config_refresh_count",
+        "Number of refreshes of the upstream config from the upstream resolver",
+        &["result"]
+    )
+    .unwrap()
+});
+
+/// Number of times an upstream peer was skipped when picking the peers of a chunk request because
+/// it's backing off after a failure
+pub static PEER_BACKOFF_SKIP_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_peer_backoff_skip_count",
+        "Number of times an upstream peer was skipped because it's backing off after a failure",
+        &["network"]
+    )
+    .unwrap()
+});
+
+/// Number of received chunk responses dropped because the queue of pending chunks was full
+pub static PENDING_CHUNK_OVERFLOW: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_pending_chunk_overflow_count",
+        "Number of chunk responses dropped because too many chunk responses were pending"
+    )
+    .unwrap()
+});
+
+/// Estimated memory of the in-memory structures of state sync, in bytes
+pub static MEMORY_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_memory_bytes",
+        "Estimated memory of the in-memory structures of state sync"
+    )
+    .unwrap()
+});
+
+/// Number of times a structure was dropped to stay under the state sync memory cap
+pub static MEMORY_SHED_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_memory_shed_total",
+        "Number of times a structure was dropped to stay under the memory cap",
+        &["structure"]
+    )
+    .unwrap()
+});
+
+/// Number of times the pinned upstream peer couldn't be requested or timed out, by reason
+pub static PINNED_UPSTREAM_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_state_sync_pinned_upstream_failures",
+        "Number of times the pinned upstream peer couldn't be requested or timed out",
+        &["reason"] // disconnected, backoff, timeout
+    )
+    .unwrap()
+});
+
+/// Number of times the node started syncing with fewer upstream peers than `min_peers_before_sync`
+/// because the wait for peers timed out
+pub static SYNC_STARTED_BELOW_MIN_PEERS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_sync_started_below_min_peers",
+        "Number of times the node started syncing with fewer upstream peers than the minimum"
+    )
+    .unwrap()
+});
+
+/// Number of chunk responses dropped because they extend past the sync ceiling version
+pub static SYNC_CEILING_DROPPED_CHUNKS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_sync_ceiling_dropped_chunks_total",
+        "Number of chunk responses dropped because they extend past the sync ceiling version"
+    )
+    .unwrap()
+});
+
+/// Number of chunk requests deferred because they were beyond the rate limit
+pub static RATE_LIMITED_REQUESTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_rate_limited_requests_total",
+        "Number of chunk requests deferred because they were beyond the rate limit"
+    )
+    .unwrap()
+});
+
+/// Number of chunk requests coalesced with an identical in-flight request instead of being sent
+pub static DEDUPED_CHUNK_REQUESTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_deduped_chunk_requests",
+        "Number of chunk requests coalesced with an identical in-flight request"
+    )
+    .unwrap()
+});
+
+/// Number of in-flight background chunk requests preempted by a targeted chunk request
+pub static PREEMPTED_BACKGROUND_REQUESTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_preempted_background_requests_total",
+        "Number of in-flight background chunk requests preempted by a targeted chunk request"
+    )
+    .unwrap()
+});
+
+/// Number of chunk requests tracked by the request manager, i.e. requests that might still get a
+/// response
+pub static IN_FLIGHT_CHUNK_REQUESTS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_state_sync_in_flight_chunk_requests",
+        "Number of chunk requests tracked by the request manager"
+    )
+    .unwrap()
+});
+
+/// Number of signature verification batches the signature verifier failed to verify, which are
+/// verified on the CPU instead
+pub static SIGNATURE_VERIFIER_ERROR_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_signature_verifier_error_total",
+        "Number of signature verification batches the signature verifier failed to verify"
+    )
+    .unwrap()
+});
+
+/// Number of executed chunks that the local storage failed to persist, as detected by reading
+/// the storage back after the commit
+pub static COMMIT_DURABILITY_CHECK_FAIL_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_commit_durability_check_fail_count",
+        "Number of executed chunks that the local storage failed to persist"
+    )
+    .unwrap()
+});
+
 pub static CONSENSUS_COMMIT_FAIL_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
         "diem_state_sync_consensus_commit_fail",
@@ -304,6 +988,25 @@ pub static CONSENSUS_COMMIT_FAIL_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     .unwrap()
 });
 
+/// Number of commit msgs from consensus that were acknowledged without any effect as they didn't
+/// commit any version beyond the previous commit msg
+pub static DUPLICATE_COMMIT_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_duplicate_commit_total",
+        "Number of duplicate commit msgs from consensus ignored"
+    )
+    .unwrap()
+});
+
+/// Number of LIs committed by syncing chunks that conflict with the last LI committed by consensus
+pub static CONSENSUS_COMMIT_CONFLICT_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_consensus_commit_conflict_total",
+        "Number of LIs committed by syncing chunks that conflict with the last consensus commit"
+    )
+    .unwrap()
+});
+
 pub static RECONFIG_PUBLISH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
         "diem_state_sync_reconfig_count",
@@ -313,6 +1016,14 @@ pub static RECONFIG_PUBLISH_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static RECONFIG_DEFERRED_NOTIFICATION_COUNT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "diem_state_sync_reconfig_deferred_notification_count",
+        "Number of times a reconfig notification to a subscription is deferred to a later publish"
+    )
+    .unwrap()
+});
+
 pub static STORAGE_READ_FAIL_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
         "diem_state_sync_storage_read_fail_count",
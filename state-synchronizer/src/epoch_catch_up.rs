@@ -0,0 +1,100 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    counters,
+    logging::{LogEntry, LogEvent, LogSchema},
+};
+use diem_logger::prelude::*;
+use diem_types::ledger_info::LedgerInfoWithSignatures;
+use std::time::{Duration, SystemTime};
+
+// DS to report progress of a node that has to cross multiple epochs to reach its target
+// (e.g. a node that is many epochs behind its waypoint or a consensus sync request target)
+pub struct EpochCatchUp {
+    // epoch of the local state
+    pub current_epoch: u64,
+    // epoch the local state will be in once the known target is reached
+    pub target_epoch: u64,
+    // time the local state last crossed an epoch boundary (or the catch up started)
+    last_progress_tst: SystemTime,
+    // max duration to go without crossing an epoch boundary before reporting a stall
+    timeout: Duration,
+    // whether a stall has been reported since the last epoch change
+    stall_reported: bool,
+}
+
+impl EpochCatchUp {
+    pub fn new(current_epoch: u64, timeout: Duration, now: SystemTime) -> Self {
+        Self {
+            current_epoch,
+            target_epoch: current_epoch,
+            last_progress_tst: now,
+            timeout,
+            stall_reported: false,
+        }
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.current_epoch < self.target_epoch
+    }
+
+    /// Updates the target epoch with the epoch that will be reached once `target_li` is synced to.
+    pub fn update_target(&mut self, target_li: &LedgerInfoWithSignatures, now: SystemTime) {
+        let target_epoch = target_li.ledger_info().next_block_epoch();
+        if target_epoch <= self.target_epoch {
+            return;
+        }
+        if !self.in_progress() {
+            self.last_progress_tst = now;
+            self.stall_reported = false;
+        }
+        self.target_epoch = target_epoch;
+        counters::EPOCH_CATCH_UP
+            .with_label_values(&[counters::TARGET_LABEL])
+            .set(target_epoch as i64);
+    }
+
+    /// Called whenever the local state moves to `new_epoch`.
+    pub fn process_epoch_change(&mut self, new_epoch: u64, now: SystemTime) {
+        let was_in_progress = self.in_progress();
+        self.current_epoch = new_epoch;
+        self.last_progress_tst = now;
+        self.stall_reported = false;
+        counters::EPOCH_CATCH_UP
+            .with_label_values(&[counters::CURRENT_LABEL])
+            .set(new_epoch as i64);
+        if was_in_progress {
+            info!(
+                LogSchema::event_log(LogEntry::EpochChange, LogEvent::CatchUpProgress)
+                    .new_epoch(new_epoch)
+                    .target_epoch(self.target_epoch),
+                "processed epoch {} of {}", new_epoch, self.target_epoch
+            );
+        }
+    }
+
+    /// Reports the catch up as stalled (once per stall) if no epoch boundary has been crossed
+    /// within the timeout.
+    pub fn check_stall(&mut self, now: SystemTime) {
+        if !self.in_progress() || self.stall_reported {
+            return;
+        }
+        let stalled = self
+            .last_progress_tst
+            .checked_add(self.timeout)
+            .map_or(false, |deadline| now.duration_since(deadline).is_ok());
+        if stalled {
+            self.stall_reported = true;
+            counters::EPOCH_CATCH_UP_STALL_COUNT.inc();
+            warn!(
+                LogSchema::event_log(LogEntry::EpochChange, LogEvent::CatchUpStall)
+                    .local_epoch(self.current_epoch)
+                    .target_epoch(self.target_epoch),
+                "no epoch boundary crossed in the last {:?} while catching up to epoch {}",
+                self.timeout,
+                self.target_epoch
+            );
+        }
+    }
+}
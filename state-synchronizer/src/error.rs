@@ -0,0 +1,61 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+/// Different reasons for state sync to fail a sync request or reject a chunk
+pub enum StateSyncError {
+    #[error("State synchronizer is unavailable: {0}")]
+    CoordinatorUnavailable(String),
+    #[error("Blocking call from a thread of the state sync runtime")]
+    BlockingCallFromRuntime,
+    #[error("Sync request cancelled")]
+    Cancelled,
+    #[error("Sync request to version {target_version} made no progress on time, the synced version is {synced_version}")]
+    NoProgress {
+        target_version: u64,
+        synced_version: u64,
+    },
+    #[error("State sync isn't initialized to its waypoint yet")]
+    NotInitialized,
+    #[error("State sync is quiesced")]
+    Quiesced,
+    #[error("State sync only serves chunks and doesn't sync")]
+    ServeOnly,
+    #[error(
+        "Sync target version {target_version} is beyond the sync ceiling version {ceiling_version}"
+    )]
+    BeyondSyncCeiling {
+        target_version: u64,
+        ceiling_version: u64,
+    },
+    #[error("Sync request replaced by a newer one")]
+    Replaced,
+    #[error("Sync request applied {epoch_changes} epoch changes without reaching its target, beyond the max of {max_epoch_changes}")]
+    TooManyEpochChanges {
+        epoch_changes: u64,
+        max_epoch_changes: u64,
+    },
+    #[error("Sync target of epoch {target_epoch} is older than the trusted epoch {trusted_epoch}")]
+    StaleTarget {
+        target_epoch: u64,
+        trusted_epoch: u64,
+    },
+    #[error(
+        "Sync target version {target_version} is behind the committed version {committed_version}"
+    )]
+    TargetBehindCommitted {
+        target_version: u64,
+        committed_version: u64,
+    },
+    #[error(
+        "Missing epoch change proofs from trusted epoch {local_epoch} to epoch {target_epoch}"
+    )]
+    UnknownEpoch { local_epoch: u64, target_epoch: u64 },
+    #[error("No ledger info reached version {version} on time, the committed version is {committed_version}")]
+    VersionNotReached {
+        version: u64,
+        committed_version: u64,
+    },
+}
@@ -4,9 +4,10 @@
 use crate::{
     counters,
     logging::{LogEntry, LogEvent, LogSchema},
-    SynchronizerState,
+    CommitOutcome, SynchronizerState,
 };
 use anyhow::{format_err, Result};
+use diem_crypto::hash::CryptoHash;
 use diem_logger::prelude::*;
 use diem_types::{
     account_state::AccountState,
@@ -14,11 +15,16 @@ use diem_types::{
     ledger_info::LedgerInfoWithSignatures,
     move_resource::MoveStorage,
     on_chain_config::{config_address, OnChainConfigPayload, ON_CHAIN_CONFIG_REGISTRY},
+    proof::TransactionListProof,
     transaction::TransactionListWithProof,
 };
 use executor_types::{ChunkExecutor, ExecutedTrees};
 use itertools::Itertools;
-use std::{collections::HashSet, convert::TryFrom, sync::Arc};
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::TryFrom,
+    sync::Arc,
+};
 use storage_interface::DbReader;
 use subscription_service::ReconfigSubscription;
 
@@ -28,13 +34,31 @@ pub trait ExecutorProxyTrait: Send {
     fn get_local_storage_state(&self) -> Result<SynchronizerState>;
 
     /// Execute and commit a batch of transactions
+    /// Returns the reconfiguration events emitted by the committed transactions.
     fn execute_chunk(
         &mut self,
         txn_list_with_proof: TransactionListWithProof,
         verified_target_li: LedgerInfoWithSignatures,
         intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
+    ) -> Result<Vec<ContractEvent>>;
+
+    /// Verifies a batch of transactions and their proofs against the given ledger info, as
+    /// execution would, without executing or committing them.
+    fn verify_chunk(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        verified_target_li: &LedgerInfoWithSignatures,
     ) -> Result<()>;
 
+    /// Returns the longest prefix of a batch of transactions that passes verification against the
+    /// given ledger info, with a proof of its own, or None if even the first transaction fails.
+    /// Fails if the proof of the batch itself doesn't match the ledger info.
+    fn verified_chunk_prefix(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        verified_target_li: &LedgerInfoWithSignatures,
+    ) -> Result<Option<TransactionListWithProof>>;
+
     /// Gets chunk of transactions given the known version, target version and the max limit.
     fn get_chunk(
         &self,
@@ -59,8 +83,16 @@ pub trait ExecutorProxyTrait: Send {
     /// we can make this entirely internal to `ExecutorProxy`'s initialization procedure
     fn load_on_chain_configs(&mut self) -> Result<()>;
 
-    /// publishes on-chain config updates to subscribed components
-    fn publish_on_chain_config_updates(&mut self, events: Vec<ContractEvent>) -> Result<()>;
+    /// publishes on-chain config updates to subscribed components, and returns the subscriptions
+    /// notified and the events they consumed
+    fn publish_on_chain_config_updates(
+        &mut self,
+        events: Vec<ContractEvent>,
+    ) -> Result<CommitOutcome>;
+
+    /// publishes on-chain config updates that were deferred by previous commits because they
+    /// exceeded the notification budget of the commit
+    fn publish_deferred_on_chain_config_updates(&mut self) -> Result<()>;
 }
 
 pub(crate) struct ExecutorProxy {
@@ -68,6 +100,12 @@ pub(crate) struct ExecutorProxy {
     executor: Box<dyn ChunkExecutor>,
     reconfig_subscriptions: Vec<ReconfigSubscription>,
     on_chain_configs: OnChainConfigPayload,
+    // indices of the subscriptions with a notification that is yet to be published, in the order
+    // they were notified
+    // A deferred notification always publishes the latest on-chain configs.
+    deferred_notifications: VecDeque<usize>,
+    // max number of notifications published to subscriptions at a time
+    max_notifications_per_commit: usize,
 }
 
 impl ExecutorProxy {
@@ -75,6 +113,7 @@ impl ExecutorProxy {
         storage: Arc<dyn DbReader>,
         executor: Box<dyn ChunkExecutor>,
         mut reconfig_subscriptions: Vec<ReconfigSubscription>,
+        max_notifications_per_commit: usize,
     ) -> Self {
         let on_chain_configs = Self::fetch_all_configs(&*storage)
             .expect("[state sync] Failed initial read of on-chain configs");
@@ -88,6 +127,59 @@ impl ExecutorProxy {
             executor,
             reconfig_subscriptions,
             on_chain_configs,
+            deferred_notifications: VecDeque::new(),
+            max_notifications_per_commit,
+        }
+    }
+
+    /// Publishes the latest on-chain configs to the deferred subscriptions, up to the notification
+    /// budget of a commit. The remaining notifications stay deferred. Returns the names of the
+    /// subscriptions notified.
+    fn publish_notifications(&mut self) -> Result<Vec<String>> {
+        let mut publish_success = true;
+        let mut notified = vec![];
+        let mut num_published = 0;
+        while num_published < self.max_notifications_per_commit {
+            let subscription = match self.deferred_notifications.pop_front() {
+                Some(index) => &mut self.reconfig_subscriptions[index],
+                None => break,
+            };
+            num_published += 1;
+            if let Err(e) = subscription.publish(self.on_chain_configs.clone()) {
+                publish_success = false;
+                error!(
+                    LogSchema::event_log(LogEntry::Reconfig, LogEvent::PublishError)
+                        .subscription_name(subscription.name.clone())
+                        .error(&e),
+                    "Failed to publish reconfig notification to subscription {}", subscription.name
+                );
+            } else {
+                debug!(
+                    LogSchema::event_log(LogEntry::Reconfig, LogEvent::Success)
+                        .subscription_name(subscription.name.clone()),
+                    "Successfully published reconfig notification to subscription {}",
+                    subscription.name
+                );
+                notified.push(subscription.name.clone());
+            }
+        }
+
+        if !self.deferred_notifications.is_empty() {
+            counters::RECONFIG_DEFERRED_NOTIFICATION_COUNT
+                .inc_by(self.deferred_notifications.len() as u64);
+            debug!(
+                LogSchema::event_log(LogEntry::Reconfig, LogEvent::Deferred)
+                    .count(self.deferred_notifications.len()),
+                "Deferred reconfig notifications beyond the budget of the commit"
+            );
+        }
+        if publish_success {
+            counters::RECONFIG_PUBLISH_COUNT
+                .with_label_values(&[counters::SUCCESS_LABEL])
+                .inc();
+            Ok(notified)
+        } else {
+            Err(format_err!("failed to publish at least one subscription"))
         }
     }
 
@@ -155,7 +247,7 @@ impl ExecutorProxyTrait for ExecutorProxy {
         txn_list_with_proof: TransactionListWithProof,
         verified_target_li: LedgerInfoWithSignatures,
         intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
-    ) -> Result<()> {
+    ) -> Result<Vec<ContractEvent>> {
         // track chunk execution time
         let timer = counters::EXECUTE_CHUNK_DURATION.start_timer();
         let reconfig_events = self.executor.execute_and_commit_chunk(
@@ -164,7 +256,7 @@ impl ExecutorProxyTrait for ExecutorProxy {
             intermediate_end_of_epoch_li,
         )?;
         timer.stop_and_record();
-        if let Err(e) = self.publish_on_chain_config_updates(reconfig_events) {
+        if let Err(e) = self.publish_on_chain_config_updates(reconfig_events.clone()) {
             error!(
                 LogSchema::event_log(LogEntry::Reconfig, LogEvent::Fail).error(&e),
                 "Failed to publish reconfig updates in execute_chunk"
@@ -173,7 +265,62 @@ impl ExecutorProxyTrait for ExecutorProxy {
                 .with_label_values(&[counters::FAIL_LABEL])
                 .inc();
         }
-        Ok(())
+        Ok(reconfig_events)
+    }
+
+    fn verify_chunk(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        verified_target_li: &LedgerInfoWithSignatures,
+    ) -> Result<()> {
+        txn_list_with_proof.verify(
+            verified_target_li.ledger_info(),
+            txn_list_with_proof.first_transaction_version,
+        )
+    }
+
+    fn verified_chunk_prefix(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        verified_target_li: &LedgerInfoWithSignatures,
+    ) -> Result<Option<TransactionListWithProof>> {
+        let first_version = match txn_list_with_proof.first_transaction_version {
+            Some(first_version) => first_version,
+            None => return Ok(None),
+        };
+        // the transaction infos are proven as a whole, so a prefix can only be proven if all of
+        // them match the LI
+        let (range_proof, txn_infos) = txn_list_with_proof.proof.clone().unpack();
+        let txn_info_hashes: Vec<_> = txn_infos.iter().map(CryptoHash::hash).collect();
+        range_proof.verify(
+            verified_target_li
+                .ledger_info()
+                .transaction_accumulator_hash(),
+            Some(first_version),
+            &txn_info_hashes,
+        )?;
+        let num_valid_txns = txn_list_with_proof
+            .transactions
+            .iter()
+            .zip(txn_infos.iter())
+            .take_while(|(txn, txn_info)| txn.hash() == txn_info.transaction_hash())
+            .count();
+        if num_valid_txns == 0 {
+            return Ok(None);
+        }
+        let prefix_proof = range_proof.prefix(first_version, &txn_info_hashes, num_valid_txns)?;
+        let prefix = TransactionListWithProof::new(
+            txn_list_with_proof.transactions[..num_valid_txns].to_vec(),
+            txn_list_with_proof
+                .events
+                .as_ref()
+                .map(|events| events.iter().take(num_valid_txns).cloned().collect()),
+            Some(first_version),
+            TransactionListProof::new(prefix_proof, txn_infos[..num_valid_txns].to_vec()),
+        );
+        // the events of the prefix, if any, are verified as well
+        prefix.verify(verified_target_li.ledger_info(), Some(first_version))?;
+        Ok(Some(prefix))
     }
 
     fn get_chunk(
@@ -207,9 +354,12 @@ impl ExecutorProxyTrait for ExecutorProxy {
         Ok(())
     }
 
-    fn publish_on_chain_config_updates(&mut self, events: Vec<ContractEvent>) -> Result<()> {
+    fn publish_on_chain_config_updates(
+        &mut self,
+        events: Vec<ContractEvent>,
+    ) -> Result<CommitOutcome> {
         if events.is_empty() {
-            return Ok(());
+            return Ok(CommitOutcome::default());
         }
         info!(LogSchema::new(LogEntry::Reconfig)
             .count(events.len())
@@ -237,42 +387,35 @@ impl ExecutorProxyTrait for ExecutorProxy {
             .collect::<HashSet<_>>();
 
         // notify subscribers
-        let mut publish_success = true;
-        for subscription in self.reconfig_subscriptions.iter_mut() {
+        let mut consumed_event_keys = HashSet::new();
+        for (index, subscription) in self.reconfig_subscriptions.iter().enumerate() {
             // publish updates if *any* of the subscribed configs changed
             // or any of the subscribed events were emitted
             let subscribed_items = subscription.subscribed_items();
-            if !changed_configs.is_disjoint(&subscribed_items.configs)
-                || !event_keys.is_disjoint(&subscribed_items.events)
+            consumed_event_keys.extend(event_keys.intersection(&subscribed_items.events).cloned());
+            if (!changed_configs.is_disjoint(&subscribed_items.configs)
+                || !event_keys.is_disjoint(&subscribed_items.events))
+                && !self.deferred_notifications.contains(&index)
             {
-                if let Err(e) = subscription.publish(new_configs.clone()) {
-                    publish_success = false;
-                    error!(
-                        LogSchema::event_log(LogEntry::Reconfig, LogEvent::PublishError)
-                            .subscription_name(subscription.name.clone())
-                            .error(&e),
-                        "Failed to publish reconfig notification to subscription {}",
-                        subscription.name
-                    );
-                } else {
-                    debug!(
-                        LogSchema::event_log(LogEntry::Reconfig, LogEvent::Success)
-                            .subscription_name(subscription.name.clone()),
-                        "Successfully published reconfig notification to subscription {}",
-                        subscription.name
-                    );
-                }
+                self.deferred_notifications.push_back(index);
             }
         }
 
         self.on_chain_configs = new_configs;
-        if publish_success {
-            counters::RECONFIG_PUBLISH_COUNT
-                .with_label_values(&[counters::SUCCESS_LABEL])
-                .inc();
-            Ok(())
-        } else {
-            Err(format_err!("failed to publish at least one subscription"))
+        let notified_subscriptions = self.publish_notifications()?;
+        Ok(CommitOutcome {
+            notified_subscriptions,
+            num_events_consumed: events
+                .iter()
+                .filter(|event| consumed_event_keys.contains(event.key()))
+                .count(),
+        })
+    }
+
+    fn publish_deferred_on_chain_config_updates(&mut self) -> Result<()> {
+        if self.deferred_notifications.is_empty() {
+            return Ok(());
         }
+        self.publish_notifications().map(|_| ())
     }
 }
@@ -0,0 +1,66 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    counters,
+    logging::{LogEntry, LogEvent, LogSchema},
+};
+use diem_logger::prelude::*;
+use diem_types::transaction::Version;
+use std::{sync::mpsc::RecvTimeoutError, thread, time::Duration};
+
+// DS to report the chunk executions that don't complete within the executor hang timeout, from a
+// single long-lived thread: the coordinator is blocked on the execution, so it can't detect the
+// hang by itself. The thread exits once the watchdog is dropped.
+pub struct ExecutorWatchdog {
+    // the known version of an execution when it starts, None when it completes
+    sender: std::sync::mpsc::Sender<Option<Version>>,
+}
+
+impl ExecutorWatchdog {
+    pub fn new(hang_timeout: Duration) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        thread::Builder::new()
+            .name("state-sync-watchdog".into())
+            .spawn(move || Self::run(receiver, hang_timeout))
+            .expect("[state sync] failed to spawn the executor watchdog thread");
+        Self { sender }
+    }
+
+    fn run(receiver: std::sync::mpsc::Receiver<Option<Version>>, hang_timeout: Duration) {
+        while let Ok(started) = receiver.recv() {
+            let known_version = match started {
+                Some(known_version) => known_version,
+                None => continue,
+            };
+            match receiver.recv_timeout(hang_timeout) {
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    counters::EXECUTOR_HANG_COUNT.inc();
+                    counters::EXECUTOR_HUNG.set(1);
+                    error!(
+                        LogSchema::event_log(LogEntry::ProcessChunkResponse, LogEvent::ExecutorHang)
+                            .version(known_version),
+                        "[state sync] CRITICAL: executing the chunk after version {} did not complete within {:?}",
+                        known_version,
+                        hang_timeout
+                    );
+                    // waits for the hung execution to complete
+                    if receiver.recv().is_err() {
+                        return;
+                    }
+                    counters::EXECUTOR_HUNG.set(0);
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    pub fn start(&self, known_version: Version) {
+        let _ = self.sender.send(Some(known_version));
+    }
+
+    pub fn stop(&self) {
+        let _ = self.sender.send(None);
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::counters;
+use diem_crypto::{hash::TransactionAccumulatorHasher, HashValue};
+use diem_types::proof::accumulator::InMemoryAccumulator;
+use std::mem::size_of;
+
+// DS to cache the frozen subtree roots of the local transaction accumulator, so that the left
+// side of the range proof of a chunk is compared to them directly instead of rebuilding an
+// accumulator from the proof. The cache is refreshed whenever the local state is synced with the
+// storage, i.e. together with every commit.
+pub struct FrozenSubtreeCache {
+    enabled: bool,
+    // (number of leaves, frozen subtree roots) of the local accumulator
+    cached: Option<(u64, Vec<HashValue>)>,
+    hits: u64,
+    lookups: u64,
+}
+
+impl FrozenSubtreeCache {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            cached: None,
+            hits: 0,
+            lookups: 0,
+        }
+    }
+
+    pub fn update(&mut self, accumulator: &InMemoryAccumulator<TransactionAccumulatorHasher>) {
+        if self.enabled {
+            self.cached = Some((
+                accumulator.num_leaves(),
+                accumulator.frozen_subtree_roots().clone(),
+            ));
+        }
+    }
+
+    /// Returns the cached frozen subtree roots if they are the ones of an accumulator with
+    /// `num_leaves` leaves.
+    pub fn get(&mut self, num_leaves: u64) -> Option<&Vec<HashValue>> {
+        if !self.enabled {
+            return None;
+        }
+        self.lookups += 1;
+        let roots = match &self.cached {
+            Some((cached_num_leaves, roots)) if *cached_num_leaves == num_leaves => {
+                self.hits += 1;
+                Some(roots)
+            }
+            _ => None,
+        };
+        counters::FROZEN_SUBTREE_CACHE_HIT_RATE.set((self.hits * 100 / self.lookups) as i64);
+        roots
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.cached
+            .as_ref()
+            .map_or(0, |(_, roots)| roots.len() * size_of::<HashValue>())
+    }
+
+    /// Drops the cached roots, which are cached again on the next sync with the storage.
+    /// Returns the memory freed.
+    pub fn shed(&mut self) -> usize {
+        let memory = self.memory_bytes();
+        self.cached = None;
+        memory
+    }
+}
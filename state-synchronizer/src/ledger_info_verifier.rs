@@ -0,0 +1,181 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    coordinator::li_memory_bytes,
+    counters,
+    logging::{LogEntry, LogEvent, LogSchema},
+    signature_verifier::{CpuSignatureVerifier, SignatureVerifier},
+};
+use anyhow::{ensure, format_err, Result};
+use diem_crypto::{hash::CryptoHash, HashValue};
+use diem_logger::prelude::*;
+use diem_types::{epoch_state::EpochState, ledger_info::LedgerInfoWithSignatures};
+use std::{
+    collections::{HashMap, VecDeque},
+    mem::size_of,
+};
+
+// DS to verify the signatures of the ledger infos carried by chunk responses.
+// With batch verification enabled, a ledger info shared by multiple chunks of the same epoch
+// (e.g. the target LI of a sync request) is verified once instead of once per chunk.
+pub struct LedgerInfoVerifier {
+    // verifies the signatures of the ledger infos, possibly offloaded to an accelerator
+    signature_verifier: Box<dyn SignatureVerifier>,
+    batch_verify: bool,
+    // epoch of the ledger infos in `verified`
+    epoch: u64,
+    // ledger infos of `epoch` whose signatures have already been verified
+    pub verified: HashMap<HashValue, LedgerInfoWithSignatures>,
+    // hashes of the ledger infos in `verified`, least recently used first
+    lru: VecDeque<HashValue>,
+    // max number of verified ledger infos to keep in memory
+    max_verified: usize,
+    // number of signature verifications handed to the crypto layer
+    pub num_crypto_calls: u64,
+}
+
+impl LedgerInfoVerifier {
+    pub fn new(
+        signature_verifier: Box<dyn SignatureVerifier>,
+        batch_verify: bool,
+        max_verified: usize,
+    ) -> Self {
+        Self {
+            signature_verifier,
+            batch_verify,
+            epoch: 0,
+            verified: HashMap::new(),
+            lru: VecDeque::new(),
+            max_verified,
+            num_crypto_calls: 0,
+        }
+    }
+
+    /// Verifies `li` against `trusted_epoch`, skipping the crypto layer if batching is enabled
+    /// and the exact same LI was already verified in this epoch.
+    pub fn verify(
+        &mut self,
+        trusted_epoch: &EpochState,
+        li: &LedgerInfoWithSignatures,
+    ) -> Result<()> {
+        self.verify_all(trusted_epoch, &[li])
+    }
+
+    /// Verifies all of `lis` against `trusted_epoch`, handing the signatures that weren't
+    /// verified yet to the signature verifier in a single batch.
+    pub fn verify_all(
+        &mut self,
+        trusted_epoch: &EpochState,
+        lis: &[&LedgerInfoWithSignatures],
+    ) -> Result<()> {
+        for li in lis {
+            ensure!(
+                li.ledger_info().epoch() == trusted_epoch.epoch,
+                "LedgerInfo has unexpected epoch {}, expected {}",
+                li.ledger_info().epoch(),
+                trusted_epoch.epoch
+            );
+        }
+        if self.batch_verify && self.epoch != trusted_epoch.epoch {
+            self.invalidate(trusted_epoch.epoch);
+        }
+        let mut unverified = vec![];
+        for li in lis.iter().copied() {
+            if self.batch_verify && self.is_verified(li) {
+                self.touch(li.ledger_info().hash());
+            } else {
+                unverified.push(li);
+            }
+        }
+        if unverified.is_empty() {
+            return Ok(());
+        }
+
+        self.num_crypto_calls += unverified.len() as u64;
+        let results = match self
+            .signature_verifier
+            .verify_batch(&trusted_epoch.verifier, &unverified)
+        {
+            Ok(results) if results.len() == unverified.len() => results,
+            Ok(results) => {
+                Self::on_signature_verifier_error(format_err!(
+                    "returned {} results for {} ledger infos",
+                    results.len(),
+                    unverified.len()
+                ));
+                CpuSignatureVerifier.verify_batch(&trusted_epoch.verifier, &unverified)?
+            }
+            Err(e) => {
+                Self::on_signature_verifier_error(e);
+                CpuSignatureVerifier.verify_batch(&trusted_epoch.verifier, &unverified)?
+            }
+        };
+        for (li, result) in unverified.into_iter().zip(results) {
+            result?;
+            if self.batch_verify && self.max_verified > 0 {
+                self.insert(li);
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets the verified LIs, which aren't trusted anymore once the trusted epoch moves to
+    /// `epoch`.
+    pub fn invalidate(&mut self, epoch: u64) {
+        self.verified.clear();
+        self.lru.clear();
+        self.epoch = epoch;
+    }
+
+    fn is_verified(&self, li: &LedgerInfoWithSignatures) -> bool {
+        self.verified.get(&li.ledger_info().hash()) == Some(li)
+    }
+
+    // Marks the verified LI of the given hash as the most recently used
+    fn touch(&mut self, hash: HashValue) {
+        if let Some(position) = self.lru.iter().position(|lru_hash| *lru_hash == hash) {
+            self.lru.remove(position);
+        }
+        self.lru.push_back(hash);
+    }
+
+    // Remembers a verified LI, evicting the least recently used ones beyond the max
+    fn insert(&mut self, li: &LedgerInfoWithSignatures) {
+        let hash = li.ledger_info().hash();
+        self.verified.insert(hash, li.clone());
+        self.touch(hash);
+        while self.lru.len() > self.max_verified {
+            if let Some(evicted) = self.lru.pop_front() {
+                self.verified.remove(&evicted);
+            }
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.verified
+            .values()
+            .map(|li| 2 * size_of::<HashValue>() + li_memory_bytes(li))
+            .sum()
+    }
+
+    /// Drops the verified LIs, which are verified again if needed. Returns the memory freed.
+    pub fn shed(&mut self) -> usize {
+        let memory = self.memory_bytes();
+        self.verified.clear();
+        self.lru.clear();
+        memory
+    }
+
+    fn on_signature_verifier_error(error: anyhow::Error) {
+        counters::SIGNATURE_VERIFIER_ERROR_COUNT.inc();
+        warn!(
+            LogSchema::event_log(
+                LogEntry::ProcessChunkResponse,
+                LogEvent::SignatureVerifierFail
+            )
+            .error(&error),
+            "falling back to verifying signatures on the CPU"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use rand::{thread_rng, Rng};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// DS to schedule the proactive broadcasts of the committed LI to peers. Broadcasts are jittered, so
+// that the nodes that started together don't all broadcast at the same time.
+pub struct LiBroadcast {
+    enabled: bool,
+    interval: Duration,
+    next_broadcast_tst: SystemTime,
+}
+
+impl LiBroadcast {
+    pub fn new(enabled: bool, interval: Duration, now: SystemTime) -> Self {
+        let mut li_broadcast = Self {
+            enabled,
+            interval,
+            next_broadcast_tst: UNIX_EPOCH,
+        };
+        li_broadcast.reschedule(now);
+        li_broadcast
+    }
+
+    /// Returns whether a broadcast is due, and schedules the next one if so
+    pub fn due(&mut self, now: SystemTime) -> bool {
+        if !self.enabled || now < self.next_broadcast_tst {
+            return false;
+        }
+        self.reschedule(now);
+        true
+    }
+
+    fn reschedule(&mut self, now: SystemTime) {
+        let jittered_interval = self.interval.mul_f64(thread_rng().gen_range(0.5, 1.0));
+        self.next_broadcast_tst = now + jittered_interval;
+    }
+}
@@ -7,19 +7,53 @@
 //! Used for node restarts, network partitions, full node syncs
 #![recursion_limit = "1024"]
 
-use diem_types::{epoch_state::EpochState, ledger_info::LedgerInfoWithSignatures};
+use anyhow::Result;
+use diem_config::network_id::NetworkId;
+use diem_types::{
+    account_address::AccountAddress, contract_event::ContractEvent, epoch_state::EpochState,
+    ledger_info::LedgerInfoWithSignatures,
+};
 use executor_types::ExecutedTrees;
-pub use synchronizer::{StateSyncClient, StateSynchronizer};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, SystemTime},
+};
+pub use synchronizer::{
+    CommitHandle, StateSyncClient, StateSyncClientBlocking, StateSynchronizer, SyncHandle,
+};
 
+mod apply_throughput;
+mod bandwidth_usage;
 pub mod chunk_request;
 pub mod chunk_response;
+mod chunk_response_cache;
+pub mod chunk_source;
+mod commit_batch;
 pub mod coordinator;
 mod counters;
+mod epoch_catch_up;
+pub mod error;
 mod executor_proxy;
+mod executor_watchdog;
+mod frozen_subtree_cache;
+mod ledger_info_verifier;
+mod li_broadcast;
 mod logging;
 pub mod network;
+pub mod peer_selector;
+mod recent_ledger_infos;
 mod request_manager;
+pub mod signature_verifier;
+mod signer_diversity;
+mod stall_escalation;
+mod sync_lifecycle;
+mod sync_rate_stats;
 mod synchronizer;
+pub mod time_provider;
+mod tip_flap_detector;
+pub mod upstream_resolver;
+mod waypoint_advancement;
 
 /// The state distinguishes between the following fields:
 /// * highest_local_li is keeping the latest certified ledger info
@@ -37,6 +71,187 @@ pub struct SynchronizerState {
     pub trusted_epoch: EpochState,
 }
 
+/// Health of state synchronizer as reported to external orchestration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncHealth {
+    // the version of the highest LI in the local storage
+    pub committed_version: u64,
+    // whether the node is caught up and idle at the tip of the chain (as opposed to syncing)
+    pub is_idle_at_tip: bool,
+    // the highest version advertised last by at least half of the peers, if any advertised
+    pub tip_estimate: Option<u64>,
+    // health of the upstream peers, per upstream network
+    pub peer_health: HashMap<NetworkId, PeerHealth>,
+    // whether the local storage advanced within the health check staleness window, or the node
+    // is idle at the tip, i.e. caught up with nothing to sync
+    pub is_progressing: bool,
+    // seconds since a commit last advanced the local storage (or the node started)
+    pub seconds_since_last_progress: u64,
+}
+
+/// Sync progress of state synchronizer, e.g. for dashboards reporting how far behind a node is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncProgress {
+    // the version of the highest LI in the local storage
+    pub committed_version: u64,
+    // the highest version of the transactions in the local storage
+    pub synced_version: u64,
+    // the version of the target of the active sync request, if any
+    pub target_version: Option<u64>,
+    // the epoch the ledger infos of the chunks are verified against
+    pub trusted_epoch: u64,
+}
+
+/// Progress of the initial sync to the waypoint, reported periodically while the node initializes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitProgress {
+    // the highest version of the transactions in the local storage
+    pub synced_version: u64,
+    // the version of the waypoint
+    pub target_version: u64,
+    // the epoch of the local storage
+    pub epoch: u64,
+}
+
+/// Number of upstream peers of a network, by health.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PeerHealth {
+    // connected peers in good standing
+    pub healthy: usize,
+    // connected peers whose score dropped below half of the max score, e.g. after sending invalid
+    // chunks or timing out
+    pub penalized: usize,
+    // known peers that are disconnected
+    pub disconnected: usize,
+}
+
+/// Bytes of the chunk requests and responses state synchronizer sent and received over a recent
+/// window, e.g. for operators on metered connections.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BandwidthStats {
+    // window the bytes are counted over
+    pub window: Duration,
+    // bytes of the chunk requests sent and of the chunk responses received to sync the node
+    pub syncing_bytes_sent: u64,
+    pub syncing_bytes_received: u64,
+    // bytes of the chunk responses sent and of the chunk requests received to serve other nodes
+    pub serving_bytes_sent: u64,
+    pub serving_bytes_received: u64,
+}
+
+/// Percentiles of the rate state synchronizer synced chunks at since it started, in transactions
+/// per second, to characterize how much the sync performance varies and not only its mean.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncRatePercentiles {
+    // number of synced chunks the percentiles are estimated over
+    pub num_samples: u64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+/// Chunk statistics of an upstream peer, e.g. for operators debugging why a node is stuck.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PeerSyncStats {
+    // chunk requests sent to the peer
+    pub chunks_requested: u64,
+    // chunk responses received from the peer
+    pub chunks_received: u64,
+    // chunk requests to the peer that timed out and chunk responses of the peer that couldn't be
+    // applied, e.g. because they were empty or invalid
+    pub chunks_failed: u64,
+    // exponentially weighted moving average of the time between sending a chunk request to the
+    // peer and receiving its response, if any response was received
+    pub avg_response_latency: Option<Duration>,
+    // chunk limit of the next requests to the peer, if the chunk limit adapts to each peer
+    // (`adaptive_chunk_sizing`)
+    pub adaptive_chunk_limit: Option<u64>,
+}
+
+/// Results of the chunk verification of state synchronizer in verification-only mode, e.g. for
+/// auditors probing whether peers serve valid, correctly proven chunks.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VerificationReport {
+    // chunks whose LI and proofs passed verification
+    pub verified_chunks: u64,
+    // chunks that failed verification, e.g. because of an invalid proof or LI
+    pub failed_chunks: u64,
+    // version ranges (first version, last version) of the chunks that passed verification
+    pub verified_versions: BTreeSet<(u64, u64)>,
+    // error of the last chunk that failed verification, if any
+    pub last_failure: Option<String>,
+}
+
+/// Reason state synchronizer most recently failed to make progress.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StallReason {
+    // there are no upstream peers to request chunks from
+    NoPeers,
+    // local storage is ahead of the versions advertised by all known peers
+    PeersBehind,
+    // the last chunk that could be applied failed verification, e.g. of its proof or ledger info
+    VerificationFailure(String),
+    // the last chunk that could be applied failed to be executed or persisted
+    ExecutionFailure(String),
+    // the chunk requests timed out without a response that could be applied
+    NoResponse,
+    // the node has nothing left to sync
+    AtTip,
+}
+
+/// Reconfiguration events committed by the node, for consumers that only follow reconfigurations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconfigNotification {
+    // epoch of the LI the events were committed with, i.e. the epoch they ended
+    pub epoch: u64,
+    // version of the LI the events were committed with
+    pub version: u64,
+    pub events: Vec<ContractEvent>,
+}
+
+/// Outcome of the reconfiguration events of a commit, e.g. for operators debugging why a
+/// reconfiguration didn't propagate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommitOutcome {
+    // names of the reconfig subscriptions notified by the commit. Notifications beyond the
+    // notification budget of the commit are deferred to the next ones and aren't listed
+    pub notified_subscriptions: Vec<String>,
+    // reconfig events of the commit subscribed to by at least one subscription
+    pub num_events_consumed: usize,
+}
+
+/// Listener of the epoch transitions of state synchronizer, e.g. for nodes that rotate their keys
+/// on epoch boundaries. Unlike reconfig subscriptions, it's handed the epoch states themselves and
+/// runs as part of the transition.
+pub trait EpochChangeListener: Send {
+    /// Called exactly once per epoch transition with the old and the new trusted epoch state,
+    /// before state synchronizer moves to the new epoch, i.e. before the chunks of the new epoch
+    /// are requested and verified. State synchronizer waits for the call to return, so it should
+    /// be quick. An error is logged and doesn't prevent the transition.
+    fn on_epoch_change(&mut self, old: &EpochState, new: &EpochState) -> Result<()>;
+}
+
+/// Notification of an epoch transition of state synchronizer, e.g. for indexers. It's sent once
+/// the LI ending the old epoch is committed, i.e. the storage is already in the new epoch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpochChangeNotification {
+    pub old_epoch: u64,
+    pub new_epoch: u64,
+    // trusted epoch state of the new epoch
+    pub epoch_state: EpochState,
+}
+
+/// Epoch transition of state synchronizer, as kept in the epoch transition history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpochTransition {
+    // the epoch moved to
+    pub epoch: u64,
+    // version of the LI ending the previous epoch
+    pub version: u64,
+    // time the transition was committed
+    pub timestamp: SystemTime,
+}
+
 impl SynchronizerState {
     pub fn new(
         highest_local_li: LedgerInfoWithSignatures,
@@ -65,6 +280,40 @@ impl SynchronizerState {
     }
 }
 
+/// Validator set of the trusted epoch of state synchronizer, for external tools that need the
+/// active set without reading raw storage.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TrustedValidatorSet {
+    pub epoch: u64,
+    // ordered by account address
+    pub validators: Vec<TrustedValidator>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TrustedValidator {
+    pub address: AccountAddress,
+    pub voting_power: u64,
+}
+
+impl From<&EpochState> for TrustedValidatorSet {
+    fn from(epoch_state: &EpochState) -> Self {
+        let verifier = &epoch_state.verifier;
+        let validators = verifier
+            .get_ordered_account_addresses_iter()
+            .map(|address| TrustedValidator {
+                address,
+                voting_power: verifier
+                    .get_voting_power(&address)
+                    .expect("missing voting power of a validator in the set"),
+            })
+            .collect();
+        Self {
+            epoch: epoch_state.epoch,
+            validators,
+        }
+    }
+}
+
 #[cfg(any(feature = "fuzzing", test))]
 mod tests;
 #[cfg(any(feature = "fuzzing", test))]
@@ -12,6 +12,13 @@ use diem_types::{
     contract_event::ContractEvent, ledger_info::LedgerInfoWithSignatures, waypoint::Waypoint,
 };
 use serde::Serialize;
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+// max number of distinct errors throttled at once. Further distinct errors are always logged
+const MAX_THROTTLED_ERRORS: usize = 1_000;
 
 #[derive(Clone, Schema)]
 pub struct LogSchema<'a> {
@@ -39,6 +46,9 @@ pub struct LogSchema<'a> {
     ledger_info: Option<LedgerInfoWithSignatures>,
     old_epoch: Option<u64>,
     new_epoch: Option<u64>,
+    target_epoch: Option<u64>,
+    expected_epoch: Option<u64>,
+    received_epoch: Option<u64>,
     request_version: Option<u64>,
     target_version: Option<u64>,
     old_multicast_level: Option<usize>,
@@ -76,6 +86,9 @@ impl<'a> LogSchema<'a> {
             ledger_info: None,
             new_epoch: None,
             old_epoch: None,
+            target_epoch: None,
+            expected_epoch: None,
+            received_epoch: None,
             request_version: None,
             target_version: None,
             old_multicast_level: None,
@@ -95,6 +108,61 @@ impl<'a> LogSchema<'a> {
     }
 }
 
+/// Throttles the logs of repeated identical errors, e.g. of a peer repeatedly sending the same bad
+/// chunk. The first occurrence of an error is logged immediately, while its repetitions over the
+/// following window are coalesced into a summary that is logged once the window expires.
+pub struct ErrorLogThrottle {
+    window: Duration,
+    // errors logged within their window: (time of the first occurrence, number of repetitions)
+    errors: HashMap<String, (SystemTime, usize)>,
+}
+
+impl ErrorLogThrottle {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            errors: HashMap::new(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records an occurrence of `error`. Returns whether it has to be logged immediately, i.e.
+    /// it's the first occurrence of the error within its window.
+    pub fn record(&mut self, error: String) -> bool {
+        if self.window == Duration::from_millis(0) {
+            return true;
+        }
+        if let Some((_, repetitions)) = self.errors.get_mut(&error) {
+            *repetitions += 1;
+            return false;
+        }
+        if self.errors.len() < MAX_THROTTLED_ERRORS {
+            self.errors.insert(error, (SystemTime::now(), 0));
+        }
+        true
+    }
+
+    /// Drops the errors whose window expired. Returns the ones that repeated within their window,
+    /// along with their number of repetitions, to be logged as a summary.
+    pub fn expire(&mut self) -> Vec<(String, usize)> {
+        let (now, window) = (SystemTime::now(), self.window);
+        let mut summaries = vec![];
+        self.errors.retain(|error, (first_tst, repetitions)| {
+            let expired = now
+                .duration_since(*first_tst)
+                .map_or(false, |age| age >= window);
+            if expired && *repetitions > 0 {
+                summaries.push((error.clone(), *repetitions));
+            }
+            !expired
+        });
+        summaries
+    }
+}
+
 #[derive(Clone, Copy, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LogEntry {
@@ -115,6 +183,20 @@ pub enum LogEntry {
     CommitFlow,
     Multicast,
     SubscriptionDeliveryFail,
+    Quiesce,
+    SyncLifecycle,
+    AheadOfPeers,
+    Shutdown,
+    Pause,
+    SyncCycle,
+    SignerDiversity,
+    MemoryCap,
+    TipEstimate,
+    StallEscalation,
+    UpstreamRefresh,
+    ChunkSource,
+    PinPeer,
+    LiBroadcast,
 }
 
 #[derive(Clone, Copy, Serialize)]
@@ -125,7 +207,19 @@ pub enum LogEvent {
     Complete,
     Timeout,
     PublishError,
+    Deferred,
     Fail,
+    Triggered,
+
+    // Waypoint events
+    Resume,
+    Advanced,
+    PinnedWaypointMismatch,
+
+    // SyncRequest events
+    Cancelled,
+    EpochChangesExceeded,
+    StaleCancel,
 
     // SendChunkRequest events
     MissingPeers,
@@ -133,6 +227,15 @@ pub enum LogEvent {
     NetworkSendError,
     Success,
     ChunkRequestInfo,
+    Quiesced,
+    ShuttingDown,
+    Coalesced,
+    Paused,
+    Preempted,
+    RateLimited,
+    ServeOnly,
+    SyncCeiling,
+    WaitingForPeers,
 
     // ProcessChunkResponse events
     Received,
@@ -140,13 +243,60 @@ pub enum LogEvent {
     ApplyChunkSuccess,
     ApplyChunkFail,
     PostCommitFail,
+    DuplicateCommit,
+    CommitConflict,
     OldResponseLI,
+    DroppedDuringShutdown,
+    DroppedWhilePaused,
+    PendingChunkOverflow,
+    FutureEpochBuffered,
+    DurabilityCheckFail,
+    ExecutorHang,
+    LocalStateRollback,
+    LocalStateReload,
+    SignatureVerifierFail,
+    Batched,
+    ChunkVerified,
+    PartialChunkApplied,
+    EquivocatingPeer,
+    EpochMismatch,
 
     // ProcessChunkRequest events
     PastEpochRequested,
     DeliverChunk,
+    Downsize,
+    NotServing,
+    ServingEnabled,
 
     // Multicast network events
     Failover,
     Recover,
+
+    // EpochChange events
+    CatchUpProgress,
+    CatchUpStall,
+    EpochProofUnavailable,
+    EpochSkipped,
+    ListenerFail,
+
+    // SyncLifecycle events
+    IdleAtTip,
+    Syncing,
+
+    // SignerDiversity events
+    BelowMin,
+
+    // MemoryCap events
+    Shed,
+    OverCap,
+
+    // TipEstimate events
+    Flapping,
+    Stable,
+
+    // StallEscalation events
+    WidenPeers,
+    RefreshPeers,
+    SwitchNetworks,
+    Alert,
 }
@@ -3,23 +3,115 @@
 
 //! Interface between StateSynchronizer and Network layers.
 
-use crate::{chunk_request::GetChunkRequest, chunk_response::GetChunkResponse, counters};
+use crate::{
+    chunk_request::{GetChunkRequest, TargetType},
+    chunk_response::{GetChunkResponse, ResponseLedgerInfo},
+    counters,
+};
 use channel::message_queues::QueueStyle;
+use diem_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+    multi_ed25519::{MultiEd25519PublicKey, MultiEd25519Signature},
+    traits::{SigningKey, Uniform},
+    HashValue,
+};
 use diem_metrics::IntCounterVec;
-use diem_types::PeerId;
+use diem_types::{
+    account_address::AccountAddress,
+    block_info::BlockInfo,
+    contract_event::ContractEvent,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    on_chain_config::new_epoch_event_key,
+    transaction::{
+        authenticator::TransactionAuthenticator, Transaction, TransactionArgument,
+        TransactionPayload, WriteSetPayload,
+    },
+    vm_status::{AbortLocation, KeptVMStatus},
+    write_set::WriteOp,
+    PeerId,
+};
+use move_core_types::language_storage::TypeTag;
 use network::{
     error::NetworkError,
     peer_manager::{ConnectionRequestSender, PeerManagerRequestSender},
     protocols::network::{NetworkEvents, NetworkSender, NewNetworkSender},
     ProtocolId,
 };
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use serde_reflection::{Registry, Samples, Tracer, TracerConfig};
+use std::collections::BTreeMap;
 
 /// StateSynchronizer network messages
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum StateSynchronizerMsg {
     GetChunkRequest(Box<GetChunkRequest>),
     GetChunkResponse(Box<GetChunkResponse>),
+    // LI committed by the sender, broadcast unsolicited to advertise its version
+    CommittedLedgerInfo(Box<LedgerInfoWithSignatures>),
+}
+
+impl StateSynchronizerMsg {
+    /// Type of the message, as a metric label
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            StateSynchronizerMsg::GetChunkRequest(_) => counters::CHUNK_REQUEST_MSG_LABEL,
+            StateSynchronizerMsg::GetChunkResponse(_) => counters::CHUNK_RESPONSE_MSG_LABEL,
+            StateSynchronizerMsg::CommittedLedgerInfo(_) => counters::COMMITTED_LI_MSG_LABEL,
+        }
+    }
+
+    /// Counts the message as received from the network
+    pub(crate) fn record_received(&self) {
+        counters::NETWORK_MESSAGES
+            .with_label_values(&[self.message_type(), counters::INBOUND_LABEL])
+            .inc();
+    }
+}
+
+/// Returns the LCS formats of `StateSynchronizerMsg` and of all the types it's made of, traced
+/// from the types themselves so that tooling (e.g. decoders in other languages) stays in sync with
+/// the wire format.
+pub fn chunk_message_formats() -> serde_reflection::Result<Registry> {
+    let mut tracer =
+        Tracer::new(TracerConfig::default().is_human_readable(lcs::is_human_readable()));
+    let mut samples = Samples::new();
+
+    // 1. Record samples for types with custom deserializers.
+    let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+    let private_key = Ed25519PrivateKey::generate(&mut rng);
+    let public_key: Ed25519PublicKey = (&private_key).into();
+    let ledger_info = LedgerInfo::new(BlockInfo::empty(), HashValue::zero());
+    let signature = private_key.sign(&ledger_info);
+    tracer.trace_value(&mut samples, &public_key)?;
+    tracer.trace_value::<MultiEd25519PublicKey>(&mut samples, &public_key.into())?;
+    tracer.trace_value(&mut samples, &signature)?;
+    tracer.trace_value::<MultiEd25519Signature>(&mut samples, &signature.clone().into())?;
+    let mut signatures = BTreeMap::new();
+    signatures.insert(AccountAddress::ZERO, signature);
+    tracer.trace_value(
+        &mut samples,
+        &LedgerInfoWithSignatures::new(ledger_info, signatures),
+    )?;
+    tracer.trace_value(&mut samples, &new_epoch_event_key())?;
+
+    // 2. Trace the main entry point + every enum separately.
+    tracer.trace_type::<StateSynchronizerMsg>(&samples)?;
+    tracer.trace_type::<TargetType>(&samples)?;
+    tracer.trace_type::<ResponseLedgerInfo>(&samples)?;
+    tracer.trace_type::<LedgerInfoWithSignatures>(&samples)?;
+    tracer.trace_type::<ContractEvent>(&samples)?;
+    tracer.trace_type::<TypeTag>(&samples)?;
+    tracer.trace_type::<KeptVMStatus>(&samples)?;
+    tracer.trace_type::<AbortLocation>(&samples)?;
+    tracer.trace_type::<Transaction>(&samples)?;
+    tracer.trace_type::<TransactionArgument>(&samples)?;
+    tracer.trace_type::<TransactionPayload>(&samples)?;
+    tracer.trace_type::<WriteSetPayload>(&samples)?;
+    tracer.trace_type::<TransactionAuthenticator>(&samples)?;
+    tracer.trace_type::<WriteOp>(&samples)?;
+
+    tracer.registry()
 }
 
 /// The interface from Network to StateSynchronizer layer.
@@ -79,6 +171,11 @@ impl StateSynchronizerSender {
         message: StateSynchronizerMsg,
     ) -> Result<(), NetworkError> {
         let protocol = ProtocolId::StateSynchronizerDirectSend;
-        self.inner.send_to(recipient, protocol, message)
+        let message_type = message.message_type();
+        self.inner.send_to(recipient, protocol, message)?;
+        counters::NETWORK_MESSAGES
+            .with_label_values(&[message_type, counters::OUTBOUND_LABEL])
+            .inc();
+        Ok(())
     }
 }
@@ -0,0 +1,70 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{request_manager::PeerInfo, PeerSyncStats};
+use diem_config::config::PeerNetworkId;
+use diem_logger::prelude::*;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    thread_rng,
+};
+use std::collections::HashMap;
+
+/// Chooses the upstream peer to send the next chunk request to, e.g. to experiment with
+/// lowest-latency-first or round-robin selection.
+pub trait PeerSelector: Send {
+    /// Picks one of the `candidates`, which are the alive upstream peers of a single network.
+    /// A peer that isn't one of the `candidates` is ignored. Returns `None` to skip the network.
+    fn pick(&self, candidates: &[PeerNetworkId], stats: &PeerStats) -> Option<PeerNetworkId>;
+}
+
+/// What state synchronizer knows about its upstream peers, for the peer selector.
+pub struct PeerStats<'a> {
+    peers: &'a HashMap<PeerNetworkId, PeerInfo>,
+}
+
+impl<'a> PeerStats<'a> {
+    pub(crate) fn new(peers: &'a HashMap<PeerNetworkId, PeerInfo>) -> Self {
+        Self { peers }
+    }
+
+    /// Score of the peer, from 1 to 100: it's increased by the chunks of the peer that make
+    /// progress, and decreased by its invalid chunks and timeouts.
+    pub fn score(&self, peer: &PeerNetworkId) -> Option<f64> {
+        self.peers.get(peer).map(|peer_info| peer_info.score())
+    }
+
+    pub fn sync_stats(&self, peer: &PeerNetworkId) -> Option<&PeerSyncStats> {
+        self.peers.get(peer).map(|peer_info| peer_info.stats())
+    }
+
+    /// Highest version the peer advertised, if any
+    pub fn highest_version(&self, peer: &PeerNetworkId) -> Option<u64> {
+        self.peers
+            .get(peer)
+            .and_then(|peer_info| peer_info.highest_version())
+    }
+}
+
+/// Picks a peer at random, weighted by its score. This is the default peer selector.
+pub struct WeightedPeerSelector;
+
+impl PeerSelector for WeightedPeerSelector {
+    fn pick(&self, candidates: &[PeerNetworkId], stats: &PeerStats) -> Option<PeerNetworkId> {
+        let weights: Vec<_> = candidates
+            .iter()
+            .map(|peer| stats.score(peer).unwrap_or(0.0))
+            .collect();
+        let weighted_index = WeightedIndex::new(&weights)
+            .map_err(|err| {
+                error!(
+                    "[state sync] (pick_peer) failed to compute weighted index, {:?}",
+                    err
+                );
+                err
+            })
+            .ok()?;
+        let mut rng = thread_rng();
+        candidates.get(weighted_index.sample(&mut rng)).cloned()
+    }
+}
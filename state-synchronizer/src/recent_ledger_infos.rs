@@ -0,0 +1,44 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use diem_config::config::PeerNetworkId;
+use diem_crypto::{hash::CryptoHash, HashValue};
+use diem_types::{ledger_info::LedgerInfoWithSignatures, transaction::Version};
+use std::collections::BTreeMap;
+
+// DS to remember the ledger infos served by peers for the most recent versions, to detect peers
+// serving ledger infos that conflict with each other for the same version
+pub struct RecentLedgerInfos {
+    // hash of the ledger info first served for a version, with the peer that served it
+    served: BTreeMap<Version, (HashValue, PeerNetworkId)>,
+}
+
+impl RecentLedgerInfos {
+    // max number of versions whose ledger info is remembered
+    const MAX_VERSIONS: usize = 100;
+
+    pub fn new() -> Self {
+        Self {
+            served: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the peer that served a ledger info other than `li` for the same version, if any.
+    pub fn conflicting_peer(&self, li: &LedgerInfoWithSignatures) -> Option<&PeerNetworkId> {
+        self.served
+            .get(&li.ledger_info().version())
+            .filter(|(hash, _)| *hash != li.ledger_info().hash())
+            .map(|(_, peer)| peer)
+    }
+
+    /// Records that `peer` served `li`, unless a ledger info was already served for its version.
+    pub fn record(&mut self, peer: &PeerNetworkId, li: &LedgerInfoWithSignatures) {
+        self.served
+            .entry(li.ledger_info().version())
+            .or_insert_with(|| (li.ledger_info().hash(), peer.clone()));
+        while self.served.len() > Self::MAX_VERSIONS {
+            let oldest = *self.served.keys().next().expect("served is not empty");
+            self.served.remove(&oldest);
+        }
+    }
+}
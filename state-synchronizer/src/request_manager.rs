@@ -2,10 +2,15 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    chunk_request::GetChunkRequest,
+    bandwidth_usage::BandwidthUsage,
+    chunk_request::{GetChunkRequest, TargetType},
     counters,
     logging::{LogEntry, LogEvent, LogSchema},
     network::{StateSynchronizerMsg, StateSynchronizerSender},
+    peer_selector::{PeerSelector, PeerStats, WeightedPeerSelector},
+    time_provider::{SystemTimeProvider, TimeProvider},
+    upstream_resolver::{StaticUpstreamResolver, UpstreamResolver},
+    PeerHealth, PeerSyncStats,
 };
 use anyhow::{bail, Result};
 use diem_config::{
@@ -13,30 +18,73 @@ use diem_config::{
     network_id::{NetworkId, NodeNetworkId},
 };
 use diem_logger::prelude::*;
+use diem_types::ledger_info::LedgerInfoWithSignatures;
 use itertools::Itertools;
 use netcore::transport::ConnectionOrigin;
-use rand::{
-    distributions::{Distribution, WeightedIndex},
-    thread_rng,
-};
+use rand::{thread_rng, Rng};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+    sync::Arc,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 const MAX_SCORE: f64 = 100.0;
 const MIN_SCORE: f64 = 1.0;
+// peers with a lower score are reported as penalized
+const PENALIZED_SCORE: f64 = MAX_SCORE / 2.0;
 const PRIMARY_NETWORK_PREFERENCE: usize = 0;
+// weight of the latest response in the moving average of the response latency of a peer
+const RESPONSE_LATENCY_WEIGHT: f64 = 0.2;
+// with adaptive chunk sizing, the chunk limit of a peer grows after its successful responses if
+// its average response latency is below the request timeout divided by this divisor
+const FAST_RESPONSE_TIMEOUT_DIVISOR: u32 = 4;
+// factor the adaptive chunk limit of a peer grows by after a fast response and shrinks by after a
+// timeout
+const CHUNK_LIMIT_RESIZE_FACTOR: u64 = 2;
 
 #[derive(Default, Debug, Clone)]
 pub struct PeerInfo {
     is_alive: bool,
     score: f64,
+    // highest version the peer advertised in its chunk requests and responses
+    highest_version: Option<u64>,
+    // version the peer advertised last, which is lower than `highest_version` if the peer
+    // advertised inconsistent versions
+    latest_version: Option<u64>,
+    // last time a chunk response of the peer made progress
+    last_useful_time: Option<SystemTime>,
+    stats: PeerSyncStats,
+    // backoff of the last failure of the peer, if it failed since its last useful chunk
+    backoff: Option<Duration>,
+    // time until which the peer is skipped when picking peers
+    backoff_until: Option<SystemTime>,
 }
 
 impl PeerInfo {
     pub fn new(is_alive: bool, score: f64) -> Self {
-        Self { is_alive, score }
+        Self {
+            is_alive,
+            score,
+            highest_version: None,
+            latest_version: None,
+            last_useful_time: None,
+            stats: PeerSyncStats::default(),
+            backoff: None,
+            backoff_until: None,
+        }
+    }
+
+    pub(crate) fn score(&self) -> f64 {
+        self.score
+    }
+
+    pub(crate) fn stats(&self) -> &PeerSyncStats {
+        &self.stats
+    }
+
+    pub(crate) fn highest_version(&self) -> Option<u64> {
+        self.highest_version
     }
 }
 
@@ -49,11 +97,24 @@ pub struct ChunkRequestInfo {
     multicast_level: usize,
     multicast_start_time: SystemTime,
     last_request_peers: Vec<PeerNetworkId>,
+    // (epoch, target) of the request last sent, if it was sent to at least one peer. An identical
+    // request is coalesced with it until it times out: its response serves both.
+    last_request: Option<(u64, TargetType)>,
+    // whether the request is a background request preempted by a targeted request: it's neither
+    // waited for nor retried until it's sent again
+    preempted: bool,
+    // peers the request was sent to before it was preempted: their responses might still arrive,
+    // so they aren't penalized for them
+    preempted_peers: Vec<PeerNetworkId>,
 }
 
 impl ChunkRequestInfo {
-    pub fn new(version: u64, peers: Vec<PeerNetworkId>, multicast_level: usize) -> Self {
-        let now = SystemTime::now();
+    pub fn new(
+        version: u64,
+        peers: Vec<PeerNetworkId>,
+        multicast_level: usize,
+        now: SystemTime,
+    ) -> Self {
         Self {
             version,
             first_request_time: now,
@@ -61,10 +122,91 @@ impl ChunkRequestInfo {
             multicast_level,
             multicast_start_time: now,
             last_request_peers: peers,
+            last_request: None,
+            preempted: false,
+            preempted_peers: vec![],
+        }
+    }
+
+    // Whether the request last sent is for background sync, as opposed to a targeted request
+    fn is_background(&self) -> bool {
+        matches!(
+            self.last_request,
+            Some((_, TargetType::HighestAvailable { .. }))
+        )
+    }
+
+    fn is_same_request(&self, req: &GetChunkRequest) -> bool {
+        self.last_request.as_ref().map_or(false, |(epoch, target)| {
+            *epoch == req.current_epoch && target == req.target()
+        })
+    }
+}
+
+/// Backoff of the upstream peers that time out or fail chunk requests, during which they're skipped
+#[derive(Clone, Debug)]
+pub struct BackoffPolicy {
+    // backoff after a first failure. If 0, peers don't back off
+    pub base: Duration,
+    // factor the backoff is multiplied by at each consecutive failure
+    pub multiplier: f64,
+    pub max: Duration,
+}
+
+impl BackoffPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            base: Duration::from_millis(0),
+            multiplier: 1.0,
+            max: Duration::from_millis(0),
+        }
+    }
+
+    fn next_backoff(&self, backoff: Option<Duration>) -> Duration {
+        match backoff {
+            Some(backoff) => backoff.mul_f64(self.multiplier).min(self.max),
+            None => self.base,
         }
     }
 }
 
+// Token bucket limiting the rate chunk requests are sent at: it holds up to `max_per_sec` tokens,
+// refilled at `max_per_sec` tokens per second, and each request sent consumes one
+struct RequestRateLimiter {
+    max_per_sec: f64,
+    tokens: f64,
+    last_refill: SystemTime,
+}
+
+impl RequestRateLimiter {
+    fn new(max_per_sec: u64, now: SystemTime) -> Self {
+        Self {
+            max_per_sec: max_per_sec as f64,
+            tokens: max_per_sec as f64,
+            last_refill: now,
+        }
+    }
+
+    // Returns whether a request can be sent at `now`
+    fn has_token(&mut self, now: SystemTime) -> bool {
+        if let Ok(elapsed) = now.duration_since(self.last_refill) {
+            self.tokens =
+                (self.tokens + elapsed.as_secs_f64() * self.max_per_sec).min(self.max_per_sec);
+            self.last_refill = now;
+        }
+        self.tokens >= 1.0
+    }
+
+    // Consumes the token of a request sent at `now`. Returns false if there's none left
+    fn try_acquire(&mut self, now: SystemTime) -> bool {
+        if !self.has_token(now) {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum PeerScoreUpdateType {
     Success,
@@ -79,10 +221,18 @@ pub enum PeerScoreUpdateType {
 pub struct RequestManager {
     // list of peers that are eligible for this node to send sync requests to
     // grouped by network preference
-    eligible_peers: BTreeMap<usize, (Vec<PeerNetworkId>, Option<WeightedIndex<f64>>)>,
+    eligible_peers: BTreeMap<usize, Vec<PeerNetworkId>>,
     peers: HashMap<PeerNetworkId, PeerInfo>,
     requests: BTreeMap<u64, ChunkRequestInfo>,
     upstream_config: UpstreamConfig,
+    // source of the upstream config, which is refreshed periodically
+    upstream_resolver: Box<dyn UpstreamResolver>,
+    // chooses the peer of each network to send chunk requests to
+    peer_selector: Box<dyn PeerSelector>,
+    backoff_policy: BackoffPolicy,
+    // all the connected peers, upstream or not, so that they're reevaluated when the upstream
+    // config changes
+    connected_peers: HashMap<PeerNetworkId, ConnectionOrigin>,
     // duration with the same version before the next attempt to get the next chunk
     request_timeout: Duration,
     // duration with the same version before multicasting, i.e. sending the next chunk request to more networks
@@ -91,6 +241,19 @@ pub struct RequestManager {
     // where network preference is specified by the upstream config
     multicast_level: usize,
     network_senders: HashMap<NodeNetworkId, StateSynchronizerSender>,
+    // if set, the only upstream peer chunk requests are sent to
+    pinned_peer: Option<PeerNetworkId>,
+    // (initial, max) chunk limit of the peers if the chunk limit adapts to each peer
+    adaptive_chunk_sizing: Option<(u64, u64)>,
+    // peers the last timed-out request was sent to, which aren't picked again to re-issue it
+    // unless there are no other peers
+    timed_out_peers: HashSet<PeerNetworkId>,
+    // clock the timeouts and backoffs are computed with
+    time_provider: Arc<dyn TimeProvider>,
+    // if set, limits the rate chunk requests are sent at
+    rate_limiter: Option<RequestRateLimiter>,
+    // if set, the chunk requests are capped so that chunks don't extend past this version
+    sync_ceiling_version: Option<u64>,
 }
 
 impl RequestManager {
@@ -99,21 +262,99 @@ impl RequestManager {
         request_timeout: Duration,
         multicast_timeout: Duration,
         network_senders: HashMap<NodeNetworkId, StateSynchronizerSender>,
+    ) -> Self {
+        Self::new_with_upstream_resolver(
+            Box::new(StaticUpstreamResolver::new(upstream_config)),
+            Box::new(WeightedPeerSelector),
+            BackoffPolicy::disabled(),
+            request_timeout,
+            multicast_timeout,
+            network_senders,
+        )
+    }
+
+    pub fn new_with_upstream_resolver(
+        upstream_resolver: Box<dyn UpstreamResolver>,
+        peer_selector: Box<dyn PeerSelector>,
+        backoff_policy: BackoffPolicy,
+        request_timeout: Duration,
+        multicast_timeout: Duration,
+        network_senders: HashMap<NodeNetworkId, StateSynchronizerSender>,
     ) -> Self {
         counters::MULTICAST_LEVEL.set(PRIMARY_NETWORK_PREFERENCE as i64);
+        let upstream_config = upstream_resolver.resolve().unwrap_or_else(|e| {
+            error!(
+                LogSchema::event_log(LogEntry::UpstreamRefresh, LogEvent::Fail).error(&e),
+                "[state sync] failed to resolve the initial upstream config"
+            );
+            UpstreamConfig::default()
+        });
         Self {
             eligible_peers: BTreeMap::new(),
             peers: HashMap::new(),
             requests: BTreeMap::new(),
             upstream_config,
+            upstream_resolver,
+            peer_selector,
+            backoff_policy,
+            connected_peers: HashMap::new(),
             request_timeout,
             multicast_timeout,
             multicast_level: PRIMARY_NETWORK_PREFERENCE,
             network_senders,
+            pinned_peer: None,
+            adaptive_chunk_sizing: None,
+            timed_out_peers: HashSet::new(),
+            time_provider: Arc::new(SystemTimeProvider),
+            rate_limiter: None,
+            sync_ceiling_version: None,
         }
     }
 
+    /// Pins the upstream peer chunk requests are sent to, or unpins it if `None`. The pinned peer
+    /// is the only one requested, even while it's unavailable.
+    pub fn set_pinned_peer(&mut self, peer: Option<PeerNetworkId>) {
+        self.pinned_peer = peer;
+    }
+
+    /// Adapts the chunk limit of the requests to each peer, starting at `initial_limit`: the limit
+    /// grows up to `max_limit` while the peer responds fast and successfully, and shrinks after
+    /// its timeouts.
+    pub fn set_adaptive_chunk_sizing(&mut self, initial_limit: u64, max_limit: u64) {
+        self.adaptive_chunk_sizing = Some((initial_limit.min(max_limit), max_limit));
+    }
+
+    /// Replaces the clock the timeouts and backoffs are computed with, which is the system clock
+    /// by default.
+    pub fn set_time_provider(&mut self, time_provider: Arc<dyn TimeProvider>) {
+        self.time_provider = time_provider;
+    }
+
+    /// Limits the chunk requests sent to `max_per_sec` per second, in bursts of up to as many
+    /// requests. The requests beyond the limit are deferred until they can be sent.
+    pub fn set_max_requests_per_sec(&mut self, max_per_sec: u64) {
+        self.rate_limiter = Some(RequestRateLimiter::new(
+            max_per_sec,
+            self.time_provider.now(),
+        ));
+    }
+
+    /// Caps the chunk requests sent from now on so that the chunks served, packed chunks included,
+    /// don't extend past `ceiling_version`.
+    pub fn set_sync_ceiling_version(&mut self, ceiling_version: u64) {
+        self.sync_ceiling_version = Some(ceiling_version);
+    }
+
+    // Returns whether the rate limit allows sending a chunk request now
+    fn can_send_request(&mut self) -> bool {
+        let now = self.time_provider.now();
+        self.rate_limiter
+            .as_mut()
+            .map_or(true, |rate_limiter| rate_limiter.has_token(now))
+    }
+
     pub fn enable_peer(&mut self, peer: PeerNetworkId, origin: ConnectionOrigin) {
+        self.connected_peers.insert(peer.clone(), origin);
         let is_upstream_peer = self.is_upstream_peer(&peer, origin);
         debug!(LogSchema::new(LogEntry::NewPeer)
             .peer(&peer)
@@ -129,12 +370,17 @@ impl RequestManager {
         if let Some(peer_info) = self.peers.get_mut(&peer) {
             peer_info.is_alive = true;
         } else {
-            self.peers.insert(peer, PeerInfo::new(true, MAX_SCORE));
+            let mut peer_info = PeerInfo::new(true, MAX_SCORE);
+            peer_info.stats.adaptive_chunk_limit = self
+                .adaptive_chunk_sizing
+                .map(|(initial_limit, _)| initial_limit);
+            self.peers.insert(peer, peer_info);
         }
         self.update_peer_selection_data();
     }
 
     pub fn disable_peer(&mut self, peer: &PeerNetworkId, origin: ConnectionOrigin) {
+        self.connected_peers.remove(peer);
         debug!(LogSchema::new(LogEntry::LostPeer)
             .peer(&peer)
             .is_upstream_peer(self.is_upstream_peer(&peer, origin)));
@@ -148,12 +394,31 @@ impl RequestManager {
         self.update_peer_selection_data();
     }
 
+    /// Returns the number of connected upstream peers
+    pub fn num_available_peers(&self) -> usize {
+        self.peers
+            .values()
+            .filter(|peer_info| peer_info.is_alive)
+            .count()
+    }
+
+    /// Returns the connected peers, whether they're upstream or not
+    pub fn connected_peers(&self) -> Vec<PeerNetworkId> {
+        self.connected_peers.keys().cloned().collect()
+    }
+
     pub fn no_available_peers(&self) -> bool {
-        self.eligible_peers.is_empty()
+        match self.pinned_peer.as_ref() {
+            Some(peer) => !self.is_alive(peer),
+            None => self.eligible_peers.is_empty(),
+        }
     }
 
     pub fn update_score(&mut self, peer: &PeerNetworkId, update_type: PeerScoreUpdateType) {
         if let Some(peer_info) = self.peers.get_mut(peer) {
+            if update_type != PeerScoreUpdateType::Success {
+                peer_info.stats.chunks_failed += 1;
+            }
             let old_score = peer_info.score;
             match update_type {
                 PeerScoreUpdateType::Success => {
@@ -170,15 +435,160 @@ impl RequestManager {
                     peer_info.score = new_score.max(MIN_SCORE);
                 }
             }
+            match update_type {
+                PeerScoreUpdateType::Success => {
+                    peer_info.backoff = None;
+                    peer_info.backoff_until = None;
+                }
+                PeerScoreUpdateType::InvalidChunk | PeerScoreUpdateType::TimeOut => {
+                    Self::backoff(peer_info, &self.backoff_policy, self.time_provider.now());
+                }
+                PeerScoreUpdateType::ChunkVersionCannotBeApplied
+                | PeerScoreUpdateType::EmptyChunk => {}
+            }
             if (old_score - peer_info.score).abs() > std::f64::EPSILON {
                 self.update_peer_selection_data();
             }
         }
     }
 
-    // Updates the information used to select a peer to send a chunk request to:
-    // * eligible_peers
-    // * weighted_index: the chance that a peer is selected from `eligible_peers` is weighted by its score
+    // Grows the adaptive chunk limit of the peer (up to the max limit) if `grow`, else shrinks it
+    // (down to one transaction). No-op if the chunk sizing isn't adaptive.
+    fn resize_chunk_limit(&mut self, peer: &PeerNetworkId, grow: bool) {
+        let (initial_limit, max_limit) = match self.adaptive_chunk_sizing {
+            Some(sizing) => sizing,
+            None => return,
+        };
+        if let Some(peer_info) = self.peers.get_mut(peer) {
+            let limit = peer_info
+                .stats
+                .adaptive_chunk_limit
+                .unwrap_or(initial_limit);
+            let new_limit = if grow {
+                limit
+                    .saturating_mul(CHUNK_LIMIT_RESIZE_FACTOR)
+                    .min(max_limit)
+            } else {
+                (limit / CHUNK_LIMIT_RESIZE_FACTOR).max(1)
+            };
+            peer_info.stats.adaptive_chunk_limit = Some(new_limit);
+        }
+    }
+
+    // Returns the adaptive chunk limit of a request to `peers`, i.e. the lowest limit of the peers,
+    // if the chunk sizing is adaptive
+    fn adaptive_chunk_limit(&self, peers: &[PeerNetworkId]) -> Option<u64> {
+        let (initial_limit, _) = self.adaptive_chunk_sizing?;
+        peers
+            .iter()
+            .map(|peer| {
+                self.peers
+                    .get(peer)
+                    .and_then(|peer_info| peer_info.stats.adaptive_chunk_limit)
+                    .unwrap_or(initial_limit)
+            })
+            .min()
+    }
+
+    // Grows the backoff of the peer after a failure, and skips the peer for a random duration
+    // between half of its backoff and its backoff, so that nodes don't retry in lockstep
+    fn backoff(peer_info: &mut PeerInfo, backoff_policy: &BackoffPolicy, now: SystemTime) {
+        if backoff_policy.base == Duration::from_millis(0) {
+            return;
+        }
+        let backoff = backoff_policy.next_backoff(peer_info.backoff);
+        let jittered_backoff = backoff.mul_f64(thread_rng().gen_range(0.5, 1.0));
+        peer_info.backoff = Some(backoff);
+        peer_info.backoff_until = now.checked_add(jittered_backoff);
+    }
+
+    fn is_backing_off(&self, peer: &PeerNetworkId, now: SystemTime) -> bool {
+        self.peers
+            .get(peer)
+            .and_then(|peer_info| peer_info.backoff_until)
+            .map_or(false, |backoff_until| now < backoff_until)
+    }
+
+    /// Queries the upstream resolver for the current upstream config. If it changed, the connected
+    /// peers of the new upstream networks are adopted, and the peers of the networks that aren't
+    /// upstream anymore are dropped.
+    pub fn refresh_upstream_config(&mut self) {
+        let upstream_config = match self.upstream_resolver.resolve() {
+            Ok(upstream_config) => upstream_config,
+            Err(e) => {
+                counters::UPSTREAM_CONFIG_REFRESH_COUNT
+                    .with_label_values(&[counters::FAIL_LABEL])
+                    .inc();
+                error!(LogSchema::event_log(LogEntry::UpstreamRefresh, LogEvent::Fail).error(&e));
+                return;
+            }
+        };
+        counters::UPSTREAM_CONFIG_REFRESH_COUNT
+            .with_label_values(&[counters::SUCCESS_LABEL])
+            .inc();
+        if upstream_config == self.upstream_config {
+            return;
+        }
+        info!(
+            LogSchema::event_log(LogEntry::UpstreamRefresh, LogEvent::Complete),
+            "[state sync] upstream networks changed from {:?} to {:?}",
+            self.upstream_config.networks,
+            upstream_config.networks
+        );
+        self.upstream_config = upstream_config;
+
+        let connected_peers: Vec<_> = self
+            .connected_peers
+            .iter()
+            .map(|(peer, origin)| (peer.clone(), *origin))
+            .collect();
+        for (peer, origin) in connected_peers {
+            if self.is_upstream_peer(&peer, origin) && !self.peers.contains_key(&peer) {
+                counters::ACTIVE_UPSTREAM_PEERS
+                    .with_label_values(&[&peer.raw_network_id().to_string()])
+                    .inc();
+                self.peers.insert(peer, PeerInfo::new(true, MAX_SCORE));
+            }
+        }
+        let upstream_config = &self.upstream_config;
+        let connected_peers = &self.connected_peers;
+        self.peers.retain(|peer, peer_info| {
+            // disconnected peers are kept as long as their network is upstream
+            let is_upstream = match connected_peers.get(peer) {
+                Some(origin) => Self::is_upstream_peer_of(upstream_config, peer, *origin),
+                None => upstream_config
+                    .get_upstream_preference(peer.raw_network_id())
+                    .is_some(),
+            };
+            if !is_upstream && peer_info.is_alive {
+                counters::ACTIVE_UPSTREAM_PEERS
+                    .with_label_values(&[&peer.raw_network_id().to_string()])
+                    .dec();
+            }
+            is_upstream
+        });
+
+        // multicast_level (=network preference) is 0-indexed
+        let max_level = self.upstream_config.upstream_count() - 1;
+        if self.multicast_level > max_level {
+            self.update_multicast(max_level, None);
+        }
+        self.update_peer_selection_data();
+    }
+
+    /// Restores the score of every upstream peer to the max and clears their backoff, so that
+    /// peers penalized in the past are as likely to be picked as the others
+    pub fn reset_peer_scores(&mut self) {
+        for peer_info in self.peers.values_mut() {
+            peer_info.score = MAX_SCORE;
+            peer_info.backoff = None;
+            peer_info.backoff_until = None;
+        }
+        self.update_peer_selection_data();
+    }
+
+    // Updates the information used to select a peer to send a chunk request to, i.e.
+    // `eligible_peers`
     fn update_peer_selection_data(&mut self) {
         // group active peers by network
         let active_peers = self
@@ -194,45 +604,15 @@ impl RequestManager {
             })
             .into_group_map();
 
-        // for each network, compute peer selection data
         self.eligible_peers = active_peers
             .into_iter()
             .map(|(network_pref, peers)| {
-                let mut eligible_peers = vec![];
-                let weights: Vec<_> = peers
-                    .iter()
-                    .map(|(peer, peer_info)| {
-                        eligible_peers.push((*peer).clone());
-                        peer_info.score
-                    })
-                    .collect();
-                let weighted_index = WeightedIndex::new(&weights)
-                    .map_err(|err| {
-                        error!(
-                            "[state sync] (pick_peer) failed to compute weighted index, {:?}",
-                            err
-                        );
-                        err
-                    })
-                    .ok();
-                (network_pref, (eligible_peers, weighted_index))
+                let eligible_peers = peers.into_iter().map(|(peer, _)| peer.clone()).collect();
+                (network_pref, eligible_peers)
             })
             .collect();
     }
 
-    fn pick_peer(
-        peers: &[PeerNetworkId],
-        weighted_index: &Option<WeightedIndex<f64>>,
-    ) -> Option<PeerNetworkId> {
-        if let Some(weighted_index) = &weighted_index {
-            let mut rng = thread_rng();
-            if let Some(peer) = peers.get(weighted_index.sample(&mut rng)) {
-                return Some(peer.clone());
-            }
-        }
-        None
-    }
-
     /// Picks a set of peers to send chunk requests to
     /// Tries to pick one peer per network, in order of network preference (where the higher the preference,
     /// the lower the value)
@@ -243,14 +623,90 @@ impl RequestManager {
     /// these networks, we find the next available network with preference level greater than the current multicast
     /// level with any live peers. If such a network is found, the multicast level is also updated to
     /// the preference level of the chosen network.
+    /// The peers that are backing off after a failure are skipped.
     pub fn pick_peers(&mut self) -> Vec<PeerNetworkId> {
+        self.select_peers(false, false)
+    }
+
+    /// Picks the peers to send `req` to. A request that crosses an epoch boundary is only sent to
+    /// the peers trusted to serve epoch changes, the other ones to any upstream peer.
+    pub fn pick_peers_for(&mut self, req: &GetChunkRequest) -> Vec<PeerNetworkId> {
+        let epoch_trusted_only = Self::crosses_epoch(req);
+        let peers = self.select_peers(false, epoch_trusted_only);
+        if peers.is_empty() && req.is_critical() {
+            // consensus is blocked on its sync request, so the request isn't held back by the
+            // backoff of the peers
+            let peers = self.select_peers(true, epoch_trusted_only);
+            if !peers.is_empty() {
+                counters::CRITICAL_SYNC_THROTTLE_BYPASSES
+                    .with_label_values(&[counters::BACKOFF_LABEL])
+                    .inc();
+            }
+            return peers;
+        }
+        peers
+    }
+
+    // Returns whether the chunks served for `req` may end the epoch they start in
+    fn crosses_epoch(req: &GetChunkRequest) -> bool {
+        let crosses = |li: &LedgerInfoWithSignatures| {
+            li.ledger_info().epoch() > req.current_epoch || li.ledger_info().ends_epoch()
+        };
+        match req.target() {
+            TargetType::TargetLedgerInfo(li) => crosses(li),
+            TargetType::HighestAvailable { target_li, .. } => {
+                target_li.as_ref().map_or(false, crosses)
+            }
+            // the waypoint is verified through the epoch changes up to its version
+            TargetType::Waypoint(_) => true,
+        }
+    }
+
+    fn select_peers(
+        &mut self,
+        ignore_backoff: bool,
+        epoch_trusted_only: bool,
+    ) -> Vec<PeerNetworkId> {
+        if let Some(peer) = self.pinned_peer.clone() {
+            return self.select_pinned_peer(peer, ignore_backoff);
+        }
+
         // Strategy: pick peers using multicast level
         // if no live peers exist for this multicast level, keep failing over to next level
 
         let mut chosen_peers = vec![];
         let mut new_multicast_level = None;
-        for (level, (peers, weighted_index)) in self.eligible_peers.iter() {
-            if let Some(peer) = Self::pick_peer(peers, weighted_index) {
+        let stats = PeerStats::new(&self.peers);
+        let now = self.time_provider.now();
+        for (level, peers) in self.eligible_peers.iter() {
+            let mut candidates: Vec<_> = peers
+                .iter()
+                .filter(|peer| {
+                    !epoch_trusted_only || self.upstream_config.is_epoch_trusted(&peer.peer_id())
+                })
+                .filter(|peer| {
+                    if ignore_backoff || !self.is_backing_off(peer, now) {
+                        return true;
+                    }
+                    counters::PEER_BACKOFF_SKIP_COUNT
+                        .with_label_values(&[&peer.raw_network_id().to_string()])
+                        .inc();
+                    false
+                })
+                .cloned()
+                .collect();
+            if candidates
+                .iter()
+                .any(|peer| !self.timed_out_peers.contains(peer))
+            {
+                candidates.retain(|peer| !self.timed_out_peers.contains(peer));
+            }
+            let peer = if candidates.is_empty() {
+                None
+            } else {
+                self.peer_selector.pick(&candidates, &stats)
+            };
+            if let Some(peer) = peer.filter(|peer| candidates.contains(peer)) {
                 chosen_peers.push(peer)
             }
             // at the minimum go through networks with preference level <= multicast_level
@@ -264,28 +720,104 @@ impl RequestManager {
 
         // we call `update_multicast` here instead of before the break to avoid mutable borrow conflict
         // with the outer loop
-        if let Some(level) = new_multicast_level {
+        // the networks of the epoch-trusted peers don't drive the multicast level of all requests
+        if let Some(level) = new_multicast_level.filter(|_| !epoch_trusted_only) {
             self.update_multicast(level, None);
         }
         chosen_peers
     }
 
-    pub fn send_chunk_request(&mut self, req: GetChunkRequest) -> Result<()> {
+    fn is_alive(&self, peer: &PeerNetworkId) -> bool {
+        self.peers
+            .get(peer)
+            .map_or(false, |peer_info| peer_info.is_alive)
+    }
+
+    // The pinned peer is selected if it's a live upstream peer that isn't backing off, other peers
+    // never are
+    fn select_pinned_peer(&self, peer: PeerNetworkId, ignore_backoff: bool) -> Vec<PeerNetworkId> {
+        if !self.is_alive(&peer) {
+            counters::PINNED_UPSTREAM_FAILURES
+                .with_label_values(&[counters::DISCONNECTED_LABEL])
+                .inc();
+            return vec![];
+        }
+        if !ignore_backoff && self.is_backing_off(&peer, self.time_provider.now()) {
+            counters::PINNED_UPSTREAM_FAILURES
+                .with_label_values(&[counters::BACKOFF_LABEL])
+                .inc();
+            return vec![];
+        }
+        vec![peer]
+    }
+
+    pub fn send_chunk_request(
+        &mut self,
+        mut req: GetChunkRequest,
+        bandwidth_usage: &mut BandwidthUsage,
+    ) -> Result<()> {
         let log = LogSchema::new(LogEntry::SendChunkRequest).chunk_req(&req);
 
+        // a request identical to an in-flight one, e.g. triggered by another code path for the
+        // same range, is coalesced with it instead of being sent again
+        if self.is_in_flight(&req) {
+            counters::DEDUPED_CHUNK_REQUESTS.inc();
+            debug!(log.event(LogEvent::Coalesced));
+            return Ok(());
+        }
+
+        // a request beyond the rate limit is deferred: it isn't tracked, so it's sent by the next
+        // progress check that finds a token available. A targeted request, i.e. to the waypoint
+        // or to the target of a sync request, takes precedence over background sync: it's sent
+        // regardless, and the in-flight background requests are preempted by it
+        let targeted = !matches!(req.target(), TargetType::HighestAvailable { .. });
+        if !targeted && !self.can_send_request() {
+            counters::RATE_LIMITED_REQUESTS.inc();
+            debug!(log.event(LogEvent::RateLimited));
+            return Ok(());
+        }
+        if targeted {
+            self.preempt_background_requests();
+        }
+
         // update internal state
-        let peers = self.pick_peers();
+        let peers = self.pick_peers_for(&req);
         if peers.is_empty() {
             warn!(log.event(LogEvent::MissingPeers));
+            if let Some(pinned_peer) = self.pinned_peer.as_ref() {
+                bail!("Pinned upstream peer {} is unavailable", pinned_peer);
+            }
             bail!("No peers to send chunk request to");
         }
+        // the token of the request is only taken once it has peers to be sent to
+        let now = self.time_provider.now();
+        if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+            if !rate_limiter.try_acquire(now) && req.is_critical() {
+                counters::CRITICAL_SYNC_THROTTLE_BYPASSES
+                    .with_label_values(&[counters::RATE_LIMIT_LABEL])
+                    .inc();
+            }
+        }
 
+        if let Some(limit) = self.adaptive_chunk_limit(&peers) {
+            req.limit = limit;
+        }
+        if let Some(ceiling_version) = self.sync_ceiling_version {
+            let remaining = std::cmp::max(ceiling_version.saturating_sub(req.known_version), 1);
+            req.limit = std::cmp::min(req.limit, remaining);
+            req.max_chunks_in_response = std::cmp::max(
+                std::cmp::min(req.max_chunks_in_response as u64, remaining / req.limit),
+                1,
+            ) as u8;
+        }
         let req_info = self.add_request(req.known_version, peers.clone());
         debug!(log
             .clone()
             .event(LogEvent::ChunkRequestInfo)
             .chunk_req_info(&req_info));
 
+        let num_peers = peers.len();
+        let request_key = (req.current_epoch, req.target().clone());
         let msg = StateSynchronizerMsg::GetChunkRequest(Box::new(req));
         let mut failed_peer_sends = vec![];
 
@@ -299,10 +831,23 @@ impl RequestManager {
             let curr_log = log.clone().peer(&peer);
             let result_label = if let Err(e) = send_result {
                 failed_peer_sends.push(peer.clone());
+                if let Some(peer_info) = self.peers.get_mut(&peer) {
+                    Self::backoff(peer_info, &self.backoff_policy, self.time_provider.now());
+                }
                 error!(curr_log.event(LogEvent::NetworkSendError).error(&e.into()));
                 counters::SEND_FAIL_LABEL
             } else {
                 debug!(curr_log.event(LogEvent::Success));
+                bandwidth_usage.record(&peer, &msg, true, self.time_provider.now());
+                if let Some(peer_info) = self.peers.get_mut(&peer) {
+                    peer_info.stats.chunks_requested += 1;
+                }
+                counters::NETWORK_CHUNK_REQUESTS
+                    .with_label_values(&[
+                        &peer.raw_network_id().to_string(),
+                        counters::OUTBOUND_LABEL,
+                    ])
+                    .inc();
                 counters::SEND_SUCCESS_LABEL
             };
             counters::REQUESTS_SENT
@@ -313,6 +858,11 @@ impl RequestManager {
                 ])
                 .inc();
         }
+        if failed_peer_sends.len() < num_peers {
+            if let Some(req_info) = self.requests.get_mut(&req_info.version) {
+                req_info.last_request = Some(request_key);
+            }
+        }
 
         if failed_peer_sends.is_empty() {
             Ok(())
@@ -322,8 +872,9 @@ impl RequestManager {
     }
 
     pub fn add_request(&mut self, version: u64, peers: Vec<PeerNetworkId>) -> ChunkRequestInfo {
+        self.timed_out_peers.clear();
+        let now = self.time_provider.now();
         if let Some(prev_request) = self.requests.get_mut(&version) {
-            let now = SystemTime::now();
             if self.multicast_level != prev_request.multicast_level {
                 // restart multicast timer for this request if multicast level changed
                 prev_request.multicast_level = self.multicast_level;
@@ -331,12 +882,14 @@ impl RequestManager {
             }
             prev_request.last_request_peers = peers;
             prev_request.last_request_time = now;
+            prev_request.preempted = false;
             prev_request.clone()
         } else {
             self.requests.insert(
                 version,
-                ChunkRequestInfo::new(version, peers, self.multicast_level),
+                ChunkRequestInfo::new(version, peers, self.multicast_level, now),
             );
+            counters::IN_FLIGHT_CHUNK_REQUESTS.set(self.requests.len() as i64);
             self.requests
                 .get(&version)
                 .expect("missing chunk request that was just added")
@@ -358,6 +911,61 @@ impl RequestManager {
 
         // update score
         self.update_score(peer, PeerScoreUpdateType::Success);
+        let fast_response_latency = self.request_timeout / FAST_RESPONSE_TIMEOUT_DIVISOR;
+        let mut is_fast = false;
+        if let Some(peer_info) = self.peers.get_mut(peer) {
+            peer_info.last_useful_time = Some(self.time_provider.now());
+            is_fast = peer_info
+                .stats
+                .avg_response_latency
+                .map_or(false, |latency| latency < fast_response_latency);
+        }
+        if is_fast {
+            self.resize_chunk_limit(peer, true);
+        }
+    }
+
+    /// Records a chunk response of the peer for the request sent with known_version = `version`.
+    /// The response latency is only measured if the request was last sent to the peer.
+    /// The request is answered, so the next identical request isn't coalesced with it anymore.
+    pub fn process_chunk_received(&mut self, peer: &PeerNetworkId, version: u64) {
+        let now = self.time_provider.now();
+        let latency = self
+            .requests
+            .get(&version)
+            .filter(|req| req.last_request_peers.contains(peer))
+            .and_then(|req| now.duration_since(req.last_request_time).ok());
+        if let Some(peer_info) = self.peers.get_mut(peer) {
+            peer_info.stats.chunks_received += 1;
+            if let Some(latency) = latency {
+                let avg_latency = match peer_info.stats.avg_response_latency {
+                    Some(avg_latency) => Duration::from_secs_f64(
+                        RESPONSE_LATENCY_WEIGHT * latency.as_secs_f64()
+                            + (1.0 - RESPONSE_LATENCY_WEIGHT) * avg_latency.as_secs_f64(),
+                    ),
+                    None => latency,
+                };
+                peer_info.stats.avg_response_latency = Some(avg_latency);
+            }
+        }
+        if let Some(req) = self.requests.get_mut(&version) {
+            req.last_request = None;
+        }
+    }
+
+    /// Returns the chunk statistics of every known upstream peer
+    pub fn get_peer_stats(&self) -> HashMap<PeerNetworkId, PeerSyncStats> {
+        self.peers
+            .iter()
+            .map(|(peer, peer_info)| (peer.clone(), peer_info.stats.clone()))
+            .collect()
+    }
+
+    /// Returns the last time a chunk response of the peer made progress, if any did.
+    pub fn last_useful_time(&self, peer: &PeerNetworkId) -> Option<SystemTime> {
+        self.peers
+            .get(peer)
+            .and_then(|peer_info| peer_info.last_useful_time)
     }
 
     // penalize peer's score for giving chunk with starting version that doesn't match local synced version
@@ -367,9 +975,11 @@ impl RequestManager {
         chunk_version: u64,
         synced_version: u64,
     ) -> Result<()> {
-        if self.is_multicast_response(chunk_version, peer) {
+        if self.is_multicast_response(chunk_version, peer)
+            || self.is_preempted_response(chunk_version, peer)
+        {
             // This chunk response was in response to a past multicast response that another
-            // peer sent a response to earlier than this peer
+            // peer sent a response to earlier than this peer, or to a preempted request
             // Don't penalize if this response did not technically time out
             bail!(
                 "[state sync] Received chunk for outdated request from {:?}: known_version: {}, received: {}",
@@ -394,6 +1004,14 @@ impl RequestManager {
         })
     }
 
+    // Whether the chunk starting at `chunk_version` answers a request preempted while it was sent
+    // to the peer, i.e. a request with known_version = `chunk_version` - 1
+    fn is_preempted_response(&self, chunk_version: u64, peer: &PeerNetworkId) -> bool {
+        self.requests
+            .get(&chunk_version.saturating_sub(1))
+            .map_or(false, |req| req.preempted_peers.contains(peer))
+    }
+
     pub fn get_last_request_time(&self, version: u64) -> Option<SystemTime> {
         self.requests
             .get(&version)
@@ -413,9 +1031,10 @@ impl RequestManager {
     }
 
     /// Removes requests whose known_version < `version` if they are older than now - `timeout`
-    /// We keep the requests that have not timed out so we don't penalize
+    /// or were sent to a single peer
+    /// We keep the multicasted requests that have not timed out so we don't penalize
     /// peers who send chunks after the first peer who sends the first successful chunk response for a
-    /// multicasted request
+    /// multicasted request, and likewise the preempted requests
     pub fn remove_requests(&mut self, version: u64) {
         // only remove requests that have timed out or sent to one peer, so we don't penalize for multicasted responses
         // that still came back on time, based on per-peer timeout
@@ -423,7 +1042,9 @@ impl RequestManager {
             .requests
             .range(..version)
             .filter_map(|(version, req)| {
-                if Self::is_timeout(req.last_request_time, self.request_timeout) {
+                if (req.last_request_peers.len() <= 1 && req.preempted_peers.is_empty())
+                    || self.is_timeout(req.last_request_time, self.request_timeout)
+                {
                     Some(*version)
                 } else {
                     None
@@ -434,32 +1055,139 @@ impl RequestManager {
         for v in versions_to_remove {
             self.requests.remove(&v);
         }
+        counters::IN_FLIGHT_CHUNK_REQUESTS.set(self.requests.len() as i64);
+    }
+
+    // Flags the in-flight background requests as preempted, so that they're neither waited for
+    // nor retried in the way of a targeted request. They stay tracked: their responses are still
+    // applied if they arrive, and their peers aren't penalized for late ones.
+    fn preempt_background_requests(&mut self) {
+        let mut num_preempted = 0;
+        for req in self.requests.values_mut() {
+            if req.is_background() && !req.preempted {
+                req.preempted = true;
+                req.preempted_peers = req.last_request_peers.clone();
+                num_preempted += 1;
+            }
+        }
+        if num_preempted == 0 {
+            return;
+        }
+        counters::PREEMPTED_BACKGROUND_REQUESTS.inc_by(num_preempted);
+        debug!(
+            LogSchema::event_log(LogEntry::SendChunkRequest, LogEvent::Preempted),
+            "preempted {} background chunk requests", num_preempted
+        );
+    }
+
+    /// Forgets all the tracked requests, so that the next chunk request is sent right away instead
+    /// of waiting for the in-flight ones to time out
+    pub fn clear_requests(&mut self) {
+        self.requests.clear();
+        counters::IN_FLIGHT_CHUNK_REQUESTS.set(0);
+    }
+
+    /// Multicasts the next chunk requests to all the upstream networks
+    pub fn multicast_to_all_networks(&mut self) {
+        // multicast_level (=network preference) is 0-indexed
+        let max_level = self.upstream_config.upstream_count() - 1;
+        self.update_multicast(max_level, None);
+    }
+
+    /// Returns the estimated memory of the tracked requests, in bytes
+    pub fn memory_bytes(&self) -> usize {
+        self.requests
+            .values()
+            .map(|req| {
+                size_of::<(u64, ChunkRequestInfo)>()
+                    + (req.last_request_peers.len() + req.preempted_peers.len())
+                        * size_of::<PeerNetworkId>()
+            })
+            .sum()
+    }
+
+    #[cfg(test)]
+    pub fn num_requests(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns whether there is a chunk request with known_version >= `version` that hasn't
+    /// timed out yet, i.e., its response might still arrive. Preempted requests aren't waited for.
+    pub fn has_in_flight_requests(&self, version: u64) -> bool {
+        self.requests.range(version..).any(|(_, req)| {
+            !req.preempted && !self.is_timeout(req.last_request_time, self.request_timeout)
+        })
+    }
+
+    /// Returns whether an identical request was sent with the same multicast level and hasn't timed
+    /// out yet
+    fn is_in_flight(&self, req: &GetChunkRequest) -> bool {
+        self.requests
+            .get(&req.known_version)
+            .map_or(false, |req_info| {
+                req_info.multicast_level == self.multicast_level
+                    && !req_info.preempted
+                    && req_info.is_same_request(req)
+                    && !self.is_timeout(req_info.last_request_time, self.request_timeout)
+            })
+    }
+
+    /// Returns the peer the chunk request with known_version = `version` was last sent to, if it
+    /// hasn't timed out yet. For a multicasted request, that's the peer of the most preferred
+    /// network.
+    pub fn active_upstream(&self, version: u64) -> Option<PeerNetworkId> {
+        self.requests
+            .get(&version)
+            .filter(|req| !self.is_timeout(req.last_request_time, self.request_timeout))
+            .and_then(|req| req.last_request_peers.first().cloned())
     }
 
     /// Checks whether the request sent with known_version = `version` has timed out
     /// Returns true if such a request timed out or does not exist, else false
+    /// A request that can't be re-issued yet because of the rate limit isn't considered timed out,
+    /// so that its peers aren't penalized until it's re-issued.
+    /// A preempted request is considered timed out right away, without penalizing its peers.
     pub fn check_timeout(&mut self, version: u64) -> bool {
         let last_request_time = self.get_last_request_time(version).unwrap_or(UNIX_EPOCH);
 
-        let is_timeout = Self::is_timeout(last_request_time, self.request_timeout);
-        if !is_timeout {
-            return is_timeout;
+        let is_preempted = self
+            .requests
+            .get(&version)
+            .map_or(false, |req| req.preempted);
+        let is_timeout = is_preempted || self.is_timeout(last_request_time, self.request_timeout);
+        // targeted requests are retried regardless of the rate limit, like they're sent
+        let is_background = self
+            .requests
+            .get(&version)
+            .map_or(true, |req| req.is_background());
+        if !is_timeout || (is_background && !self.can_send_request()) {
+            return false;
         }
 
         // update peer info based on timeout
         let peers_to_penalize = match self.requests.get(&version) {
-            Some(prev_request) => prev_request.last_request_peers.clone(),
-            None => {
+            Some(prev_request) if !prev_request.preempted => {
+                prev_request.last_request_peers.clone()
+            }
+            _ => {
                 return is_timeout;
             }
         };
+        // the timed-out request is re-issued to other peers
+        self.timed_out_peers = peers_to_penalize.iter().cloned().collect();
         for peer in peers_to_penalize.iter() {
             self.update_score(peer, PeerScoreUpdateType::TimeOut);
+            self.resize_chunk_limit(peer, false);
+            if self.pinned_peer.as_ref() == Some(peer) {
+                counters::PINNED_UPSTREAM_FAILURES
+                    .with_label_values(&[counters::TIMEOUT_LABEL])
+                    .inc();
+            }
         }
 
         // increment multicast level if this request is also multicast-timed-out
         let multicast_start_time = self.get_multicast_start_time(version).unwrap_or(UNIX_EPOCH);
-        if Self::is_timeout(multicast_start_time, self.multicast_timeout) {
+        if self.is_timeout(multicast_start_time, self.multicast_timeout) {
             let new_multicast_level = std::cmp::min(
                 self.multicast_level + 1,
                 self.upstream_config.upstream_count() - 1, // multicast_level (=network preference) is 0-indexed
@@ -470,8 +1198,15 @@ impl RequestManager {
     }
 
     pub fn is_upstream_peer(&self, peer: &PeerNetworkId, origin: ConnectionOrigin) -> bool {
-        let is_network_upstream = self
-            .upstream_config
+        Self::is_upstream_peer_of(&self.upstream_config, peer, origin)
+    }
+
+    fn is_upstream_peer_of(
+        upstream_config: &UpstreamConfig,
+        peer: &PeerNetworkId,
+        origin: ConnectionOrigin,
+    ) -> bool {
+        let is_network_upstream = upstream_config
             .get_upstream_preference(peer.raw_network_id())
             .is_some();
         // check for case whether the peer is a public downstream peer, even if the public network is upstream
@@ -482,6 +1217,72 @@ impl RequestManager {
         }
     }
 
+    /// Records a version advertised by the peer, e.g., the known version of its chunk request.
+    /// Returns whether the version regresses from the previous advertisement of the peer.
+    pub fn update_peer_highest_version(&mut self, peer: &PeerNetworkId, version: u64) -> bool {
+        match self.peers.get_mut(peer) {
+            Some(peer_info) => {
+                peer_info.highest_version = Some(
+                    peer_info
+                        .highest_version
+                        .map_or(version, |highest_version| highest_version.max(version)),
+                );
+                let regressed = peer_info
+                    .latest_version
+                    .map_or(false, |latest_version| version < latest_version);
+                peer_info.latest_version = Some(version);
+                regressed
+            }
+            None => false,
+        }
+    }
+
+    /// Estimates the tip of the chain as the highest version that at least half of the alive
+    /// peers advertised last, so that a minority of peers advertising inconsistent or bogus
+    /// versions can't move the estimate.
+    pub fn tip_estimate(&self) -> Option<u64> {
+        let mut versions = self
+            .peers
+            .values()
+            .filter(|peer_info| peer_info.is_alive)
+            .filter_map(|peer_info| peer_info.latest_version)
+            .collect::<Vec<_>>();
+        if versions.is_empty() {
+            return None;
+        }
+        versions.sort_unstable();
+        Some(versions[(versions.len() - 1) / 2])
+    }
+
+    /// Aggregates the known peers of every network into healthy, penalized and disconnected ones
+    pub fn peer_health(&self) -> HashMap<NetworkId, PeerHealth> {
+        let mut peer_health: HashMap<NetworkId, PeerHealth> = HashMap::new();
+        for (peer, peer_info) in self.peers.iter() {
+            let health = peer_health.entry(peer.raw_network_id()).or_default();
+            if !peer_info.is_alive {
+                health.disconnected += 1;
+            } else if peer_info.score < PENALIZED_SCORE {
+                health.penalized += 1;
+            } else {
+                health.healthy += 1;
+            }
+        }
+        peer_health
+    }
+
+    /// Returns the highest version advertised by each alive peer that advertised one
+    pub fn peer_highest_versions(&self) -> Vec<(PeerNetworkId, u64)> {
+        self.peers
+            .iter()
+            .filter(|(_, peer_info)| peer_info.is_alive)
+            .filter_map(|(peer, peer_info)| {
+                peer_info
+                    .highest_version
+                    .map(|highest_version| (peer.clone(), highest_version))
+            })
+            .collect()
+    }
+
     pub fn is_known_upstream_peer(&self, peer: &PeerNetworkId) -> bool {
         self.peers.contains_key(peer)
     }
@@ -491,13 +1292,19 @@ impl RequestManager {
         self.peers.get(peer).map(|p| p.score)
     }
 
-    // Returns whether the timeout for the given params has occurred, compared to SystemTime at function call
+    #[cfg(test)]
+    pub fn peer_backoff(&self, peer: &PeerNetworkId) -> Option<Duration> {
+        self.peers.get(peer).and_then(|p| p.backoff)
+    }
+
+    // Returns whether the timeout for the given params has occurred, compared to the time provider
+    // at function call
     // returns true if the timeout (=`timeout_start + timeout_duration`) has happened, else false
-    fn is_timeout(timeout_start: SystemTime, timeout_duration: Duration) -> bool {
+    fn is_timeout(&self, timeout_start: SystemTime, timeout_duration: Duration) -> bool {
         timeout_start
             .checked_add(timeout_duration)
             .map_or(false, |deadline| {
-                SystemTime::now().duration_since(deadline).is_ok()
+                self.time_provider.now().duration_since(deadline).is_ok()
             })
     }
 
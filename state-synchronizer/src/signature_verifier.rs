@@ -0,0 +1,82 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use diem_types::{
+    ledger_info::LedgerInfoWithSignatures,
+    validator_verifier::{ValidatorVerifier, VerifyError},
+};
+
+/// Verifies the quorum signatures of ledger infos on behalf of state synchronizer, e.g. to
+/// offload the verification for very large validator sets to a hardware accelerator.
+pub trait SignatureVerifier: Send {
+    /// Verifies the signatures of each of `ledger_infos` against `verifier` and returns one
+    /// result per ledger info, in the same order.
+    /// All the ledger infos to verify at once are handed over in a single call, so that
+    /// implementations can batch them. The call blocks state synchronizer until it returns.
+    /// An `Err` reports a failure of the verifier itself (e.g. an unavailable device), in which
+    /// case the ledger infos are verified on the CPU instead of being considered invalid.
+    fn verify_batch(
+        &self,
+        verifier: &ValidatorVerifier,
+        ledger_infos: &[&LedgerInfoWithSignatures],
+    ) -> Result<Vec<Result<()>>>;
+}
+
+/// Verifies the signatures on the CPU, one ledger info after the other.
+pub struct CpuSignatureVerifier;
+
+impl SignatureVerifier for CpuSignatureVerifier {
+    fn verify_batch(
+        &self,
+        verifier: &ValidatorVerifier,
+        ledger_infos: &[&LedgerInfoWithSignatures],
+    ) -> Result<Vec<Result<()>>> {
+        Ok(ledger_infos
+            .iter()
+            .map(|li| li.verify_signatures(verifier).map_err(Into::into))
+            .collect())
+    }
+}
+
+/// Verifies the signatures on the CPU without exiting early on the first invalid signature, so
+/// that the verification time doesn't reveal which validator's signature failed. This is slower
+/// than `CpuSignatureVerifier`, which batches the signatures of a ledger info and stops at the
+/// first invalid one. The outcome of the verification is the same.
+pub struct ConstantTimeSignatureVerifier;
+
+impl ConstantTimeSignatureVerifier {
+    fn verify(
+        verifier: &ValidatorVerifier,
+        li: &LedgerInfoWithSignatures,
+    ) -> std::result::Result<(), VerifyError> {
+        // the checks on the signers only depend on the set of signers, which is public
+        if li.signatures().len() > verifier.len() {
+            return Err(VerifyError::TooManySignatures {
+                num_of_signatures: li.signatures().len(),
+                num_of_authors: verifier.len(),
+            });
+        }
+        verifier.check_voting_power(li.signatures().keys())?;
+        // every signature is verified, and the first failure reported once all of them are
+        li.signatures()
+            .iter()
+            .fold(Ok(()), |result, (author, signature)| {
+                let verified = verifier.verify(*author, li.ledger_info(), signature);
+                result.and(verified)
+            })
+    }
+}
+
+impl SignatureVerifier for ConstantTimeSignatureVerifier {
+    fn verify_batch(
+        &self,
+        verifier: &ValidatorVerifier,
+        ledger_infos: &[&LedgerInfoWithSignatures],
+    ) -> Result<Vec<Result<()>>> {
+        Ok(ledger_infos
+            .iter()
+            .map(|li| Self::verify(verifier, li).map_err(Into::into))
+            .collect())
+    }
+}
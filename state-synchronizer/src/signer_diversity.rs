@@ -0,0 +1,73 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use diem_types::{account_address::AccountAddress, ledger_info::LedgerInfoWithSignatures};
+use std::{
+    collections::{HashSet, VecDeque},
+    mem::size_of,
+};
+
+// DS to monitor validator participation: tracks the signers of the recently committed LIs over a
+// sliding window. This is diagnostic only, LIs are accepted regardless of their signers.
+pub struct SignerDiversity {
+    // signers of the most recently committed LIs, oldest first
+    recent_signers: VecDeque<Vec<AccountAddress>>,
+    window: usize,
+    min_distinct_signers: usize,
+    // whether the distinct signers of the previous window were below `min_distinct_signers`
+    below_min: bool,
+}
+
+impl SignerDiversity {
+    pub fn new(window: usize, min_distinct_signers: usize) -> Self {
+        Self {
+            recent_signers: VecDeque::with_capacity(window),
+            window,
+            min_distinct_signers,
+            below_min: false,
+        }
+    }
+
+    /// Records the signers of a newly committed LI. Returns the number of distinct signers over
+    /// the window and whether it just dropped below the minimum. Nothing is reported until the
+    /// window is full.
+    pub fn record(&mut self, li: &LedgerInfoWithSignatures) -> Option<(usize, bool)> {
+        if self.min_distinct_signers == 0 || self.window == 0 {
+            return None;
+        }
+        if self.recent_signers.len() == self.window {
+            self.recent_signers.pop_front();
+        }
+        self.recent_signers
+            .push_back(li.signatures().keys().cloned().collect());
+        if self.recent_signers.len() < self.window {
+            return None;
+        }
+        let distinct_signers = self
+            .recent_signers
+            .iter()
+            .flatten()
+            .collect::<HashSet<_>>()
+            .len();
+        let below_min = distinct_signers < self.min_distinct_signers;
+        let dropped_below_min = below_min && !self.below_min;
+        self.below_min = below_min;
+        Some((distinct_signers, dropped_below_min))
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.recent_signers
+            .iter()
+            .map(|signers| {
+                size_of::<Vec<AccountAddress>>() + signers.len() * AccountAddress::LENGTH
+            })
+            .sum()
+    }
+
+    /// Drops the recent signers, so that the window starts over. Returns the memory freed.
+    pub fn shed(&mut self) -> usize {
+        let memory = self.memory_bytes();
+        self.recent_signers.clear();
+        memory
+    }
+}
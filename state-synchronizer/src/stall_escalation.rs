@@ -0,0 +1,109 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use diem_config::config::StateSyncConfig;
+use diem_types::transaction::Version;
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EscalationStep {
+    WidenPeers,
+    RefreshPeers,
+    SwitchNetworks,
+    Alert,
+}
+
+// DS to escalate through recovery steps when the node goes too long without syncing a new version
+// while it has something to sync. Each step is taken once, in order, once its threshold is
+// reached, until the node makes progress again. A critical sync, which consensus is blocked on,
+// doesn't wait the thresholds of the recovery steps beyond one multicast timeout per step.
+pub struct StallEscalation {
+    // enabled steps with their threshold, in the order they're taken
+    steps: Vec<(EscalationStep, Duration)>,
+    // interval between the recovery steps of a critical sync
+    critical_step_interval: Duration,
+    // highest version synced and the time it was first observed
+    last_progress: (Version, SystemTime),
+    // number of steps taken since the last progress
+    num_steps_taken: usize,
+}
+
+impl StallEscalation {
+    pub fn new(config: &StateSyncConfig, version: Version, now: SystemTime) -> Self {
+        let steps = vec![
+            (
+                EscalationStep::WidenPeers,
+                config.stall_escalation_widen_peers_ms,
+            ),
+            (
+                EscalationStep::RefreshPeers,
+                config.stall_escalation_refresh_peers_ms,
+            ),
+            (
+                EscalationStep::SwitchNetworks,
+                config.stall_escalation_switch_networks_ms,
+            ),
+            (EscalationStep::Alert, config.stall_escalation_alert_ms),
+        ]
+        .into_iter()
+        .filter(|(_, threshold_ms)| *threshold_ms > 0)
+        .map(|(step, threshold_ms)| (step, Duration::from_millis(threshold_ms)))
+        .collect();
+        Self {
+            steps,
+            critical_step_interval: Duration::from_millis(config.multicast_timeout_ms),
+            last_progress: (version, now),
+            num_steps_taken: 0,
+        }
+    }
+
+    /// Records the highest version synced, or that the node has nothing to sync. Returns the
+    /// steps to take now, in order, each with whether it's taken before its threshold because
+    /// the sync is critical.
+    pub fn update(
+        &mut self,
+        version: Version,
+        at_tip: bool,
+        critical: bool,
+        now: SystemTime,
+    ) -> Vec<(EscalationStep, bool)> {
+        if at_tip || version != self.last_progress.0 {
+            self.last_progress = (version, now);
+            self.num_steps_taken = 0;
+            return vec![];
+        }
+        let stalled_for = self.stalled_for(now);
+        let mut steps = vec![];
+        while self.num_steps_taken < self.steps.len() {
+            let (threshold, bypassed) = self.threshold(self.num_steps_taken, critical);
+            if stalled_for < threshold {
+                break;
+            }
+            steps.push((self.steps[self.num_steps_taken].0, bypassed));
+            self.num_steps_taken += 1;
+        }
+        steps
+    }
+
+    // Returns the threshold of the step at `index`, and whether it's below the configured one. The
+    // alert isn't brought forward, as it's not a recovery step.
+    fn threshold(&self, index: usize, critical: bool) -> (Duration, bool) {
+        let (step, threshold) = self.steps[index];
+        if !critical
+            || step == EscalationStep::Alert
+            || self.critical_step_interval == Duration::from_millis(0)
+        {
+            return (threshold, false);
+        }
+        let critical_threshold = self.critical_step_interval * (index as u32 + 1);
+        if critical_threshold < threshold {
+            (critical_threshold, true)
+        } else {
+            (threshold, false)
+        }
+    }
+
+    pub fn stalled_for(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.last_progress.1).unwrap_or_default()
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::{Duration, SystemTime};
+
+// DS to track whether the node is actively syncing or idle at the tip of the chain.
+// A transition only happens once the new state has been observed for the debounce duration,
+// so that a node hovering near the tip doesn't flap between the two.
+pub struct SyncLifecycle {
+    pub idle_at_tip: bool,
+    // time the node was first observed in the state opposite to `idle_at_tip`
+    pending_transition_tst: Option<SystemTime>,
+    debounce: Duration,
+}
+
+impl SyncLifecycle {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            idle_at_tip: false,
+            pending_transition_tst: None,
+            debounce,
+        }
+    }
+
+    /// Records whether the node is currently at the tip. Returns true if this results in a
+    /// transition between syncing and idle at the tip.
+    pub fn update(&mut self, at_tip: bool, now: SystemTime) -> bool {
+        if at_tip == self.idle_at_tip {
+            self.pending_transition_tst = None;
+            return false;
+        }
+        let first_observed = *self.pending_transition_tst.get_or_insert(now);
+        let debounced = now
+            .duration_since(first_observed)
+            .map_or(false, |elapsed| elapsed >= self.debounce);
+        if debounced {
+            self.idle_at_tip = at_tip;
+            self.pending_transition_tst = None;
+        }
+        debounced
+    }
+}
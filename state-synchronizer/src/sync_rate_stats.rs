@@ -0,0 +1,167 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{counters, SyncRatePercentiles};
+use std::{cmp::Ordering, time::Duration};
+
+// DS to estimate a quantile of a stream of samples in constant memory, with the P-square
+// algorithm: five markers track the min, the quantile halfway between the min and the estimated
+// quantile, the estimated quantile, the one halfway to the max, and the max. The markers are
+// adjusted with a piecewise-parabolic interpolation as samples are observed
+struct QuantileEstimator {
+    quantile: f64,
+    num_samples: usize,
+    // heights of the markers, ascending. Until there are five samples, the samples themselves
+    heights: [f64; 5],
+    // actual and desired positions of the markers, starting at 1
+    positions: [f64; 5],
+    desired_positions: [f64; 5],
+}
+
+impl QuantileEstimator {
+    fn new(quantile: f64) -> Self {
+        Self {
+            quantile,
+            num_samples: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired_positions: [
+                1.0,
+                1.0 + 2.0 * quantile,
+                1.0 + 4.0 * quantile,
+                3.0 + 2.0 * quantile,
+                5.0,
+            ],
+        }
+    }
+
+    fn observe(&mut self, sample: f64) {
+        if self.num_samples < 5 {
+            self.heights[self.num_samples] = sample;
+            self.num_samples += 1;
+            if self.num_samples == 5 {
+                self.heights
+                    .sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            }
+            return;
+        }
+        self.num_samples += 1;
+
+        // the markers above the cell of the sample move up by one position
+        let cell = if sample < self.heights[0] {
+            self.heights[0] = sample;
+            0
+        } else if sample >= self.heights[4] {
+            self.heights[4] = sample;
+            3
+        } else {
+            (1..5)
+                .find(|&i| sample < self.heights[i])
+                .map_or(3, |i| i - 1)
+        };
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        let q = self.quantile;
+        let increments = [0.0, q / 2.0, q, (1.0 + q) / 2.0, 1.0];
+        for (desired, increment) in self.desired_positions.iter_mut().zip(increments.iter()) {
+            *desired += increment;
+        }
+
+        // the middle markers that are off their desired position by more than one move towards it
+        for i in 1..4 {
+            let offset = self.desired_positions[i] - self.positions[i];
+            if (offset >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (offset <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0)
+            {
+                let step = offset.signum();
+                let height = self.parabolic(i, step);
+                self.heights[i] = if self.heights[i - 1] < height && height < self.heights[i + 1] {
+                    height
+                } else {
+                    self.linear(i, step)
+                };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (h, n) = (&self.heights, &self.positions);
+        h[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, step: f64) -> f64 {
+        let (h, n) = (&self.heights, &self.positions);
+        let j = if step > 0.0 { i + 1 } else { i - 1 };
+        h[i] + step * (h[j] - h[i]) / (n[j] - n[i])
+    }
+
+    fn estimate(&self) -> Option<f64> {
+        match self.num_samples {
+            0 => None,
+            num_samples if num_samples < 5 => {
+                let mut samples = self.heights[..num_samples].to_vec();
+                samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                let index = ((num_samples - 1) as f64 * self.quantile).round() as usize;
+                Some(samples[index])
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+// DS to estimate the percentiles of the rate chunks are synced at since the node started, from
+// the first request of each chunk to its commit, without storing the samples
+pub struct SyncRateStats {
+    num_samples: u64,
+    p50: QuantileEstimator,
+    p90: QuantileEstimator,
+    p99: QuantileEstimator,
+}
+
+impl SyncRateStats {
+    pub fn new() -> Self {
+        Self {
+            num_samples: 0,
+            p50: QuantileEstimator::new(0.5),
+            p90: QuantileEstimator::new(0.9),
+            p99: QuantileEstimator::new(0.99),
+        }
+    }
+
+    /// Records a chunk of `num_txns` transactions synced in `duration`.
+    pub fn record(&mut self, num_txns: u64, duration: Duration) {
+        if duration == Duration::from_millis(0) {
+            return;
+        }
+        let rate = num_txns as f64 / duration.as_secs_f64();
+        self.num_samples += 1;
+        for (estimator, label) in [
+            (&mut self.p50, counters::P50_LABEL),
+            (&mut self.p90, counters::P90_LABEL),
+            (&mut self.p99, counters::P99_LABEL),
+        ]
+        .iter_mut()
+        {
+            estimator.observe(rate);
+            if let Some(estimate) = estimator.estimate() {
+                counters::SYNC_RATE
+                    .with_label_values(&[*label])
+                    .set(estimate as i64);
+            }
+        }
+    }
+
+    /// Returns the estimated percentiles, if any chunk was synced.
+    pub fn percentiles(&self) -> Option<SyncRatePercentiles> {
+        Some(SyncRatePercentiles {
+            num_samples: self.num_samples,
+            p50: self.p50.estimate()?,
+            p90: self.p90.estimate()?,
+            p99: self.p99.estimate()?,
+        })
+    }
+}
@@ -1,30 +1,43 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
+#[cfg(any(test, feature = "fuzzing"))]
+use crate::chunk_response::GetChunkResponse;
 use crate::{
-    coordinator::{CoordinatorMessage, SyncCoordinator, SyncRequest},
+    chunk_source::{ChunkSource, FileChunkSource},
+    coordinator::{
+        CoordinatorMessage, SyncCoordinator, SyncCoordinatorDeps, SyncRequest, VersionSyncRequest,
+    },
     counters,
+    error::StateSyncError,
     executor_proxy::{ExecutorProxy, ExecutorProxyTrait},
     network::{StateSynchronizerEvents, StateSynchronizerSender},
-    SynchronizerState,
+    signature_verifier::{ConstantTimeSignatureVerifier, CpuSignatureVerifier, SignatureVerifier},
+    upstream_resolver::{StaticUpstreamResolver, UpstreamResolver},
+    BandwidthStats, CommitOutcome, EpochChangeListener, EpochTransition, InitProgress,
+    PeerSyncStats, ReconfigNotification, StallReason, SyncHealth, SyncProgress,
+    SyncRatePercentiles, SynchronizerState, TrustedValidatorSet, VerificationReport,
 };
-use anyhow::{format_err, Result};
+use anyhow::{format_err, Context, Result};
 use diem_config::{
-    config::{NodeConfig, RoleType, StateSyncConfig, UpstreamConfig},
+    config::{NodeConfig, PeerNetworkId, RoleType, StateSyncConfig},
     network_id::NodeNetworkId,
 };
+use diem_infallible::Mutex;
 use diem_mempool::{CommitNotification, CommitResponse};
 use diem_types::{
-    contract_event::ContractEvent, ledger_info::LedgerInfoWithSignatures, transaction::Transaction,
-    waypoint::Waypoint,
+    contract_event::ContractEvent, epoch_change::EpochChangeProof,
+    ledger_info::LedgerInfoWithSignatures, transaction::Transaction, waypoint::Waypoint,
 };
 use executor_types::ChunkExecutor;
 use futures::{
     channel::{mpsc, oneshot},
+    executor::block_on,
     future::Future,
     SinkExt,
 };
 use std::{
     boxed::Box,
+    cell::Cell,
     collections::HashMap,
     sync::Arc,
     time::{Duration, SystemTime},
@@ -32,13 +45,24 @@ use std::{
 use storage_interface::DbReader;
 use subscription_service::ReconfigSubscription;
 use tokio::{
-    runtime::{Builder, Runtime},
+    runtime::{Builder, Handle, Runtime},
     time::timeout,
 };
 
+// max duration to wait for the tasks left on the runtime, e.g. the network event handlers, once
+// the coordinator acknowledged a shutdown
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+thread_local! {
+    // whether the current thread is one of the runtime of a state synchronizer
+    static IS_STATE_SYNC_THREAD: Cell<bool> = Cell::new(false);
+}
+
 pub struct StateSynchronizer {
-    _runtime: Runtime,
+    runtime: Runtime,
     coordinator_sender: mpsc::UnboundedSender<CoordinatorMessage>,
+    sync_generation: Arc<Mutex<u64>>,
+    commit_timeout: Duration,
 }
 
 impl StateSynchronizer {
@@ -56,26 +80,88 @@ impl StateSynchronizer {
         waypoint: Waypoint,
         reconfig_event_subscriptions: Vec<ReconfigSubscription>,
     ) -> Self {
-        let runtime = Builder::new()
-            .thread_name("state-sync")
-            .threaded_scheduler()
-            .enable_all()
-            .build()
-            .expect("[state synchronizer] failed to create runtime");
+        Self::bootstrap_with_waypoints(
+            network,
+            state_sync_to_mempool_sender,
+            storage,
+            executor,
+            config,
+            vec![waypoint],
+            reconfig_event_subscriptions,
+        )
+    }
 
-        let executor_proxy = ExecutorProxy::new(storage, executor, reconfig_event_subscriptions);
+    /// Setup state synchronizer with multiple trusted epoch-ending waypoints, ordered by version.
+    /// The node syncs to the highest one before it's initialized, and the LIs it commits at the
+    /// version of each waypoint have to match it: a chunk that mismatches one is rejected, so that
+    /// a peer serving a valid-looking history of another fork is caught.
+    pub fn bootstrap_with_waypoints(
+        network: Vec<(
+            NodeNetworkId,
+            StateSynchronizerSender,
+            StateSynchronizerEvents,
+        )>,
+        state_sync_to_mempool_sender: mpsc::Sender<CommitNotification>,
+        storage: Arc<dyn DbReader>,
+        executor: Box<dyn ChunkExecutor>,
+        config: &NodeConfig,
+        waypoints: Vec<Waypoint>,
+        reconfig_event_subscriptions: Vec<ReconfigSubscription>,
+    ) -> Self {
+        let runtime = Self::create_runtime(&config.state_sync);
+
+        let executor_proxy = ExecutorProxy::new(
+            storage,
+            executor,
+            reconfig_event_subscriptions,
+            config.state_sync.max_reconfig_notifications_per_commit,
+        );
+        let signature_verifier: Box<dyn SignatureVerifier> =
+            if config.state_sync.constant_time_signature_verification {
+                Box::new(ConstantTimeSignatureVerifier)
+            } else {
+                Box::new(CpuSignatureVerifier)
+            };
+        let chunk_source = config
+            .state_sync
+            .chunk_source_dir
+            .as_ref()
+            .map(|dir| Box::new(FileChunkSource::new(dir)) as Box<dyn ChunkSource>);
         Self::bootstrap_with_executor_proxy(
             runtime,
             network,
             state_sync_to_mempool_sender,
             config.base.role,
-            waypoint,
+            waypoints,
             &config.state_sync,
-            config.upstream.clone(),
+            Box::new(StaticUpstreamResolver::new(config.upstream.clone())),
             executor_proxy,
+            SyncCoordinatorDeps {
+                signature_verifier,
+                chunk_source,
+                ..SyncCoordinatorDeps::default()
+            },
         )
     }
 
+    /// Creates the runtime of state synchronizer, with the configured number of worker threads
+    pub(crate) fn create_runtime(config: &StateSyncConfig) -> Runtime {
+        let mut builder = Builder::new();
+        builder
+            .thread_name("state-sync")
+            .on_thread_start(|| IS_STATE_SYNC_THREAD.with(|is_state_sync| is_state_sync.set(true)))
+            .threaded_scheduler()
+            .enable_all();
+        if let Some(runtime_threads) = config.runtime_threads {
+            builder.core_threads(runtime_threads);
+        }
+        builder
+            .build()
+            .expect("[state synchronizer] failed to create runtime")
+    }
+
+    /// Setup state synchronizer on top of the given executor proxy. The epoch transitions are
+    /// notified to the `epoch_change_sender` of `deps`, if set, once they're committed.
     pub fn bootstrap_with_executor_proxy<E: ExecutorProxyTrait + 'static>(
         runtime: Runtime,
         network: Vec<(
@@ -85,10 +171,11 @@ impl StateSynchronizer {
         )>,
         state_sync_to_mempool_sender: mpsc::Sender<CommitNotification>,
         role: RoleType,
-        waypoint: Waypoint,
+        waypoints: Vec<Waypoint>,
         state_sync_config: &StateSyncConfig,
-        upstream_config: UpstreamConfig,
+        upstream_resolver: Box<dyn UpstreamResolver>,
         executor_proxy: E,
+        deps: SyncCoordinatorDeps,
     ) -> Self {
         let (coordinator_sender, coordinator_receiver) = mpsc::unbounded();
 
@@ -106,43 +193,112 @@ impl StateSynchronizer {
             state_sync_to_mempool_sender,
             network_senders,
             role,
-            waypoint,
+            waypoints,
             state_sync_config.clone(),
-            upstream_config,
+            upstream_resolver,
             executor_proxy,
             initial_state,
+            deps,
         );
         runtime.spawn(coordinator.start(network));
 
         Self {
-            _runtime: runtime,
+            runtime,
             coordinator_sender,
+            sync_generation: Arc::new(Mutex::new(0)),
+            commit_timeout: Duration::from_millis(state_sync_config.commit_timeout_ms),
         }
     }
 
     pub fn create_client(&self) -> Arc<StateSyncClient> {
-        Arc::new(StateSyncClient::new(self.coordinator_sender.clone()))
+        Arc::new(StateSyncClient::new_with_generation(
+            self.coordinator_sender.clone(),
+            self.sync_generation.clone(),
+            self.commit_timeout,
+        ))
+    }
+
+    /// Creates a client whose methods block until they complete, driven on the runtime of state
+    /// synchronizer, for consumers that aren't async.
+    pub fn create_blocking_client(&self) -> StateSyncClientBlocking {
+        StateSyncClientBlocking::new(self.create_client(), self.runtime.handle().clone())
     }
 
     /// The function returns a future that is fulfilled when the state synchronizer is
     /// caught up with the waypoint specified in the local config.
-    pub async fn wait_until_initialized(&self) -> Result<()> {
+    pub async fn wait_until_initialized(&self) -> Result<(), StateSyncError> {
+        wait_until_initialized(self.coordinator_sender.clone()).await
+    }
+
+    /// Same as `wait_until_initialized`, and reports the progress of the initialization to
+    /// `progress` every `init_progress_interval_chunks` committed chunks. The channel is closed
+    /// once the initialization completes or fails.
+    pub async fn wait_until_initialized_with_progress(
+        &self,
+        progress: mpsc::Sender<InitProgress>,
+    ) -> Result<(), StateSyncError> {
         let mut sender = self.coordinator_sender.clone();
         let (cb_sender, cb_receiver) = oneshot::channel();
         sender
-            .send(CoordinatorMessage::WaitInitialize(cb_sender))
-            .await?;
-        cb_receiver.await?
+            .send(CoordinatorMessage::WaitInitializeWithProgress(
+                cb_sender, progress,
+            ))
+            .await
+            .map_err(coordinator_unavailable)?;
+        cb_receiver.await.map_err(coordinator_unavailable)?
+    }
+
+    /// Shuts state synchronizer down gracefully: the coordinator stops issuing chunk requests,
+    /// drains the responses to the in-flight ones (up to `shutdown_drain_timeout_ms`) and stops.
+    /// The runtime is only shut down once the coordinator acknowledged the shutdown, so that no
+    /// chunk is interrupted mid-commit.
+    /// It blocks the calling thread until then, so it must not be called from an async context.
+    pub fn shutdown(mut self) -> Result<()> {
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        self.coordinator_sender
+            .unbounded_send(CoordinatorMessage::Shutdown(cb_sender))
+            .map_err(coordinator_unavailable)?;
+        let result = self.runtime.block_on(cb_receiver);
+        self.runtime.shutdown_timeout(RUNTIME_SHUTDOWN_TIMEOUT);
+        result.map_err(coordinator_unavailable)?
     }
 }
 
 pub struct StateSyncClient {
     coordinator_sender: mpsc::UnboundedSender<CoordinatorMessage>,
+    // generation of the latest sync request, shared by the clients of the same StateSynchronizer
+    // Sync requests and cancels are submitted while holding it, so that the coordinator receives
+    // them in generation order
+    sync_generation: Arc<Mutex<u64>>,
+    // max duration to wait for the ACK of a commit
+    commit_timeout: Duration,
 }
 
 impl StateSyncClient {
     pub fn new(coordinator_sender: mpsc::UnboundedSender<CoordinatorMessage>) -> Self {
-        Self { coordinator_sender }
+        Self::new_with_commit_timeout(
+            coordinator_sender,
+            Duration::from_millis(StateSyncConfig::default().commit_timeout_ms),
+        )
+    }
+
+    pub fn new_with_commit_timeout(
+        coordinator_sender: mpsc::UnboundedSender<CoordinatorMessage>,
+        commit_timeout: Duration,
+    ) -> Self {
+        Self::new_with_generation(coordinator_sender, Arc::new(Mutex::new(0)), commit_timeout)
+    }
+
+    fn new_with_generation(
+        coordinator_sender: mpsc::UnboundedSender<CoordinatorMessage>,
+        sync_generation: Arc<Mutex<u64>>,
+        commit_timeout: Duration,
+    ) -> Self {
+        Self {
+            coordinator_sender,
+            sync_generation,
+            commit_timeout,
+        }
     }
 
     /// Sync validator's state to target.
@@ -150,68 +306,665 @@ impl StateSyncClient {
     /// In case of failure (`Result::Error`) the LI of storage remains unchanged, and the validator
     /// can assume there were no modifications to the storage made.
     /// It is up to state synchronizer to decide about the specific criteria for the failure
-    /// (e.g., lack of progress with all of the peer validators).
-    pub fn sync_to(&self, target: LedgerInfoWithSignatures) -> impl Future<Output = Result<()>> {
-        let mut sender = self.coordinator_sender.clone();
+    /// (e.g., lack of progress with all of the peer validators), reported as a `StateSyncError`.
+    /// A sync request replaces the previous one, and can be cancelled via `cancel_sync` until
+    /// it's complete.
+    pub fn sync_to(
+        &self,
+        target: LedgerInfoWithSignatures,
+    ) -> impl Future<Output = Result<(), StateSyncError>> {
+        let submitted = self.submit_sync_request(target);
+        async move {
+            let (_, cb_receiver) = submitted?;
+            cb_receiver.await.map_err(coordinator_unavailable)?
+        }
+    }
+
+    /// Same as `sync_to`, and returns a handle of the sync request: the request is cancelled if
+    /// the handle is cancelled or dropped before the request completes, e.g. once the caller
+    /// doesn't need the target anymore.
+    pub fn sync_to_cancellable(
+        &self,
+        target: LedgerInfoWithSignatures,
+    ) -> Result<SyncHandle, StateSyncError> {
+        let (generation, callback_rcv) = self.submit_sync_request(target)?;
+        Ok(SyncHandle {
+            coordinator_sender: self.coordinator_sender.clone(),
+            generation,
+            callback_rcv,
+            completed: false,
+        })
+    }
+
+    // Submits a sync request of a new generation, and returns the generation and the receiver of
+    // the result of the request
+    fn submit_sync_request(
+        &self,
+        target: LedgerInfoWithSignatures,
+    ) -> Result<(u64, oneshot::Receiver<Result<(), StateSyncError>>), StateSyncError> {
         let (callback, cb_receiver) = oneshot::channel();
+        let mut generation = self.sync_generation.lock();
+        *generation += 1;
         let request = SyncRequest {
             callback,
             target,
             last_progress_tst: SystemTime::now(),
+            generation: *generation,
+        };
+        self.coordinator_sender
+            .unbounded_send(CoordinatorMessage::Request(Box::new(request)))
+            .map_err(coordinator_unavailable)?;
+        Ok((*generation, cb_receiver))
+    }
+
+    /// Sync the node's state to the given version, without a target LI: the node syncs to the
+    /// highest LIs available, across epochs, until a committed LI reaches the version.
+    /// In case of success (`Result::Ok`) the version is committed, which is immediate if it
+    /// already was. In case of failure (`Result::Error`) no LI reached the version on time.
+    /// A sync request to a version replaces the previous one, which fails.
+    pub fn sync_to_version(
+        &self,
+        version: u64,
+    ) -> impl Future<Output = Result<(), StateSyncError>> {
+        let (callback, cb_receiver) = oneshot::channel();
+        let request = VersionSyncRequest {
+            callback,
+            version,
+            last_progress_tst: SystemTime::now(),
         };
+        let submitted = self
+            .coordinator_sender
+            .unbounded_send(CoordinatorMessage::SyncToVersion(Box::new(request)))
+            .map_err(coordinator_unavailable);
         async move {
-            sender
-                .send(CoordinatorMessage::Request(Box::new(request)))
-                .await?;
-            cb_receiver.await?
+            submitted?;
+            cb_receiver.await.map_err(coordinator_unavailable)?
         }
     }
 
+    /// Cancels the latest sync request submitted via `sync_to`, which fails with an error.
+    /// The cancel only applies to the sync request that was the latest when it was issued: if a
+    /// newer sync request is submitted concurrently, it isn't cancelled.
+    pub fn cancel_sync(&self) -> Result<()> {
+        let generation = self.sync_generation.lock();
+        self.coordinator_sender
+            .unbounded_send(CoordinatorMessage::CancelSync(*generation))
+            .map_err(|e| coordinator_unavailable(e).into())
+    }
+
     /// Notifies state synchronizer about new version
     pub fn commit(
         &self,
         // *successfully* committed transactions
         committed_txns: Vec<Transaction>,
         reconfig_events: Vec<ContractEvent>,
+    ) -> impl Future<Output = Result<()>> {
+        self.submit_commit(committed_txns, reconfig_events, None)
+    }
+
+    /// Same as `commit`, and returns the reconfig subscriptions notified of `reconfig_events`,
+    /// e.g. to debug a reconfiguration that didn't propagate.
+    pub fn commit_with_outcome(
+        &self,
+        // *successfully* committed transactions
+        committed_txns: Vec<Transaction>,
+        reconfig_events: Vec<ContractEvent>,
+    ) -> impl Future<Output = Result<CommitOutcome>> {
+        let (outcome_callback, outcome_rcv) = oneshot::channel();
+        let commit = self.submit_commit(committed_txns, reconfig_events, Some(outcome_callback));
+        async move {
+            commit.await?;
+            outcome_rcv.await.map_err(coordinator_unavailable)?
+        }
+    }
+
+    // Notifies state synchronizer about new version and waits for the ACK. The outcome of the
+    // reconfig events is sent to `outcome_callback`, if any.
+    fn submit_commit(
+        &self,
+        committed_txns: Vec<Transaction>,
+        reconfig_events: Vec<ContractEvent>,
+        outcome_callback: Option<oneshot::Sender<Result<CommitOutcome>>>,
     ) -> impl Future<Output = Result<()>> {
         let mut sender = self.coordinator_sender.clone();
+        let commit_timeout = self.commit_timeout;
         async move {
             let (callback, callback_rcv) = oneshot::channel();
-            sender
-                .send(CoordinatorMessage::Commit(
+            let msg = match outcome_callback {
+                Some(outcome_callback) => CoordinatorMessage::CommitWithOutcome(
                     committed_txns,
                     reconfig_events,
                     callback,
-                ))
-                .await?;
+                    outcome_callback,
+                ),
+                None => CoordinatorMessage::Commit(committed_txns, reconfig_events, callback),
+            };
+            sender.send(msg).await.map_err(coordinator_unavailable)?;
 
-            match timeout(Duration::from_secs(5), callback_rcv).await {
+            match timeout(commit_timeout, callback_rcv).await {
                 Err(_) => {
                     counters::COMMIT_FLOW_FAIL
                         .with_label_values(&[counters::STATE_SYNC_LABEL])
                         .inc();
                     Err(format_err!("[state sync client] failed to receive commit ACK from state synchronizer on time"))
                 }
-                Ok(resp) => {
-                    let CommitResponse { msg } = resp??;
-                    if msg != "" {
-                        Err(format_err!("[state sync client] commit failed: {:?}", msg))
-                    } else {
-                        Ok(())
-                    }
-                }
+                Ok(resp) => commit_result(resp),
             }
         }
     }
 
+    /// Notifies state synchronizer about new version without waiting for the ACK: the returned
+    /// handle tracks the completion of the commit.
+    /// Commits are processed in the order they are submitted, so that callers can submit multiple
+    /// commits without waiting for the previous ones to complete.
+    pub fn commit_async(
+        &self,
+        // *successfully* committed transactions
+        committed_txns: Vec<Transaction>,
+        reconfig_events: Vec<ContractEvent>,
+    ) -> Result<CommitHandle> {
+        let (callback, callback_rcv) = oneshot::channel();
+        self.coordinator_sender
+            .unbounded_send(CoordinatorMessage::Commit(
+                committed_txns,
+                reconfig_events,
+                callback,
+            ))
+            .map_err(coordinator_unavailable)?;
+        Ok(CommitHandle { callback_rcv })
+    }
+
+    /// Returns the health of StateSynchronizer, e.g. whether the node is idle at the tip of the
+    /// chain and ready to take consensus traffic
+    pub fn health_check(&self) -> impl Future<Output = Result<SyncHealth>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::HealthCheck(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let health = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(health)
+        }
+    }
+
+    /// Prepares the node for a planned restart: state synchronizer stops issuing new requests
+    /// and the returned future is fulfilled once all the transactions synced so far are committed.
+    /// Unlike a shutdown, the node keeps serving other nodes and committing consensus blocks.
+    pub fn quiesce(&self) -> impl Future<Output = Result<()>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::Quiesce(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            cb_receiver.await.map_err(coordinator_unavailable)?
+        }
+    }
+
+    /// Returns a stream of the reconfiguration events committed by the node, along with the epoch
+    /// and version they were committed at. The subscription ends when the receiver is dropped.
+    pub fn subscribe_reconfigs(
+        &self,
+    ) -> impl Future<Output = Result<mpsc::UnboundedReceiver<ReconfigNotification>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (subscriber, receiver) = mpsc::unbounded();
+        async move {
+            sender
+                .send(CoordinatorMessage::SubscribeReconfigs(subscriber))
+                .await
+                .map_err(coordinator_unavailable)?;
+            Ok(receiver)
+        }
+    }
+
+    /// Registers a listener called at every epoch transition of state synchronizer from now on,
+    /// with the old and the new epoch states (see `EpochChangeListener`).
+    pub fn add_epoch_change_listener(
+        &self,
+        listener: Box<dyn EpochChangeListener>,
+    ) -> impl Future<Output = Result<()>> {
+        let mut sender = self.coordinator_sender.clone();
+        async move {
+            sender
+                .send(CoordinatorMessage::AddEpochChangeListener(listener))
+                .await
+                .map_err(coordinator_unavailable)?;
+            Ok(())
+        }
+    }
+
+    /// Prompts state synchronizer to run a background sync pass now (e.g., right after peers
+    /// reconnect) rather than at the next tick. Unlike `sync_to`, the pass has no specific target.
+    pub fn trigger_sync_cycle(&self) -> impl Future<Output = Result<()>> {
+        let mut sender = self.coordinator_sender.clone();
+        async move {
+            sender
+                .send(CoordinatorMessage::TriggerSyncCycle)
+                .await
+                .map_err(coordinator_unavailable)?;
+            Ok(())
+        }
+    }
+
+    /// Pauses syncing, e.g. during a maintenance window: state synchronizer stops requesting and
+    /// applying chunks until it's resumed. It keeps serving other nodes and committing consensus
+    /// blocks meanwhile.
+    pub fn pause(&self) -> impl Future<Output = Result<()>> {
+        let mut sender = self.coordinator_sender.clone();
+        async move {
+            sender
+                .send(CoordinatorMessage::Pause)
+                .await
+                .map_err(coordinator_unavailable)?;
+            Ok(())
+        }
+    }
+
+    /// Resumes syncing after a `pause`, from the state of the local storage.
+    pub fn resume(&self) -> impl Future<Output = Result<()>> {
+        let mut sender = self.coordinator_sender.clone();
+        async move {
+            sender
+                .send(CoordinatorMessage::Resume)
+                .await
+                .map_err(coordinator_unavailable)?;
+            Ok(())
+        }
+    }
+
+    /// Pins the peer chunks are requested from, e.g. a trusted archival node, or unpins it if
+    /// `None`. While a peer is pinned, no other peer is requested, even if the pinned one is
+    /// unavailable.
+    pub fn pin_peer(&self, peer: Option<PeerNetworkId>) -> impl Future<Output = Result<()>> {
+        let mut sender = self.coordinator_sender.clone();
+        async move {
+            sender
+                .send(CoordinatorMessage::PinPeer(peer))
+                .await
+                .map_err(coordinator_unavailable)?;
+            Ok(())
+        }
+    }
+
+    /// Returns the chain of verified epoch-ending LIs from `start_epoch` to the latest verified
+    /// epoch, e.g., for light clients to bootstrap from an old waypoint without syncing the
+    /// transactions in between.
+    pub fn get_epoch_change_proof(
+        &self,
+        start_epoch: u64,
+    ) -> impl Future<Output = Result<EpochChangeProof>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetEpochChangeProof(
+                    start_epoch,
+                    cb_sender,
+                ))
+                .await
+                .map_err(coordinator_unavailable)?;
+            cb_receiver.await.map_err(coordinator_unavailable)?
+        }
+    }
+
+    /// Returns the reason state synchronizer most recently failed to make progress, i.e. the
+    /// reason it found when its last chunk request timed out (or it had no peers to send one to),
+    /// or `None` if it never stalled.
+    pub fn last_stall_reason(&self) -> impl Future<Output = Result<Option<StallReason>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetLastStallReason(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let reason = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(reason)
+        }
+    }
+
+    /// Returns the peer (and its network) state synchronizer is currently syncing from, i.e. the
+    /// peer of the in-flight chunk request for the next version, or `None` if it's idle.
+    pub fn active_upstream(&self) -> impl Future<Output = Result<Option<PeerNetworkId>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetActiveUpstream(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let peer = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(peer)
+        }
+    }
+
+    /// Returns the target LI of the sync request state synchronizer is currently syncing to, or
+    /// `None` if there is none, e.g. the node only syncs in the background.
+    pub fn active_target(&self) -> impl Future<Output = Result<Option<LedgerInfoWithSignatures>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetActiveTarget(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let target = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(target)
+        }
+    }
+
+    /// Returns the chunks requested from, received from and failed by every known upstream peer,
+    /// along with the moving average of its response latency.
+    pub fn get_peer_stats(
+        &self,
+    ) -> impl Future<Output = Result<HashMap<PeerNetworkId, PeerSyncStats>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetPeerStats(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let stats = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(stats)
+        }
+    }
+
+    /// Returns the bytes of the chunk requests and responses sent and received over the recent
+    /// window set by `bandwidth_stats_window_ms`, both to sync the node and to serve other nodes.
+    pub fn bandwidth_stats(&self) -> impl Future<Output = Result<BandwidthStats>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetBandwidthStats(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let stats = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(stats)
+        }
+    }
+
+    /// Returns the p50 / p90 / p99 rates state synchronizer synced chunks at since it started, in
+    /// transactions per second, or None if it didn't sync any chunk yet.
+    pub fn sync_rate_percentiles(
+        &self,
+    ) -> impl Future<Output = Result<Option<SyncRatePercentiles>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetSyncRatePercentiles(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let percentiles = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(percentiles)
+        }
+    }
+
+    /// Returns the chunks verified so far in verification-only mode (`verification_only`), and the
+    /// ones that failed verification.
+    pub fn get_verification_report(&self) -> impl Future<Output = Result<VerificationReport>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetVerificationReport(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let report = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(report)
+        }
+    }
+
+    /// Returns the most recent epoch transitions of the node, oldest first, up to
+    /// `epoch_transition_history_size` of them.
+    pub fn get_epoch_transition_history(
+        &self,
+    ) -> impl Future<Output = Result<Vec<EpochTransition>>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetEpochTransitionHistory(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let history = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(history)
+        }
+    }
+
+    /// Reloads the state of the coordinator from the local storage, e.g. after the storage was
+    /// truncated or restored by an external process, and returns the reloaded state. The in-flight
+    /// chunk requests are aborted, and syncing resumes from the reloaded version.
+    pub fn reload_from_storage(&self) -> impl Future<Output = Result<SynchronizerState>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::ReloadFromStorage(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            cb_receiver.await.map_err(coordinator_unavailable)?
+        }
+    }
+
+    /// Returns the validators and their voting powers of the latest verified epoch
+    pub fn get_validator_set(&self) -> impl Future<Output = Result<TrustedValidatorSet>> {
+        let state = self.get_state();
+        async move { Ok(TrustedValidatorSet::from(&state.await?.trusted_epoch)) }
+    }
+
+    /// Returns how far state synchronizer synced, without waiting for any sync to complete: it's
+    /// answered from the in-memory state, without any network round trip.
+    pub fn get_sync_state(&self) -> impl Future<Output = Result<SyncProgress>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::GetSyncProgress(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let progress = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(progress)
+        }
+    }
+
     /// Returns information about StateSynchronizer internal state
     pub fn get_state(&self) -> impl Future<Output = Result<SynchronizerState>> {
         let mut sender = self.coordinator_sender.clone();
         let (cb_sender, cb_receiver) = oneshot::channel();
         async move {
-            sender.send(CoordinatorMessage::GetState(cb_sender)).await?;
-            let info = cb_receiver.await?;
+            sender
+                .send(CoordinatorMessage::GetState(cb_sender))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let info = cb_receiver.await.map_err(coordinator_unavailable)?;
             Ok(info)
         }
     }
+
+    /// Feeds `response` to StateSynchronizer as if it was received from `peer`: it goes through
+    /// the same verification and commit flow as the chunk responses received from the network, so
+    /// it's only applied if `peer` is a connected upstream peer.
+    /// Returns the StateSynchronizer internal state once the response is processed.
+    #[cfg(any(test, feature = "fuzzing"))]
+    pub fn inject_chunk_response(
+        &self,
+        peer: PeerNetworkId,
+        response: GetChunkResponse,
+    ) -> impl Future<Output = Result<SynchronizerState>> {
+        let mut sender = self.coordinator_sender.clone();
+        let (cb_sender, cb_receiver) = oneshot::channel();
+        async move {
+            sender
+                .send(CoordinatorMessage::InjectChunkResponse(
+                    peer,
+                    Box::new(response),
+                    cb_sender,
+                ))
+                .await
+                .map_err(coordinator_unavailable)?;
+            let info = cb_receiver.await.map_err(coordinator_unavailable)?;
+            Ok(info)
+        }
+    }
+}
+
+/// Tracks a sync request submitted via `StateSyncClient::sync_to_cancellable`. The request is
+/// cancelled if the handle is dropped before the request completes, including while waiting for
+/// its result.
+pub struct SyncHandle {
+    coordinator_sender: mpsc::UnboundedSender<CoordinatorMessage>,
+    generation: u64,
+    callback_rcv: oneshot::Receiver<Result<(), StateSyncError>>,
+    // whether the result was received, or the request cancelled already
+    completed: bool,
+}
+
+impl SyncHandle {
+    /// Returns the id of the sync request, i.e. its generation: the ids of the sync requests of a
+    /// state synchronizer increase monotonically.
+    pub fn request_id(&self) -> u64 {
+        self.generation
+    }
+
+    /// Cancels the sync request, which fails with `StateSyncError::Cancelled`. Cancelling a
+    /// request that completed already, or was replaced by a newer one, is a no-op.
+    pub fn cancel(mut self) {
+        self.send_cancel();
+    }
+
+    /// Waits for the sync request to complete.
+    pub async fn result(mut self) -> Result<(), StateSyncError> {
+        let result = (&mut self.callback_rcv).await;
+        self.completed = true;
+        result.map_err(coordinator_unavailable)?
+    }
+
+    fn send_cancel(&mut self) {
+        if self.completed {
+            return;
+        }
+        self.completed = true;
+        // the result is only pending if the request is still active
+        if let Ok(None) = self.callback_rcv.try_recv() {
+            // the coordinator is gone if the send fails, along with the request
+            let _ = self
+                .coordinator_sender
+                .unbounded_send(CoordinatorMessage::CancelSync(self.generation));
+        }
+    }
+}
+
+impl Drop for SyncHandle {
+    fn drop(&mut self) {
+        self.send_cancel();
+    }
+}
+
+/// Tracks a commit submitted via `StateSyncClient::commit_async`.
+pub struct CommitHandle {
+    callback_rcv: oneshot::Receiver<Result<CommitResponse>>,
+}
+
+impl CommitHandle {
+    /// Returns the result of the commit if state synchronizer has processed it, or `None` if
+    /// the commit is still pending. The result is returned only once.
+    pub fn try_result(&mut self) -> Option<Result<()>> {
+        match self.callback_rcv.try_recv() {
+            Ok(None) => None,
+            Ok(Some(resp)) => Some(commit_result(Ok(resp))),
+            Err(e) => Some(commit_result(Err(e))),
+        }
+    }
+
+    /// Waits for state synchronizer to process the commit.
+    /// Unlike `StateSyncClient::commit`, there is no timeout: callers that need one can wrap the
+    /// returned future.
+    pub async fn result(self) -> Result<()> {
+        commit_result(self.callback_rcv.await)
+    }
+}
+
+// Waits until state synchronizer is caught up with the waypoint specified in the local config
+async fn wait_until_initialized(
+    mut sender: mpsc::UnboundedSender<CoordinatorMessage>,
+) -> Result<(), StateSyncError> {
+    let (cb_sender, cb_receiver) = oneshot::channel();
+    sender
+        .send(CoordinatorMessage::WaitInitialize(cb_sender))
+        .await
+        .map_err(coordinator_unavailable)?;
+    cb_receiver.await.map_err(coordinator_unavailable)?
+}
+
+/// Wrapper of a `StateSyncClient` whose methods block the calling thread until they complete,
+/// for consumers with a synchronous control loop. The futures are driven on the calling thread,
+/// in the context of the given runtime (e.g. for the commit timeout).
+/// The methods must not be called from the threads of the runtime of state synchronizer: they'd
+/// block the coordinator they're waiting for. Such calls fail instead of blocking.
+pub struct StateSyncClientBlocking {
+    client: Arc<StateSyncClient>,
+    handle: Handle,
+}
+
+impl StateSyncClientBlocking {
+    pub fn new(client: Arc<StateSyncClient>, handle: Handle) -> Self {
+        Self { client, handle }
+    }
+
+    /// Blocking version of `StateSyncClient::sync_to`.
+    pub fn sync_to(&self, target: LedgerInfoWithSignatures) -> Result<(), StateSyncError> {
+        Self::ensure_blocking_allowed()?;
+        let sync = self.client.sync_to(target);
+        self.block_on(sync)
+    }
+
+    /// Blocking version of `StateSyncClient::commit`.
+    pub fn commit(
+        &self,
+        // *successfully* committed transactions
+        committed_txns: Vec<Transaction>,
+        reconfig_events: Vec<ContractEvent>,
+    ) -> Result<()> {
+        Self::ensure_blocking_allowed()?;
+        let commit = self.client.commit(committed_txns, reconfig_events);
+        self.block_on(commit)
+    }
+
+    /// Blocking version of `StateSynchronizer::wait_until_initialized`.
+    pub fn wait_until_initialized(&self) -> Result<()> {
+        Self::ensure_blocking_allowed()?;
+        let sender = self.client.coordinator_sender.clone();
+        self.block_on(async move { wait_until_initialized(sender).await.map_err(Into::into) })
+    }
+
+    // Fails the calls from the threads of the runtime of state synchronizer, before anything is
+    // submitted to the coordinator
+    fn ensure_blocking_allowed() -> Result<(), StateSyncError> {
+        if IS_STATE_SYNC_THREAD.with(Cell::get) {
+            return Err(StateSyncError::BlockingCallFromRuntime);
+        }
+        Ok(())
+    }
+
+    fn block_on<T, F: Future<Output = T>>(&self, future: F) -> T {
+        self.handle.enter(|| block_on(future))
+    }
+}
+
+fn commit_result(resp: Result<Result<CommitResponse>, oneshot::Canceled>) -> Result<()> {
+    let CommitResponse { result } = resp.map_err(coordinator_unavailable)??;
+    result
+        .map(|_ack| ())
+        .context("[state sync client] commit failed")
+}
+
+// Reports a failure to reach the coordinator, e.g. once it's shut down
+fn coordinator_unavailable(e: impl std::fmt::Display) -> StateSyncError {
+    StateSyncError::CoordinatorUnavailable(e.to_string())
 }
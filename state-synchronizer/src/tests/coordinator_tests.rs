@@ -0,0 +1,6087 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    apply_throughput::ApplyThroughput,
+    chunk_request::{GetChunkRequest, TargetType},
+    chunk_response::{GetChunkResponse, ResponseLedgerInfo},
+    chunk_source::FileChunkSource,
+    coordinator::{
+        CoordinatorMessage, SyncCoordinator, SyncCoordinatorDeps, SyncRequest, VersionSyncRequest,
+    },
+    counters,
+    error::StateSyncError,
+    network::{StateSynchronizerMsg, StateSynchronizerSender},
+    signature_verifier::{CpuSignatureVerifier, SignatureVerifier},
+    sync_rate_stats::SyncRateStats,
+    tests::{
+        helpers::{MockExecutorProxy, SynchronizerEnvHelper},
+        mock_storage::MockStorage,
+    },
+    time_provider::{MockTimeProvider, SystemTimeProvider, TimeProvider},
+    upstream_resolver::StaticUpstreamResolver,
+    BandwidthStats, CommitOutcome, EpochChangeListener, EpochChangeNotification, EpochTransition,
+    InitProgress, PeerHealth, ReconfigNotification, StallReason, StateSyncClient, SyncHealth,
+    SyncProgress, SynchronizerState, TrustedValidator, TrustedValidatorSet,
+};
+use channel::{diem_channel, message_queues::QueueStyle};
+use diem_config::{
+    config::{
+        AdvertisementPolicy, NodeConfig, PeerNetworkId, RoleType, StateSyncConfig, WaypointConfig,
+    },
+    network_id::{NetworkId, NodeNetworkId},
+};
+use diem_crypto::HashValue;
+use diem_infallible::{Mutex, RwLock};
+use diem_mempool::{CommitError, CommitNotification, CommitResponse};
+use diem_types::{
+    account_config::xus_tag,
+    block_info::BlockInfo,
+    contract_event::ContractEvent,
+    epoch_state::EpochState,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    on_chain_config::new_epoch_event_key,
+    proof::{TransactionAccumulatorRangeProof, TransactionListProof},
+    transaction::TransactionListWithProof,
+    validator_verifier::{random_validator_verifier, ValidatorVerifier},
+    waypoint::Waypoint,
+    PeerId,
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    FutureExt, StreamExt,
+};
+use netcore::transport::ConnectionOrigin;
+use network::{
+    peer_manager::{ConnectionRequestSender, PeerManagerRequest, PeerManagerRequestSender},
+    protocols::network::NewNetworkSender,
+    ProtocolId,
+};
+use proptest::{collection, prelude::*};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    num::NonZeroUsize,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+type NetworkRequestsReceiver = diem_channel::Receiver<(PeerId, ProtocolId), PeerManagerRequest>;
+
+// Builds a validator coordinator on top of a mock storage, along with the receiving end of its
+// network sender. The coordinator isn't driven by an event loop: tests feed it messages directly.
+struct CoordinatorBuilder {
+    storage: Arc<RwLock<MockStorage>>,
+    config: StateSyncConfig,
+    waypoints: Vec<Waypoint>,
+    signature_verifier: Box<dyn SignatureVerifier>,
+    time_provider: Arc<dyn TimeProvider>,
+    epoch_change_sender: Option<mpsc::Sender<EpochChangeNotification>>,
+    mempool_sender: Option<mpsc::Sender<CommitNotification>>,
+}
+
+impl CoordinatorBuilder {
+    fn new(storage: MockStorage, config: StateSyncConfig) -> Self {
+        Self::with_shared_storage(Arc::new(RwLock::new(storage)), config)
+    }
+
+    // Builds the coordinator on top of a storage the test keeps a handle on, e.g. to modify the
+    // storage behind the back of the coordinator.
+    fn with_shared_storage(storage: Arc<RwLock<MockStorage>>, config: StateSyncConfig) -> Self {
+        Self {
+            storage,
+            config,
+            waypoints: vec![Waypoint::default()],
+            signature_verifier: Box::new(CpuSignatureVerifier),
+            time_provider: Arc::new(SystemTimeProvider),
+            epoch_change_sender: None,
+            mempool_sender: None,
+        }
+    }
+
+    fn waypoint(self, waypoint: Waypoint) -> Self {
+        self.waypoints(vec![waypoint])
+    }
+
+    fn waypoints(mut self, waypoints: Vec<Waypoint>) -> Self {
+        self.waypoints = waypoints;
+        self
+    }
+
+    fn signature_verifier(mut self, signature_verifier: Box<dyn SignatureVerifier>) -> Self {
+        self.signature_verifier = signature_verifier;
+        self
+    }
+
+    // Reads the time from `time_provider`, e.g. a mock clock the test advances instead of waiting
+    // for timeouts to expire.
+    fn time_provider(mut self, time_provider: Arc<dyn TimeProvider>) -> Self {
+        self.time_provider = time_provider;
+        self
+    }
+
+    fn epoch_change_sender(
+        mut self,
+        epoch_change_sender: mpsc::Sender<EpochChangeNotification>,
+    ) -> Self {
+        self.epoch_change_sender = Some(epoch_change_sender);
+        self
+    }
+
+    // Notifies the commits to a mempool the test plays, instead of a mempool that is gone.
+    fn mempool_sender(mut self, mempool_sender: mpsc::Sender<CommitNotification>) -> Self {
+        self.mempool_sender = Some(mempool_sender);
+        self
+    }
+
+    fn build(self) -> (SyncCoordinator<MockExecutorProxy>, NetworkRequestsReceiver) {
+        let (_coordinator_sender, coordinator_receiver) = mpsc::unbounded();
+        let mempool_sender = self
+            .mempool_sender
+            .unwrap_or_else(|| mpsc::channel(1_024).0);
+        let node_config = NodeConfig::default_for_validator();
+        let initial_state = self.storage.read().get_local_storage_state();
+
+        // mock network senders
+        let (network_reqs_tx, network_reqs_rx) =
+            diem_channel::new(QueueStyle::FIFO, NonZeroUsize::new(8).unwrap(), None);
+        let (connection_reqs_tx, _) =
+            diem_channel::new(QueueStyle::FIFO, NonZeroUsize::new(8).unwrap(), None);
+        let network_sender = StateSynchronizerSender::new(
+            PeerManagerRequestSender::new(network_reqs_tx),
+            ConnectionRequestSender::new(connection_reqs_tx),
+        );
+        let network_senders = vec![
+            (validator_network_id(), network_sender.clone()),
+            (public_network_id(), network_sender),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        let coordinator = SyncCoordinator::new(
+            coordinator_receiver,
+            mempool_sender,
+            network_senders,
+            RoleType::Validator,
+            self.waypoints,
+            self.config,
+            Box::new(StaticUpstreamResolver::new(node_config.upstream)),
+            MockExecutorProxy::new(SynchronizerEnvHelper::default_handler(), self.storage),
+            initial_state,
+            SyncCoordinatorDeps {
+                signature_verifier: self.signature_verifier,
+                epoch_change_sender: self.epoch_change_sender,
+                time_provider: self.time_provider,
+                ..SyncCoordinatorDeps::default()
+            },
+        );
+        (coordinator, network_reqs_rx)
+    }
+}
+
+fn create_coordinator(
+    storage: MockStorage,
+    state_sync_config: StateSyncConfig,
+) -> (SyncCoordinator<MockExecutorProxy>, NetworkRequestsReceiver) {
+    CoordinatorBuilder::new(storage, state_sync_config).build()
+}
+
+async fn get_state(coordinator: &mut SyncCoordinator<MockExecutorProxy>) -> SynchronizerState {
+    let (state_sender, mut state_rcv) = oneshot::channel();
+    coordinator
+        .process_client_message(CoordinatorMessage::GetState(state_sender))
+        .await;
+    state_rcv.try_recv().unwrap().unwrap()
+}
+
+async fn health_check(coordinator: &mut SyncCoordinator<MockExecutorProxy>) -> SyncHealth {
+    let (sender, mut receiver) = oneshot::channel();
+    coordinator
+        .process_client_message(CoordinatorMessage::HealthCheck(sender))
+        .await;
+    receiver.try_recv().unwrap().unwrap()
+}
+
+async fn sync_progress(coordinator: &mut SyncCoordinator<MockExecutorProxy>) -> SyncProgress {
+    let (sender, mut receiver) = oneshot::channel();
+    coordinator
+        .process_client_message(CoordinatorMessage::GetSyncProgress(sender))
+        .await;
+    receiver.try_recv().unwrap().unwrap()
+}
+
+async fn last_stall_reason(
+    coordinator: &mut SyncCoordinator<MockExecutorProxy>,
+) -> Option<StallReason> {
+    let (sender, mut receiver) = oneshot::channel();
+    coordinator
+        .process_client_message(CoordinatorMessage::GetLastStallReason(sender))
+        .await;
+    receiver.try_recv().unwrap().unwrap()
+}
+
+async fn active_upstream(
+    coordinator: &mut SyncCoordinator<MockExecutorProxy>,
+) -> Option<PeerNetworkId> {
+    let (sender, mut receiver) = oneshot::channel();
+    coordinator
+        .process_client_message(CoordinatorMessage::GetActiveUpstream(sender))
+        .await;
+    receiver.try_recv().unwrap().unwrap()
+}
+
+async fn active_target(
+    coordinator: &mut SyncCoordinator<MockExecutorProxy>,
+) -> Option<LedgerInfoWithSignatures> {
+    let (sender, mut receiver) = oneshot::channel();
+    coordinator
+        .process_client_message(CoordinatorMessage::GetActiveTarget(sender))
+        .await;
+    receiver.try_recv().unwrap().unwrap()
+}
+
+// Returns the next chunk response sent by the coordinator.
+fn next_sent_chunk_response(network_reqs_rx: &mut NetworkRequestsReceiver) -> GetChunkResponse {
+    match network_reqs_rx.next().now_or_never() {
+        Some(Some(PeerManagerRequest::SendMessage(_, message))) => {
+            match lcs::from_bytes(&message.mdata).unwrap() {
+                StateSynchronizerMsg::GetChunkResponse(response) => *response,
+                _ => panic!("expected a chunk response"),
+            }
+        }
+        _ => panic!("expected a sent message"),
+    }
+}
+
+fn next_sent_chunk_request(network_reqs_rx: &mut NetworkRequestsReceiver) -> GetChunkRequest {
+    match network_reqs_rx.next().now_or_never() {
+        Some(Some(PeerManagerRequest::SendMessage(_, message))) => {
+            match lcs::from_bytes(&message.mdata).unwrap() {
+                StateSynchronizerMsg::GetChunkRequest(request) => *request,
+                _ => panic!("expected a chunk request"),
+            }
+        }
+        _ => panic!("expected a sent message"),
+    }
+}
+
+// Returns the number of messages sent by the coordinator since the last call.
+fn num_sent_messages(network_reqs_rx: &mut NetworkRequestsReceiver) -> usize {
+    let mut num_messages = 0;
+    while let Some(Some(_)) = network_reqs_rx.next().now_or_never() {
+        num_messages += 1;
+    }
+    num_messages
+}
+
+fn validator_network_id() -> NodeNetworkId {
+    NodeNetworkId::new(NetworkId::Validator, 0)
+}
+
+fn public_network_id() -> NodeNetworkId {
+    NodeNetworkId::new(NetworkId::Public, 0)
+}
+
+// Builds a chunk response carrying at most `limit` transactions of `storage` after
+// `known_version`, up to the version of `target_li`.
+fn create_chunk_response(
+    storage: &MockStorage,
+    known_version: u64,
+    limit: u64,
+    target_li: LedgerInfoWithSignatures,
+) -> StateSynchronizerMsg {
+    let txns = storage.get_chunk(known_version + 1, limit, target_li.ledger_info().version());
+    let txn_list_with_proof = TransactionListWithProof::new(
+        txns,
+        None,
+        Some(known_version + 1),
+        MockStorage::chunk_proof(known_version + 1),
+    );
+    StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+        ResponseLedgerInfo::VerifiableLedgerInfo(target_li),
+        txn_list_with_proof,
+    )))
+}
+
+fn move_to_next_epoch(storage: &mut MockStorage) {
+    let (signers, verifier) = random_validator_verifier(1, None, true);
+    storage.move_to_next_epoch(signers[0].clone(), (&verifier).into());
+}
+
+#[test]
+fn test_epoch_catch_up_progress() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+
+    // The upstream storage moves through several epochs
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    for _ in 0..4 {
+        upstream.commit_new_txns(10);
+        move_to_next_epoch(&mut upstream);
+    }
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    let target_epoch = target_li.ledger_info().epoch();
+    assert_eq!(target_epoch, 5);
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    assert_eq!(coordinator.epoch_catch_up_progress(), None);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            coordinator.epoch_catch_up_progress(),
+            Some((1, target_epoch))
+        );
+
+        // Every epoch ending LI moves the local node one epoch closer to the target
+        let mut known_version = 0;
+        for epoch in 1..target_epoch {
+            let epoch_ending_li = upstream.get_epoch_changes(epoch).unwrap();
+            let end_version = epoch_ending_li.ledger_info().version();
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(
+                        &upstream,
+                        known_version,
+                        end_version - known_version,
+                        epoch_ending_li,
+                    ),
+                )
+                .await;
+            known_version = end_version;
+
+            let expected_progress = if epoch + 1 < target_epoch {
+                Some((epoch + 1, target_epoch))
+            } else {
+                None
+            };
+            assert_eq!(coordinator.epoch_catch_up_progress(), expected_progress);
+        }
+
+        coordinator
+            .process_one_message(
+                peer,
+                create_chunk_response(
+                    &upstream,
+                    known_version,
+                    target_li.ledger_info().version() - known_version,
+                    target_li,
+                ),
+            )
+            .await;
+        assert_eq!(coordinator.epoch_catch_up_progress(), None);
+    });
+}
+
+// Syncs a fresh node to the upstream's highest LI in chunks of `chunk_size`. Returns the
+// number of LI signature verifications handed to the crypto layer.
+fn sync_in_chunks(
+    upstream: &MockStorage,
+    local: MockStorage,
+    chunk_size: u64,
+    batch_verify_signatures: bool,
+) -> u64 {
+    let config = StateSyncConfig {
+        batch_verify_signatures,
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(local, config);
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let target_li = upstream.highest_local_li();
+    let target_version = target_li.ledger_info().version();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // every chunk carries the same target LI
+        for known_version in (0..target_version).step_by(chunk_size as usize) {
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(upstream, known_version, chunk_size, target_li.clone()),
+                )
+                .await;
+        }
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(
+            local_state.highest_local_li.ledger_info().version(),
+            target_version
+        );
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+        coordinator.num_li_crypto_calls()
+    })
+}
+
+#[test]
+fn test_batch_verify_signatures() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(100);
+
+    // 20 same-epoch chunks
+    let chunk_size = 5;
+    let per_chunk_calls = sync_in_chunks(
+        &upstream,
+        MockStorage::new(genesis_li.clone(), signers[0].clone()),
+        chunk_size,
+        false,
+    );
+    let batched_calls = sync_in_chunks(
+        &upstream,
+        MockStorage::new(genesis_li, signers[0].clone()),
+        chunk_size,
+        true,
+    );
+    assert_eq!(per_chunk_calls, 100 / chunk_size);
+    assert_eq!(batched_calls, 1);
+}
+
+#[test]
+fn test_quiesce() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let first_li = upstream.highest_local_li();
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // sync half of the transactions committed by `first_li`
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, first_li.clone()),
+            )
+            .await;
+        let (quiesce_sender, mut quiesce_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Quiesce(quiesce_sender))
+            .await;
+        assert!(quiesce_rcv.try_recv().unwrap().is_none());
+        assert!(num_sent_messages(&mut network_reqs_rx) > 0);
+
+        // committing the synced transactions completes the quiesce
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 10, 10, first_li),
+            )
+            .await;
+        assert!(quiesce_rcv.try_recv().unwrap().unwrap().is_ok());
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(
+            local_state.highest_version_in_local_storage(),
+            local_state.highest_local_li.ledger_info().version()
+        );
+
+        // no new requests are issued, even though the sync request isn't fulfilled yet
+        num_sent_messages(&mut network_reqs_rx);
+        coordinator.process_new_peer(
+            PeerNetworkId(validator_network_id(), PeerId::random()),
+            ConnectionOrigin::Outbound,
+        );
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // and new sync requests are rejected
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_err());
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+    });
+}
+
+#[test]
+fn test_accumulator_continuity() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the first chunk extends the local accumulator
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+        let peer_score = coordinator.peer_score(&peer).unwrap();
+
+        // the second chunk comes with a proof relative to an accumulator that doesn't chain with
+        // the one of the first chunk
+        let left_siblings = vec![HashValue::random(); 11u64.count_ones() as usize];
+        let txn_list_with_proof = TransactionListWithProof::new(
+            upstream.get_chunk(11, 10, 20),
+            None,
+            Some(11),
+            TransactionListProof::new(
+                TransactionAccumulatorRangeProof::new(left_siblings, vec![]),
+                vec![],
+            ),
+        );
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                    ResponseLedgerInfo::VerifiableLedgerInfo(target_li),
+                    txn_list_with_proof,
+                ))),
+            )
+            .await;
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+        assert!(coordinator.peer_score(&peer).unwrap() < peer_score);
+    });
+}
+
+#[test]
+fn test_highest_version_advertisement_policy() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    let target_li = storage.highest_local_li();
+    storage.commit_new_txns(10);
+    let highest_li = storage.highest_local_li();
+
+    // validators only advertise their highest version to each other
+    let config = StateSyncConfig {
+        highest_version_advertisement: AdvertisementPolicy {
+            validator: Some(vec![NetworkId::Validator]),
+            full_node: None,
+        },
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(storage, config);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        for (network_id, expected_highest_li) in vec![
+            (validator_network_id(), Some(highest_li)),
+            (public_network_id(), None),
+        ] {
+            let request = GetChunkRequest::new(
+                0,
+                1,
+                10,
+                TargetType::HighestAvailable {
+                    target_li: Some(target_li.clone()),
+                    timeout_ms: 0,
+                },
+            );
+            coordinator
+                .process_one_message(
+                    PeerNetworkId(network_id, PeerId::random()),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+                )
+                .await;
+            match next_sent_chunk_response(&mut network_reqs_rx).response_li {
+                ResponseLedgerInfo::ProgressiveLedgerInfo { highest_li, .. } => {
+                    assert_eq!(highest_li, expected_highest_li)
+                }
+                _ => panic!("expected a progressive ledger info"),
+            }
+        }
+    });
+}
+
+#[test]
+fn test_sync_lifecycle() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            idle_at_tip_debounce_ms: 0,
+            ..StateSyncConfig::default()
+        },
+    );
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        assert!(!health_check(&mut coordinator).await.is_idle_at_tip);
+
+        // the progress check of a new peer finds the node at the tip
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        assert!(health_check(&mut coordinator).await.is_idle_at_tip);
+
+        // a sync request for a higher LI moves the node back to syncing
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert!(!health_check(&mut coordinator).await.is_idle_at_tip);
+
+        // committing up to the target of the sync request completes it
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert!(!health_check(&mut coordinator).await.is_idle_at_tip);
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 10, target_li))
+            .await;
+        let health = health_check(&mut coordinator).await;
+        assert!(health.is_idle_at_tip);
+        assert_eq!(health.committed_version, 20);
+    });
+}
+
+#[test]
+fn test_ahead_of_peers() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(20);
+    let highest_li = storage.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) =
+        create_coordinator(storage, StateSyncConfig::default());
+    let peers: Vec<_> = (0..2)
+        .map(|_| PeerNetworkId(validator_network_id(), PeerId::random()))
+        .collect();
+    for peer in &peers {
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+    // nothing is known about the versions of the peers yet
+    assert!(!coordinator.is_ahead_of_peers());
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the peers advertise their versions by requesting chunks
+        for (peer, known_version) in peers.iter().zip(vec![5, 10]) {
+            let request = GetChunkRequest::new(
+                known_version,
+                1,
+                10,
+                TargetType::TargetLedgerInfo(highest_li.clone()),
+            );
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+                )
+                .await;
+        }
+        // the next progress check detects that local storage is ahead of all peers
+        coordinator.process_new_peer(peers[0].clone(), ConnectionOrigin::Outbound);
+        assert!(coordinator.is_ahead_of_peers());
+
+        // a peer advertising a higher version clears the condition
+        let request = GetChunkRequest::new(30, 1, 10, TargetType::TargetLedgerInfo(highest_li));
+        coordinator
+            .process_one_message(
+                peers[1].clone(),
+                StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+            )
+            .await;
+        coordinator.process_new_peer(peers[0].clone(), ConnectionOrigin::Outbound);
+        assert!(!coordinator.is_ahead_of_peers());
+    });
+}
+
+#[test]
+fn test_epoch_proof_unavailable() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+
+    // the target is two epochs ahead of the local node
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    for _ in 0..2 {
+        upstream.commit_new_txns(10);
+        move_to_next_epoch(&mut upstream);
+    }
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    assert_eq!(target_li.ledger_info().epoch(), 3);
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            sync_request_timeout_ms: 0,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // only the epoch change proof of the first epoch is available
+        let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+        let end_version = epoch_ending_li.ledger_info().version();
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, end_version, epoch_ending_li),
+            )
+            .await;
+        assert_eq!(get_state(&mut coordinator).await.trusted_epoch.epoch, 2);
+
+        // a chunk carrying the target LI can't be verified without the second proof
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, end_version, 10, target_li),
+            )
+            .await;
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.highest_version_in_local_storage(), end_version);
+
+        // the timed out sync request fails with the missing proof details
+        coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+        let error = callback_rcv.try_recv().unwrap().unwrap().unwrap_err();
+        assert_eq!(
+            error,
+            StateSyncError::UnknownEpoch {
+                local_epoch: 2,
+                target_epoch: 3,
+            }
+        );
+    });
+}
+
+#[test]
+fn test_chunk_response_downsizing() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(20);
+    let target_li = storage.highest_local_li();
+
+    // only chunks of up to 5 transactions fit into a response
+    let max_response = match create_chunk_response(&storage, 0, 5, target_li.clone()) {
+        StateSynchronizerMsg::GetChunkResponse(response) => response,
+        _ => panic!("expected a chunk response"),
+    };
+    let config = StateSyncConfig {
+        max_chunk_response_bytes: lcs::to_bytes(&max_response).unwrap().len(),
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(storage, config);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // a request for 20 transactions is halved until the response fits
+        let request = GetChunkRequest::new(0, 1, 20, TargetType::TargetLedgerInfo(target_li));
+        coordinator
+            .process_one_message(
+                PeerNetworkId(validator_network_id(), PeerId::random()),
+                StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+            )
+            .await;
+        let response = next_sent_chunk_response(&mut network_reqs_rx);
+        assert_eq!(response.txn_list_with_proof.transactions.len(), 5);
+        assert_eq!(response, *max_response);
+    });
+}
+
+#[test]
+fn test_chunk_response_byte_limit() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    let (txns, _) = storage.commit_new_txns_with_metadata(20, 10_000);
+    let target_li = storage.highest_local_li();
+    let txn_bytes = lcs::to_bytes(&txns[0]).unwrap().len();
+    assert!(txn_bytes > 10_000);
+
+    let max_chunk_bytes = 5 * txn_bytes + txn_bytes / 2;
+    let config = StateSyncConfig {
+        max_chunk_bytes,
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(storage, config);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // (requested byte limit, expected number of transactions): the chunk stops at the last
+        // transaction within the min of both limits, but always has one
+        for &(requested_bytes, num_txns) in &[
+            (0, 5),
+            (3 * txn_bytes as u64, 3),
+            (100 * txn_bytes as u64, 5),
+            (1, 1),
+        ] {
+            let request =
+                GetChunkRequest::new(0, 1, 20, TargetType::TargetLedgerInfo(target_li.clone()))
+                    .with_max_chunk_bytes(requested_bytes);
+            coordinator
+                .process_one_message(
+                    PeerNetworkId(validator_network_id(), PeerId::random()),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+                )
+                .await;
+            let response = next_sent_chunk_response(&mut network_reqs_rx);
+            let transactions = response.txn_list_with_proof.transactions;
+            assert_eq!(transactions.len(), num_txns);
+            assert_eq!(transactions, txns[..num_txns].to_vec());
+            let chunk_bytes: usize = transactions
+                .iter()
+                .map(|txn| lcs::to_bytes(txn).unwrap().len())
+                .sum();
+            assert!(chunk_bytes <= max_chunk_bytes);
+        }
+    });
+}
+
+#[test]
+fn test_trusted_validator_set() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+    let next_epoch_state = epoch_ending_li
+        .ledger_info()
+        .next_epoch_state()
+        .unwrap()
+        .clone();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let validator_set =
+            TrustedValidatorSet::from(&get_state(&mut coordinator).await.trusted_epoch);
+        assert_eq!(validator_set.epoch, 1);
+        assert_eq!(
+            validator_set.validators,
+            vec![TrustedValidator {
+                address: signers[0].author(),
+                voting_power: 1000,
+            }]
+        );
+
+        // the validator set follows the trusted epoch across an epoch transition
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: epoch_ending_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                peer,
+                create_chunk_response(&upstream, 0, 10, epoch_ending_li),
+            )
+            .await;
+        let validator_set =
+            TrustedValidatorSet::from(&get_state(&mut coordinator).await.trusted_epoch);
+        assert_eq!(validator_set.epoch, 2);
+        assert_eq!(validator_set, TrustedValidatorSet::from(&next_epoch_state));
+        assert_ne!(validator_set.validators[0].address, signers[0].author());
+
+        // and is serializable for external consumers
+        let bytes = lcs::to_bytes(&validator_set).unwrap();
+        assert_eq!(
+            lcs::from_bytes::<TrustedValidatorSet>(&bytes).unwrap(),
+            validator_set
+        );
+    });
+}
+
+#[test]
+fn test_chunks_during_shutdown() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    for &drain_timeout_ms in &[0, 60_000] {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                shutdown_drain_timeout_ms: drain_timeout_ms,
+                // keep the chunk request in flight for the whole test
+                tick_interval_ms: 60_000,
+                ..StateSyncConfig::default()
+            },
+        );
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let upstream = &upstream;
+        let target_li = target_li.clone();
+        rt.block_on(async move {
+            // a chunk request is in flight when the shutdown is accepted
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            let (shutdown_sender, mut shutdown_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Shutdown(shutdown_sender))
+                .await;
+
+            if drain_timeout_ms > 0 {
+                // the response to the in-flight request is processed before stopping
+                assert!(!coordinator.check_shutdown());
+                coordinator
+                    .process_one_message(
+                        peer.clone(),
+                        create_chunk_response(upstream, 0, 10, target_li.clone()),
+                    )
+                    .await;
+                let local_state = get_state(&mut coordinator).await;
+                assert_eq!(local_state.highest_version_in_local_storage(), 10);
+            }
+
+            // nothing is left to drain, so any further response is dropped
+            assert!(coordinator.check_shutdown());
+            assert!(shutdown_rcv.try_recv().unwrap().unwrap().is_ok());
+            let synced_version = get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage();
+            coordinator
+                .process_one_message(
+                    peer,
+                    create_chunk_response(upstream, synced_version, 10, target_li),
+                )
+                .await;
+            let local_state = get_state(&mut coordinator).await;
+            assert_eq!(
+                local_state.highest_version_in_local_storage(),
+                synced_version
+            );
+        });
+    }
+}
+
+#[test]
+fn test_commit_durability_check() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    for &verify_commit_durability in &[false, true] {
+        // the local storage silently drops every write
+        let mut storage = MockStorage::new(genesis_li.clone(), signers[0].clone());
+        storage.set_drop_writes(true);
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            storage,
+            StateSyncConfig {
+                verify_commit_durability,
+                ..StateSyncConfig::default()
+            },
+        );
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let upstream = &upstream;
+        let target_li = target_li.clone();
+        rt.block_on(async move {
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+
+            let num_failures = counters::COMMIT_DURABILITY_CHECK_FAIL_COUNT.get();
+            coordinator
+                .process_one_message(peer, create_chunk_response(upstream, 0, 10, target_li))
+                .await;
+            // the dropped write is only caught if the storage is read back
+            let expected_failures = num_failures + verify_commit_durability as u64;
+            assert_eq!(
+                counters::COMMIT_DURABILITY_CHECK_FAIL_COUNT.get(),
+                expected_failures
+            );
+        });
+    }
+}
+
+#[test]
+fn test_subscribe_reconfigs() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    // both commits are of the same version
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        storage,
+        StateSyncConfig {
+            ignore_duplicate_commits: false,
+            ..StateSyncConfig::default()
+        },
+    );
+    let reconfig_event = ContractEvent::new(new_epoch_event_key(), 0, xus_tag(), vec![]);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (subscriber, mut receiver) = mpsc::unbounded();
+        let (dropped_subscriber, dropped_receiver) = mpsc::unbounded();
+        for subscriber in vec![subscriber, dropped_subscriber] {
+            coordinator
+                .process_client_message(CoordinatorMessage::SubscribeReconfigs(subscriber))
+                .await;
+        }
+        drop(dropped_receiver);
+
+        // commits without reconfig events aren't notified
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Commit(vec![], vec![], callback))
+            .await;
+        assert!(receiver.next().now_or_never().is_none());
+
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Commit(
+                vec![],
+                vec![reconfig_event.clone()],
+                callback,
+            ))
+            .await;
+        let committed_li = get_state(&mut coordinator).await.highest_local_li;
+        assert_eq!(
+            receiver.next().now_or_never(),
+            Some(Some(ReconfigNotification {
+                epoch: committed_li.ledger_info().epoch(),
+                version: committed_li.ledger_info().version(),
+                events: vec![reconfig_event],
+            }))
+        );
+    });
+}
+
+#[test]
+fn test_accept_stronger_targets() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(5);
+    let requested_li = upstream.highest_local_li();
+    upstream.commit_new_txns(5);
+    let stronger_li = upstream.highest_local_li();
+
+    for &accept_stronger_targets in &[false, true] {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                accept_stronger_targets,
+                ..StateSyncConfig::default()
+            },
+        );
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let upstream = &upstream;
+        let requested_li = requested_li.clone();
+        let stronger_li = stronger_li.clone();
+        rt.block_on(async move {
+            let (callback, mut callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: requested_li,
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+
+            // the peer responds with a chunk proven to a newer target than the requested one
+            coordinator
+                .process_one_message(peer, create_chunk_response(upstream, 0, 10, stronger_li))
+                .await;
+            let synced_version = get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage();
+            if accept_stronger_targets {
+                // the node advanced beyond the requested target, which completes the request
+                assert_eq!(synced_version, 10);
+                assert!(matches!(callback_rcv.try_recv(), Ok(Some(Ok(())))));
+            } else {
+                assert_eq!(synced_version, 0);
+                assert!(matches!(callback_rcv.try_recv(), Ok(None)));
+            }
+        });
+    }
+}
+
+#[test]
+fn test_chunk_commit_latency() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // other tests may commit chunks concurrently, so only a lower bound on the number of
+        // observations holds
+        let latency = counters::CHUNK_COMMIT_LATENCY.with_label_values(&["false"]);
+        let (num_observations, total_latency) =
+            (latency.get_sample_count(), latency.get_sample_sum());
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 10, target_li))
+            .await;
+        assert!(latency.get_sample_count() > num_observations);
+        let observed_latency = latency.get_sample_sum() - total_latency;
+        assert!(observed_latency > 0.0 && observed_latency < 60.0);
+    });
+}
+
+#[test]
+fn test_time_to_first_chunk() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        std::thread::sleep(Duration::from_millis(10));
+
+        // other tests may sync concurrently, so only lower bounds hold
+        let (num_observations, total_duration) = (
+            counters::TIME_TO_FIRST_CHUNK.get_sample_count(),
+            counters::TIME_TO_FIRST_CHUNK.get_sample_sum(),
+        );
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 5, target_li))
+            .await;
+        assert!(counters::TIME_TO_FIRST_CHUNK.get_sample_count() > num_observations);
+        assert!(counters::TIME_TO_FIRST_CHUNK.get_sample_sum() - total_duration >= 0.01);
+    });
+}
+
+#[test]
+fn test_disable_serving_during_bootstrap() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let waypoint_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li.clone(), signers[0].clone()),
+        StateSyncConfig::default(),
+    )
+    .waypoint(Waypoint::new_any(waypoint_li.ledger_info()))
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    num_sent_messages(&mut network_reqs_rx);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // chunk requests are ignored before the node is bootstrapped to its waypoint
+        let request = GetChunkRequest::new(0, 1, 10, TargetType::TargetLedgerInfo(genesis_li));
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+            )
+            .await;
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // bootstrap to the waypoint
+        let txn_list_with_proof = match create_chunk_response(&upstream, 0, 10, waypoint_li.clone())
+        {
+            StateSynchronizerMsg::GetChunkResponse(response) => response.txn_list_with_proof,
+            _ => panic!("expected a chunk response"),
+        };
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                    ResponseLedgerInfo::LedgerInfoForWaypoint {
+                        waypoint_li: waypoint_li.clone(),
+                        end_of_epoch_li: None,
+                    },
+                    txn_list_with_proof,
+                ))),
+            )
+            .await;
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+        num_sent_messages(&mut network_reqs_rx);
+
+        // chunk requests are served once the node is bootstrapped
+        let request = GetChunkRequest::new(5, 1, 10, TargetType::TargetLedgerInfo(waypoint_li));
+        coordinator
+            .process_one_message(
+                peer,
+                StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+            )
+            .await;
+        let response = next_sent_chunk_response(&mut network_reqs_rx);
+        assert_eq!(response.txn_list_with_proof.transactions.len(), 5);
+    });
+}
+
+#[test]
+fn test_epoch_skip_rejected() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+
+    // an epoch-ending LI that jumps two epochs ahead
+    let li = upstream.highest_local_li().ledger_info().clone();
+    let (_, next_verifier) = random_validator_verifier(1, None, true);
+    let epoch_skipping_li = LedgerInfo::new(
+        BlockInfo::new(
+            li.epoch(),
+            li.round(),
+            li.consensus_block_id(),
+            li.transaction_accumulator_hash(),
+            li.version(),
+            li.timestamp_usecs(),
+            Some(EpochState {
+                epoch: li.epoch() + 2,
+                verifier: next_verifier,
+            }),
+        ),
+        li.consensus_data_hash(),
+    );
+    let mut signatures = BTreeMap::new();
+    signatures.insert(signers[0].author(), signers[0].sign(&epoch_skipping_li));
+    let epoch_skipping_li = LedgerInfoWithSignatures::new(epoch_skipping_li, signatures);
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: epoch_skipping_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        let num_violations = counters::EPOCH_SKIP_VIOLATION_COUNT.get();
+        coordinator
+            .process_one_message(
+                peer,
+                create_chunk_response(&upstream, 0, 10, epoch_skipping_li),
+            )
+            .await;
+        assert_eq!(
+            counters::EPOCH_SKIP_VIOLATION_COUNT.get(),
+            num_violations + 1
+        );
+        let state = get_state(&mut coordinator).await;
+        assert_eq!(state.highest_version_in_local_storage(), 0);
+        assert_eq!(state.epoch(), 1);
+    });
+}
+
+#[test]
+fn test_trigger_sync_cycle() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            tick_interval_ms: 10,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert!(num_sent_messages(&mut network_reqs_rx) > 0);
+
+        // the chunk request times out, but no new request is issued until the next tick
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // unless a sync cycle is triggered
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert!(num_sent_messages(&mut network_reqs_rx) > 0);
+    });
+}
+
+#[test]
+fn test_chunk_request_priority() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let first_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // without a sync request, applying a chunk is followed by a background request
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, first_li),
+            )
+            .await;
+        let request = next_sent_chunk_request(&mut network_reqs_rx);
+        assert!(matches!(
+            request.target(),
+            TargetType::HighestAvailable { .. }
+        ));
+        num_sent_messages(&mut network_reqs_rx);
+
+        // a sync request from consensus takes precedence over background sync
+        let consensus_requests = counters::CHUNK_REQUESTS_PER_PRIORITY
+            .with_label_values(&[counters::CONSENSUS_PRIORITY_LABEL]);
+        let num_consensus_requests = consensus_requests.get();
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        let request = next_sent_chunk_request(&mut network_reqs_rx);
+        assert_eq!(request.target(), &TargetType::TargetLedgerInfo(target_li));
+        assert!(consensus_requests.get() > num_consensus_requests);
+    });
+}
+
+#[test]
+fn test_epoch_change_proof() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let genesis_epoch_state = genesis_li.ledger_info().next_epoch_state().unwrap().clone();
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    for _ in 0..3 {
+        storage.commit_new_txns(10);
+        move_to_next_epoch(&mut storage);
+    }
+
+    let (mut coordinator, _network_reqs_rx) =
+        create_coordinator(storage, StateSyncConfig::default());
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let trusted_epoch = get_state(&mut coordinator).await.trusted_epoch;
+        assert_eq!(trusted_epoch.epoch, 4);
+
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::GetEpochChangeProof(
+                genesis_epoch_state.epoch,
+                callback,
+            ))
+            .await;
+        let proof = callback_rcv.try_recv().unwrap().unwrap().unwrap();
+        assert_eq!(proof.ledger_info_with_sigs.len(), 3);
+
+        // a verifier trusting the genesis epoch advances to the trusted epoch of the node
+        let epoch_ending_li = proof.verify(&genesis_epoch_state).unwrap();
+        assert_eq!(
+            epoch_ending_li.ledger_info().next_epoch_state(),
+            Some(&trusted_epoch)
+        );
+
+        // there is no proof from the trusted epoch onwards
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::GetEpochChangeProof(4, callback))
+            .await;
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_err());
+    });
+}
+
+#[test]
+fn test_executor_hang_detection() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    // the local storage takes longer to write than the executor hang timeout
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.set_write_delay(Duration::from_millis(200));
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        storage,
+        StateSyncConfig {
+            executor_hang_timeout_ms: 20,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        let num_hangs = counters::EXECUTOR_HANG_COUNT.get();
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 10, target_li))
+            .await;
+        assert_eq!(counters::EXECUTOR_HANG_COUNT.get(), num_hangs + 1);
+        // the hung execution is reported, not cancelled
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+    });
+}
+
+#[test]
+fn test_serving_networks() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    let local_li = storage.highest_local_li();
+    let mut upstream = storage.clone();
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    // the validator syncs from the validator network, but only serves the public network
+    let config = StateSyncConfig {
+        serving_networks: Some(vec![NetworkId::Public]),
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(storage, config);
+    let validator_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let public_peer = PeerNetworkId(public_network_id(), PeerId::random());
+    coordinator.process_new_peer(validator_peer.clone(), ConnectionOrigin::Outbound);
+    coordinator.process_new_peer(public_peer.clone(), ConnectionOrigin::Inbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        match network_reqs_rx.next().now_or_never() {
+            Some(Some(PeerManagerRequest::SendMessage(peer_id, _))) => {
+                assert_eq!(peer_id, validator_peer.peer_id())
+            }
+            _ => panic!("expected a sent chunk request"),
+        }
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        for (peer, is_served) in vec![(validator_peer, false), (public_peer, true)] {
+            let request =
+                GetChunkRequest::new(0, 1, 10, TargetType::TargetLedgerInfo(local_li.clone()));
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+                )
+                .await;
+            match network_reqs_rx.next().now_or_never() {
+                Some(Some(PeerManagerRequest::SendMessage(peer_id, _))) => {
+                    assert!(is_served);
+                    assert_eq!(peer_id, peer.peer_id());
+                }
+                _ => assert!(!is_served),
+            }
+        }
+    });
+}
+
+#[test]
+fn test_signer_diversity() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    let mut target_lis = vec![];
+    for _ in 0..3 {
+        upstream.commit_new_txns(10);
+        target_lis.push(upstream.highest_local_li());
+    }
+
+    // all LIs are signed by the same validator
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            distinct_signers_window: 3,
+            min_distinct_signers: 2,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let num_alerts = counters::SIGNER_DIVERSITY_ALERT_COUNT.get();
+        for (i, target_li) in target_lis.into_iter().enumerate() {
+            let known_version = 10 * i as u64;
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(&upstream, known_version, 10, target_li),
+                )
+                .await;
+            // the alert only fires once the window is full
+            let expected_alerts = if i < 2 { num_alerts } else { num_alerts + 1 };
+            assert_eq!(
+                counters::SIGNER_DIVERSITY_ALERT_COUNT.get(),
+                expected_alerts
+            );
+        }
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            30
+        );
+    });
+}
+
+#[test]
+fn test_stale_sync_cancel() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+    let (client_sender, mut client_rcv) = mpsc::unbounded();
+    let client = StateSyncClient::new(client_sender);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let first_sync = client.sync_to(target_li.clone());
+        client.cancel_sync().unwrap();
+        let mut second_sync = Box::pin(client.sync_to(target_li));
+        let first_request = client_rcv.next().await.unwrap();
+        let cancel = client_rcv.next().await.unwrap();
+        let second_request = client_rcv.next().await.unwrap();
+
+        // the cancel issued for the first sync request is only delivered after the second one
+        let num_stale_cancels = counters::STALE_SYNC_CANCEL_COUNT.get();
+        coordinator.process_client_message(first_request).await;
+        coordinator.process_client_message(second_request).await;
+        coordinator.process_client_message(cancel).await;
+        assert_eq!(
+            counters::STALE_SYNC_CANCEL_COUNT.get(),
+            num_stale_cancels + 1
+        );
+
+        // the second sync request replaced the first one, but survives the stale cancel
+        assert_eq!(first_sync.await, Err(StateSyncError::Replaced));
+        assert!((&mut second_sync).now_or_never().is_none());
+
+        // until it's cancelled itself
+        client.cancel_sync().unwrap();
+        coordinator
+            .process_client_message(client_rcv.next().await.unwrap())
+            .await;
+        assert_eq!(second_sync.await, Err(StateSyncError::Cancelled));
+        assert_eq!(
+            counters::STALE_SYNC_CANCEL_COUNT.get(),
+            num_stale_cancels + 1
+        );
+    });
+}
+
+#[derive(Clone, Copy)]
+enum AcceleratorMode {
+    Verify,
+    Reject,
+    Unavailable,
+}
+
+// Accelerator verifier that counts the batches it's handed and behaves according to its mode.
+struct MockAcceleratorVerifier {
+    mode: Arc<Mutex<AcceleratorMode>>,
+    num_batches: Arc<Mutex<u64>>,
+}
+
+impl SignatureVerifier for MockAcceleratorVerifier {
+    fn verify_batch(
+        &self,
+        verifier: &ValidatorVerifier,
+        ledger_infos: &[&LedgerInfoWithSignatures],
+    ) -> anyhow::Result<Vec<anyhow::Result<()>>> {
+        *self.num_batches.lock() += 1;
+        match *self.mode.lock() {
+            AcceleratorMode::Verify => CpuSignatureVerifier.verify_batch(verifier, ledger_infos),
+            AcceleratorMode::Reject => Ok(ledger_infos
+                .iter()
+                .map(|_| Err(anyhow::format_err!("invalid signature")))
+                .collect()),
+            AcceleratorMode::Unavailable => Err(anyhow::format_err!("accelerator unavailable")),
+        }
+    }
+}
+
+#[test]
+fn test_signature_verifier() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(30);
+    let target_li = upstream.highest_local_li();
+
+    let mode = Arc::new(Mutex::new(AcceleratorMode::Verify));
+    let num_batches = Arc::new(Mutex::new(0));
+    let (mut coordinator, _network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    )
+    .signature_verifier(Box::new(MockAcceleratorVerifier {
+        mode: mode.clone(),
+        num_batches: num_batches.clone(),
+    }))
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the LI of the chunk is verified by the accelerator
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(*num_batches.lock(), 1);
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+
+        // a chunk whose LI the accelerator rejects isn't applied
+        *mode.lock() = AcceleratorMode::Reject;
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 10, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(*num_batches.lock(), 2);
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+
+        // an accelerator failure doesn't reject the chunk: its LI is verified on the CPU instead
+        *mode.lock() = AcceleratorMode::Unavailable;
+        let num_errors = counters::SIGNATURE_VERIFIER_ERROR_COUNT.get();
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 10, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(*num_batches.lock(), 3);
+        assert_eq!(
+            counters::SIGNATURE_VERIFIER_ERROR_COUNT.get(),
+            num_errors + 1
+        );
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            20
+        );
+    });
+}
+
+#[test]
+fn test_bandwidth_stats() {
+    // Returns the bytes of the chunk requests and responses sent since the last call.
+    fn sent_bytes(network_reqs_rx: &mut NetworkRequestsReceiver) -> (u64, u64) {
+        let (mut request_bytes, mut response_bytes) = (0, 0);
+        while let Some(Some(PeerManagerRequest::SendMessage(_, message))) =
+            network_reqs_rx.next().now_or_never()
+        {
+            match lcs::from_bytes(&message.mdata).unwrap() {
+                StateSynchronizerMsg::GetChunkRequest(_) => {
+                    request_bytes += message.mdata.len() as u64
+                }
+                StateSynchronizerMsg::GetChunkResponse(_) => {
+                    response_bytes += message.mdata.len() as u64
+                }
+                StateSynchronizerMsg::CommittedLedgerInfo(_) => {}
+            }
+        }
+        (request_bytes, response_bytes)
+    }
+
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    let local_li = storage.highest_local_li();
+    let mut upstream = storage.clone();
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) =
+        create_coordinator(storage, StateSyncConfig::default());
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // sync from the peer
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        let chunk_response = create_chunk_response(&upstream, 10, 10, target_li);
+        let syncing_bytes_received = lcs::to_bytes(&chunk_response).unwrap().len() as u64;
+        coordinator
+            .process_one_message(peer.clone(), chunk_response)
+            .await;
+        let (syncing_bytes_sent, _) = sent_bytes(&mut network_reqs_rx);
+
+        // serve the peer
+        let chunk_request = StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+            0,
+            1,
+            10,
+            TargetType::TargetLedgerInfo(local_li),
+        )));
+        let serving_bytes_received = lcs::to_bytes(&chunk_request).unwrap().len() as u64;
+        coordinator.process_one_message(peer, chunk_request).await;
+        let (_, serving_bytes_sent) = sent_bytes(&mut network_reqs_rx);
+
+        let (stats_sender, mut stats_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::GetBandwidthStats(stats_sender))
+            .await;
+        let stats = stats_rcv.try_recv().unwrap().unwrap();
+        assert!(syncing_bytes_sent > 0);
+        assert!(serving_bytes_sent > 0);
+        assert_eq!(
+            stats,
+            BandwidthStats {
+                window: Duration::from_millis(StateSyncConfig::default().bandwidth_stats_window_ms),
+                syncing_bytes_sent,
+                syncing_bytes_received,
+                serving_bytes_sent,
+                serving_bytes_received,
+            }
+        );
+    });
+}
+
+#[test]
+fn test_memory_cap() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    // the cap can't be met: everything that can be shed is shed
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            batch_verify_signatures: true,
+            max_state_sync_memory_bytes: 1,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(coordinator.num_li_crypto_calls(), 1);
+
+        let num_cache_sheds = counters::MEMORY_SHED_COUNT
+            .with_label_values(&[counters::VERIFIED_LI_CACHE_LABEL])
+            .get();
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert!(
+            counters::MEMORY_SHED_COUNT
+                .with_label_values(&[counters::VERIFIED_LI_CACHE_LABEL])
+                .get()
+                > num_cache_sheds
+        );
+        // the structures needed to sync are kept, e.g. the in-flight chunk request
+        assert!(coordinator.memory_bytes() > 0);
+
+        // the target LI is verified again, as the cache of verified LIs was shed
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 10, target_li))
+            .await;
+        assert_eq!(coordinator.num_li_crypto_calls(), 2);
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+    });
+}
+
+#[test]
+fn test_epoch_walk_resume() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    for _ in 0..2 {
+        upstream.commit_new_txns(10);
+        move_to_next_epoch(&mut upstream);
+    }
+    let first_epoch_li = upstream.get_epoch_changes(1).unwrap();
+    let waypoint_li = upstream.get_epoch_changes(2).unwrap();
+    let waypoint = Waypoint::new_epoch_boundary(waypoint_li.ledger_info()).unwrap();
+
+    // the node crashed after verifying and committing the first epoch of its walk
+    let mut storage = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    storage
+        .add_txns_with_li(upstream.get_chunk(1, 10, 10), first_epoch_li, None)
+        .unwrap();
+    let num_resumes = counters::EPOCH_WALK_RESUME_COUNT.get();
+    let (mut coordinator, mut network_reqs_rx) =
+        CoordinatorBuilder::new(storage, StateSyncConfig::default())
+            .waypoint(waypoint)
+            .build();
+    assert_eq!(counters::EPOCH_WALK_RESUME_COUNT.get(), num_resumes + 1);
+
+    // the walk resumes after the first epoch
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    let request = next_sent_chunk_request(&mut network_reqs_rx);
+    assert_eq!(request.known_version, 10);
+    assert_eq!(request.current_epoch, 2);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let txn_list_with_proof =
+            match create_chunk_response(&upstream, 10, 10, waypoint_li.clone()) {
+                StateSynchronizerMsg::GetChunkResponse(response) => response.txn_list_with_proof,
+                _ => panic!("expected a chunk response"),
+            };
+        coordinator
+            .process_one_message(
+                peer,
+                StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                    ResponseLedgerInfo::LedgerInfoForWaypoint {
+                        waypoint_li,
+                        end_of_epoch_li: None,
+                    },
+                    txn_list_with_proof,
+                ))),
+            )
+            .await;
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.highest_local_li.ledger_info().version(), 20);
+        assert_eq!(local_state.epoch(), 3);
+    });
+
+    // a node that restarts at genesis doesn't resume a walk
+    let num_resumes = counters::EPOCH_WALK_RESUME_COUNT.get();
+    let _coordinator = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    )
+    .waypoint(waypoint)
+    .build();
+    assert_eq!(counters::EPOCH_WALK_RESUME_COUNT.get(), num_resumes);
+}
+
+#[test]
+fn test_chunk_response_size_bounds() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(23);
+    let target_li = upstream.highest_local_li();
+
+    // responses of up to 10 transactions are accepted, down to 5 transactions
+    let max_response = match create_chunk_response(&upstream, 0, 10, target_li.clone()) {
+        StateSynchronizerMsg::GetChunkResponse(response) => response,
+        _ => panic!("expected a chunk response"),
+    };
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            chunk_limit: 10,
+            max_received_chunk_bytes: lcs::to_bytes(&max_response).unwrap().len(),
+            min_chunk_response_percent: 50,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    let violations = |bound: &str| {
+        counters::CHUNK_RESPONSE_SIZE_VIOLATION_COUNT
+            .with_label_values(&[
+                &peer.raw_network_id().to_string(),
+                &peer.peer_id().to_string(),
+                bound,
+            ])
+            .get()
+    };
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // a chunk trickling 2 transactions is rejected
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 2, target_li.clone()),
+            )
+            .await;
+        assert_eq!(violations(counters::UNDER_MIN_LABEL), 1);
+
+        // a chunk of 15 transactions is rejected
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 15, target_li.clone()),
+            )
+            .await;
+        assert_eq!(violations(counters::OVER_MAX_LABEL), 1);
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.highest_version_in_local_storage(), 0);
+
+        // chunks within the bounds are applied, as is a short chunk reaching the target LI
+        for (known_version, limit) in vec![(0, 10), (10, 10), (20, 3)] {
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(&upstream, known_version, limit, target_li.clone()),
+                )
+                .await;
+        }
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.highest_version_in_local_storage(), 23);
+        assert_eq!(violations(counters::UNDER_MIN_LABEL), 1);
+        assert_eq!(violations(counters::OVER_MAX_LABEL), 1);
+    });
+}
+
+#[test]
+fn test_tip_flapping() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(20);
+    let highest_li = storage.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        storage,
+        StateSyncConfig {
+            tip_flap_threshold: 3,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peers: Vec<_> = (0..3)
+        .map(|_| PeerNetworkId(validator_network_id(), PeerId::random()))
+        .collect();
+    for peer in &peers {
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let num_flaps = counters::TIP_FLAP_COUNT.get();
+        // two peers consistently advertise version 15, while the third one contradicts itself
+        for _ in 0..3 {
+            for (peer, known_version) in peers.iter().zip(vec![15, 15, 1_000]) {
+                coordinator
+                    .process_one_message(
+                        peer.clone(),
+                        StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+                            known_version,
+                            1,
+                            10,
+                            TargetType::TargetLedgerInfo(highest_li.clone()),
+                        ))),
+                    )
+                    .await;
+            }
+            assert_eq!(health_check(&mut coordinator).await.tip_estimate, Some(15));
+            assert!(!coordinator.is_tip_flapping());
+
+            coordinator
+                .process_one_message(
+                    peers[2].clone(),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+                        5,
+                        1,
+                        10,
+                        TargetType::TargetLedgerInfo(highest_li.clone()),
+                    ))),
+                )
+                .await;
+            assert_eq!(health_check(&mut coordinator).await.tip_estimate, Some(15));
+        }
+
+        // the third regression within the window is reported as flapping
+        assert!(coordinator.is_tip_flapping());
+        assert!(counters::TIP_FLAP_COUNT.get() > num_flaps);
+    });
+}
+
+// Epoch change listener that records the transitions it's called with, and fails if asked to.
+struct MockEpochChangeListener {
+    transitions: Arc<Mutex<Vec<(EpochState, EpochState)>>>,
+    fail: bool,
+}
+
+impl EpochChangeListener for MockEpochChangeListener {
+    fn on_epoch_change(&mut self, old: &EpochState, new: &EpochState) -> anyhow::Result<()> {
+        self.transitions.lock().push((old.clone(), new.clone()));
+        if self.fail {
+            return Err(anyhow::format_err!("key rotation failed"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_epoch_change_listener() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    upstream.commit_new_txns(10);
+    let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+    let target_li = upstream.highest_local_li();
+
+    let storage = MockStorage::new(genesis_li, signers[0].clone());
+    let old_epoch_state = storage.get_local_storage_state().trusted_epoch;
+    let (mut coordinator, _network_reqs_rx) =
+        create_coordinator(storage, StateSyncConfig::default());
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the failing listener doesn't prevent the other one from being called
+        let transitions: Vec<_> = (0..2).map(|_| Arc::new(Mutex::new(vec![]))).collect();
+        for (transitions, fail) in transitions.iter().zip(vec![true, false]) {
+            coordinator
+                .process_client_message(CoordinatorMessage::AddEpochChangeListener(Box::new(
+                    MockEpochChangeListener {
+                        transitions: transitions.clone(),
+                        fail,
+                    },
+                )))
+                .await;
+        }
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // chunks of the first epoch, then of the second epoch
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, epoch_ending_li.clone()),
+            )
+            .await;
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 10, target_li))
+            .await;
+
+        // the single transition is reported once to each listener, and applied despite the
+        // listener failure
+        let new_epoch_state = epoch_ending_li
+            .ledger_info()
+            .next_epoch_state()
+            .unwrap()
+            .clone();
+        assert_eq!(new_epoch_state.epoch, 2);
+        for transitions in &transitions {
+            assert_eq!(
+                *transitions.lock(),
+                vec![(old_epoch_state.clone(), new_epoch_state.clone())]
+            );
+        }
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.trusted_epoch, new_epoch_state);
+        assert_eq!(local_state.highest_version_in_local_storage(), 20);
+    });
+}
+
+#[test]
+fn test_epoch_change_notification() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    upstream.commit_new_txns(10);
+    let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+    let target_li = upstream.highest_local_li();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (epoch_change_sender, mut epoch_change_receiver) = mpsc::channel(8);
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .epoch_change_sender(epoch_change_sender)
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the chunk ending the first epoch is committed by the time the transition is notified
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, epoch_ending_li.clone()),
+            )
+            .await;
+        let notification = epoch_change_receiver.try_next().unwrap().unwrap();
+        assert_eq!(
+            notification,
+            EpochChangeNotification {
+                old_epoch: 1,
+                new_epoch: 2,
+                epoch_state: epoch_ending_li
+                    .ledger_info()
+                    .next_epoch_state()
+                    .unwrap()
+                    .clone(),
+            }
+        );
+        assert_eq!(storage.read().highest_local_li(), epoch_ending_li);
+
+        // chunks within the new epoch don't notify any transition
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 10, target_li))
+            .await;
+        assert!(epoch_change_receiver.try_next().is_err());
+    });
+}
+
+#[test]
+fn test_pending_chunk_overflow() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let max_pending_chunks = 3;
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            max_pending_chunks,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peers: Vec<_> = (0..5)
+        .map(|_| PeerNetworkId(validator_network_id(), PeerId::random()))
+        .collect();
+    for peer in &peers {
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // a flood of responses that don't make progress, with the useful one in the middle
+        let overflow_count = counters::PENDING_CHUNK_OVERFLOW.get();
+        for i in 0..20 {
+            let known_version = if i == 10 { 0 } else { 10 };
+            coordinator.receive_message(
+                peers[i % peers.len()].clone(),
+                create_chunk_response(&upstream, known_version, 10, target_li.clone()),
+            );
+            assert!(coordinator.num_pending_chunks() <= max_pending_chunks);
+        }
+        assert_eq!(
+            counters::PENDING_CHUNK_OVERFLOW.get() - overflow_count,
+            20 - max_pending_chunks as u64
+        );
+
+        // the response that makes progress was kept
+        coordinator.process_pending_chunks().await;
+        assert_eq!(coordinator.num_pending_chunks(), 0);
+        let local_state = get_state(&mut coordinator).await;
+        assert!(local_state.highest_version_in_local_storage() >= 10);
+    });
+}
+
+#[test]
+fn test_buffer_future_epoch_chunks() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    for _ in 0..2 {
+        upstream.commit_new_txns(10);
+        move_to_next_epoch(&mut upstream);
+    }
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    assert_eq!(target_li.ledger_info().epoch(), 3);
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            buffer_future_epoch_chunks: true,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the chunk of the third epoch arrives first and can't be verified yet
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 20, 10, target_li),
+            )
+            .await;
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            0
+        );
+
+        // the chunks of the first two epochs move the node to the third epoch, where the buffered
+        // chunk is verified and applied
+        let mut known_version = 0;
+        for epoch in 1..3 {
+            let epoch_ending_li = upstream.get_epoch_changes(epoch).unwrap();
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(&upstream, known_version, 10, epoch_ending_li),
+                )
+                .await;
+            known_version += 10;
+        }
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.trusted_epoch.epoch, 3);
+        assert_eq!(local_state.highest_local_li.ledger_info().version(), 30);
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+    });
+}
+
+#[test]
+fn test_last_stall_reason() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    // chunk requests time out right away
+    let config = StateSyncConfig {
+        tick_interval_ms: 0,
+        ..StateSyncConfig::default()
+    };
+    let sync_request = |target: &LedgerInfoWithSignatures| {
+        CoordinatorMessage::Request(Box::new(SyncRequest {
+            callback: oneshot::channel().0,
+            target: target.clone(),
+            last_progress_tst: SystemTime::now(),
+            generation: 0,
+        }))
+    };
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            config.clone(),
+        );
+        assert_eq!(last_stall_reason(&mut coordinator).await, None);
+
+        // the node has nothing to sync
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            last_stall_reason(&mut coordinator).await,
+            Some(StallReason::AtTip)
+        );
+
+        // the node has no peers to sync from
+        coordinator
+            .process_client_message(sync_request(&target_li))
+            .await;
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            last_stall_reason(&mut coordinator).await,
+            Some(StallReason::NoPeers)
+        );
+
+        // the peer doesn't respond
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            last_stall_reason(&mut coordinator).await,
+            Some(StallReason::NoResponse)
+        );
+
+        // the peer responds with a chunk proven to a LI signed by an unknown validator
+        let (other_signers, _) = random_validator_verifier(1, None, true);
+        let mut forged_upstream = MockStorage::new(genesis_li.clone(), other_signers[0].clone());
+        forged_upstream.commit_new_txns(10);
+        let forged_li = forged_upstream.highest_local_li();
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&forged_upstream, 0, 10, forged_li),
+            )
+            .await;
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert!(matches!(
+            last_stall_reason(&mut coordinator).await,
+            Some(StallReason::VerificationFailure(_))
+        ));
+
+        // the storage fails to persist the chunk
+        let mut storage = MockStorage::new(genesis_li.clone(), signers[0].clone());
+        storage.set_drop_writes(true);
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            storage,
+            StateSyncConfig {
+                verify_commit_durability: true,
+                ..config.clone()
+            },
+        );
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        coordinator
+            .process_client_message(sync_request(&target_li))
+            .await;
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert!(matches!(
+            last_stall_reason(&mut coordinator).await,
+            Some(StallReason::ExecutionFailure(_))
+        ));
+
+        // the only peer is behind the local storage
+        let local_storage = upstream.clone();
+        upstream.commit_new_txns(10);
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(local_storage, config);
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        coordinator
+            .process_client_message(sync_request(&upstream.highest_local_li()))
+            .await;
+        coordinator
+            .process_one_message(
+                peer,
+                StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+                    5,
+                    1,
+                    10,
+                    TargetType::TargetLedgerInfo(target_li),
+                ))),
+            )
+            .await;
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            last_stall_reason(&mut coordinator).await,
+            Some(StallReason::PeersBehind)
+        );
+    });
+}
+
+#[test]
+fn test_mempool_commit_failure() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    let failure = CommitError::MempoolCommitFailed("mempool is full".to_string());
+
+    // mempool answers the commit notification with a failure, or drops it without answering
+    for mempool_response in vec![Some(CommitResponse::failure(failure.clone())), None] {
+        let (mempool_sender, mut mempool_receiver) = mpsc::channel(8);
+        let (mut coordinator, _network_reqs_rx) =
+            CoordinatorBuilder::new(storage.clone(), StateSyncConfig::default())
+                .mempool_sender(mempool_sender)
+                .build();
+        let expected_error = if mempool_response.is_some() {
+            failure.clone()
+        } else {
+            CommitError::MempoolAckCanceled
+        };
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            tokio::spawn(async move {
+                let notification: CommitNotification = mempool_receiver.next().await.unwrap();
+                if let Some(response) = mempool_response {
+                    let _ = notification.callback.send(Ok(response));
+                }
+            });
+
+            // the failure of mempool is forwarded to consensus
+            let (callback, mut callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Commit(vec![], vec![], callback))
+                .await;
+            let response = callback_rcv.try_recv().unwrap().unwrap().unwrap();
+            assert_eq!(response.result, Err(expected_error));
+        });
+    }
+}
+
+#[test]
+fn test_duplicate_commit() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    let reconfig_event = ContractEvent::new(new_epoch_event_key(), 0, xus_tag(), vec![]);
+
+    for &ignore_duplicate_commits in &[false, true] {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            storage.clone(),
+            StateSyncConfig {
+                ignore_duplicate_commits,
+                ..StateSyncConfig::default()
+            },
+        );
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let reconfig_event = reconfig_event.clone();
+        rt.block_on(async move {
+            let (subscriber, mut receiver) = mpsc::unbounded();
+            coordinator
+                .process_client_message(CoordinatorMessage::SubscribeReconfigs(subscriber))
+                .await;
+
+            // consensus sends the same commit twice
+            let num_duplicates = counters::DUPLICATE_COMMIT_COUNT.get();
+            for _ in 0..2 {
+                let (callback, mut callback_rcv) = oneshot::channel();
+                coordinator
+                    .process_client_message(CoordinatorMessage::Commit(
+                        vec![],
+                        vec![reconfig_event.clone()],
+                        callback,
+                    ))
+                    .await;
+                assert!(matches!(callback_rcv.try_recv(), Ok(Some(Ok(_)))));
+            }
+            assert_eq!(
+                counters::DUPLICATE_COMMIT_COUNT.get(),
+                num_duplicates + ignore_duplicate_commits as u64
+            );
+
+            // the ignored duplicate isn't notified again
+            assert!(receiver.next().now_or_never().is_some());
+            assert_eq!(
+                receiver.next().now_or_never().is_some(),
+                !ignore_duplicate_commits
+            );
+        });
+    }
+}
+
+#[test]
+fn test_frozen_subtree_cache() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(30);
+    let target_li = upstream.highest_local_li();
+
+    // a chunk with a proof relative to an accumulator that doesn't chain with the local one
+    let left_siblings = vec![HashValue::random(); 11u64.count_ones() as usize];
+    let invalid_response = StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+        ResponseLedgerInfo::VerifiableLedgerInfo(target_li.clone()),
+        TransactionListWithProof::new(
+            upstream.get_chunk(11, 10, 30),
+            None,
+            Some(11),
+            TransactionListProof::new(
+                TransactionAccumulatorRangeProof::new(left_siblings, vec![]),
+                vec![],
+            ),
+        ),
+    )));
+
+    let mut results = vec![];
+    for &cache_frozen_subtrees in &[false, true] {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                cache_frozen_subtrees,
+                ..StateSyncConfig::default()
+            },
+        );
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let (upstream, target_li) = (&upstream, target_li.clone());
+        let invalid_response = invalid_response.clone();
+        results.push(rt.block_on(async move {
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+
+            // record the synced version after a valid, an invalid and another valid chunk
+            let mut synced_versions = vec![];
+            for response in vec![
+                create_chunk_response(upstream, 0, 10, target_li.clone()),
+                invalid_response,
+                create_chunk_response(upstream, 10, 10, target_li),
+            ] {
+                coordinator
+                    .process_one_message(peer.clone(), response)
+                    .await;
+                synced_versions.push(
+                    get_state(&mut coordinator)
+                        .await
+                        .highest_version_in_local_storage(),
+                );
+            }
+            (synced_versions, coordinator.peer_score(&peer))
+        }));
+        if cache_frozen_subtrees {
+            assert_eq!(counters::FROZEN_SUBTREE_CACHE_HIT_RATE.get(), 100);
+        }
+    }
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[0].0, vec![10, 10, 20]);
+}
+
+#[test]
+fn test_stale_sync_target() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    move_to_next_epoch(&mut storage);
+    storage.commit_new_txns(10);
+    assert_eq!(storage.highest_local_li().ledger_info().epoch(), 2);
+
+    // the epoch ending LI of the first epoch is already committed
+    let committed_li = storage.get_epoch_changes(1).unwrap();
+    // a target of the first epoch beyond what's committed
+    let li = committed_li.ledger_info();
+    let uncommitted_li = LedgerInfoWithSignatures::new(
+        LedgerInfo::new(
+            BlockInfo::new(
+                li.epoch(),
+                li.round() + 1,
+                HashValue::random(),
+                HashValue::random(),
+                li.version() + 100,
+                li.timestamp_usecs() + 1,
+                None,
+            ),
+            HashValue::zero(),
+        ),
+        BTreeMap::new(),
+    );
+    let stale_target_error = StateSyncError::StaleTarget {
+        target_epoch: 1,
+        trusted_epoch: 2,
+    };
+
+    for &accept_committed_stale_targets in &[false, true] {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            storage.clone(),
+            StateSyncConfig {
+                accept_committed_stale_targets,
+                ..StateSyncConfig::default()
+            },
+        );
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let (committed_li, uncommitted_li) = (committed_li.clone(), uncommitted_li.clone());
+        let stale_target_error = stale_target_error.clone();
+        rt.block_on(async move {
+            let num_stale_targets = counters::STALE_SYNC_TARGET_COUNT.get();
+            let mut results = vec![];
+            for target in vec![committed_li, uncommitted_li] {
+                let (callback, mut callback_rcv) = oneshot::channel();
+                coordinator
+                    .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                        callback,
+                        target,
+                        last_progress_tst: SystemTime::now(),
+                        generation: 0,
+                    })))
+                    .await;
+                // stale targets complete immediately
+                results.push(callback_rcv.try_recv().unwrap().unwrap());
+            }
+            assert_eq!(
+                counters::STALE_SYNC_TARGET_COUNT.get(),
+                num_stale_targets + 2
+            );
+
+            let committed_result = results.remove(0);
+            if accept_committed_stale_targets {
+                assert!(committed_result.is_ok());
+            } else {
+                assert_eq!(committed_result.unwrap_err(), stale_target_error);
+            }
+            assert_eq!(results.remove(0).unwrap_err(), stale_target_error);
+        });
+    }
+}
+
+#[test]
+fn test_consensus_commit_conflict() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(5);
+    let target_li = upstream.highest_local_li();
+
+    for &verify_consensus_commits in &[false, true] {
+        let mut storage = MockStorage::new(genesis_li.clone(), signers[0].clone());
+        storage.commit_new_txns(10);
+        let storage = Arc::new(RwLock::new(storage));
+        let (mut coordinator, _network_reqs_rx) = CoordinatorBuilder::with_shared_storage(
+            storage.clone(),
+            StateSyncConfig {
+                verify_consensus_commits,
+                ..StateSyncConfig::default()
+            },
+        )
+        .build();
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let (upstream, genesis_li, signer) = (&upstream, genesis_li.clone(), signers[0].clone());
+        let target_li = target_li.clone();
+        rt.block_on(async move {
+            // consensus commits up to version 10
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Commit(vec![], vec![], callback))
+                .await;
+
+            // the storage loses the versions committed by consensus, so that syncing commits
+            // versions consensus already committed
+            *storage.write() = MockStorage::new(genesis_li, signer);
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            let num_conflicts = counters::CONSENSUS_COMMIT_CONFLICT_COUNT.get();
+            coordinator
+                .process_one_message(peer, create_chunk_response(upstream, 0, 5, target_li))
+                .await;
+            assert_eq!(
+                get_state(&mut coordinator)
+                    .await
+                    .highest_version_in_local_storage(),
+                5
+            );
+            assert_eq!(
+                counters::CONSENSUS_COMMIT_CONFLICT_COUNT.get(),
+                num_conflicts + verify_consensus_commits as u64
+            );
+        });
+    }
+}
+
+#[test]
+fn test_peer_health() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        assert!(health_check(&mut coordinator).await.peer_health.is_empty());
+
+        let healthy_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        let penalized_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        let lost_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        for peer in vec![&healthy_peer, &penalized_peer, &lost_peer] {
+            coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        }
+        coordinator.process_lost_peer(lost_peer, ConnectionOrigin::Outbound);
+        // public peers aren't upstream peers of validators
+        coordinator.process_new_peer(
+            PeerNetworkId(public_network_id(), PeerId::random()),
+            ConnectionOrigin::Outbound,
+        );
+
+        // the penalized peer sends chunks that don't extend the local accumulator
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        for _ in 0..4 {
+            let txn_list_with_proof = TransactionListWithProof::new(
+                upstream.get_chunk(1, 10, 20),
+                None,
+                Some(1),
+                TransactionListProof::new(
+                    TransactionAccumulatorRangeProof::new(vec![HashValue::random()], vec![]),
+                    vec![],
+                ),
+            );
+            coordinator
+                .process_one_message(
+                    penalized_peer.clone(),
+                    StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                        ResponseLedgerInfo::VerifiableLedgerInfo(target_li.clone()),
+                        txn_list_with_proof,
+                    ))),
+                )
+                .await;
+        }
+
+        let peer_health = health_check(&mut coordinator).await.peer_health;
+        assert_eq!(peer_health.len(), 1);
+        assert_eq!(
+            peer_health.get(&NetworkId::Validator),
+            Some(&PeerHealth {
+                healthy: 1,
+                penalized: 1,
+                disconnected: 1,
+            })
+        );
+    });
+}
+
+#[test]
+fn test_commit_batch() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(40);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            min_commit_batch_versions: 30,
+            max_commit_batch_delay_ms: 60_000,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // chunks of 10 transactions are batched until the batch reaches 30 transactions
+        let num_coalesced_commits = counters::COALESCED_COMMIT_COUNT.get();
+        for (known_version, synced_version) in vec![(0, 0), (10, 0), (20, 30)] {
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(&upstream, known_version, 10, target_li.clone()),
+                )
+                .await;
+            assert_eq!(
+                get_state(&mut coordinator)
+                    .await
+                    .highest_version_in_local_storage(),
+                synced_version
+            );
+        }
+        assert_eq!(
+            counters::COALESCED_COMMIT_COUNT.get(),
+            num_coalesced_commits + 1
+        );
+
+        // the last chunk reaches the target LI, so it's committed right away on its own
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 30, 10, target_li))
+            .await;
+        let local_state = get_state(&mut coordinator).await;
+        assert_eq!(local_state.highest_version_in_local_storage(), 40);
+        assert_eq!(local_state.highest_local_li.ledger_info().version(), 40);
+        assert_eq!(
+            counters::COALESCED_COMMIT_COUNT.get(),
+            num_coalesced_commits + 1
+        );
+    });
+}
+
+#[test]
+fn test_active_upstream() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // validators don't sync without a sync request
+        assert_eq!(active_upstream(&mut coordinator).await, None);
+
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(active_upstream(&mut coordinator).await, Some(peer.clone()));
+
+        // the sync request is fulfilled by the first chunk, so no further chunk is requested
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li),
+            )
+            .await;
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            10
+        );
+        assert_eq!(active_upstream(&mut coordinator).await, None);
+    });
+}
+
+#[test]
+fn test_chunk_version_range_mismatch() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // 20 transactions from version 1 can't be proven against an LI at version 10
+        let num_mismatches = counters::CHUNK_VERSION_RANGE_MISMATCH_COUNT
+            .with_label_values(&[
+                &peer.raw_network_id().to_string(),
+                &peer.peer_id().to_string(),
+            ])
+            .get();
+        let txn_list_with_proof = TransactionListWithProof::new(
+            upstream.get_chunk(1, 20, 20),
+            None,
+            Some(1),
+            MockStorage::chunk_proof(1),
+        );
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                    ResponseLedgerInfo::VerifiableLedgerInfo(target_li),
+                    txn_list_with_proof,
+                ))),
+            )
+            .await;
+        assert_eq!(
+            counters::CHUNK_VERSION_RANGE_MISMATCH_COUNT
+                .with_label_values(&[
+                    &peer.raw_network_id().to_string(),
+                    &peer.peer_id().to_string(),
+                ])
+                .get(),
+            num_mismatches + 1
+        );
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_version_in_local_storage(),
+            0
+        );
+    });
+}
+
+#[test]
+fn test_sync_progress() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let trusted_epoch = get_state(&mut coordinator).await.trusted_epoch.epoch;
+        assert_eq!(
+            sync_progress(&mut coordinator).await,
+            SyncProgress {
+                committed_version: 0,
+                synced_version: 0,
+                target_version: None,
+                trusted_epoch,
+            }
+        );
+
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            sync_progress(&mut coordinator).await.target_version,
+            Some(10)
+        );
+
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 10, target_li))
+            .await;
+        assert_eq!(
+            sync_progress(&mut coordinator).await,
+            SyncProgress {
+                committed_version: 10,
+                synced_version: 10,
+                target_version: None,
+                trusted_epoch,
+            }
+        );
+    });
+}
+
+#[test]
+fn test_sync_to_version() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+
+    // the target version is in the epoch after the one of the local node
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    upstream.commit_new_txns(10);
+    let highest_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            sync_request_timeout_ms: 0,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // a version that is already committed is reached right away
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::SyncToVersion(Box::new(
+                VersionSyncRequest {
+                    callback,
+                    version: 0,
+                    last_progress_tst: SystemTime::now(),
+                },
+            )))
+            .await;
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::SyncToVersion(Box::new(
+                VersionSyncRequest {
+                    callback,
+                    version: 15,
+                    last_progress_tst: SystemTime::now(),
+                },
+            )))
+            .await;
+        assert_eq!(
+            sync_progress(&mut coordinator).await.target_version,
+            Some(15)
+        );
+
+        // the epoch ending LI doesn't reach the version, the sync continues in the next epoch
+        let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+        let end_version = epoch_ending_li.ledger_info().version();
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, end_version, epoch_ending_li),
+            )
+            .await;
+        assert_eq!(get_state(&mut coordinator).await.trusted_epoch.epoch, 2);
+        assert!(callback_rcv.try_recv().unwrap().is_none());
+
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, end_version, 10, highest_li),
+            )
+            .await;
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+        assert_eq!(sync_progress(&mut coordinator).await.target_version, None);
+
+        // a version no LI reaches fails once the request times out
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::SyncToVersion(Box::new(
+                VersionSyncRequest {
+                    callback,
+                    version: 100,
+                    last_progress_tst: SystemTime::now(),
+                },
+            )))
+            .await;
+        coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+        assert_eq!(
+            callback_rcv.try_recv().unwrap().unwrap(),
+            Err(StateSyncError::VersionNotReached {
+                version: 100,
+                committed_version: 20,
+            })
+        );
+    });
+}
+
+#[test]
+fn test_sync_to_version_before_initialization() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let waypoint_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    )
+    .waypoint(Waypoint::new_any(waypoint_li.ledger_info()))
+    .build();
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the request fails right away instead of being dropped
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::SyncToVersion(Box::new(
+                VersionSyncRequest {
+                    callback,
+                    version: 5,
+                    last_progress_tst: SystemTime::now(),
+                },
+            )))
+            .await;
+        assert_eq!(
+            callback_rcv.try_recv().unwrap().unwrap(),
+            Err(StateSyncError::NotInitialized)
+        );
+    });
+}
+
+#[test]
+fn test_sync_rate_percentiles() {
+    // a synthetic run where the rate of every 100 chunks goes through 1..=100 txns per second
+    let mut stats = SyncRateStats::new();
+    assert_eq!(stats.percentiles(), None);
+    for i in 0..1000 {
+        stats.record((i * 37) % 100 + 1, Duration::from_secs(1));
+    }
+    let percentiles = stats.percentiles().unwrap();
+    assert_eq!(percentiles.num_samples, 1000);
+    assert!((percentiles.p50 - 50.0).abs() < 3.0, "{:?}", percentiles);
+    assert!((percentiles.p90 - 90.0).abs() < 3.0, "{:?}", percentiles);
+    assert!((percentiles.p99 - 99.0).abs() < 3.0, "{:?}", percentiles);
+
+    // the percentiles of the chunks synced by the coordinator are reported
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 10, target_li))
+            .await;
+
+        let (callback, callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::GetSyncRatePercentiles(callback))
+            .await;
+        let percentiles = callback_rcv.await.unwrap().unwrap();
+        assert_eq!(percentiles.num_samples, 1);
+        assert!(percentiles.p50 > 0.0);
+    });
+}
+
+#[test]
+fn test_stall_escalation() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            stall_escalation_widen_peers_ms: 100,
+            stall_escalation_refresh_peers_ms: 400,
+            stall_escalation_switch_networks_ms: 700,
+            stall_escalation_alert_ms: 1_000,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the peer never responds to the chunk requests of the sync request
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        let steps = [
+            counters::WIDEN_PEERS_LABEL,
+            counters::REFRESH_PEERS_LABEL,
+            counters::SWITCH_NETWORKS_LABEL,
+            counters::ALERT_LABEL,
+        ];
+        let num_steps_taken = || {
+            steps
+                .iter()
+                .map(|step| {
+                    counters::STALL_ESCALATION_COUNT
+                        .with_label_values(&[*step])
+                        .get()
+                })
+                .collect::<Vec<_>>()
+        };
+        let initial_num_steps_taken = num_steps_taken();
+
+        // the steps are taken one at a time, in order, as the stall goes on
+        std::thread::sleep(Duration::from_millis(250));
+        for num_steps in 1..=steps.len() {
+            coordinator
+                .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+                .await;
+            let expected_num_steps_taken = initial_num_steps_taken
+                .iter()
+                .enumerate()
+                .map(|(i, num_taken)| num_taken + if i < num_steps { 1 } else { 0 })
+                .collect::<Vec<_>>();
+            assert_eq!(num_steps_taken(), expected_num_steps_taken);
+            std::thread::sleep(Duration::from_millis(300));
+        }
+
+        // every step is only taken once per stall
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        let expected_num_steps_taken = initial_num_steps_taken
+            .iter()
+            .map(|num_taken| num_taken + 1)
+            .collect::<Vec<_>>();
+        assert_eq!(num_steps_taken(), expected_num_steps_taken);
+    });
+}
+
+#[test]
+fn test_serve_committed_only() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let committed_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let synced_li = upstream.highest_local_li();
+
+    // the local storage is synced to version 20, but only committed up to version 10
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage
+        .add_txns_with_li(upstream.get_chunk(1, 10, 10), committed_li.clone(), None)
+        .unwrap();
+    storage
+        .add_txns_with_li(upstream.get_chunk(11, 10, 20), committed_li.clone(), None)
+        .unwrap();
+    assert_eq!(storage.version(), 20);
+    let (mut coordinator, mut network_reqs_rx) =
+        create_coordinator(storage, StateSyncConfig::default());
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let num_served_beyond_committed = counters::SERVE_BEYOND_COMMITTED_COUNT.get();
+        for target in vec![
+            TargetType::TargetLedgerInfo(synced_li.clone()),
+            TargetType::HighestAvailable {
+                target_li: Some(synced_li.clone()),
+                timeout_ms: 0,
+            },
+        ] {
+            coordinator
+                .process_one_message(
+                    PeerNetworkId(validator_network_id(), PeerId::random()),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+                        0, 1, 20, target,
+                    ))),
+                )
+                .await;
+            // the chunk is proven against the committed LI and stops at its version
+            let response = next_sent_chunk_response(&mut network_reqs_rx);
+            assert_eq!(response.response_li.version(), 10);
+            assert_eq!(response.txn_list_with_proof.len(), 10);
+        }
+        assert_eq!(
+            counters::SERVE_BEYOND_COMMITTED_COUNT.get(),
+            num_served_beyond_committed
+        );
+    });
+}
+
+#[test]
+fn test_waypoint_advancement() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    for _ in 0..3 {
+        storage.commit_new_txns(10);
+        move_to_next_epoch(&mut storage);
+    }
+    // the latest epoch-ending LI is at version 30, the next LI is in the middle of an epoch
+    storage.commit_new_txns(10);
+    let epoch_ending_li = storage.get_epoch_ending_ledger_info(30).unwrap();
+    let storage = Arc::new(RwLock::new(storage));
+
+    let waypoint_file = diem_temppath::TempPath::new();
+    let config = StateSyncConfig {
+        waypoint_advancement_file: Some(waypoint_file.path().to_path_buf()),
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), config.clone()).build();
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let num_advancements = counters::WAYPOINT_ADVANCEMENT_COUNT.get();
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            counters::WAYPOINT_ADVANCEMENT_COUNT.get(),
+            num_advancements + 1
+        );
+        // the waypoint is only advanced once per interval
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            counters::WAYPOINT_ADVANCEMENT_COUNT.get(),
+            num_advancements + 1
+        );
+    });
+
+    // after a restart, the node trusts the advanced waypoint instead of the original one, so it
+    // doesn't have to walk the epochs before it again
+    let waypoint = WaypointConfig::FromFile(waypoint_file.path().to_path_buf()).waypoint();
+    assert_eq!(waypoint.version(), 30);
+    assert!(waypoint.verify(epoch_ending_li.ledger_info()).is_ok());
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage, config)
+            .waypoint(waypoint)
+            .build();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::WaitInitialize(callback))
+            .await;
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+    });
+}
+
+#[test]
+fn test_rollback_after_partial_commit() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    // the storage persists part of the first chunk and fails
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.fail_next_write_after(4);
+    let (mut coordinator, _network_reqs_rx) =
+        create_coordinator(storage, StateSyncConfig::default());
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+
+        // the in-memory state reflects the partially persisted chunk, not covered by any LI
+        let progress = sync_progress(&mut coordinator).await;
+        assert_eq!(progress.synced_version, 4);
+        assert_eq!(progress.committed_version, 0);
+        let state = get_state(&mut coordinator).await;
+        assert_eq!(state.highest_version_in_local_storage(), 4);
+        assert_eq!(state.highest_local_li.ledger_info().version(), 0);
+        assert!(callback_rcv.try_recv().unwrap().is_none());
+
+        // the retry continues from the persisted version
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 4, 6, target_li),
+            )
+            .await;
+        let progress = sync_progress(&mut coordinator).await;
+        assert_eq!(progress.synced_version, 10);
+        assert_eq!(progress.committed_version, 10);
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+    });
+}
+
+#[test]
+fn test_network_message_counters() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    let msgs = vec![
+        StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+            0,
+            1,
+            10,
+            TargetType::TargetLedgerInfo(target_li.clone()),
+        ))),
+        create_chunk_response(&upstream, 0, 10, target_li),
+    ];
+    let num_messages = |msg: &StateSynchronizerMsg, direction: &str| {
+        counters::NETWORK_MESSAGES
+            .with_label_values(&[msg.message_type(), direction])
+            .get()
+    };
+
+    // sent messages are counted once the network accepts them
+    let (network_reqs_tx, _network_reqs_rx) =
+        diem_channel::new(QueueStyle::FIFO, NonZeroUsize::new(8).unwrap(), None);
+    let (connection_reqs_tx, _) =
+        diem_channel::new(QueueStyle::FIFO, NonZeroUsize::new(8).unwrap(), None);
+    let mut network_sender = StateSynchronizerSender::new(
+        PeerManagerRequestSender::new(network_reqs_tx),
+        ConnectionRequestSender::new(connection_reqs_tx),
+    );
+    for msg in msgs.iter() {
+        // other tests may send messages concurrently
+        let num_sent = num_messages(msg, counters::OUTBOUND_LABEL);
+        network_sender
+            .send_to(PeerId::random(), msg.clone())
+            .unwrap();
+        assert!(num_messages(msg, counters::OUTBOUND_LABEL) > num_sent);
+    }
+
+    // received messages are counted whether they're processed or not
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        for msg in msgs {
+            let num_received = num_messages(&msg, counters::INBOUND_LABEL);
+            coordinator
+                .process_one_message(peer.clone(), msg.clone())
+                .await;
+            assert!(num_messages(&msg, counters::INBOUND_LABEL) > num_received);
+        }
+    });
+}
+
+// Network input delivered to the coordinator by the reordering harness
+#[derive(Clone, Debug)]
+enum DeliveredInput {
+    // chunk response at the given index of the harness' chunk responses
+    Chunk(usize),
+    // commit notification from consensus, which duplicates the previous one as nothing else
+    // commits to the storage
+    Commit,
+}
+
+// Drives a coordinator syncing to version 30 with the given inputs, i.e. chunk responses and
+// commit notifications in any order and possibly duplicated, as the network may deliver them, and
+// then with the chunk responses needed to finish the sync in order. After every input, the
+// committed state has to be monotonic, match the storage and only hold upstream transactions.
+fn check_reordered_inputs(inputs: Vec<DeliveredInput>) {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    let mut lis = vec![];
+    for _ in 0..3 {
+        upstream.commit_new_txns(10);
+        lis.push(upstream.highest_local_li());
+    }
+    let target_li = lis[2].clone();
+
+    // every chunk of 10 transactions, proven against every LI at or beyond its end
+    let mut chunks = vec![];
+    for (i, known_version) in [0, 10, 20].iter().enumerate() {
+        for li in lis[i..].iter() {
+            chunks.push(create_chunk_response(
+                &upstream,
+                *known_version,
+                10,
+                li.clone(),
+            ));
+        }
+    }
+    let in_order_chunks: Vec<_> = [0, 10, 20]
+        .iter()
+        .map(|known_version| {
+            create_chunk_response(&upstream, *known_version, 10, target_li.clone())
+        })
+        .collect();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        let (mut committed_version, mut synced_version) = (0, 0);
+        let inputs = inputs
+            .into_iter()
+            .map(|input| match input {
+                DeliveredInput::Chunk(i) => Some(chunks[i % chunks.len()].clone()),
+                DeliveredInput::Commit => None,
+            })
+            .chain(in_order_chunks.into_iter().map(Some));
+        for input in inputs {
+            match input {
+                Some(chunk) => coordinator.process_one_message(peer.clone(), chunk).await,
+                None => {
+                    let (callback, _callback_rcv) = oneshot::channel();
+                    coordinator
+                        .process_client_message(CoordinatorMessage::Commit(
+                            vec![],
+                            vec![],
+                            callback,
+                        ))
+                        .await;
+                }
+            }
+
+            let state = get_state(&mut coordinator).await;
+            let new_committed_version = state.highest_local_li.ledger_info().version();
+            let new_synced_version = state.highest_version_in_local_storage();
+            assert!(new_committed_version >= committed_version);
+            assert!(new_synced_version >= synced_version);
+            assert!(new_synced_version >= new_committed_version);
+            committed_version = new_committed_version;
+            synced_version = new_synced_version;
+
+            let local_storage = storage.read();
+            let storage_state = local_storage.get_local_storage_state();
+            assert_eq!(storage_state.highest_local_li, state.highest_local_li);
+            assert_eq!(
+                storage_state.highest_version_in_local_storage(),
+                synced_version
+            );
+            assert_eq!(
+                local_storage.get_chunk(1, synced_version, synced_version),
+                upstream.get_chunk(1, synced_version, synced_version)
+            );
+        }
+        assert_eq!(committed_version, 30);
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+    });
+}
+
+fn delivered_inputs_strategy() -> impl Strategy<Value = Vec<DeliveredInput>> {
+    collection::vec(
+        prop_oneof![
+            3 => any::<usize>().prop_map(DeliveredInput::Chunk),
+            1 => Just(DeliveredInput::Commit),
+        ],
+        0..30,
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_reordered_inputs(inputs in delivered_inputs_strategy()) {
+        check_reordered_inputs(inputs);
+    }
+}
+
+#[test]
+fn test_verification_only() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    // the LIs of a signer outside of the validator set fail verification
+    let (other_signers, _, _, _) = SynchronizerEnvHelper::initial_setup(1);
+    let mut forged = MockStorage::new(genesis_li.clone(), other_signers[0].clone());
+    forged.commit_new_txns(10);
+    let forged_li = forged.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            verification_only: true,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        num_sent_messages(&mut network_reqs_rx);
+
+        // verified chunks aren't committed, and the same chunk is requested again
+        for _ in 0..2 {
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(&upstream, 0, 10, target_li.clone()),
+                )
+                .await;
+            assert_eq!(sync_progress(&mut coordinator).await.synced_version, 0);
+            assert_eq!(
+                next_sent_chunk_request(&mut network_reqs_rx).known_version,
+                0
+            );
+            num_sent_messages(&mut network_reqs_rx);
+        }
+        coordinator
+            .process_one_message(peer, create_chunk_response(&forged, 0, 10, forged_li))
+            .await;
+
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::GetVerificationReport(callback))
+            .await;
+        let report = callback_rcv.try_recv().unwrap().unwrap();
+        assert_eq!(report.verified_chunks, 2);
+        assert_eq!(report.failed_chunks, 1);
+        assert_eq!(
+            report.verified_versions.into_iter().collect::<Vec<_>>(),
+            vec![(1, 10)]
+        );
+        assert!(report.last_failure.is_some());
+        let state = get_state(&mut coordinator).await;
+        assert_eq!(state.highest_local_li.ledger_info().version(), 0);
+        assert_eq!(state.highest_version_in_local_storage(), 0);
+    });
+}
+
+#[test]
+fn test_verification_only_stall_escalation() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    for &verification_only in &[false, true] {
+        let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                // chunk requests don't time out during the test
+                tick_interval_ms: 60_000,
+                stall_escalation_widen_peers_ms: 0,
+                stall_escalation_refresh_peers_ms: 100,
+                stall_escalation_switch_networks_ms: 0,
+                stall_escalation_alert_ms: 0,
+                verification_only,
+                ..StateSyncConfig::default()
+            },
+        );
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+        let target_li = target_li.clone();
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li,
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            assert_eq!(num_sent_messages(&mut network_reqs_rx), 1);
+
+            // refreshing the peers drops the in-flight request, which is then sent again before it
+            // timed out. The synced version of a node that only verifies chunks never advances,
+            // so it doesn't escalate
+            std::thread::sleep(Duration::from_millis(250));
+            coordinator
+                .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+                .await;
+            let num_resent = if verification_only { 0 } else { 1 };
+            assert_eq!(num_sent_messages(&mut network_reqs_rx), num_resent);
+        });
+    }
+}
+
+/// Writes the chunks of `upstream` starting after the given versions to `dir`, one file per chunk
+/// named after its first version.
+fn write_chunk_files(
+    dir: &Path,
+    upstream: &MockStorage,
+    known_versions: &[u64],
+    limit: u64,
+    target_li: LedgerInfoWithSignatures,
+) {
+    for known_version in known_versions {
+        let response =
+            match create_chunk_response(upstream, *known_version, limit, target_li.clone()) {
+                StateSynchronizerMsg::GetChunkResponse(response) => response,
+                _ => unreachable!(),
+            };
+        fs::write(
+            dir.join(format!("{:020}", known_version + 1)),
+            lcs::to_bytes(&*response).unwrap(),
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_chunk_source() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let dir = diem_temppath::TempPath::new();
+    dir.create_as_dir().unwrap();
+    write_chunk_files(dir.path(), &upstream, &[0, 3, 6, 9], 3, target_li);
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+    num_sent_messages(&mut network_reqs_rx);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        coordinator
+            .drain_chunk_source(Box::new(FileChunkSource::new(dir.path())))
+            .await;
+        let state = get_state(&mut coordinator).await;
+        assert_eq!(state.highest_version_in_local_storage(), 10);
+        assert_eq!(state.highest_local_li.ledger_info().version(), 10);
+        // no chunk is requested from the network while the source is drained
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+    });
+}
+
+#[test]
+fn test_corrupt_chunk_source() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let dir = diem_temppath::TempPath::new();
+    dir.create_as_dir().unwrap();
+    write_chunk_files(dir.path(), &upstream, &[0, 4, 8], 4, target_li);
+    // truncate the second chunk file
+    let truncated_file = dir.path().join(format!("{:020}", 5));
+    let bytes = fs::read(&truncated_file).unwrap();
+    fs::write(&truncated_file, &bytes[..bytes.len() / 2]).unwrap();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let num_failures = counters::CHUNK_SOURCE_COUNT
+            .with_label_values(&[counters::FAIL_LABEL])
+            .get();
+        coordinator
+            .drain_chunk_source(Box::new(FileChunkSource::new(dir.path())))
+            .await;
+        // the chunks before the truncated file are applied, the rest is left to the network
+        let state = get_state(&mut coordinator).await;
+        assert_eq!(state.highest_version_in_local_storage(), 4);
+        assert!(
+            counters::CHUNK_SOURCE_COUNT
+                .with_label_values(&[counters::FAIL_LABEL])
+                .get()
+                > num_failures
+        );
+    });
+}
+
+#[test]
+fn test_init_progress() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let waypoint_li = upstream.highest_local_li();
+
+    let config = StateSyncConfig {
+        init_progress_interval_chunks: 2,
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::new(MockStorage::new(genesis_li, signers[0].clone()), config)
+            .waypoint(Waypoint::new_any(waypoint_li.ledger_info()))
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (cb_sender, mut cb_receiver) = oneshot::channel();
+        let (progress_sender, mut progress_receiver) = mpsc::channel(10);
+        coordinator
+            .process_client_message(CoordinatorMessage::WaitInitializeWithProgress(
+                cb_sender,
+                progress_sender,
+            ))
+            .await;
+        let progress = |synced_version| InitProgress {
+            synced_version,
+            target_version: 10,
+            epoch: 1,
+        };
+        assert_eq!(progress_receiver.try_next().unwrap(), Some(progress(0)));
+
+        // sync to the waypoint in chunks of 2 versions, the progress is reported every 2 chunks
+        for known_version in (0..10).step_by(2) {
+            let txn_list_with_proof =
+                match create_chunk_response(&upstream, known_version, 2, waypoint_li.clone()) {
+                    StateSynchronizerMsg::GetChunkResponse(response) => {
+                        response.txn_list_with_proof
+                    }
+                    _ => panic!("expected a chunk response"),
+                };
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                        ResponseLedgerInfo::LedgerInfoForWaypoint {
+                            waypoint_li: waypoint_li.clone(),
+                            end_of_epoch_li: None,
+                        },
+                        txn_list_with_proof,
+                    ))),
+                )
+                .await;
+        }
+        assert_eq!(progress_receiver.try_next().unwrap(), Some(progress(4)));
+        assert_eq!(progress_receiver.try_next().unwrap(), Some(progress(8)));
+        // the channel is closed once initialized
+        assert_eq!(progress_receiver.try_next().unwrap(), None);
+        assert_eq!(cb_receiver.try_recv().unwrap(), Some(Ok(())));
+    });
+}
+
+#[test]
+fn test_deduped_chunk_requests() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+    num_sent_messages(&mut network_reqs_rx);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let num_deduped = counters::DEDUPED_CHUNK_REQUESTS.get();
+        // both sync requests trigger a chunk request for the same range and target
+        for _ in 0..2 {
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+        }
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 1);
+        assert!(counters::DEDUPED_CHUNK_REQUESTS.get() > num_deduped);
+    });
+}
+
+#[test]
+fn test_pinned_upstream() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let first_target = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let second_target = upstream.highest_local_li();
+
+    let other_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let pinned_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            pinned_upstream: Some(pinned_peer.clone()),
+            ..StateSyncConfig::default()
+        },
+    );
+    coordinator.process_new_peer(other_peer.clone(), ConnectionOrigin::Outbound);
+    coordinator.process_new_peer(pinned_peer.clone(), ConnectionOrigin::Outbound);
+    num_sent_messages(&mut network_reqs_rx);
+
+    let recipients = |network_reqs_rx: &mut NetworkRequestsReceiver| {
+        let mut recipients = vec![];
+        while let Some(Some(PeerManagerRequest::SendMessage(peer_id, _))) =
+            network_reqs_rx.next().now_or_never()
+        {
+            recipients.push(peer_id);
+        }
+        recipients
+    };
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let sync_request = |target: LedgerInfoWithSignatures| {
+            let (callback, _callback_rcv) = oneshot::channel();
+            CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            }))
+        };
+        coordinator
+            .process_client_message(sync_request(first_target))
+            .await;
+        assert_eq!(
+            recipients(&mut network_reqs_rx),
+            vec![pinned_peer.peer_id()]
+        );
+
+        // no other peer is requested while the pinned peer is gone
+        coordinator.process_lost_peer(pinned_peer.clone(), ConnectionOrigin::Outbound);
+        coordinator
+            .process_client_message(sync_request(second_target.clone()))
+            .await;
+        assert!(recipients(&mut network_reqs_rx).is_empty());
+
+        // the other peers are requested again once unpinned
+        coordinator
+            .process_client_message(CoordinatorMessage::PinPeer(None))
+            .await;
+        coordinator
+            .process_client_message(sync_request(second_target))
+            .await;
+        assert_eq!(recipients(&mut network_reqs_rx), vec![other_peer.peer_id()]);
+    });
+}
+
+#[test]
+fn test_apply_throughput() {
+    let now = SystemTime::now();
+    let mut throughput = ApplyThroughput::new(Duration::from_millis(500));
+    assert_eq!(throughput.update(now), 0);
+    throughput.record(100, now);
+    throughput.record(50, now);
+    // 150 versions over a window of half a second
+    assert_eq!(throughput.update(now), 300);
+
+    // the rate decays to zero once nothing was applied over the window
+    assert_eq!(throughput.update(now + Duration::from_millis(600)), 0);
+
+    let mut disabled = ApplyThroughput::new(Duration::from_millis(0));
+    disabled.record(100, now);
+    assert_eq!(disabled.update(now), 0);
+}
+
+#[test]
+fn test_serve_only() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    storage.commit_new_txns(10);
+    let committed_li = storage.highest_local_li();
+    let mut upstream = MockStorage::new(genesis_li, signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        storage,
+        StateSyncConfig {
+            serve_only: true,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // sync requests fail right away, without requesting any chunk
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            callback_rcv.try_recv().unwrap(),
+            Some(Err(StateSyncError::ServeOnly))
+        );
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // chunk requests are served
+        let request = GetChunkRequest::new(0, 1, 10, TargetType::TargetLedgerInfo(committed_li));
+        coordinator
+            .process_one_message(
+                peer,
+                StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+            )
+            .await;
+        let response = next_sent_chunk_response(&mut network_reqs_rx);
+        assert_eq!(response.txn_list_with_proof.transactions.len(), 10);
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+    });
+}
+
+#[test]
+fn test_epoch_transition_history() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    for _ in 0..3 {
+        upstream.commit_new_txns(10);
+        move_to_next_epoch(&mut upstream);
+    }
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    // only the last two transitions are kept
+    let config = StateSyncConfig {
+        epoch_transition_history_size: 2,
+        ..StateSyncConfig::default()
+    };
+    let (mut coordinator, _network_reqs_rx) =
+        create_coordinator(MockStorage::new(genesis_li, signers[0].clone()), config);
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        let start_time = SystemTime::now();
+        let mut known_version = 0;
+        let mut end_versions = vec![];
+        for epoch in 1..4 {
+            let epoch_ending_li = upstream.get_epoch_changes(epoch).unwrap();
+            let end_version = epoch_ending_li.ledger_info().version();
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(
+                        &upstream,
+                        known_version,
+                        end_version - known_version,
+                        epoch_ending_li,
+                    ),
+                )
+                .await;
+            known_version = end_version;
+            end_versions.push(end_version);
+        }
+
+        let (callback, callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::GetEpochTransitionHistory(callback))
+            .await;
+        let history: Vec<EpochTransition> = callback_rcv.await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|transition| (transition.epoch, transition.version))
+                .collect::<Vec<_>>(),
+            vec![(3, end_versions[1]), (4, end_versions[2])]
+        );
+        assert!(history[0].timestamp >= start_time);
+        assert!(history[0].timestamp <= history[1].timestamp);
+    });
+}
+
+#[test]
+fn test_min_peers_before_sync() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let first_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let second_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    for timeout_ms in vec![60_000, 0] {
+        let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                min_peers_before_sync: 2,
+                min_peers_before_sync_timeout_ms: timeout_ms,
+                ..StateSyncConfig::default()
+            },
+        );
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        let (first_li, second_li, target_li) =
+            (first_li.clone(), second_li.clone(), target_li.clone());
+        let upstream = &upstream;
+        let below_min_peers = counters::SYNC_STARTED_BELOW_MIN_PEERS.get();
+        rt.block_on(async move {
+            // the chunk applied is followed by a background request
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(upstream, 0, 10, first_li),
+                )
+                .await;
+            if timeout_ms == 0 {
+                // the wait timed out, so the node syncs from the single peer
+                assert_eq!(num_sent_messages(&mut network_reqs_rx), 1);
+                assert!(counters::SYNC_STARTED_BELOW_MIN_PEERS.get() > below_min_peers);
+                return;
+            }
+
+            // background sync waits for a second peer, a sync request from consensus doesn't
+            assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: second_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            let request = next_sent_chunk_request(&mut network_reqs_rx);
+            assert_eq!(request.known_version, 10);
+            assert_eq!(
+                request.target(),
+                &TargetType::TargetLedgerInfo(second_li.clone())
+            );
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(upstream, 10, 10, second_li),
+                )
+                .await;
+            assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+            coordinator.process_new_peer(
+                PeerNetworkId(validator_network_id(), PeerId::random()),
+                ConnectionOrigin::Outbound,
+            );
+            coordinator
+                .process_one_message(peer, create_chunk_response(upstream, 20, 10, target_li))
+                .await;
+            let request = next_sent_chunk_request(&mut network_reqs_rx);
+            assert_eq!(request.known_version, 30);
+            assert!(matches!(
+                request.target(),
+                TargetType::HighestAvailable { .. }
+            ));
+        });
+    }
+}
+
+#[test]
+fn test_health_check_progress() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    for staleness_ms in vec![60_000, 0] {
+        let (mut coordinator, _network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                health_check_staleness_ms: staleness_ms,
+                idle_at_tip_debounce_ms: 0,
+                ..StateSyncConfig::default()
+            },
+        );
+        let target_li = target_li.clone();
+        let upstream = &upstream;
+        rt.block_on(async move {
+            // a node at the tip is progressing, however long ago it last committed
+            let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+            coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+            std::thread::sleep(Duration::from_millis(10));
+            let health = health_check(&mut coordinator).await;
+            assert!(health.is_idle_at_tip);
+            assert!(health.is_progressing);
+
+            // a syncing node is only progressing if it committed within the staleness window
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            coordinator
+                .process_one_message(peer, create_chunk_response(upstream, 0, 10, target_li))
+                .await;
+            std::thread::sleep(Duration::from_millis(10));
+            let health = health_check(&mut coordinator).await;
+            assert!(!health.is_idle_at_tip);
+            assert_eq!(health.committed_version, 0);
+            assert_eq!(health.is_progressing, staleness_ms > 0);
+            assert_eq!(health.seconds_since_last_progress, 0);
+        });
+    }
+}
+
+#[test]
+fn test_verified_li_cache_epoch_change() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    upstream.commit_new_txns(20);
+    let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            batch_verify_signatures: true,
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the LI ending the first epoch is verified under the first epoch, and forgotten once
+        // the node moves to the second epoch
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, epoch_ending_li),
+            )
+            .await;
+        assert_eq!(get_state(&mut coordinator).await.epoch(), 2);
+        assert_eq!(coordinator.num_verified_lis(), 0);
+
+        // LIs of the second epoch are verified once under the new epoch, then cached
+        let crypto_calls = coordinator.num_li_crypto_calls();
+        for known_version in vec![10, 20] {
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(&upstream, known_version, 10, target_li.clone()),
+                )
+                .await;
+        }
+        assert_eq!(coordinator.num_li_crypto_calls(), crypto_calls + 1);
+        assert_eq!(coordinator.num_verified_lis(), 1);
+        assert_eq!(
+            get_state(&mut coordinator)
+                .await
+                .highest_local_li
+                .ledger_info()
+                .version(),
+            30
+        );
+    });
+}
+
+#[test]
+fn test_commit_with_outcome() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the commit is ACKed, then its outcome is reported
+        let (callback, mut callback_rcv) = oneshot::channel();
+        let (outcome_callback, mut outcome_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::CommitWithOutcome(
+                vec![],
+                vec![],
+                callback,
+                outcome_callback,
+            ))
+            .await;
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+        assert_eq!(
+            outcome_rcv.try_recv().unwrap().unwrap().unwrap(),
+            CommitOutcome::default()
+        );
+    });
+}
+
+#[test]
+fn test_proactive_li_broadcast() {
+    // Returns the number of committed LIs sent since the last call.
+    fn num_sent_lis(network_reqs_rx: &mut NetworkRequestsReceiver) -> usize {
+        let mut num_lis = 0;
+        while let Some(Some(PeerManagerRequest::SendMessage(_, message))) =
+            network_reqs_rx.next().now_or_never()
+        {
+            if let StateSynchronizerMsg::CommittedLedgerInfo(_) =
+                lcs::from_bytes(&message.mdata).unwrap()
+            {
+                num_lis += 1;
+            }
+        }
+        num_lis
+    }
+
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let upstream_li = upstream.highest_local_li();
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    for proactive_li_broadcast in vec![false, true] {
+        let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+            MockStorage::new(genesis_li.clone(), signers[0].clone()),
+            StateSyncConfig {
+                proactive_li_broadcast,
+                proactive_li_broadcast_interval_ms: 0,
+                ..StateSyncConfig::default()
+            },
+        );
+        let upstream_li = upstream_li.clone();
+        rt.block_on(async move {
+            let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+            coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+            coordinator
+                .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+                .await;
+            assert_eq!(
+                num_sent_lis(&mut network_reqs_rx),
+                proactive_li_broadcast as usize
+            );
+
+            // the broadcast LI of an upstream peer advertises its version
+            coordinator
+                .process_one_message(
+                    peer,
+                    StateSynchronizerMsg::CommittedLedgerInfo(Box::new(upstream_li.clone())),
+                )
+                .await;
+            assert_eq!(health_check(&mut coordinator).await.tip_estimate, Some(20));
+
+            // nodes that are syncing don't broadcast their LI
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: upstream_li,
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            coordinator
+                .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+                .await;
+            assert_eq!(num_sent_lis(&mut network_reqs_rx), 0);
+        });
+    }
+}
+
+#[test]
+fn test_reload_from_storage() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(5);
+    // the storage as it was before syncing, to restore it to
+    let backup = storage.clone();
+    let storage = Arc::new(RwLock::new(storage));
+    let (mut coordinator, mut network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 5, 10, target_li),
+            )
+            .await;
+        assert_eq!(storage.read().version(), 15);
+        // the next chunk is requested from the synced version
+        num_sent_messages(&mut network_reqs_rx);
+
+        // the storage is rolled back by an external process
+        *storage.write() = backup;
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::ReloadFromStorage(callback))
+            .await;
+        let state = callback_rcv.try_recv().unwrap().unwrap().unwrap();
+        assert_eq!(state.highest_version_in_local_storage(), 5);
+        assert_eq!(state.highest_local_li.ledger_info().version(), 5);
+
+        // syncing resumes from the reloaded version
+        coordinator
+            .process_client_message(CoordinatorMessage::TriggerSyncCycle)
+            .await;
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            5
+        );
+    });
+}
+
+#[test]
+fn test_partial_chunk_application() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    for partial_chunk_application in vec![false, true] {
+        let mut storage = MockStorage::new(genesis_li.clone(), signers[0].clone());
+        // the last transactions of the first chunk fail verification
+        storage.set_corrupted_versions(vec![9, 10].into_iter().collect());
+        let storage = Arc::new(RwLock::new(storage));
+        let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::with_shared_storage(
+            storage.clone(),
+            StateSyncConfig {
+                partial_chunk_application,
+                ..StateSyncConfig::default()
+            },
+        )
+        .build();
+        let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+        let target_li = target_li.clone();
+        let upstream = &upstream;
+        rt.block_on(async move {
+            let (callback, _callback_rcv) = oneshot::channel();
+            coordinator
+                .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                    callback,
+                    target: target_li.clone(),
+                    last_progress_tst: SystemTime::now(),
+                    generation: 0,
+                })))
+                .await;
+            num_sent_messages(&mut network_reqs_rx);
+
+            let num_partial_chunks = counters::PARTIAL_CHUNK_APPLIED.get();
+            coordinator
+                .process_one_message(peer, create_chunk_response(upstream, 0, 10, target_li))
+                .await;
+            if !partial_chunk_application {
+                // the whole chunk is rejected
+                assert_eq!(storage.read().version(), 0);
+                return;
+            }
+            // the valid prefix is committed, and only the remainder is requested again
+            assert_eq!(storage.read().version(), 8);
+            assert_eq!(
+                counters::PARTIAL_CHUNK_APPLIED.get(),
+                num_partial_chunks + 1
+            );
+            assert_eq!(
+                next_sent_chunk_request(&mut network_reqs_rx).known_version,
+                8
+            );
+        });
+    }
+}
+
+#[test]
+fn test_mock_time_provider_health_staleness() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let time_provider = Arc::new(MockTimeProvider::new());
+    let (mut coordinator, _network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            health_check_staleness_ms: 60_000,
+            ..StateSyncConfig::default()
+        },
+    )
+    .time_provider(time_provider.clone())
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: time_provider.now(),
+                generation: 0,
+            })))
+            .await;
+        assert!(health_check(&mut coordinator).await.is_progressing);
+
+        // the node goes stale as soon as the clock moves past the staleness window, without
+        // waiting for it
+        time_provider.advance(Duration::from_secs(61));
+        let health = health_check(&mut coordinator).await;
+        assert!(!health.is_progressing);
+        assert_eq!(health.seconds_since_last_progress, 61);
+
+        // a commit makes the node progress again
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 10, target_li))
+            .await;
+        let health = health_check(&mut coordinator).await;
+        assert!(health.is_progressing);
+        assert_eq!(health.seconds_since_last_progress, 0);
+    });
+}
+
+#[test]
+fn test_pinned_waypoint_mismatch() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+
+    // the node pins the end of the first epoch of the chain and syncs to the end of the second one
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    let pinned_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    let waypoint_li = upstream.highest_local_li();
+    let waypoints = vec![
+        Waypoint::new_epoch_boundary(pinned_li.ledger_info()).unwrap(),
+        Waypoint::new_epoch_boundary(waypoint_li.ledger_info()).unwrap(),
+    ];
+
+    // a fork of the first epoch, validly signed by the validators of that epoch, that moves to
+    // another validator set
+    let mut fork = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    fork.commit_new_txns(10);
+    move_to_next_epoch(&mut fork);
+    let fork_li = fork.highest_local_li();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .waypoints(waypoints)
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let chunks = vec![(&fork, fork_li, false), (&upstream, pinned_li, true)];
+        for (chunk_storage, end_of_epoch_li, matches_pinned_waypoint) in chunks {
+            let txn_list_with_proof =
+                match create_chunk_response(chunk_storage, 0, 10, end_of_epoch_li.clone()) {
+                    StateSynchronizerMsg::GetChunkResponse(response) => {
+                        response.txn_list_with_proof
+                    }
+                    _ => panic!("expected a chunk response"),
+                };
+            let num_mismatches = counters::PINNED_WAYPOINT_MISMATCH.get();
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                        ResponseLedgerInfo::LedgerInfoForWaypoint {
+                            waypoint_li: waypoint_li.clone(),
+                            end_of_epoch_li: Some(end_of_epoch_li),
+                        },
+                        txn_list_with_proof,
+                    ))),
+                )
+                .await;
+            if matches_pinned_waypoint {
+                assert_eq!(storage.read().version(), 10);
+            } else {
+                // the fork passes the verification of the highest waypoint and of the signatures,
+                // but mismatches the pinned waypoint it crosses
+                assert_eq!(storage.read().version(), 0);
+                assert_eq!(counters::PINNED_WAYPOINT_MISMATCH.get(), num_mismatches + 1);
+            }
+        }
+    });
+}
+
+#[test]
+fn test_rate_limited_chunk_requests() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let time_provider = Arc::new(MockTimeProvider::new());
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            max_requests_per_sec: Some(1),
+            ..StateSyncConfig::default()
+        },
+    )
+    .time_provider(time_provider.clone())
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // a sync to a version is background sync, unlike a sync request from consensus
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::SyncToVersion(Box::new(
+                VersionSyncRequest {
+                    callback,
+                    version: target_li.ledger_info().version(),
+                    last_progress_tst: time_provider.now(),
+                },
+            )))
+            .await;
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            0
+        );
+
+        // the request following the commit is beyond the limit of one request per second
+        let num_rate_limited = counters::RATE_LIMITED_REQUESTS.get();
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 0, 10, target_li))
+            .await;
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+        assert!(counters::RATE_LIMITED_REQUESTS.get() > num_rate_limited);
+
+        // the deferred request isn't timed out until it can be sent
+        coordinator.check_progress();
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // the next progress check sends it once the bucket is refilled
+        time_provider.advance(Duration::from_secs(1));
+        coordinator.check_progress();
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            10
+        );
+    });
+}
+
+#[test]
+fn test_critical_sync_bypasses_background_throttles() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let time_provider = Arc::new(MockTimeProvider::new());
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            base_backoff_ms: 10_000,
+            chunk_request_timeout_ms: Some(100),
+            max_requests_per_sec: Some(1),
+            ..StateSyncConfig::default()
+        },
+    )
+    .time_provider(time_provider.clone())
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer, ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the first request takes the only token of the rate limit
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: time_provider.now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            0
+        );
+
+        // the peer never responds, so it backs off and the chunk request is re-issued beyond the
+        // rate limit at every tick
+        let num_bypasses = |throttle| {
+            counters::CRITICAL_SYNC_THROTTLE_BYPASSES
+                .with_label_values(&[throttle])
+                .get()
+        };
+        for _ in 0..5 {
+            let num_rate_limit_bypasses = num_bypasses(counters::RATE_LIMIT_LABEL);
+            let num_backoff_bypasses = num_bypasses(counters::BACKOFF_LABEL);
+            time_provider.advance(Duration::from_millis(100));
+            coordinator.check_progress();
+            let request = next_sent_chunk_request(&mut network_reqs_rx);
+            assert_eq!(request.known_version, 0);
+            assert_eq!(
+                request.target(),
+                &TargetType::TargetLedgerInfo(target_li.clone())
+            );
+            assert!(num_bypasses(counters::RATE_LIMIT_LABEL) > num_rate_limit_bypasses);
+            assert!(num_bypasses(counters::BACKOFF_LABEL) > num_backoff_bypasses);
+        }
+    });
+}
+
+#[test]
+fn test_inject_chunk_response() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    )
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let downstream = PeerNetworkId(public_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    coordinator.process_new_peer(downstream.clone(), ConnectionOrigin::Inbound);
+
+    let chunk_response = |known_version| match create_chunk_response(
+        &upstream,
+        known_version,
+        10,
+        target_li.clone(),
+    ) {
+        StateSynchronizerMsg::GetChunkResponse(response) => response,
+        _ => panic!("expected a chunk response"),
+    };
+    let (first_chunk, second_chunk) = (chunk_response(0), chunk_response(10));
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the injected response is committed like one received from the network
+        let (state_sender, state_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::InjectChunkResponse(
+                peer,
+                first_chunk,
+                state_sender,
+            ))
+            .await;
+        assert_eq!(state_rcv.await.unwrap().synced_trees.version(), Some(10));
+
+        // and rejected if it isn't from an upstream peer
+        let (state_sender, state_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::InjectChunkResponse(
+                downstream,
+                second_chunk,
+                state_sender,
+            ))
+            .await;
+        assert_eq!(state_rcv.await.unwrap().synced_trees.version(), Some(10));
+    });
+}
+
+#[test]
+fn test_equivocating_peer() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+    // a fork with a conflicting LI for the same version, signed by other validators
+    let (fork_signers, _) = random_validator_verifier(1, None, true);
+    let mut fork = MockStorage::new(genesis_li.clone(), fork_signers[0].clone());
+    fork.commit_new_txns(20);
+    let fork_li = fork.highest_local_li();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .build();
+    let honest_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let equivocating_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(honest_peer.clone(), ConnectionOrigin::Outbound);
+    coordinator.process_new_peer(equivocating_peer.clone(), ConnectionOrigin::Outbound);
+    let num_equivocations = |peer: &PeerNetworkId| {
+        counters::EQUIVOCATING_PEER
+            .with_label_values(&[
+                &peer.raw_network_id().to_string(),
+                &peer.peer_id().to_string(),
+            ])
+            .get()
+    };
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                honest_peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(storage.read().version(), 10);
+
+        // the conflicting LI is recorded, and the chunk of the fork rejected as its LI doesn't
+        // verify
+        coordinator
+            .process_one_message(
+                equivocating_peer.clone(),
+                create_chunk_response(&fork, 10, 10, fork_li),
+            )
+            .await;
+        assert_eq!(num_equivocations(&equivocating_peer), 1);
+        assert_eq!(storage.read().version(), 10);
+
+        // sync goes on with the honest peer
+        coordinator
+            .process_one_message(
+                honest_peer.clone(),
+                create_chunk_response(&upstream, 10, 10, target_li),
+            )
+            .await;
+        assert_eq!(num_equivocations(&honest_peer), 0);
+        assert_eq!(storage.read().version(), 20);
+    });
+}
+
+#[test]
+fn test_equivocating_peers_with_verified_lis() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    // a fork with a conflicting LI for the same version, signed by the trusted validators
+    let mut fork = upstream.clone();
+    upstream.commit_new_txns(10);
+    fork.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    let fork_li = fork.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let first_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    let second_peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(first_peer.clone(), ConnectionOrigin::Outbound);
+    coordinator.process_new_peer(second_peer.clone(), ConnectionOrigin::Outbound);
+    let num_equivocations = |peer: &PeerNetworkId| {
+        counters::EQUIVOCATING_PEER
+            .with_label_values(&[
+                &peer.raw_network_id().to_string(),
+                &peer.peer_id().to_string(),
+            ])
+            .get()
+    };
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                first_peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li),
+            )
+            .await;
+        let first_peer_score = coordinator.peer_score(&first_peer).unwrap();
+        let second_peer_score = coordinator.peer_score(&second_peer).unwrap();
+
+        // both LIs verify, so there's no telling which peer is honest: both are flagged and
+        // down-ranked
+        coordinator
+            .process_one_message(
+                second_peer.clone(),
+                create_chunk_response(&fork, 10, 10, fork_li),
+            )
+            .await;
+        assert_eq!(num_equivocations(&first_peer), 1);
+        assert_eq!(num_equivocations(&second_peer), 1);
+        assert!(coordinator.peer_score(&first_peer).unwrap() < first_peer_score);
+        assert!(coordinator.peer_score(&second_peer).unwrap() < second_peer_score);
+    });
+}
+
+#[test]
+fn test_chunk_response_cache() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(10);
+    let storage = Arc::new(RwLock::new(storage));
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::with_shared_storage(
+        storage.clone(),
+        StateSyncConfig {
+            chunk_response_cache_bytes: 1_000_000,
+            ..StateSyncConfig::default()
+        },
+    )
+    .build();
+    let request = GetChunkRequest::new(
+        0,
+        1,
+        20,
+        TargetType::HighestAvailable {
+            target_li: None,
+            timeout_ms: 0,
+        },
+    );
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the second of two lagging peers requesting the same chunk is served from the cache
+        let num_hits = counters::CHUNK_RESPONSE_CACHE_HITS.get();
+        let mut responses = vec![];
+        for _ in 0..2 {
+            coordinator
+                .process_one_message(
+                    PeerNetworkId(validator_network_id(), PeerId::random()),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(request.clone())),
+                )
+                .await;
+            responses.push(next_sent_chunk_response(&mut network_reqs_rx));
+        }
+        assert_eq!(counters::CHUNK_RESPONSE_CACHE_HITS.get(), num_hits + 1);
+        assert_eq!(responses[0].response_li.version(), 10);
+        assert_eq!(
+            responses[0].txn_list_with_proof,
+            responses[1].txn_list_with_proof
+        );
+
+        // once the committed LI advances, the chunk is proven relative to the new one
+        storage.write().commit_new_txns(10);
+        coordinator
+            .process_one_message(
+                PeerNetworkId(validator_network_id(), PeerId::random()),
+                StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+            )
+            .await;
+        let response = next_sent_chunk_response(&mut network_reqs_rx);
+        assert_eq!(counters::CHUNK_RESPONSE_CACHE_HITS.get(), num_hits + 1);
+        assert_eq!(response.response_li.version(), 20);
+        assert_eq!(response.txn_list_with_proof.transactions.len(), 20);
+    });
+}
+
+#[test]
+fn test_serve_multiple_chunks_in_response() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut storage = MockStorage::new(genesis_li, signers[0].clone());
+    storage.commit_new_txns(25);
+    let target_li = storage.highest_local_li();
+    let (mut coordinator, mut network_reqs_rx) = create_coordinator(
+        storage,
+        StateSyncConfig {
+            max_chunks_in_response: 3,
+            ..StateSyncConfig::default()
+        },
+    );
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // (requested number of chunks, expected size of each chunk): the chunks stop at the min
+        // of the own and the requested number, and at the version of the response LI
+        for (requested_chunks, chunk_sizes) in
+            vec![(1, vec![10]), (2, vec![10, 10]), (5, vec![10, 10, 5])]
+        {
+            let request =
+                GetChunkRequest::new(0, 1, 10, TargetType::TargetLedgerInfo(target_li.clone()))
+                    .with_max_chunks_in_response(requested_chunks);
+            coordinator
+                .process_one_message(
+                    PeerNetworkId(validator_network_id(), PeerId::random()),
+                    StateSynchronizerMsg::GetChunkRequest(Box::new(request)),
+                )
+                .await;
+            let chunks = next_sent_chunk_response(&mut network_reqs_rx).into_chunks();
+            assert_eq!(
+                chunks
+                    .iter()
+                    .map(|chunk| chunk.txn_list_with_proof.len())
+                    .collect::<Vec<_>>(),
+                chunk_sizes
+            );
+            for (i, chunk) in chunks.iter().enumerate() {
+                assert_eq!(
+                    chunk.txn_list_with_proof.first_transaction_version,
+                    Some(1 + 10 * i as u64)
+                );
+            }
+        }
+    });
+}
+
+#[test]
+fn test_apply_multiple_chunks_in_response() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(30);
+    let target_li = upstream.highest_local_li();
+    let chunks = |known_versions: &[u64]| {
+        let mut chunks = known_versions.iter().map(|known_version| {
+            match create_chunk_response(&upstream, *known_version, 5, target_li.clone()) {
+                StateSynchronizerMsg::GetChunkResponse(response) => response.txn_list_with_proof,
+                _ => panic!("expected a chunk response"),
+            }
+        });
+        let mut response = GetChunkResponse::new(
+            ResponseLedgerInfo::VerifiableLedgerInfo(target_li.clone()),
+            chunks.next().unwrap(),
+        );
+        response.additional_chunks = chunks.collect();
+        StateSynchronizerMsg::GetChunkResponse(Box::new(response))
+    };
+    let (all_valid, second_invalid) = (chunks(&[0, 5, 10]), chunks(&[15, 20, 25]));
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::with_shared_storage(
+        storage.clone(),
+        StateSyncConfig {
+            max_chunks_in_response: 3,
+            ..StateSyncConfig::default()
+        },
+    )
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        let request = next_sent_chunk_request(&mut network_reqs_rx);
+        assert_eq!(request.max_chunks_in_response, 3);
+
+        // the chunks are applied in order, and the next ones requested once, after the last one
+        coordinator
+            .process_one_message(peer.clone(), all_valid)
+            .await;
+        assert_eq!(storage.read().version(), 15);
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            15
+        );
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // the chunks following the first one that fails verification aren't applied
+        storage
+            .write()
+            .set_corrupted_versions(vec![22].into_iter().collect());
+        coordinator.process_one_message(peer, second_invalid).await;
+        assert_eq!(storage.read().version(), 20);
+    });
+}
+
+#[test]
+fn test_epoch_mismatch_chunk() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    move_to_next_epoch(&mut upstream);
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+    let epoch_ending_li = upstream.get_epoch_changes(1).unwrap();
+    let end_version = epoch_ending_li.ledger_info().version();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (mut coordinator, _network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    let num_mismatches = |direction| {
+        counters::EPOCH_MISMATCH_CHUNK
+            .with_label_values(&[direction])
+            .get()
+    };
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // a chunk relative to an LI of the next epoch is rejected as ahead of the trusted epoch
+        let num_ahead = num_mismatches(counters::AHEAD_LABEL);
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 5, target_li),
+            )
+            .await;
+        assert!(num_mismatches(counters::AHEAD_LABEL) > num_ahead);
+        assert_eq!(storage.read().version(), 0);
+
+        // once the node moved to the next epoch, a chunk relative to an LI of the first epoch is
+        // rejected as behind it
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, end_version, epoch_ending_li.clone()),
+            )
+            .await;
+        assert_eq!(get_state(&mut coordinator).await.trusted_epoch.epoch, 2);
+        let num_behind = num_mismatches(counters::BEHIND_LABEL);
+        coordinator
+            .process_one_message(
+                peer,
+                create_chunk_response(&upstream, end_version, 5, epoch_ending_li),
+            )
+            .await;
+        assert!(num_mismatches(counters::BEHIND_LABEL) > num_behind);
+        assert_eq!(storage.read().version(), end_version);
+    });
+}
+
+#[test]
+fn test_sync_ceiling() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(30);
+    let target_li = upstream.highest_local_li();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::with_shared_storage(
+        storage.clone(),
+        StateSyncConfig {
+            chunk_limit: 10,
+            sync_ceiling_version: Some(15),
+            ..StateSyncConfig::default()
+        },
+    )
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // a sync request beyond the ceiling can't be reached
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            callback_rcv.try_recv().unwrap(),
+            Some(Err(StateSyncError::BeyondSyncCeiling {
+                target_version: 30,
+                ceiling_version: 15,
+            }))
+        );
+
+        // a sync request to the ceiling is synced to, with chunks that stop at the ceiling
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::SyncToVersion(Box::new(
+                VersionSyncRequest {
+                    callback,
+                    version: 15,
+                    last_progress_tst: SystemTime::now(),
+                },
+            )))
+            .await;
+        assert_eq!(next_sent_chunk_request(&mut network_reqs_rx).limit, 10);
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(next_sent_chunk_request(&mut network_reqs_rx).limit, 5);
+
+        // a chunk extending past the ceiling is dropped
+        let num_dropped = counters::SYNC_CEILING_DROPPED_CHUNKS.get();
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 10, 10, target_li.clone()),
+            )
+            .await;
+        assert!(counters::SYNC_CEILING_DROPPED_CHUNKS.get() > num_dropped);
+        assert_eq!(storage.read().version(), 10);
+
+        // the node stops at the ceiling, without requesting more chunks
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 5, target_li))
+            .await;
+        assert_eq!(storage.read().version(), 15);
+        assert!(callback_rcv.try_recv().unwrap().unwrap().is_ok());
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+        coordinator.check_progress();
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+    });
+}
+
+#[test]
+fn test_active_target() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let first_target_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let second_target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        assert_eq!(active_target(&mut coordinator).await, None);
+
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: first_target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            active_target(&mut coordinator).await,
+            Some(first_target_li.clone())
+        );
+
+        // there is no active target once the sync request is fulfilled
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, first_target_li),
+            )
+            .await;
+        assert_eq!(active_target(&mut coordinator).await, None);
+
+        // nor once it's cancelled
+        let (callback, _callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: second_target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 1,
+            })))
+            .await;
+        assert_eq!(
+            active_target(&mut coordinator).await,
+            Some(second_target_li)
+        );
+        coordinator
+            .process_client_message(CoordinatorMessage::CancelSync(1))
+            .await;
+        assert_eq!(active_target(&mut coordinator).await, None);
+    });
+}
+
+#[test]
+fn test_chunk_response_size_metrics() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    let network = peer.raw_network_id().to_string();
+    let sender = peer.peer_id().to_string();
+    let labels = [network.as_str(), sender.as_str()];
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the size is recorded on receipt, whether or not the chunk is applied
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li),
+            )
+            .await;
+        let response_bytes = counters::CHUNK_RESPONSE_BYTES.with_label_values(&labels);
+        assert_eq!(response_bytes.get_sample_count(), 1);
+        assert!(response_bytes.get_sample_sum() > 0.0);
+        let response_txns = counters::CHUNK_RESPONSE_TXNS.with_label_values(&labels);
+        assert_eq!(response_txns.get_sample_count(), 1);
+        assert_eq!(response_txns.get_sample_sum(), 10.0);
+    });
+}
+
+#[test]
+fn test_targeted_request_preempts_background_sync() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let first_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let time_provider = Arc::new(MockTimeProvider::new());
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            max_requests_per_sec: Some(1),
+            ..StateSyncConfig::default()
+        },
+    )
+    .time_provider(time_provider.clone())
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        // the background request takes the only token of the rate limit
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, first_li),
+            )
+            .await;
+        let request = next_sent_chunk_request(&mut network_reqs_rx);
+        assert!(matches!(
+            request.target(),
+            TargetType::HighestAvailable { .. }
+        ));
+
+        // the sync request is sent right away anyway, and preempts the background request
+        let num_preempted = counters::PREEMPTED_BACKGROUND_REQUESTS.get();
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: time_provider.now(),
+                generation: 0,
+            })))
+            .await;
+        let request = next_sent_chunk_request(&mut network_reqs_rx);
+        assert_eq!(request.known_version, 10);
+        assert_eq!(
+            request.target(),
+            &TargetType::TargetLedgerInfo(target_li.clone())
+        );
+        assert!(counters::PREEMPTED_BACKGROUND_REQUESTS.get() > num_preempted);
+
+        // the chunks of the sync target are fetched first
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 10, target_li))
+            .await;
+        assert_eq!(callback_rcv.try_recv().unwrap(), Some(Ok(())));
+    });
+}
+
+#[test]
+fn test_preempted_request_late_response() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(10);
+    let first_li = upstream.highest_local_li();
+    upstream.commit_new_txns(10);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, mut network_reqs_rx) = CoordinatorBuilder::new(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig::default(),
+    )
+    .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, first_li),
+            )
+            .await;
+        let request = next_sent_chunk_request(&mut network_reqs_rx);
+        assert!(matches!(
+            request.target(),
+            TargetType::HighestAvailable { .. }
+        ));
+
+        // the background request is preempted by the sync request, which is answered first
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 10, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(callback_rcv.try_recv().unwrap(), Some(Ok(())));
+        let peer_score = coordinator.peer_score(&peer).unwrap();
+
+        // the late response to the preempted request doesn't penalize the peer
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 10, 10, target_li),
+            )
+            .await;
+        assert_eq!(coordinator.peer_score(&peer).unwrap(), peer_score);
+    });
+}
+
+#[test]
+fn test_max_epoch_changes_per_session() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+
+    // a long chain of tiny epochs
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    for _ in 0..10 {
+        upstream.commit_new_txns(1);
+        move_to_next_epoch(&mut upstream);
+    }
+    upstream.commit_new_txns(1);
+    let target_li = upstream.highest_local_li();
+
+    let (mut coordinator, _network_reqs_rx) = create_coordinator(
+        MockStorage::new(genesis_li, signers[0].clone()),
+        StateSyncConfig {
+            max_epoch_changes_per_session: Some(3),
+            ..StateSyncConfig::default()
+        },
+    );
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: SystemTime::now(),
+                generation: 0,
+            })))
+            .await;
+
+        // the sync request is aborted by the epoch change beyond the max
+        let mut known_version = 0;
+        for epoch in 1..=4 {
+            assert_eq!(callback_rcv.try_recv().unwrap(), None);
+            let epoch_ending_li = upstream.get_epoch_changes(epoch).unwrap();
+            let end_version = epoch_ending_li.ledger_info().version();
+            coordinator
+                .process_one_message(
+                    peer.clone(),
+                    create_chunk_response(
+                        &upstream,
+                        known_version,
+                        end_version - known_version,
+                        epoch_ending_li,
+                    ),
+                )
+                .await;
+            known_version = end_version;
+        }
+        assert_eq!(get_state(&mut coordinator).await.trusted_epoch.epoch, 5);
+        assert_eq!(
+            callback_rcv.try_recv().unwrap(),
+            Some(Err(StateSyncError::TooManyEpochChanges {
+                epoch_changes: 4,
+                max_epoch_changes: 3,
+            }))
+        );
+
+        // the epoch changes are counted from zero again for the next sync request
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li,
+                last_progress_tst: SystemTime::now(),
+                generation: 1,
+            })))
+            .await;
+        let epoch_ending_li = upstream.get_epoch_changes(5).unwrap();
+        let end_version = epoch_ending_li.ledger_info().version();
+        coordinator
+            .process_one_message(
+                peer,
+                create_chunk_response(
+                    &upstream,
+                    known_version,
+                    end_version - known_version,
+                    epoch_ending_li,
+                ),
+            )
+            .await;
+        assert_eq!(get_state(&mut coordinator).await.trusted_epoch.epoch, 6);
+        assert_eq!(callback_rcv.try_recv().unwrap(), None);
+    });
+}
+
+#[test]
+fn test_pause_and_resume() {
+    let (signers, validator_info, _keys, _addrs) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let mut upstream = MockStorage::new(genesis_li.clone(), signers[0].clone());
+    upstream.commit_new_txns(20);
+    let target_li = upstream.highest_local_li();
+
+    let storage = Arc::new(RwLock::new(MockStorage::new(
+        genesis_li,
+        signers[0].clone(),
+    )));
+    let time_provider = Arc::new(MockTimeProvider::new());
+    let (mut coordinator, mut network_reqs_rx) =
+        CoordinatorBuilder::with_shared_storage(storage.clone(), StateSyncConfig::default())
+            .time_provider(time_provider.clone())
+            .build();
+    let peer = PeerNetworkId(validator_network_id(), PeerId::random());
+    coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        let (callback, mut callback_rcv) = oneshot::channel();
+        coordinator
+            .process_client_message(CoordinatorMessage::Request(Box::new(SyncRequest {
+                callback,
+                target: target_li.clone(),
+                last_progress_tst: time_provider.now(),
+                generation: 0,
+            })))
+            .await;
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            0
+        );
+
+        // while paused, the chunk responses are queued and no chunk is requested, even once the
+        // in-flight request timed out
+        coordinator
+            .process_client_message(CoordinatorMessage::Pause)
+            .await;
+        assert_eq!(counters::SYNC_PAUSED.get(), 1);
+        coordinator
+            .process_one_message(
+                peer.clone(),
+                create_chunk_response(&upstream, 0, 10, target_li.clone()),
+            )
+            .await;
+        assert_eq!(coordinator.num_pending_chunks(), 1);
+        assert_eq!(storage.read().version(), 0);
+        time_provider.advance(Duration::from_secs(60));
+        coordinator.check_progress();
+        assert_eq!(num_sent_messages(&mut network_reqs_rx), 0);
+
+        // on resume, the queued chunk is applied and syncing continues from it
+        coordinator
+            .process_client_message(CoordinatorMessage::Resume)
+            .await;
+        assert_eq!(counters::SYNC_PAUSED.get(), 0);
+        assert_eq!(coordinator.num_pending_chunks(), 0);
+        assert_eq!(storage.read().version(), 10);
+        assert_eq!(
+            next_sent_chunk_request(&mut network_reqs_rx).known_version,
+            10
+        );
+        coordinator
+            .process_one_message(peer, create_chunk_response(&upstream, 10, 10, target_li))
+            .await;
+        assert_eq!(callback_rcv.try_recv().unwrap(), Some(Ok(())));
+    });
+}
@@ -4,12 +4,13 @@
 use crate::{
     chunk_request::{GetChunkRequest, TargetType},
     chunk_response::{GetChunkResponse, ResponseLedgerInfo},
-    coordinator::SyncCoordinator,
+    coordinator::{SyncCoordinator, SyncCoordinatorDeps},
     network::{StateSynchronizerMsg, StateSynchronizerSender},
     tests::{
         helpers::{MockExecutorProxy, SynchronizerEnvHelper},
         mock_storage::MockStorage,
     },
+    upstream_resolver::StaticUpstreamResolver,
 };
 use channel::{diem_channel, message_queues::QueueStyle};
 use diem_config::{
@@ -18,10 +19,11 @@ use diem_config::{
 };
 use diem_infallible::RwLock;
 use diem_types::{
-    ledger_info::LedgerInfoWithSignatures, transaction::TransactionListWithProof,
-    waypoint::Waypoint, PeerId,
+    epoch_change::Verifier, ledger_info::LedgerInfoWithSignatures,
+    transaction::TransactionListWithProof, waypoint::Waypoint, PeerId,
 };
 use futures::channel::mpsc;
+use netcore::transport::ConnectionOrigin;
 use network::{
     peer_manager::{ConnectionRequestSender, PeerManagerRequestSender},
     protocols::network::NewNetworkSender,
@@ -44,9 +46,77 @@ proptest! {
     fn test_state_sync_msg_fuzzer(input in state_sync_msg_strategy()) {
         test_state_sync_msg_fuzzer_impl(input);
     }
+
+    #[test]
+    fn test_chunk_response_fuzzer(input in chunk_response_bytes_strategy()) {
+        test_chunk_response_fuzzer_impl(&input);
+    }
 }
 
 pub fn test_state_sync_msg_fuzzer_impl(msg: StateSynchronizerMsg) {
+    process_fuzzed_message(msg, false);
+}
+
+/// Deserializes a chunk response from arbitrary bytes, like a malformed response received from the
+/// network, and verifies it: the verification has to fail cleanly instead of panicking.
+pub fn test_chunk_response_fuzzer_impl(data: &[u8]) {
+    let response: GetChunkResponse = match lcs::from_bytes(data) {
+        Ok(response) => response,
+        Err(_) => return,
+    };
+
+    // the LIs and the proof are verified against the genesis epoch, whether the LIs are valid
+    // or not, so that the proof verification is fuzzed too
+    let (_, validator_info, _, _) = SynchronizerEnvHelper::initial_setup(1);
+    let genesis_li = SynchronizerEnvHelper::genesis_li(&validator_info);
+    let trusted_epoch = genesis_li
+        .ledger_info()
+        .next_epoch_state()
+        .expect("the genesis LI ends an epoch");
+    let txn_list_with_proof = &response.txn_list_with_proof;
+    let lis = match &response.response_li {
+        ResponseLedgerInfo::VerifiableLedgerInfo(li) => vec![li],
+        ResponseLedgerInfo::ProgressiveLedgerInfo {
+            target_li,
+            highest_li,
+        } => std::iter::once(target_li).chain(highest_li).collect(),
+        ResponseLedgerInfo::LedgerInfoForWaypoint {
+            waypoint_li,
+            end_of_epoch_li,
+        } => std::iter::once(waypoint_li)
+            .chain(end_of_epoch_li)
+            .collect(),
+    };
+    for li in lis {
+        let _ = trusted_epoch.verify(li);
+        let _ = txn_list_with_proof.verify(
+            li.ledger_info(),
+            txn_list_with_proof.first_transaction_version,
+        );
+    }
+
+    // and so is handling the response by the coordinator, from a known upstream peer
+    process_fuzzed_message(
+        StateSynchronizerMsg::GetChunkResponse(Box::new(response)),
+        true,
+    );
+}
+
+pub fn chunk_response_bytes_strategy() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        any::<GetChunkResponse>().prop_map(|response| lcs::to_bytes(&response).unwrap()),
+        // truncated responses
+        (any::<GetChunkResponse>(), any::<prop::sample::Index>()).prop_map(|(response, index)| {
+            let bytes = lcs::to_bytes(&response).unwrap();
+            bytes[..index.index(bytes.len())].to_vec()
+        }),
+        prop::collection::vec(any::<u8>(), 0..1024),
+    ]
+}
+
+// Processes `msg` from a peer, which is a connected upstream peer of the coordinator if
+// `from_upstream_peer`
+fn process_fuzzed_message(msg: StateSynchronizerMsg, from_upstream_peer: bool) {
     // start up coordinator
     let (_coordinator_sender, coordinator_receiver) = mpsc::unbounded();
     let (mempool_sender, _mempool_receiver) = mpsc::channel(1_024);
@@ -76,20 +146,23 @@ pub fn test_state_sync_msg_fuzzer_impl(msg: StateSynchronizerMsg) {
         mempool_sender,
         network_senders,
         RoleType::Validator,
-        Waypoint::default(),
+        vec![Waypoint::default()],
         config.state_sync,
-        config.upstream,
+        Box::new(StaticUpstreamResolver::new(config.upstream)),
         MockExecutorProxy::new(SynchronizerEnvHelper::default_handler(), storage_proxy),
         initial_state,
+        SyncCoordinatorDeps::default(),
     );
     let mut rt = tokio::runtime::Builder::new()
         .basic_scheduler()
         .build()
         .unwrap();
+    let peer = PeerNetworkId(node_network_id, *PEER_ID);
+    if from_upstream_peer {
+        coordinator.process_new_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
     rt.block_on(async move {
-        coordinator
-            .process_one_message(PeerNetworkId(node_network_id, *PEER_ID), msg)
-            .await;
+        coordinator.process_one_message(peer, msg).await;
     });
 }
 
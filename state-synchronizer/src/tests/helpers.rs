@@ -2,9 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    executor_proxy::ExecutorProxyTrait, tests::mock_storage::MockStorage, SynchronizerState,
+    executor_proxy::ExecutorProxyTrait, tests::mock_storage::MockStorage, CommitOutcome,
+    SynchronizerState,
 };
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use diem_config::config::HANDSHAKE_VERSION;
 use diem_crypto::{hash::ACCUMULATOR_PLACEHOLDER_HASH, test_utils::TEST_SEED, x25519, Uniform};
 use diem_infallible::RwLock;
@@ -103,6 +104,19 @@ impl MockExecutorProxy {
     pub(crate) fn new(handler: MockRpcHandler, storage: Arc<RwLock<MockStorage>>) -> Self {
         Self { handler, storage }
     }
+
+    // Returns the number of transactions at the start of the chunk that pass verification, i.e.
+    // that aren't at a corrupted version of the storage
+    fn num_valid_txns(&self, txn_list_with_proof: &TransactionListWithProof) -> usize {
+        let first_version = match txn_list_with_proof.first_transaction_version {
+            Some(first_version) => first_version,
+            None => return 0,
+        };
+        let storage = self.storage.read();
+        (first_version..first_version + txn_list_with_proof.len() as u64)
+            .take_while(|version| !storage.is_corrupted(*version))
+            .count()
+    }
 }
 
 impl ExecutorProxyTrait for MockExecutorProxy {
@@ -115,15 +129,59 @@ impl ExecutorProxyTrait for MockExecutorProxy {
         txn_list_with_proof: TransactionListWithProof,
         ledger_info_with_sigs: LedgerInfoWithSignatures,
         intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
-    ) -> Result<()> {
+    ) -> Result<Vec<ContractEvent>> {
+        ensure!(
+            self.num_valid_txns(&txn_list_with_proof) == txn_list_with_proof.len(),
+            "Chunk has transactions that fail verification"
+        );
         self.storage.write().add_txns_with_li(
             txn_list_with_proof.transactions,
             ledger_info_with_sigs,
             intermediate_end_of_epoch_li,
+        )?;
+        Ok(vec![])
+    }
+
+    fn verify_chunk(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        verified_target_li: &LedgerInfoWithSignatures,
+    ) -> Result<()> {
+        // the mock proofs don't prove anything: only check the chunk is covered by the LI
+        let last_version = txn_list_with_proof
+            .first_transaction_version
+            .map(|first_version| first_version + txn_list_with_proof.len() as u64 - 1);
+        ensure!(
+            last_version.map_or(false, |version| version
+                <= verified_target_li.ledger_info().version()),
+            "Chunk isn't covered by LI {}",
+            verified_target_li
+        );
+        ensure!(
+            self.num_valid_txns(txn_list_with_proof) == txn_list_with_proof.len(),
+            "Chunk has transactions that fail verification"
         );
         Ok(())
     }
 
+    fn verified_chunk_prefix(
+        &self,
+        txn_list_with_proof: &TransactionListWithProof,
+        _verified_target_li: &LedgerInfoWithSignatures,
+    ) -> Result<Option<TransactionListWithProof>> {
+        let num_valid_txns = self.num_valid_txns(txn_list_with_proof);
+        let first_version = match txn_list_with_proof.first_transaction_version {
+            Some(first_version) if num_valid_txns > 0 => first_version,
+            _ => return Ok(None),
+        };
+        Ok(Some(TransactionListWithProof::new(
+            txn_list_with_proof.transactions[..num_valid_txns].to_vec(),
+            None,
+            Some(first_version),
+            MockStorage::chunk_proof(first_version),
+        )))
+    }
+
     fn get_chunk(
         &self,
         known_version: u64,
@@ -135,12 +193,9 @@ impl ExecutorProxyTrait for MockExecutorProxy {
             .read()
             .get_chunk(known_version + 1, limit, target_version);
         let first_txn_version = txns.first().map(|_| known_version + 1);
-        let txns_with_proof = TransactionListWithProof::new(
-            txns,
-            None,
-            first_txn_version,
-            TransactionListProof::new_empty(),
-        );
+        let proof = first_txn_version
+            .map_or_else(TransactionListProof::new_empty, MockStorage::chunk_proof);
+        let txns_with_proof = TransactionListWithProof::new(txns, None, first_txn_version, proof);
         (self.handler)(txns_with_proof)
     }
 
@@ -161,7 +216,14 @@ impl ExecutorProxyTrait for MockExecutorProxy {
         Ok(())
     }
 
-    fn publish_on_chain_config_updates(&mut self, _events: Vec<ContractEvent>) -> Result<()> {
+    fn publish_on_chain_config_updates(
+        &mut self,
+        _events: Vec<ContractEvent>,
+    ) -> Result<CommitOutcome> {
+        Ok(CommitOutcome::default())
+    }
+
+    fn publish_deferred_on_chain_config_updates(&mut self) -> Result<()> {
         Ok(())
     }
 }
@@ -2,11 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    coordinator::SyncCoordinatorDeps,
     network::{StateSynchronizerEvents, StateSynchronizerMsg, StateSynchronizerSender},
     tests::{
         helpers::{MockExecutorProxy, MockRpcHandler, SynchronizerEnvHelper},
         mock_storage::MockStorage,
     },
+    upstream_resolver::StaticUpstreamResolver,
     StateSyncClient, StateSynchronizer,
 };
 use anyhow::{bail, Result};
@@ -272,10 +274,11 @@ impl SynchronizerEnv {
             network_handles,
             mempool_channel,
             role,
-            waypoint,
+            vec![waypoint],
             &config.state_sync,
-            config.upstream,
+            Box::new(StaticUpstreamResolver::new(config.upstream)),
             MockExecutorProxy::new(handler, storage_proxy.clone()),
+            SyncCoordinatorDeps::default(),
         );
         self.mempools
             .push(MockSharedMempool::new(Some(mempool_requests)));
@@ -456,7 +459,7 @@ fn check_chunk_request(msg: StateSynchronizerMsg, known_version: u64, target_ver
             assert_eq!(req.known_version, known_version);
             assert_eq!(req.target().version(), target_version);
         }
-        StateSynchronizerMsg::GetChunkResponse(_) => {
+        _ => {
             panic!("received chunk response when expecting chunk request");
         }
     }
@@ -469,9 +472,6 @@ fn check_chunk_response(
     chunk_length: usize,
 ) {
     match msg {
-        StateSynchronizerMsg::GetChunkRequest(_) => {
-            panic!("received chunk response when expecting chunk request");
-        }
         StateSynchronizerMsg::GetChunkResponse(resp) => {
             assert_eq!(resp.response_li.version(), response_li_version);
             assert_eq!(
@@ -480,6 +480,9 @@ fn check_chunk_response(
             );
             assert_eq!(resp.txn_list_with_proof.transactions.len(), chunk_length)
         }
+        _ => {
+            panic!("received another message when expecting chunk response");
+        }
     }
 }
 
@@ -1374,3 +1377,31 @@ fn test_multicast_failover() {
     env.assert_no_message_sent(fn_0_second);
     env.assert_no_message_sent(fn_0_public);
 }
+
+#[test]
+fn test_shutdown() {
+    let mut env = SynchronizerEnv::new(2);
+    env.start_next_synchronizer(
+        SynchronizerEnv::default_handler(),
+        RoleType::Validator,
+        Waypoint::default(),
+        false,
+        None,
+    );
+    env.start_next_synchronizer(
+        SynchronizerEnv::default_handler(),
+        RoleType::Validator,
+        Waypoint::default(),
+        false,
+        None,
+    );
+    env.commit(0, 20);
+    env.sync_to(1, env.latest_li(0));
+
+    // the coordinator acknowledges the shutdown and stops serving its clients
+    let synchronizer = env.synchronizers.pop().unwrap();
+    assert!(synchronizer.shutdown().is_ok());
+    env.commit(0, 30);
+    assert!(block_on(env.clients[1].sync_to(env.latest_li(0))).is_err());
+    assert_eq!(env.latest_li(1).ledger_info().version(), 20);
+}
@@ -15,13 +15,17 @@ use diem_types::{
     transaction::{authenticator::AuthenticationKey, SignedTransaction},
 };
 use diem_types::{
-    epoch_state::EpochState, ledger_info::LedgerInfoWithSignatures, transaction::Transaction,
+    epoch_state::EpochState,
+    ledger_info::LedgerInfoWithSignatures,
+    proof::{TransactionAccumulatorRangeProof, TransactionListProof},
+    transaction::Transaction,
     validator_signer::ValidatorSigner,
 };
 use executor_types::ExecutedTrees;
 #[cfg(test)]
 use std::collections::BTreeMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 #[cfg(test)]
 use transaction_builder::encode_peer_to_peer_with_metadata_script;
 #[cfg(test)]
@@ -42,6 +46,16 @@ pub struct MockStorage {
     signer: ValidatorSigner,
     // A validator verifier of the latest epoch
     epoch_state: EpochState,
+    // if set, writes are silently dropped to mock a faulty storage
+    drop_writes: bool,
+    // duration every write takes, to mock a slow storage
+    write_delay: Duration,
+    // if set, the next write only persists the given number of transactions, without the LI, and
+    // fails, to mock a storage failing mid-commit
+    partial_write: Option<usize>,
+    // versions whose synced transactions fail verification, to mock peers serving invalid
+    // transactions
+    corrupted_versions: HashSet<u64>,
 }
 
 impl MockStorage {
@@ -52,14 +66,43 @@ impl MockStorage {
         ledger_infos.insert(0, genesis_li);
         Self {
             transactions: vec![],
-            synced_trees: ExecutedTrees::new_empty(),
+            // the genesis transaction is the only leaf of the accumulator
+            synced_trees: ExecutedTrees::new(HashValue::zero(), vec![HashValue::zero()], 1),
             ledger_infos,
             epoch_num,
             signer,
             epoch_state,
+            drop_writes: false,
+            write_delay: Duration::default(),
+            partial_write: None,
+            corrupted_versions: HashSet::new(),
         }
     }
 
+    #[cfg(test)]
+    pub fn set_drop_writes(&mut self, drop_writes: bool) {
+        self.drop_writes = drop_writes;
+    }
+
+    #[cfg(test)]
+    pub fn set_write_delay(&mut self, write_delay: Duration) {
+        self.write_delay = write_delay;
+    }
+
+    #[cfg(test)]
+    pub fn fail_next_write_after(&mut self, num_txns: usize) {
+        self.partial_write = Some(num_txns);
+    }
+
+    #[cfg(test)]
+    pub fn set_corrupted_versions(&mut self, versions: HashSet<u64>) {
+        self.corrupted_versions = versions;
+    }
+
+    pub fn is_corrupted(&self, version: u64) -> bool {
+        self.corrupted_versions.contains(&version)
+    }
+
     fn add_txns(&mut self, txns: &mut Vec<Transaction>) {
         self.transactions.append(txns);
         let num_leaves = self.transactions.len() + 1;
@@ -127,20 +170,42 @@ impl MockStorage {
         res
     }
 
+    // Returns an empty proof for a chunk starting at `first_txn_version`, whose left side is
+    // consistent with the dummy transaction accumulator of the storage.
+    pub fn chunk_proof(first_txn_version: u64) -> TransactionListProof {
+        let left_siblings = vec![HashValue::zero(); first_txn_version.count_ones() as usize];
+        TransactionListProof::new(
+            TransactionAccumulatorRangeProof::new(left_siblings, vec![]),
+            vec![],
+        )
+    }
+
     pub fn add_txns_with_li(
         &mut self,
         mut transactions: Vec<Transaction>,
         verified_target_li: LedgerInfoWithSignatures,
         intermediate_end_of_epoch_li: Option<LedgerInfoWithSignatures>,
-    ) {
+    ) -> Result<()> {
+        std::thread::sleep(self.write_delay);
+        if self.drop_writes {
+            return Ok(());
+        }
+        if let Some(num_txns) = self.partial_write.take() {
+            transactions.truncate(num_txns);
+            self.add_txns(&mut transactions);
+            bail!(
+                "Injected failure after persisting {} transactions",
+                num_txns
+            );
+        }
         self.add_txns(&mut transactions);
         if let Some(li) = intermediate_end_of_epoch_li {
             self.epoch_num = li.ledger_info().epoch() + 1;
             self.ledger_infos.insert(li.ledger_info().epoch(), li);
-            return;
+            return Ok(());
         }
         if verified_target_li.ledger_info().epoch() != self.epoch_num() {
-            return;
+            return Ok(());
         }
 
         // store ledger info only if version matches last tx
@@ -154,16 +219,28 @@ impl MockStorage {
                 self.epoch_state = next_epoch_state.clone();
             }
         }
+        Ok(())
     }
 
     // Generate new dummy txns and updates the LI
     // with the version corresponding to the new transactions, signed by this storage signer.
     #[cfg(test)]
     pub fn commit_new_txns(&mut self, num_txns: u64) -> (Vec<Transaction>, Vec<SignedTransaction>) {
+        self.commit_new_txns_with_metadata(num_txns, 0)
+    }
+
+    // Same as `commit_new_txns`, with transactions carrying `metadata_bytes` bytes of metadata,
+    // e.g. to generate oversized transactions.
+    #[cfg(test)]
+    pub fn commit_new_txns_with_metadata(
+        &mut self,
+        num_txns: u64,
+        metadata_bytes: usize,
+    ) -> (Vec<Transaction>, Vec<SignedTransaction>) {
         let mut committed_txns = vec![];
         let mut signed_txns = vec![];
         for _ in 0..num_txns {
-            let txn = Self::gen_mock_user_txn();
+            let txn = Self::gen_mock_user_txn(metadata_bytes);
             self.add_txns(&mut vec![txn.clone()]);
             committed_txns.push(txn.clone());
             if let Transaction::UserTransaction(signed_txn) = txn {
@@ -175,14 +252,14 @@ impl MockStorage {
     }
 
     #[cfg(test)]
-    fn gen_mock_user_txn() -> Transaction {
+    fn gen_mock_user_txn(metadata_bytes: usize) -> Transaction {
         let sender = AccountAddress::random();
         let receiver = AuthenticationKey::random();
         let program = encode_peer_to_peer_with_metadata_script(
             xus_tag(),
             receiver.derived_address(),
             1,
-            vec![],
+            vec![0; metadata_bytes],
             vec![],
         );
         Transaction::UserTransaction(get_test_signed_txn(
@@ -1,6 +1,8 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(test)]
+mod coordinator_tests;
 mod helpers;
 #[cfg(test)]
 mod integration_tests;
@@ -1,13 +1,18 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::executor_proxy::{ExecutorProxy, ExecutorProxyTrait};
+use crate::{
+    executor_proxy::{ExecutorProxy, ExecutorProxyTrait},
+    CommitOutcome,
+};
+use channel::diem_channel::Receiver;
 use compiled_stdlib::transaction_scripts::StdlibScript;
+use diem_config::config::StateSyncConfig;
 use diem_crypto::{ed25519::*, HashValue, PrivateKey, Uniform};
 use diem_types::{
     account_config::{diem_root_address, xus_tag},
     block_metadata::BlockMetadata,
-    on_chain_config::{OnChainConfig, VMPublishingOption},
+    on_chain_config::{OnChainConfig, OnChainConfigPayload, VMPublishingOption},
     transaction::{Transaction, WriteSetPayload},
 };
 use diem_vm::DiemVM;
@@ -44,7 +49,12 @@ fn test_on_chain_config_pub_sub() {
 
     let mut block_executor = Box::new(Executor::<DiemVM>::new(db_rw.clone()));
     let chunk_executor = Box::new(Executor::<DiemVM>::new(db_rw));
-    let mut executor_proxy = ExecutorProxy::new(db, chunk_executor, vec![subscription]);
+    let mut executor_proxy = ExecutorProxy::new(
+        db,
+        chunk_executor,
+        vec![subscription],
+        StateSyncConfig::default().max_reconfig_notifications_per_commit,
+    );
 
     assert!(
         reconfig_receiver
@@ -62,9 +72,10 @@ fn test_on_chain_config_pub_sub() {
     ////////////////////////////////////////////////////////
     // Case 1: don't publish for no reconfiguration event //
     ////////////////////////////////////////////////////////
-    executor_proxy
+    let outcome = executor_proxy
         .publish_on_chain_config_updates(vec![])
         .expect("failed to publish on-chain configs");
+    assert_eq!(outcome, CommitOutcome::default());
 
     assert_eq!(
         reconfig_receiver.select_next_some().now_or_never(),
@@ -123,9 +134,12 @@ fn test_on_chain_config_pub_sub() {
         !reconfig_events.is_empty(),
         "expected reconfig events from executor commit"
     );
-    executor_proxy
+    let outcome = executor_proxy
         .publish_on_chain_config_updates(reconfig_events)
         .expect("failed to publish on-chain configs");
+    // the subscription is notified of the changed config, not of the events
+    assert_eq!(outcome.notified_subscriptions, vec!["test".to_string()]);
+    assert_eq!(outcome.num_events_consumed, 0);
 
     let receive_reconfig = async {
         let payload = reconfig_receiver.select_next_some().await;
@@ -287,3 +301,106 @@ fn test_on_chain_config_pub_sub() {
 
     assert!(executor_proxy.get_epoch_ending_ledger_info(3).is_err());
 }
+
+// Returns the number of receivers with a pending reconfig notification, consuming the notifications
+fn num_notified(receivers: &mut [Receiver<(), OnChainConfigPayload>]) -> usize {
+    receivers
+        .iter_mut()
+        .filter_map(|receiver| receiver.select_next_some().now_or_never())
+        .count()
+}
+
+#[test]
+fn test_deferred_reconfig_notifications() {
+    // set up more reconfig subscriptions than can be notified by a single commit
+    let num_subscriptions = 10;
+    let max_notifications_per_commit = 3;
+    let (subscriptions, mut reconfig_receivers): (Vec<_>, Vec<_>) = (0..num_subscriptions)
+        .map(|i| {
+            ReconfigSubscription::subscribe_all(
+                &format!("test_{}", i),
+                vec![VMPublishingOption::CONFIG_ID],
+                vec![],
+            )
+        })
+        .unzip();
+
+    let (genesis, validators) = vm_genesis::test_genesis_change_set_and_validators(Some(1));
+    let genesis_key = vm_genesis::GENESIS_KEYPAIR.0.clone();
+    let genesis_txn = Transaction::GenesisTransaction(WriteSetPayload::Direct(genesis));
+    let db_path = diem_temppath::TempPath::new();
+    db_path.create_as_dir().unwrap();
+    let (db, db_rw) = DbReaderWriter::wrap(DiemDB::new_for_test(db_path.path()));
+    bootstrap_genesis::<DiemVM>(&db_rw, &genesis_txn).unwrap();
+
+    let mut block_executor = Box::new(Executor::<DiemVM>::new(db_rw.clone()));
+    let chunk_executor = Box::new(Executor::<DiemVM>::new(db_rw));
+    let mut executor_proxy = ExecutorProxy::new(
+        db,
+        chunk_executor,
+        subscriptions,
+        max_notifications_per_commit,
+    );
+    executor_proxy
+        .load_on_chain_configs()
+        .expect("failed to load on-chain configs");
+
+    // the initial config notification isn't subject to the budget
+    assert_eq!(num_notified(&mut reconfig_receivers), num_subscriptions);
+
+    // commit a block that changes the subscribed config
+    let txn1 = encode_block_prologue_script(gen_block_metadata(1, validators[0].owner_address));
+    let txn2 = get_test_signed_transaction(
+        diem_root_address(),
+        /* sequence_number = */ 1,
+        genesis_key.clone(),
+        genesis_key.public_key(),
+        Some(encode_add_to_script_allow_list_script(
+            HashValue::sha3_256_of(&[]).to_vec(),
+            0,
+        )),
+    );
+    let block1_id = gen_block_id(1);
+    let parent_block_id = block_executor.committed_block_id();
+    let output = block_executor
+        .execute_block((block1_id, vec![txn1, txn2]), parent_block_id)
+        .expect("failed to execute block");
+    let ledger_info_with_sigs = gen_ledger_info_with_sigs(1, output, block1_id, vec![]);
+    let (_, reconfig_events) = block_executor
+        .commit_blocks(vec![block1_id], ledger_info_with_sigs)
+        .unwrap();
+    assert!(
+        !reconfig_events.is_empty(),
+        "expected reconfig events from executor commit"
+    );
+
+    // the commit only notifies as many subscriptions as its budget allows
+    executor_proxy
+        .publish_on_chain_config_updates(reconfig_events)
+        .expect("failed to publish on-chain configs");
+    assert_eq!(
+        num_notified(&mut reconfig_receivers),
+        max_notifications_per_commit
+    );
+
+    // the remaining notifications are deferred, not dropped
+    let mut num_deferred_notified = 0;
+    while num_deferred_notified < num_subscriptions - max_notifications_per_commit {
+        executor_proxy
+            .publish_deferred_on_chain_config_updates()
+            .expect("failed to publish deferred on-chain configs");
+        let num_newly_notified = num_notified(&mut reconfig_receivers);
+        assert!(num_newly_notified > 0 && num_newly_notified <= max_notifications_per_commit);
+        num_deferred_notified += num_newly_notified;
+    }
+    assert_eq!(
+        num_deferred_notified,
+        num_subscriptions - max_notifications_per_commit
+    );
+
+    // every subscription was notified exactly once
+    executor_proxy
+        .publish_deferred_on_chain_config_updates()
+        .expect("failed to publish deferred on-chain configs");
+    assert_eq!(num_notified(&mut reconfig_receivers), 0);
+}
@@ -1,10 +1,50 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::request_manager::{PeerScoreUpdateType, RequestManager};
-use diem_config::config::{PeerNetworkId, UpstreamConfig};
+use crate::{
+    chunk_request::{GetChunkRequest, TargetType},
+    chunk_response::{GetChunkResponse, ResponseLedgerInfo},
+    coordinator::CoordinatorMessage,
+    counters,
+    error::StateSyncError,
+    logging::ErrorLogThrottle,
+    network::{chunk_message_formats, StateSynchronizerMsg},
+    peer_selector::{PeerSelector, PeerStats, WeightedPeerSelector},
+    request_manager::{BackoffPolicy, PeerScoreUpdateType, RequestManager},
+    signature_verifier::{ConstantTimeSignatureVerifier, CpuSignatureVerifier, SignatureVerifier},
+    stall_escalation::{EscalationStep, StallEscalation},
+    time_provider::MockTimeProvider,
+    upstream_resolver::{StaticUpstreamResolver, UpstreamResolver},
+    StateSyncClient, StateSyncClientBlocking, StateSynchronizer,
+};
+use anyhow::Result;
+use diem_config::{
+    config::{PeerNetworkId, StateSyncConfig, UpstreamConfig},
+    network_id::{NetworkId, NodeNetworkId},
+};
+use diem_crypto::HashValue;
+use diem_infallible::Mutex;
+use diem_mempool::{CommitError, CommitResponse};
+use diem_types::{
+    block_info::BlockInfo,
+    epoch_state::EpochState,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    transaction::TransactionListWithProof,
+    validator_signer::ValidatorSigner,
+    validator_verifier::random_validator_verifier,
+    PeerId,
+};
+use futures::{channel::mpsc, future::join_all, StreamExt};
 use netcore::transport::ConnectionOrigin;
-use std::{collections::HashMap, time::Duration};
+use serde_reflection::{ContainerFormat, Registry};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 #[test]
 fn test_request_manager() {
@@ -100,3 +140,828 @@ fn test_request_manager_request_metadata() {
             <= request_manager.get_last_request_time(1).unwrap()
     );
 }
+
+#[test]
+fn test_request_manager_peer_stats() {
+    let peers = vec![
+        PeerNetworkId::random_validator(),
+        PeerNetworkId::random_validator(),
+    ];
+    let mut request_manager = RequestManager::new(
+        UpstreamConfig::default(),
+        Duration::from_secs(0),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    for peer in peers.iter() {
+        request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+
+    // the latency is only measured for the peer the request was sent to
+    request_manager.add_request(1, vec![peers[0].clone()]);
+    request_manager.process_chunk_received(&peers[0], 1);
+    request_manager.process_chunk_received(&peers[1], 1);
+    request_manager.check_timeout(1);
+
+    let peer_stats = request_manager.get_peer_stats();
+    assert_eq!(peer_stats.len(), 2);
+    let stats = &peer_stats[&peers[0]];
+    assert_eq!(stats.chunks_received, 1);
+    assert_eq!(stats.chunks_failed, 1);
+    assert!(stats.avg_response_latency.is_some());
+    let stats = &peer_stats[&peers[1]];
+    assert_eq!(stats.chunks_received, 1);
+    assert_eq!(stats.chunks_failed, 0);
+    assert!(stats.avg_response_latency.is_none());
+}
+
+#[test]
+fn test_adaptive_chunk_sizing() {
+    let peer = PeerNetworkId::random_validator();
+    let mut request_manager = RequestManager::new(
+        UpstreamConfig::default(),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    request_manager.set_adaptive_chunk_sizing(100, 300);
+    request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    assert_eq!(
+        request_manager.get_peer_stats()[&peer].adaptive_chunk_limit,
+        Some(100)
+    );
+
+    // fast successful responses grow the chunk limit up to the max limit
+    for expected_limit in vec![200, 300, 300] {
+        request_manager.add_request(1, vec![peer.clone()]);
+        request_manager.process_chunk_received(&peer, 1);
+        request_manager.process_success_response(&peer);
+        assert_eq!(
+            request_manager.get_peer_stats()[&peer].adaptive_chunk_limit,
+            Some(expected_limit)
+        );
+    }
+
+    // timeouts shrink the chunk limit
+    let mut request_manager = RequestManager::new(
+        UpstreamConfig::default(),
+        Duration::from_secs(0),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    request_manager.set_adaptive_chunk_sizing(100, 300);
+    request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    request_manager.add_request(1, vec![peer.clone()]);
+    assert!(request_manager.check_timeout(1));
+    assert_eq!(
+        request_manager.get_peer_stats()[&peer].adaptive_chunk_limit,
+        Some(50)
+    );
+
+    // without adaptive chunk sizing, there's no chunk limit per peer
+    let mut request_manager = RequestManager::new(
+        UpstreamConfig::default(),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    request_manager.add_request(1, vec![peer.clone()]);
+    request_manager.process_chunk_received(&peer, 1);
+    request_manager.process_success_response(&peer);
+    assert_eq!(
+        request_manager.get_peer_stats()[&peer].adaptive_chunk_limit,
+        None
+    );
+}
+
+// picks the peer that advertised the highest version
+struct HighestVersionPeerSelector;
+
+impl PeerSelector for HighestVersionPeerSelector {
+    fn pick(&self, candidates: &[PeerNetworkId], stats: &PeerStats) -> Option<PeerNetworkId> {
+        candidates
+            .iter()
+            .max_by_key(|peer| stats.highest_version(peer))
+            .cloned()
+    }
+}
+
+// picks a peer that isn't one of the candidates
+struct UnknownPeerSelector;
+
+impl PeerSelector for UnknownPeerSelector {
+    fn pick(&self, _candidates: &[PeerNetworkId], _stats: &PeerStats) -> Option<PeerNetworkId> {
+        Some(PeerNetworkId::random_validator())
+    }
+}
+
+#[test]
+fn test_peer_selector() {
+    let peers = vec![
+        PeerNetworkId::random_validator(),
+        PeerNetworkId::random_validator(),
+        PeerNetworkId::random_validator(),
+    ];
+    let mut request_manager = RequestManager::new_with_upstream_resolver(
+        Box::new(StaticUpstreamResolver::new(UpstreamConfig::default())),
+        Box::new(HighestVersionPeerSelector),
+        BackoffPolicy::disabled(),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    for (i, peer) in peers.iter().enumerate() {
+        request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+        request_manager.update_peer_highest_version(peer, [10, 30, 20][i]);
+    }
+    for _ in 0..10 {
+        assert_eq!(request_manager.pick_peers(), vec![peers[1].clone()]);
+    }
+
+    // the selector can't pick a peer that isn't eligible
+    let mut request_manager = RequestManager::new_with_upstream_resolver(
+        Box::new(StaticUpstreamResolver::new(UpstreamConfig::default())),
+        Box::new(UnknownPeerSelector),
+        BackoffPolicy::disabled(),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    for peer in peers.iter() {
+        request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+    assert!(request_manager.pick_peers().is_empty());
+}
+
+#[test]
+fn test_epoch_trusted_peers() {
+    let trusted_peer = PeerNetworkId::random_validator();
+    let other_peer = PeerNetworkId::random_validator();
+    let upstream_config = UpstreamConfig {
+        epoch_trusted_peers: vec![trusted_peer.peer_id()],
+        ..UpstreamConfig::default()
+    };
+    let mut request_manager = RequestManager::new_with_upstream_resolver(
+        Box::new(StaticUpstreamResolver::new(upstream_config)),
+        Box::new(HighestVersionPeerSelector),
+        BackoffPolicy::disabled(),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    request_manager.enable_peer(trusted_peer.clone(), ConnectionOrigin::Outbound);
+    request_manager.enable_peer(other_peer.clone(), ConnectionOrigin::Outbound);
+    request_manager.update_peer_highest_version(&trusted_peer, 10);
+    request_manager.update_peer_highest_version(&other_peer, 30);
+    let target = |epoch, version, next_epoch_state| {
+        TargetType::TargetLedgerInfo(LedgerInfoWithSignatures::new(
+            LedgerInfo::new(
+                BlockInfo::new(
+                    epoch,
+                    0,
+                    HashValue::zero(),
+                    HashValue::zero(),
+                    version,
+                    0,
+                    next_epoch_state,
+                ),
+                HashValue::zero(),
+            ),
+            BTreeMap::new(),
+        ))
+    };
+
+    // mid-epoch requests are sent to any peer
+    let mid_epoch = GetChunkRequest::new(0, 1, 100, target(1, 20, None));
+    assert_eq!(request_manager.pick_peers_for(&mid_epoch), vec![other_peer]);
+
+    // the requests crossing an epoch boundary only to the trusted ones
+    let next_epoch = GetChunkRequest::new(0, 1, 100, target(2, 20, None));
+    assert_eq!(
+        request_manager.pick_peers_for(&next_epoch),
+        vec![trusted_peer.clone()]
+    );
+    let end_of_epoch = GetChunkRequest::new(0, 1, 100, target(1, 20, Some(EpochState::empty())));
+    assert_eq!(
+        request_manager.pick_peers_for(&end_of_epoch),
+        vec![trusted_peer.clone()]
+    );
+    let waypoint = GetChunkRequest::new(0, 1, 100, TargetType::Waypoint(20));
+    assert_eq!(
+        request_manager.pick_peers_for(&waypoint),
+        vec![trusted_peer]
+    );
+}
+
+#[test]
+fn test_chunk_request_timeout_reissue() {
+    let peers = vec![
+        PeerNetworkId::random_validator(),
+        PeerNetworkId::random_validator(),
+    ];
+    let mut request_manager = RequestManager::new_with_upstream_resolver(
+        Box::new(StaticUpstreamResolver::new(UpstreamConfig::default())),
+        Box::new(HighestVersionPeerSelector),
+        BackoffPolicy::disabled(),
+        Duration::from_secs(0),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    for (i, peer) in peers.iter().enumerate() {
+        request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+        request_manager.update_peer_highest_version(peer, [30, 10][i]);
+    }
+    assert_eq!(request_manager.pick_peers(), vec![peers[0].clone()]);
+    request_manager.add_request(1, vec![peers[0].clone()]);
+
+    // once the request timed out, it's re-issued to another peer, even without backoff, and the
+    // timed-out peer is counted as failed
+    assert!(request_manager.check_timeout(1));
+    assert_eq!(request_manager.get_peer_stats()[&peers[0]].chunks_failed, 1);
+    assert_eq!(request_manager.pick_peers(), vec![peers[1].clone()]);
+    request_manager.add_request(1, vec![peers[1].clone()]);
+
+    // the timed-out peer is picked again for the next requests
+    assert_eq!(request_manager.pick_peers(), vec![peers[0].clone()]);
+
+    // the timed-out peer is still picked if there's no other peer
+    request_manager.disable_peer(&peers[1], ConnectionOrigin::Outbound);
+    request_manager.add_request(2, vec![peers[0].clone()]);
+    assert!(request_manager.check_timeout(2));
+    assert_eq!(request_manager.pick_peers(), vec![peers[0].clone()]);
+}
+
+#[test]
+fn test_peer_backoff() {
+    // the peers are on a network of their own, so that no other test skips peers of their network
+    let network_id = NetworkId::Private("peer_backoff".to_string());
+    let peers = vec![
+        PeerNetworkId(NodeNetworkId::new(network_id.clone(), 0), PeerId::random()),
+        PeerNetworkId(NodeNetworkId::new(network_id.clone(), 0), PeerId::random()),
+    ];
+    let upstream_config = UpstreamConfig {
+        networks: vec![network_id],
+        ..UpstreamConfig::default()
+    };
+    let mut request_manager = RequestManager::new_with_upstream_resolver(
+        Box::new(StaticUpstreamResolver::new(upstream_config)),
+        Box::new(WeightedPeerSelector),
+        BackoffPolicy {
+            base: Duration::from_secs(10),
+            multiplier: 2.0,
+            max: Duration::from_secs(30),
+        },
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    for peer in peers.iter() {
+        request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+    let network = peers[0].raw_network_id().to_string();
+    let num_skips = counters::PEER_BACKOFF_SKIP_COUNT
+        .with_label_values(&[&network])
+        .get();
+
+    // the backoff grows at every consecutive failure, up to the max
+    let mut backoffs = vec![];
+    for _ in 0..3 {
+        request_manager.update_score(&peers[0], PeerScoreUpdateType::TimeOut);
+        backoffs.push(request_manager.peer_backoff(&peers[0]).unwrap());
+    }
+    assert_eq!(
+        backoffs,
+        vec![
+            Duration::from_secs(10),
+            Duration::from_secs(20),
+            Duration::from_secs(30)
+        ]
+    );
+    for _ in 0..10 {
+        assert_eq!(request_manager.pick_peers(), vec![peers[1].clone()]);
+    }
+    assert_eq!(
+        counters::PEER_BACKOFF_SKIP_COUNT
+            .with_label_values(&[&network])
+            .get()
+            - num_skips,
+        10
+    );
+
+    // no peer is picked while all of them are backing off
+    request_manager.update_score(&peers[1], PeerScoreUpdateType::InvalidChunk);
+    assert!(request_manager.pick_peers().is_empty());
+
+    // a useful chunk resets the backoff
+    request_manager.update_score(&peers[0], PeerScoreUpdateType::Success);
+    assert!(request_manager.peer_backoff(&peers[0]).is_none());
+    assert_eq!(request_manager.pick_peers(), vec![peers[0].clone()]);
+}
+
+struct MockUpstreamResolver {
+    upstream_config: Arc<Mutex<UpstreamConfig>>,
+}
+
+impl UpstreamResolver for MockUpstreamResolver {
+    fn resolve(&self) -> Result<UpstreamConfig> {
+        Ok(self.upstream_config.lock().clone())
+    }
+}
+
+#[test]
+fn test_upstream_resolver() {
+    let upstream_config = Arc::new(Mutex::new(UpstreamConfig::default()));
+    let mut request_manager = RequestManager::new_with_upstream_resolver(
+        Box::new(MockUpstreamResolver {
+            upstream_config: upstream_config.clone(),
+        }),
+        Box::new(WeightedPeerSelector),
+        BackoffPolicy::disabled(),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    let vfn_peer = PeerNetworkId(
+        NodeNetworkId::new(NetworkId::vfn_network(), 0),
+        PeerId::random(),
+    );
+    request_manager.enable_peer(vfn_peer.clone(), ConnectionOrigin::Outbound);
+    assert!(!request_manager.is_known_upstream_peer(&vfn_peer));
+    assert!(request_manager.no_available_peers());
+
+    // the VFN network becomes upstream at runtime, and its connected peer is adopted
+    let refresh_count = counters::UPSTREAM_CONFIG_REFRESH_COUNT
+        .with_label_values(&[counters::SUCCESS_LABEL])
+        .get();
+    upstream_config.lock().networks = vec![NetworkId::vfn_network()];
+    request_manager.refresh_upstream_config();
+    assert!(request_manager.is_known_upstream_peer(&vfn_peer));
+    assert_eq!(request_manager.pick_peers(), vec![vfn_peer.clone()]);
+    assert_eq!(
+        counters::UPSTREAM_CONFIG_REFRESH_COUNT
+            .with_label_values(&[counters::SUCCESS_LABEL])
+            .get()
+            - refresh_count,
+        1
+    );
+
+    // and the peer is dropped once its network isn't upstream anymore
+    upstream_config.lock().networks = vec![];
+    request_manager.refresh_upstream_config();
+    assert!(!request_manager.is_known_upstream_peer(&vfn_peer));
+    assert!(request_manager.no_available_peers());
+}
+
+#[test]
+fn test_commit_handles() {
+    let (coordinator_sender, mut coordinator_receiver) = mpsc::unbounded();
+    let client = StateSyncClient::new(coordinator_sender);
+    let mut handles: Vec<_> = (0..3)
+        .map(|_| client.commit_async(vec![], vec![]).unwrap())
+        .collect();
+    assert!(handles[0].try_result().is_none());
+
+    // the commits reach the coordinator in order, and only the second one fails
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async move {
+        for i in 0..3 {
+            match coordinator_receiver.next().await {
+                Some(CoordinatorMessage::Commit(_, _, callback)) => {
+                    let response = if i == 1 {
+                        CommitResponse::failure(CommitError::MempoolAckTimeout)
+                    } else {
+                        CommitResponse::success()
+                    };
+                    callback.send(Ok(response)).unwrap();
+                }
+                _ => panic!("expected a commit"),
+            }
+        }
+
+        // the handles are awaited out of order and each gets the result of its own commit
+        let third = handles.pop().unwrap();
+        let second = handles.pop().unwrap();
+        assert!(third.result().await.is_ok());
+        assert!(handles[0].try_result().unwrap().is_ok());
+        // the failure is typed, so the client can match on it
+        let error = second.result().await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<CommitError>(),
+            Some(&CommitError::MempoolAckTimeout)
+        );
+    });
+}
+
+#[test]
+fn test_commit_timeout() {
+    let (coordinator_sender, _coordinator_receiver) = mpsc::unbounded();
+    let client =
+        StateSyncClient::new_with_commit_timeout(coordinator_sender, Duration::from_millis(10));
+
+    // the commit is never acknowledged
+    let num_commit_timeouts = counters::COMMIT_FLOW_FAIL
+        .with_label_values(&[counters::STATE_SYNC_LABEL])
+        .get();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    assert!(rt.block_on(client.commit(vec![], vec![])).is_err());
+    assert_eq!(
+        counters::COMMIT_FLOW_FAIL
+            .with_label_values(&[counters::STATE_SYNC_LABEL])
+            .get(),
+        num_commit_timeouts + 1
+    );
+}
+
+#[test]
+fn test_sync_to_coordinator_unavailable() {
+    let target = LedgerInfoWithSignatures::new(
+        LedgerInfo::new(BlockInfo::empty(), HashValue::zero()),
+        BTreeMap::new(),
+    );
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    // the coordinator drops the request without completing it
+    let (coordinator_sender, mut coordinator_receiver) = mpsc::unbounded();
+    let client = StateSyncClient::new(coordinator_sender);
+    let sync = client.sync_to(target.clone());
+    drop(rt.block_on(coordinator_receiver.next()));
+    assert!(matches!(
+        rt.block_on(sync),
+        Err(StateSyncError::CoordinatorUnavailable(_))
+    ));
+
+    // the coordinator stopped
+    drop(coordinator_receiver);
+    assert!(matches!(
+        rt.block_on(client.sync_to(target)),
+        Err(StateSyncError::CoordinatorUnavailable(_))
+    ));
+
+    // the other calls report it as well, behind their anyhow errors
+    for error in vec![
+        rt.block_on(client.commit(vec![], vec![])).unwrap_err(),
+        rt.block_on(client.health_check()).unwrap_err(),
+    ] {
+        assert!(matches!(
+            error.downcast_ref::<StateSyncError>(),
+            Some(StateSyncError::CoordinatorUnavailable(_))
+        ));
+    }
+}
+
+#[test]
+fn test_sync_to_cancellable() {
+    let target = LedgerInfoWithSignatures::new(
+        LedgerInfo::new(BlockInfo::empty(), HashValue::zero()),
+        BTreeMap::new(),
+    );
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    let (coordinator_sender, mut coordinator_receiver) = mpsc::unbounded();
+    let client = StateSyncClient::new(coordinator_sender);
+    let mut next_request = |coordinator_receiver: &mut mpsc::UnboundedReceiver<
+        CoordinatorMessage,
+    >| match rt.block_on(coordinator_receiver.next()) {
+        Some(CoordinatorMessage::Request(request)) => *request,
+        _ => panic!("expected a sync request"),
+    };
+
+    // dropping the handle of an active request cancels it
+    let handle = client.sync_to_cancellable(target.clone()).unwrap();
+    let first_request = next_request(&mut coordinator_receiver);
+    assert_eq!(first_request.generation, handle.request_id());
+    drop(handle);
+    assert!(matches!(
+        coordinator_receiver.try_next(),
+        Ok(Some(CoordinatorMessage::CancelSync(generation))) if generation == first_request.generation
+    ));
+
+    // the request ids increase monotonically, and a completed request isn't cancelled
+    let handle = client.sync_to_cancellable(target).unwrap();
+    let second_request = next_request(&mut coordinator_receiver);
+    assert!(second_request.generation > first_request.generation);
+    assert!(second_request.callback.send(Ok(())).is_ok());
+    handle.cancel();
+    assert!(coordinator_receiver.try_next().is_err());
+}
+
+#[test]
+fn test_blocking_client() {
+    let target = LedgerInfoWithSignatures::new(
+        LedgerInfo::new(BlockInfo::empty(), HashValue::zero()),
+        BTreeMap::new(),
+    );
+    let rt = StateSynchronizer::create_runtime(&StateSyncConfig::default());
+    let (coordinator_sender, mut coordinator_receiver) = mpsc::unbounded();
+    rt.spawn(async move {
+        while let Some(msg) = coordinator_receiver.next().await {
+            let sent = match msg {
+                CoordinatorMessage::Request(request) => request.callback.send(Ok(())).is_ok(),
+                CoordinatorMessage::WaitInitialize(callback) => callback.send(Ok(())).is_ok(),
+                CoordinatorMessage::Commit(_, _, callback) => {
+                    callback.send(Ok(CommitResponse::success())).is_ok()
+                }
+                _ => panic!("unexpected coordinator message"),
+            };
+            assert!(sent);
+        }
+    });
+    let client = Arc::new(StateSyncClient::new(coordinator_sender));
+    let blocking_client = StateSyncClientBlocking::new(client, rt.handle().clone());
+
+    // the calls of a thread outside of the runtime complete
+    assert!(blocking_client.wait_until_initialized().is_ok());
+    assert!(blocking_client.sync_to(target.clone()).is_ok());
+    assert!(blocking_client.commit(vec![], vec![]).is_ok());
+
+    // the calls of a thread of the runtime fail instead of blocking it
+    let result = futures::executor::block_on(
+        rt.handle()
+            .spawn(async move { blocking_client.sync_to(target) }),
+    )
+    .unwrap();
+    assert_eq!(result, Err(StateSyncError::BlockingCallFromRuntime));
+}
+
+#[test]
+fn test_chunk_message_formats() {
+    fn msg_variant(msg: &StateSynchronizerMsg) -> &'static str {
+        match msg {
+            StateSynchronizerMsg::GetChunkRequest(_) => "GetChunkRequest",
+            StateSynchronizerMsg::GetChunkResponse(_) => "GetChunkResponse",
+            StateSynchronizerMsg::CommittedLedgerInfo(_) => "CommittedLedgerInfo",
+        }
+    }
+    fn target_variant(target: &TargetType) -> &'static str {
+        match target {
+            TargetType::TargetLedgerInfo(_) => "TargetLedgerInfo",
+            TargetType::HighestAvailable { .. } => "HighestAvailable",
+            TargetType::Waypoint(_) => "Waypoint",
+        }
+    }
+    fn response_li_variant(response_li: &ResponseLedgerInfo) -> &'static str {
+        match response_li {
+            ResponseLedgerInfo::VerifiableLedgerInfo(_) => "VerifiableLedgerInfo",
+            ResponseLedgerInfo::ProgressiveLedgerInfo { .. } => "ProgressiveLedgerInfo",
+            ResponseLedgerInfo::LedgerInfoForWaypoint { .. } => "LedgerInfoForWaypoint",
+        }
+    }
+    fn variants(registry: &Registry, name: &str) -> BTreeSet<String> {
+        match registry.get(name) {
+            Some(ContainerFormat::Enum(variants)) => {
+                variants.values().map(|named| named.name.clone()).collect()
+            }
+            format => panic!("Unexpected format for {}: {:?}", name, format),
+        }
+    }
+
+    let registry = chunk_message_formats().unwrap();
+
+    let li = LedgerInfoWithSignatures::new(
+        LedgerInfo::new(BlockInfo::empty(), HashValue::zero()),
+        BTreeMap::new(),
+    );
+    let targets = vec![
+        TargetType::TargetLedgerInfo(li.clone()),
+        TargetType::HighestAvailable {
+            target_li: Some(li.clone()),
+            timeout_ms: 10_000,
+        },
+        TargetType::Waypoint(10),
+    ];
+    let response_lis = vec![
+        ResponseLedgerInfo::VerifiableLedgerInfo(li.clone()),
+        ResponseLedgerInfo::ProgressiveLedgerInfo {
+            target_li: li.clone(),
+            highest_li: Some(li.clone()),
+        },
+        ResponseLedgerInfo::LedgerInfoForWaypoint {
+            waypoint_li: li.clone(),
+            end_of_epoch_li: Some(li.clone()),
+        },
+    ];
+    assert_eq!(
+        variants(&registry, "TargetType"),
+        targets
+            .iter()
+            .map(|t| target_variant(t).to_string())
+            .collect()
+    );
+    assert_eq!(
+        variants(&registry, "ResponseLedgerInfo"),
+        response_lis
+            .iter()
+            .map(|r| response_li_variant(r).to_string())
+            .collect()
+    );
+
+    let msgs: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            StateSynchronizerMsg::GetChunkRequest(Box::new(GetChunkRequest::new(
+                10, 1, 250, target,
+            )))
+        })
+        .chain(response_lis.into_iter().map(|response_li| {
+            StateSynchronizerMsg::GetChunkResponse(Box::new(GetChunkResponse::new(
+                response_li,
+                TransactionListWithProof::new_empty(),
+            )))
+        }))
+        .chain(std::iter::once(StateSynchronizerMsg::CommittedLedgerInfo(
+            Box::new(li),
+        )))
+        .collect();
+    assert_eq!(
+        variants(&registry, "StateSynchronizerMsg"),
+        msgs.iter().map(|m| msg_variant(m).to_string()).collect()
+    );
+
+    for msg in msgs {
+        let bytes = lcs::to_bytes(&msg).unwrap();
+        let decoded: StateSynchronizerMsg = lcs::from_bytes(&bytes).unwrap();
+        assert_eq!(lcs::to_bytes(&decoded).unwrap(), bytes);
+    }
+}
+
+#[test]
+fn test_critical_chunk_request() {
+    let li = LedgerInfoWithSignatures::new(
+        LedgerInfo::new(BlockInfo::empty(), HashValue::zero()),
+        BTreeMap::new(),
+    );
+    let req =
+        GetChunkRequest::new(10, 1, 250, TargetType::TargetLedgerInfo(li)).with_critical(true);
+    assert!(req.is_critical());
+
+    // the marker is local to the requester, it isn't sent to the peers
+    let decoded: GetChunkRequest = lcs::from_bytes(&lcs::to_bytes(&req).unwrap()).unwrap();
+    assert!(!decoded.is_critical());
+}
+
+#[test]
+fn test_request_table_bounded() {
+    let peers = vec![
+        PeerNetworkId::random_validator(),
+        PeerNetworkId::random_validator(),
+    ];
+    let mut request_manager = RequestManager::new(
+        UpstreamConfig::default(),
+        Duration::from_millis(100),
+        Duration::from_secs(30),
+        HashMap::new(),
+    );
+    let time_provider = Arc::new(MockTimeProvider::new());
+    request_manager.set_time_provider(time_provider.clone());
+    for peer in peers.iter() {
+        request_manager.enable_peer(peer.clone(), ConnectionOrigin::Outbound);
+    }
+
+    // requests sent to a single peer are removed as soon as they complete, even if they haven't
+    // timed out yet
+    for version in 0..1000 {
+        request_manager.add_request(version, vec![peers[0].clone()]);
+        request_manager.add_request(version + 1, vec![peers[1].clone()]);
+        request_manager.remove_requests(version + 1);
+        assert!(request_manager.num_requests() <= 2);
+    }
+
+    // multicasted requests are kept until they time out
+    for version in 1000..1100 {
+        request_manager.add_request(version, peers.clone());
+        request_manager.remove_requests(version + 1);
+    }
+    assert_eq!(request_manager.num_requests(), 100);
+    time_provider.advance(Duration::from_millis(100));
+    request_manager.remove_requests(1100);
+    assert_eq!(request_manager.num_requests(), 0);
+}
+
+#[test]
+fn test_constant_time_signature_verifier() {
+    let (signers, verifier) = random_validator_verifier(4, None, true);
+    let ledger_info = LedgerInfo::new(BlockInfo::empty(), HashValue::zero());
+    let sign = |signers: &[ValidatorSigner]| {
+        signers
+            .iter()
+            .map(|signer| (signer.author(), signer.sign(&ledger_info)))
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    // a quorum of valid signatures
+    let valid = sign(&signers[..3]);
+    // one of the signatures is made by another validator
+    let mut invalid_signature = valid.clone();
+    invalid_signature.insert(signers[1].author(), signers[2].sign(&ledger_info));
+    // not enough signatures for a quorum
+    let too_little_voting_power = sign(&signers[..2]);
+    // one of the signatures is made by a validator outside of the validator set
+    let mut unknown_author = valid.clone();
+    unknown_author.insert(
+        ValidatorSigner::random(None).author(),
+        signers[0].sign(&ledger_info),
+    );
+    let ledger_infos: Vec<_> = vec![
+        valid,
+        invalid_signature,
+        too_little_voting_power,
+        unknown_author,
+    ]
+    .into_iter()
+    .map(|signatures| LedgerInfoWithSignatures::new(ledger_info.clone(), signatures))
+    .collect();
+    let ledger_infos: Vec<_> = ledger_infos.iter().collect();
+
+    let outcomes = |signature_verifier: &dyn SignatureVerifier| {
+        signature_verifier
+            .verify_batch(&verifier, &ledger_infos)
+            .unwrap()
+            .into_iter()
+            .map(|result| result.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>()
+    };
+    let constant_time_outcomes = outcomes(&ConstantTimeSignatureVerifier);
+    assert_eq!(constant_time_outcomes, outcomes(&CpuSignatureVerifier));
+    assert!(constant_time_outcomes[0].is_ok());
+    assert!(constant_time_outcomes[1..]
+        .iter()
+        .all(|result| result.is_err()));
+}
+
+#[test]
+fn test_runtime_threads() {
+    for &runtime_threads in &[1, 2] {
+        let mut runtime = StateSynchronizer::create_runtime(&StateSyncConfig {
+            runtime_threads: Some(runtime_threads),
+            ..StateSyncConfig::default()
+        });
+
+        // every blocking task occupies a worker thread, so at most one task per thread runs at once
+        let num_running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let (num_running, max_running) = (num_running.clone(), max_running.clone());
+                runtime.spawn(async move {
+                    let running = num_running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(running, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(100));
+                    num_running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        runtime.block_on(join_all(tasks));
+        assert_eq!(max_running.load(Ordering::SeqCst), runtime_threads);
+    }
+}
+
+#[test]
+fn test_error_log_throttle() {
+    let mut throttle = ErrorLogThrottle::new(Duration::from_millis(100));
+    let error = "peer 1: invalid chunk".to_string();
+
+    // only the first error of a burst is logged immediately
+    let logged: Vec<_> = (0..10).map(|_| throttle.record(error.clone())).collect();
+    assert!(logged[0]);
+    assert!(logged[1..].iter().all(|logged| !logged));
+    // distinct errors are throttled separately
+    assert!(throttle.record("peer 2: invalid chunk".to_string()));
+    assert!(throttle.expire().is_empty());
+
+    // the repetitions are summarized once the window expires, and the error is logged again
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(throttle.expire(), vec![(error.clone(), 9)]);
+    assert!(throttle.expire().is_empty());
+    assert!(throttle.record(error.clone()));
+
+    // without a window, every error is logged
+    let mut throttle = ErrorLogThrottle::new(Duration::from_millis(0));
+    assert!((0..10).all(|_| throttle.record(error.clone())));
+}
+
+#[test]
+fn test_critical_stall_escalation() {
+    let config = StateSyncConfig {
+        multicast_timeout_ms: 100,
+        stall_escalation_widen_peers_ms: 10_000,
+        stall_escalation_refresh_peers_ms: 20_000,
+        stall_escalation_switch_networks_ms: 0,
+        stall_escalation_alert_ms: 30_000,
+        ..StateSyncConfig::default()
+    };
+    let start = std::time::SystemTime::now();
+    let mut background = StallEscalation::new(&config, 0, start);
+    let mut critical = StallEscalation::new(&config, 0, start);
+    let now = start + Duration::from_millis(250);
+
+    // a critical sync takes a recovery step per multicast timeout, but the alert isn't brought
+    // forward
+    assert!(background.update(0, false, false, now).is_empty());
+    assert_eq!(
+        critical.update(0, false, true, now),
+        vec![
+            (EscalationStep::WidenPeers, true),
+            (EscalationStep::RefreshPeers, true)
+        ]
+    );
+}
@@ -0,0 +1,59 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(any(test, feature = "fuzzing"))]
+use diem_infallible::Mutex;
+#[cfg(any(test, feature = "fuzzing"))]
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// Clock state synchronizer reads the current time from, to compute the staleness, backoffs and
+/// timeouts of the sync process. It's pluggable so that the timeout logic can be tested without
+/// waiting for the timeouts to expire.
+pub trait TimeProvider: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// Reads the time from the system clock.
+pub struct SystemTimeProvider;
+
+impl TimeProvider for SystemTimeProvider {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves forward when advanced explicitly.
+#[cfg(any(test, feature = "fuzzing"))]
+pub struct MockTimeProvider {
+    now: Mutex<SystemTime>,
+}
+
+#[cfg(any(test, feature = "fuzzing"))]
+impl MockTimeProvider {
+    /// Creates a clock stopped at the current system time.
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(SystemTime::now()),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock();
+        *now += duration;
+    }
+}
+
+#[cfg(any(test, feature = "fuzzing"))]
+impl Default for MockTimeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "fuzzing"))]
+impl TimeProvider for MockTimeProvider {
+    fn now(&self) -> SystemTime {
+        *self.now.lock()
+    }
+}
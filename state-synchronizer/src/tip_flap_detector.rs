@@ -0,0 +1,60 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+// DS to detect flapping of the tip of the chain as advertised by peers: a peer advertising a
+// lower version than it did before makes a tip estimate that trusts single advertisements regress.
+// Too many regressions within the window hint at conflicting or malicious peers.
+pub struct TipFlapDetector {
+    // times of the most recent regressions, oldest first (at most `threshold` of them)
+    pub regressions: VecDeque<SystemTime>,
+    pub window: Duration,
+    threshold: usize,
+    pub flapping: bool,
+}
+
+impl TipFlapDetector {
+    pub fn new(window: Duration, threshold: usize) -> Self {
+        Self {
+            regressions: VecDeque::new(),
+            window,
+            threshold,
+            flapping: false,
+        }
+    }
+
+    pub fn record_regression(&mut self, now: SystemTime) {
+        if self.threshold == 0 {
+            return;
+        }
+        self.regressions.push_back(now);
+        if self.regressions.len() > self.threshold {
+            self.regressions.pop_front();
+        }
+    }
+
+    /// Drops the regressions that fell out of the window. Returns true if this results in a
+    /// transition between flapping and stable.
+    pub fn update(&mut self, now: SystemTime) -> bool {
+        let window = self.window;
+        while let Some(tst) = self.regressions.front() {
+            let expired = now
+                .duration_since(*tst)
+                .map_or(false, |elapsed| elapsed > window);
+            if !expired {
+                break;
+            }
+            self.regressions.pop_front();
+        }
+        let flapping = self.threshold > 0 && self.regressions.len() >= self.threshold;
+        if flapping == self.flapping {
+            return false;
+        }
+        self.flapping = flapping;
+        true
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use diem_config::config::UpstreamConfig;
+
+/// Resolves the upstream networks state synchronizer syncs from, e.g. out of a service discovery
+/// system in dynamic environments, so that they can change without a restart.
+pub trait UpstreamResolver: Send {
+    /// Returns the current upstream config. It's queried periodically, every
+    /// `upstream_refresh_interval_ms`, and blocks state synchronizer until it returns.
+    /// An `Err` keeps the previous upstream config.
+    fn resolve(&self) -> Result<UpstreamConfig>;
+}
+
+/// Resolves to the upstream config the node was started with.
+pub struct StaticUpstreamResolver {
+    upstream_config: UpstreamConfig,
+}
+
+impl StaticUpstreamResolver {
+    pub fn new(upstream_config: UpstreamConfig) -> Self {
+        Self { upstream_config }
+    }
+}
+
+impl UpstreamResolver for StaticUpstreamResolver {
+    fn resolve(&self) -> Result<UpstreamConfig> {
+        Ok(self.upstream_config.clone())
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use diem_types::waypoint::Waypoint;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// DS to periodically advance the waypoint the node trusts on restart to its latest committed
+// epoch-ending LI, by persisting it in the file the node reads its waypoint from.
+pub struct WaypointAdvancement {
+    file: Option<PathBuf>,
+    interval: Duration,
+    last_check_tst: SystemTime,
+}
+
+impl WaypointAdvancement {
+    pub fn new(file: Option<PathBuf>, interval: Duration) -> Self {
+        Self {
+            file,
+            interval,
+            last_check_tst: UNIX_EPOCH,
+        }
+    }
+
+    /// Returns the file to advance the waypoint in if an advancement is due, and starts a new
+    /// interval
+    pub fn due(&mut self, now: SystemTime) -> Option<PathBuf> {
+        let file = self.file.clone()?;
+        let due = now
+            .duration_since(self.last_check_tst)
+            .map_or(false, |elapsed| elapsed >= self.interval);
+        if !due {
+            return None;
+        }
+        self.last_check_tst = now;
+        Some(file)
+    }
+
+    /// Replaces the waypoint in `file`. The waypoint is written to a temporary file first, which
+    /// is then renamed, so that a crash never leaves a partially written waypoint behind.
+    pub fn persist(file: &Path, waypoint: &Waypoint) -> Result<()> {
+        let mut tmp_file = file.as_os_str().to_owned();
+        tmp_file.push(".tmp");
+        fs::write(&tmp_file, waypoint.to_string())?;
+        fs::rename(&tmp_file, file)?;
+        Ok(())
+    }
+}
@@ -89,6 +89,7 @@ static ALL_TARGETS: Lazy<BTreeMap<&'static str, Box<dyn FuzzTargetImpl>>> = Lazy
         Box::new(secure_storage_vault::VaultUnsealedResponse::default()),
         // State Sync
         Box::new(state_sync::StateSyncMsg::default()),
+        Box::new(state_sync::StateSyncChunkResponse::default()),
         // Storage
         // Box::new(storage::StorageSaveBlocks::default()),
         Box::new(storage::StorageSchemaDecode::default()),
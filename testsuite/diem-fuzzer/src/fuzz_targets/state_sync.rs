@@ -3,7 +3,10 @@
 
 use crate::{corpus_from_strategy, fuzz_data_to_value, FuzzTargetImpl};
 use diem_proptest_helpers::ValueGenerator;
-use state_synchronizer::fuzzing::{state_sync_msg_strategy, test_state_sync_msg_fuzzer_impl};
+use state_synchronizer::fuzzing::{
+    chunk_response_bytes_strategy, state_sync_msg_strategy, test_chunk_response_fuzzer_impl,
+    test_state_sync_msg_fuzzer_impl,
+};
 
 #[derive(Debug, Default)]
 pub struct StateSyncMsg;
@@ -22,3 +25,20 @@ impl FuzzTargetImpl for StateSyncMsg {
         test_state_sync_msg_fuzzer_impl(msg);
     }
 }
+
+#[derive(Debug, Default)]
+pub struct StateSyncChunkResponse;
+
+impl FuzzTargetImpl for StateSyncChunkResponse {
+    fn description(&self) -> &'static str {
+        "State sync chunk responses do not panic on malformed bytes or proofs"
+    }
+
+    fn generate(&self, _idx: usize, gen: &mut ValueGenerator) -> Option<Vec<u8>> {
+        Some(gen.generate(chunk_response_bytes_strategy()))
+    }
+
+    fn fuzz(&self, data: &[u8]) {
+        test_chunk_response_fuzzer_impl(data);
+    }
+}
@@ -440,6 +440,85 @@ where
 
         Ok(())
     }
+
+    /// Returns the proof of the first `num_leaves` leaves of the range this proof authenticates,
+    /// given the index of the first leaf and the hashes of all the leaves of the range. The prefix
+    /// has the same left siblings as the range, while its right siblings are computed from the
+    /// leaves after it and the right siblings of the range.
+    pub fn prefix(
+        &self,
+        first_leaf_index: u64,
+        leaf_hashes: &[HashValue],
+        num_leaves: usize,
+    ) -> Result<Self> {
+        ensure!(
+            num_leaves > 0 && num_leaves <= leaf_hashes.len(),
+            "Prefix of {} leaves of a range of {} leaves.",
+            num_leaves,
+            leaf_hashes.len(),
+        );
+
+        let mut left_sibling_iter = self.left_siblings.iter();
+        let mut right_sibling_iter = self.right_siblings.iter();
+        let mut prefix_right_siblings = vec![];
+
+        // Indices, within the current level, of the first node of the range and of the last node
+        // of the prefix.
+        let mut first_index = first_leaf_index;
+        let mut prefix_last_index = first_leaf_index + num_leaves as u64 - 1;
+        let mut current_hashes = leaf_hashes.to_vec();
+
+        // Reduce the range the same way `verify` does, and record the right sibling of the last
+        // node of the prefix on every level where it's a left child.
+        while current_hashes.len() > 1
+            || left_sibling_iter.len() > 0
+            || right_sibling_iter.len() > 0
+        {
+            let last_index = first_index + current_hashes.len() as u64 - 1;
+            let mut parent_hashes = vec![];
+            let mut children = current_hashes.as_slice();
+
+            if first_index % 2 == 1 {
+                let left_hash = *left_sibling_iter.next().ok_or_else(|| {
+                    format_err!("First child is a right child, but missing sibling on the left.")
+                })?;
+                parent_hashes.push(MerkleTreeInternalNode::<H>::new(left_hash, children[0]).hash());
+                children = &children[1..];
+            }
+
+            let mut children_iter = children.chunks_exact(2);
+            while let Some(chunk) = children_iter.next() {
+                parent_hashes.push(MerkleTreeInternalNode::<H>::new(chunk[0], chunk[1]).hash());
+            }
+
+            let mut last_right_sibling = None;
+            if let [left_hash] = children_iter.remainder() {
+                let right_hash = *right_sibling_iter.next().ok_or_else(|| {
+                    format_err!("Last child is a left child, but missing sibling on the right.")
+                })?;
+                last_right_sibling = Some(right_hash);
+                parent_hashes.push(MerkleTreeInternalNode::<H>::new(*left_hash, right_hash).hash());
+            }
+
+            if prefix_last_index % 2 == 0 {
+                // The sibling is either in the range or, for the last node of the range, one of
+                // the right siblings of the range.
+                let right_hash = if prefix_last_index < last_index {
+                    current_hashes[(prefix_last_index + 1 - first_index) as usize]
+                } else {
+                    last_right_sibling
+                        .ok_or_else(|| format_err!("Missing sibling on the right of the prefix."))?
+                };
+                prefix_right_siblings.push(right_hash);
+            }
+
+            first_index /= 2;
+            prefix_last_index /= 2;
+            current_hashes = parent_hashes;
+        }
+
+        Ok(Self::new(self.left_siblings.clone(), prefix_right_siblings))
+    }
 }
 
 impl<H> std::fmt::Debug for AccumulatorRangeProof<H> {
@@ -11,7 +11,8 @@ use crate::{
         definition::MAX_ACCUMULATOR_PROOF_DEPTH, AccountStateProof, AccumulatorExtensionProof,
         EventAccumulatorInternalNode, EventAccumulatorProof, EventProof, SparseMerkleInternalNode,
         SparseMerkleLeafNode, SparseMerkleProof, TestAccumulatorInternalNode, TestAccumulatorProof,
-        TransactionAccumulatorInternalNode, TransactionAccumulatorProof, TransactionInfoWithProof,
+        TestAccumulatorRangeProof, TransactionAccumulatorInternalNode, TransactionAccumulatorProof,
+        TransactionInfoWithProof,
     },
     transaction::{RawTransaction, Script, Transaction, TransactionInfo},
     vm_status::KeptVMStatus,
@@ -82,6 +83,45 @@ fn test_verify_three_element_accumulator() {
     );
 }
 
+#[test]
+fn test_accumulator_range_proof_prefix() {
+    let element0_hash = b"hello".test_only_hash();
+    let element1_hash = b"world".test_only_hash();
+    let element2_hash = b"foo".test_only_hash();
+    let element3_hash = b"bar".test_only_hash();
+    let internal0_hash = TestAccumulatorInternalNode::new(element0_hash, element1_hash).hash();
+    let internal1_hash = TestAccumulatorInternalNode::new(element2_hash, element3_hash).hash();
+    let root_hash = TestAccumulatorInternalNode::new(internal0_hash, internal1_hash).hash();
+
+    // the right sibling of the prefix is computed from the leaves of the range
+    let leaves = vec![element0_hash, element1_hash, element2_hash, element3_hash];
+    let proof = TestAccumulatorRangeProof::new(vec![], vec![]);
+    assert!(proof.verify(root_hash, Some(0), &leaves).is_ok());
+    let prefix = proof.prefix(0, &leaves, 1).unwrap();
+    assert_eq!(
+        prefix,
+        TestAccumulatorRangeProof::new(vec![], vec![element1_hash, internal1_hash])
+    );
+    assert!(prefix.verify(root_hash, Some(0), &leaves[..1]).is_ok());
+    let prefix = proof.prefix(0, &leaves, 3).unwrap();
+    assert!(prefix.verify(root_hash, Some(0), &leaves[..3]).is_ok());
+
+    // or from the right siblings of the range
+    let leaves = vec![element1_hash, element2_hash];
+    let proof = TestAccumulatorRangeProof::new(vec![element0_hash], vec![element3_hash]);
+    assert!(proof.verify(root_hash, Some(1), &leaves).is_ok());
+    let prefix = proof.prefix(1, &leaves, 1).unwrap();
+    assert_eq!(
+        prefix,
+        TestAccumulatorRangeProof::new(vec![element0_hash], vec![internal1_hash])
+    );
+    assert!(prefix.verify(root_hash, Some(1), &leaves[..1]).is_ok());
+    assert_eq!(proof.prefix(1, &leaves, 2).unwrap(), proof);
+
+    assert!(proof.prefix(1, &leaves, 0).is_err());
+    assert!(proof.prefix(1, &leaves, 3).is_err());
+}
+
 #[test]
 fn test_accumulator_proof_max_siblings_leftmost() {
     let element_hash = b"hello".test_only_hash();